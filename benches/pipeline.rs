@@ -0,0 +1,132 @@
+use chuck::codeowners::CodeOwners;
+use chuck::discovery;
+use chuck::policy::Policy;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const COMMITS: usize = 200;
+
+/// A throwaway repository with `COMMITS` commits touching a few files each
+fn scratch_repo() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("chuck-bench-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+
+    let git = |args: &[&str]| {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(&dir)
+            .env("GIT_AUTHOR_NAME", "Bench")
+            .env("GIT_AUTHOR_EMAIL", "bench@example.com")
+            .env("GIT_COMMITTER_NAME", "Bench")
+            .env("GIT_COMMITTER_EMAIL", "bench@example.com")
+            .output()
+            .unwrap()
+            .status;
+        assert!(status.success(), "git {:?} failed", args);
+    };
+
+    git(&["init", "-q"]);
+    for i in 0..COMMITS {
+        for j in 0..3 {
+            let path = dir.join(format!("src/module{}/file{}.rs", i % 10, (i + j) % 25));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, format!("// change {} {}\n", i, j)).unwrap();
+        }
+        git(&["add", "-A"]);
+        git(&["commit", "-q", "-m", &format!("Change {}", i)]);
+    }
+    dir
+}
+
+fn synthetic_log(count: usize) -> String {
+    (0..count)
+        .map(|i| {
+            format!(
+                "{:040x}\x1f{:040x}\x1fAuthor {}\x1f2025-01-20T14:30:00+00:00\x1fCommit subject {}\x1e\n",
+                i, i + 1, i % 17, i
+            )
+        })
+        .collect()
+}
+
+fn synthetic_paths(count: usize) -> Vec<String> {
+    (0..count)
+        .map(|i| format!("src/area{}/sub{}/file{}.rs", i % 40, i % 7, i))
+        .collect()
+}
+
+fn discovery(c: &mut Criterion) {
+    let log = synthetic_log(5_000);
+    c.bench_function("discovery/parse_log 5k", |b| {
+        b.iter(|| discovery::parse_log(black_box(&log)).unwrap())
+    });
+
+    let name_status: String = synthetic_paths(10_000)
+        .iter()
+        .enumerate()
+        .map(|(i, path)| match i % 5 {
+            0 => format!("R087\told/{}\t{}\n", path, path),
+            1 => format!("A\t{}\n", path),
+            _ => format!("M\t{}\n", path),
+        })
+        .collect();
+    c.bench_function("discovery/parse_name_status 10k", |b| {
+        b.iter(|| discovery::parse_name_status(black_box(&name_status)))
+    });
+
+    let repo = scratch_repo();
+    std::env::set_current_dir(&repo).unwrap();
+
+    let mut group = c.benchmark_group("discovery/git");
+    group.sample_size(10);
+    group.bench_function("read_log + files", |b| {
+        b.iter(|| {
+            for entry in discovery::read_log(&["HEAD"]).unwrap() {
+                black_box(discovery::get_commit_files(&entry.hash, false).unwrap());
+            }
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("diff/git");
+    group.sample_size(10);
+    group.bench_function("patch_ids", |b| {
+        b.iter(|| discovery::patch_ids(&["log", "-p", "--no-merges", "HEAD"]).unwrap())
+    });
+    group.finish();
+
+    let _ = fs::remove_dir_all(&repo);
+}
+
+fn diff(c: &mut Criterion) {
+    let paths = synthetic_paths(10_000);
+
+    let owners = CodeOwners::parse(
+        "* @org/everyone\n\
+         /src/area1*/ @org/core\n\
+         *.md @org/docs\n\
+         src/**/sub3/ @alice\n\
+         /src/area39/ @bob bob@example.com\n",
+    );
+    c.bench_function("diff/codeowners 10k paths", |b| {
+        b.iter(|| owners.owners_of(paths.iter().map(String::as_str)))
+    });
+
+    let policy: Policy = toml::from_str(
+        r#"
+        allowed_paths = ["src/", "docs/**/*.md"]
+        forbidden_paths = ["src/area7/", "*.lock", "**/sub5/*.rs"]
+        max_files = 5000
+        "#,
+    )
+    .unwrap();
+    c.bench_function("diff/policy 10k paths", |b| {
+        b.iter(|| policy.violations(paths.iter().map(String::as_str), 50))
+    });
+}
+
+criterion_group!(benches, discovery, diff);
+criterion_main!(benches);
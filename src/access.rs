@@ -0,0 +1,274 @@
+use crate::forge;
+use crate::network;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+/// Where the chuck branch gets pushed
+#[derive(Debug, Clone, PartialEq)]
+pub enum PushAccess {
+    /// Straight to the template
+    Direct,
+    /// To the user's fork of the template, `owner/name`, with the PR opened across
+    Fork { repo: String },
+}
+
+impl PushAccess {
+    /// URL to push to, in the same style (SSH or HTTPS) as the template's
+    pub fn fork_url(&self, template_url: &str) -> Option<String> {
+        let PushAccess::Fork { repo } = self else {
+            return None;
+        };
+        Some(if template_url.starts_with("git@github.com:") {
+            format!("git@github.com:{}.git", repo)
+        } else {
+            format!("https://github.com/{}.git", repo)
+        })
+    }
+
+    /// `head` for the pull request: the branch name, prefixed with the fork's owner
+    pub fn pr_head(&self, branch: &str) -> String {
+        match self {
+            PushAccess::Direct => branch.to_string(),
+            PushAccess::Fork { repo } => {
+                let owner = repo.split('/').next().unwrap_or(repo);
+                format!("{}:{}", owner, branch)
+            }
+        }
+    }
+}
+
+/// Checks up front whether the user can push to the template. When they can't, finds
+/// their fork of it, creating one if they don't have one yet.
+pub fn preflight(template_repo: &str, verbose: bool) -> Result<PushAccess> {
+    if can_push(template_repo)? {
+        return Ok(PushAccess::Direct);
+    }
+
+    let user = login()?;
+    let name = template_repo.split('/').nth(1).unwrap_or(template_repo);
+    let fork = format!("{}/{}", user, name);
+    match parent_of(&fork)? {
+        Some(parent) if parent == template_repo => {
+            if verbose {
+                println!("🧔 VERBOSE: Using existing fork {}", fork);
+            }
+        }
+        Some(_) => {
+            return Err(anyhow!(
+                "You can't push to {} and {} isn't a fork of it. Fork {} yourself and push there.",
+                template_repo,
+                fork,
+                template_repo
+            ))
+        }
+        None => {
+            println!("🧔 Forking {} to {}...", template_repo, fork);
+            create_fork(template_repo)?;
+        }
+    }
+    Ok(PushAccess::Fork { repo: fork })
+}
+
+/// What's wrong with the token for pushing to `repo` and opening pull requests there,
+/// if anything. Only classic tokens say what they're scoped to; fine-grained ones and
+/// GitHub App tokens pass here and are left to the push.
+pub fn token_problem(repo: &str) -> Result<Option<String>> {
+    let context = "Failed to look up your GitHub user";
+    let response = forge::get("user", &[], context)?;
+    if response.status == 401 {
+        return Ok(Some(format!(
+            "GitHub doesn't take it: {}",
+            response.error()
+        )));
+    }
+    if !response.is_success() {
+        return Err(anyhow!(network::describe_failure(
+            context,
+            &response.error()
+        )));
+    }
+    let Some(scopes) = response.header("x-oauth-scopes") else {
+        return Ok(None);
+    };
+    let scopes: Vec<&str> = scopes
+        .split(',')
+        .map(str::trim)
+        .filter(|scope| !scope.is_empty())
+        .collect();
+    if scopes.contains(&"repo") || (scopes.contains(&"public_repo") && !is_private(repo)?) {
+        return Ok(None);
+    }
+    let has = if scopes.is_empty() {
+        "no scopes".to_string()
+    } else {
+        format!("only {}", scopes.join(", "))
+    };
+    Ok(Some(format!(
+        "your token has {}, and pushing to {} needs repo",
+        has, repo
+    )))
+}
+
+/// Whether the signed-in user has push permission on `repo`
+pub fn can_push(repo: &str) -> Result<bool> {
+    let json = forge::get_json(
+        &format!("repos/{}", repo),
+        &format!("Failed to look up {}", repo),
+    )?;
+    Ok(json.pointer("/permissions/push") == Some(&Value::Bool(true)))
+}
+
+/// Whether `repo` is private (or internal) rather than public
+pub fn is_private(repo: &str) -> Result<bool> {
+    let json = forge::get_json(
+        &format!("repos/{}", repo),
+        &format!("Failed to look up {}", repo),
+    )?;
+    json.get("private")
+        .and_then(Value::as_bool)
+        .ok_or_else(|| anyhow!("GitHub didn't say whether {} is private", repo))
+}
+
+fn login() -> Result<String> {
+    let json = forge::get_json("user", "Failed to look up your GitHub user")?;
+    json.get("login")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Failed to look up your GitHub user"))
+}
+
+/// The repository `repo` was forked from, `Some("")` when it isn't a fork and `None`
+/// when it doesn't exist
+fn parent_of(repo: &str) -> Result<Option<String>> {
+    let context = format!("Failed to look up {}", repo);
+    let response = forge::get(&format!("repos/{}", repo), &[], &context)?;
+    if response.status == 404 {
+        return Ok(None);
+    }
+    if !response.is_success() {
+        return Err(anyhow!(network::describe_failure(
+            &context,
+            &response.error()
+        )));
+    }
+    let json = response.json()?;
+    Ok(Some(
+        json.pointer("/parent/full_name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+    ))
+}
+
+fn create_fork(template_repo: &str) -> Result<()> {
+    forge::send(
+        "POST",
+        &format!("repos/{}/forks", template_repo),
+        &serde_json::json!({}),
+        &format!("Failed to fork {}", template_repo),
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    fn without_push() -> FakeRunner {
+        FakeRunner::new()
+            .on(
+                "gh api repos/acme/template",
+                r#"{"permissions":{"push":false}}"#,
+            )
+            .on("gh api user", r#"{"login":"jane"}"#)
+    }
+
+    #[test]
+    fn checks_the_token_can_push() {
+        let scoped = |scopes: &str| {
+            format!(
+                "HTTP/2.0 200 OK\r\nX-Oauth-Scopes: {}\r\n\r\n{{\"login\":\"jane\"}}",
+                scopes
+            )
+        };
+        let problem = |fake: FakeRunner| {
+            process::with_runner(Rc::new(fake), || token_problem("acme/template")).unwrap()
+        };
+        let public = r#"{"private":false}"#;
+
+        assert_eq!(
+            problem(FakeRunner::new().on("gh api user", &scoped("repo, workflow"))),
+            None
+        );
+        assert_eq!(
+            problem(
+                FakeRunner::new()
+                    .on("gh api user", &scoped("public_repo"))
+                    .on("gh api repos/acme/template", public)
+            ),
+            None
+        );
+        assert_eq!(
+            problem(FakeRunner::new().on("gh api user", &scoped("read:org, gist"))),
+            Some(
+                "your token has only read:org, gist, and pushing to acme/template needs repo"
+                    .to_string()
+            )
+        );
+        // Fine-grained tokens don't say
+        assert_eq!(
+            problem(FakeRunner::new().on("gh api user", r#"{"login":"jane"}"#)),
+            None
+        );
+    }
+
+    #[test]
+    fn pushes_directly_with_write_access() {
+        let fake = Rc::new(FakeRunner::new().on(
+            "gh api repos/acme/template",
+            r#"{"permissions":{"push":true}}"#,
+        ));
+        let access = process::with_runner(fake.clone(), || preflight("acme/template", false));
+
+        assert_eq!(access.unwrap(), PushAccess::Direct);
+        assert_eq!(fake.calls().len(), 1);
+    }
+
+    #[test]
+    fn uses_an_existing_fork() {
+        let fake = Rc::new(without_push().on(
+            "gh api repos/jane/template",
+            r#"{"parent":{"full_name":"acme/template"}}"#,
+        ));
+        let access = process::with_runner(fake.clone(), || preflight("acme/template", false));
+
+        let access = access.unwrap();
+        assert_eq!(
+            access,
+            PushAccess::Fork {
+                repo: "jane/template".to_string()
+            }
+        );
+        assert_eq!(access.pr_head("chuck-from-x"), "jane:chuck-from-x");
+        assert_eq!(
+            access.fork_url("git@github.com:acme/template.git").unwrap(),
+            "git@github.com:jane/template.git"
+        );
+        assert!(fake.calls_to("gh api repos/acme/template/forks").is_empty());
+    }
+
+    #[test]
+    fn forks_when_there_is_no_fork_yet() {
+        let fake =
+            Rc::new(without_push().fail("gh api repos/jane/template", "gh: Not Found (HTTP 404)"));
+        let access = process::with_runner(fake.clone(), || preflight("acme/template", false));
+
+        assert!(matches!(access.unwrap(), PushAccess::Fork { .. }));
+        assert_eq!(
+            fake.calls_to("gh api repos/acme/template/forks"),
+            ["gh api repos/acme/template/forks --include --method POST --input -"]
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::forge;
+use crate::network;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::process::Command;
+
+/// A commit the project could contribute, as listed in the summary
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub short_hash: String,
+    pub message: String,
+    pub author: String,
+    pub files: usize,
+    /// Things a reviewer should know, e.g. "likely a template fix"
+    pub notes: Vec<String>,
+}
+
+/// Commits left out of the list and why, for the line under it
+#[derive(Debug, Default)]
+pub struct Skipped {
+    pub upstream: usize,
+    pub proposed: usize,
+    pub project_only: usize,
+}
+
+/// The Markdown report for the job summary and the tracking issue
+pub fn summary(repo: &str, template: &str, candidates: &[Candidate], skipped: &Skipped) -> String {
+    let mut markdown = if candidates.is_empty() {
+        format!(
+            "## 🧔 {} has nothing new to contribute to {}\n",
+            repo, template
+        )
+    } else {
+        format!(
+            "## 🧔 {} could contribute {} commit{} to {}\n\n\
+             | Commit | Message | Author | Files | Notes |\n\
+             | --- | --- | --- | --- | --- |\n",
+            repo,
+            candidates.len(),
+            if candidates.len() == 1 { "" } else { "s" },
+            template
+        )
+    };
+    for candidate in candidates {
+        markdown.push_str(&format!(
+            "| `{}` | {} | {} | {} | {} |\n",
+            candidate.short_hash,
+            escape(&candidate.message),
+            escape(&candidate.author),
+            candidate.files,
+            escape(&candidate.notes.join(", "))
+        ));
+    }
+
+    let skipped: Vec<String> = [
+        (skipped.upstream, "already in the template"),
+        (skipped.proposed, "already proposed"),
+        (skipped.project_only, "project-only"),
+    ]
+    .iter()
+    .filter(|(count, _)| *count > 0)
+    .map(|(count, what)| format!("{} {}", count, what))
+    .collect();
+    if !skipped.is_empty() {
+        markdown.push_str(&format!("\nNot listed: {}.\n", skipped.join(", ")));
+    }
+    if !candidates.is_empty() {
+        markdown.push_str("\nRun `chuck` in a checkout of the project to pick and propose them.\n");
+    }
+    markdown
+}
+
+/// Table cells can't hold pipes or newlines
+fn escape(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+/// Appends `markdown` to the job summary, or prints it outside of GitHub Actions
+pub fn write_job_summary(markdown: &str) -> Result<()> {
+    let Some(path) = env::var_os("GITHUB_STEP_SUMMARY") else {
+        println!("\n{}", markdown);
+        return Ok(());
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", markdown)?;
+    println!("🧔 Wrote the job summary");
+    Ok(())
+}
+
+/// Sets the step's `candidates` output for later steps to branch on
+pub fn write_output(candidates: usize) -> Result<()> {
+    if let Some(path) = env::var_os("GITHUB_OUTPUT") {
+        let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+        writeln!(file, "candidates={}", candidates)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+struct Issue {
+    number: u64,
+    title: String,
+}
+
+/// Title of the template issue that tracks what `repo` could contribute
+pub fn tracking_issue_title(repo: &str) -> String {
+    format!("Upstreamable commits from {}", repo)
+}
+
+/// Puts `markdown` in the template's open tracking issue for `repo`, opening one if
+/// there are commits to list and there's none yet. Returns the issue's URL or number.
+pub fn update_tracking_issue(
+    template: &str,
+    repo: &str,
+    markdown: &str,
+    has_candidates: bool,
+) -> Result<Option<String>> {
+    forge::require_gh("--tracking-issue")?;
+    let title = tracking_issue_title(repo);
+    let output = gh(&[
+        "issue",
+        "list",
+        "--repo",
+        template,
+        "--state",
+        "open",
+        "--search",
+        &format!("\"{}\" in:title", title),
+        "--json",
+        "number,title",
+    ])?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(network::describe_failure(
+            "Failed to look for the tracking issue",
+            &error
+        )));
+    }
+    let issues: Vec<Issue> = serde_json::from_slice(&output.stdout)?;
+
+    // The search is fuzzy, so insist on the exact title
+    let output = match issues.iter().find(|issue| issue.title == title) {
+        Some(issue) => gh(&[
+            "issue",
+            "edit",
+            &issue.number.to_string(),
+            "--repo",
+            template,
+            "--body",
+            markdown,
+        ])?,
+        None if has_candidates => gh(&[
+            "issue", "create", "--repo", template, "--title", &title, "--body", markdown,
+        ])?,
+        None => return Ok(None),
+    };
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(network::describe_failure(
+            "Failed to update the tracking issue",
+            &error
+        )));
+    }
+
+    // gh prints the issue's URL as the last line
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .last()
+        .map(|line| line.trim().to_string()))
+}
+
+fn gh(args: &[&str]) -> Result<std::process::Output> {
+    Command::new("gh").args(args).output_network().map_err(|e| {
+        process::command_error(e, "GitHub CLI not found. Install with: brew install gh")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    fn candidate(message: &str, notes: &[&str]) -> Candidate {
+        Candidate {
+            short_hash: "abc1234".to_string(),
+            message: message.to_string(),
+            author: "Jane Doe".to_string(),
+            files: 2,
+            notes: notes.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn lists_candidates_in_a_table() {
+        let markdown = summary(
+            "acme/app",
+            "acme/template",
+            &[candidate("Fix a | b", &["likely a template fix"])],
+            &Skipped {
+                upstream: 2,
+                proposed: 0,
+                project_only: 1,
+            },
+        );
+
+        assert!(markdown.starts_with("## 🧔 acme/app could contribute 1 commit to acme/template\n"));
+        assert!(markdown
+            .contains("| `abc1234` | Fix a \\| b | Jane Doe | 2 | likely a template fix |\n"));
+        assert!(markdown.contains("Not listed: 2 already in the template, 1 project-only."));
+    }
+
+    #[test]
+    fn updates_the_open_tracking_issue() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on(
+                    "gh issue list",
+                    r#"[{"number":3,"title":"Upstreamable commits from acme/app-old"},{"number":7,"title":"Upstreamable commits from acme/app"}]"#,
+                )
+                .on("gh issue edit", "https://github.com/acme/template/issues/7\n"),
+        );
+
+        let url = process::with_runner(fake.clone(), || {
+            update_tracking_issue("acme/template", "acme/app", "body", true).unwrap()
+        });
+
+        assert_eq!(
+            url.as_deref(),
+            Some("https://github.com/acme/template/issues/7")
+        );
+        assert_eq!(
+            fake.calls_to("gh issue edit"),
+            ["gh issue edit 7 --repo acme/template --body body"]
+        );
+        assert!(fake.calls_to("gh issue create").is_empty());
+    }
+
+    #[test]
+    fn opens_an_issue_only_when_there_is_something_to_list() {
+        let fake = Rc::new(FakeRunner::new().on("gh issue list", "[]"));
+
+        process::with_runner(fake.clone(), || {
+            update_tracking_issue("acme/template", "acme/app", "nothing", false).unwrap();
+            update_tracking_issue("acme/template", "acme/app", "body", true).unwrap();
+        });
+
+        assert_eq!(
+            fake.calls_to("gh issue create"),
+            ["gh issue create --repo acme/template --title Upstreamable commits from acme/app --body body"]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// How many template commits back `closest_base` looks, along the first-parent line
+const SEARCH_DEPTH: usize = 500;
+
+const NO_BLOB: &str = "0000000000000000000000000000000000000000";
+
+/// Blob SHA of every file in a tree, by path
+pub type Blobs = HashMap<String, String>;
+
+/// How much of a template commit's tree a project still has as it was
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub commit: String,
+    /// Template files the project has byte for byte
+    pub identical: usize,
+    /// Template files the project changed or doesn't have
+    pub differing: usize,
+}
+
+impl Match {
+    fn compare(commit: &str, project: &Blobs, template: &Blobs) -> Self {
+        let identical = template
+            .iter()
+            .filter(|(path, blob)| project.get(*path) == Some(blob))
+            .count();
+        Self {
+            commit: commit.to_string(),
+            identical,
+            differing: template.len() - identical,
+        }
+    }
+
+    /// More files in common, or as many and fewer the project would lack
+    fn better_than(&self, other: &Match) -> bool {
+        (self.identical, other.differing) > (other.identical, self.differing)
+    }
+}
+
+/// Every file in `rev`'s tree with its blob
+pub fn blobs(rev: &str) -> Result<Blobs> {
+    let output = Command::new("git")
+        .args(["ls-tree", "-r", "-z", rev])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git ls-tree"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to list files in {}: {}", rev, error.trim()));
+    }
+
+    // "<mode> <type> <sha>\t<path>"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter_map(|entry| {
+            let (info, path) = entry.split_once('\t')?;
+            let sha = info.split(' ').nth(2)?;
+            Some((path.to_string(), sha.to_string()))
+        })
+        .collect())
+}
+
+/// The commit on `tip`'s first-parent line whose tree `project` is closest to, the
+/// newest of equally close ones. `None` when they have no file in common.
+pub fn closest_base(tip: &str, project: &Blobs) -> Result<Option<Match>> {
+    let output = Command::new("git")
+        .args([
+            "rev-list",
+            "--first-parent",
+            &format!("--max-count={}", SEARCH_DEPTH),
+            tip,
+        ])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-list"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to list {}: {}", tip, error.trim()));
+    }
+
+    let mut best: Option<Match> = None;
+    for commit in String::from_utf8_lossy(&output.stdout).lines() {
+        let candidate = Match::compare(commit, project, &blobs(commit)?);
+        if best.as_ref().is_none_or(|best| candidate.better_than(best)) {
+            best = Some(candidate);
+        }
+    }
+    Ok(best.filter(|best| best.identical > 0))
+}
+
+/// `(hash, subject)` of the project's commits that only brought in files exactly as
+/// `base` has them, like the commit a copy of the template started with
+pub fn copied_commits(base: &Blobs) -> Result<Vec<(String, String)>> {
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "core.quotePath=false",
+            "log",
+            "--no-merges",
+            "--no-renames",
+            "--raw",
+            "--no-abbrev",
+            "--format=%x1e%H%x1f%s",
+        ])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to read the history: {}", error.trim()));
+    }
+
+    Ok(parse_copied(&String::from_utf8_lossy(&output.stdout), base))
+}
+
+fn parse_copied(log: &str, base: &Blobs) -> Vec<(String, String)> {
+    let mut copied = Vec::new();
+    for record in log.split('\x1e').filter(|r| !r.trim().is_empty()) {
+        let mut lines = record.lines();
+        let Some((hash, subject)) = lines.next().and_then(|l| l.split_once('\x1f')) else {
+            continue;
+        };
+        // ":<old mode> <new mode> <old sha> <new sha> <status>\t<path>"
+        let changes: Vec<Option<(&str, &str)>> = lines
+            .filter(|l| l.starts_with(':'))
+            .map(|l| {
+                let (info, path) = l.split_once('\t')?;
+                Some((path, info.split(' ').nth(3)?))
+            })
+            .collect();
+        let from_base = |change: &Option<(&str, &str)>| {
+            change.is_some_and(|(path, blob)| {
+                blob != NO_BLOB && base.get(path).is_some_and(|b| b == blob)
+            })
+        };
+        if !changes.is_empty() && changes.iter().all(from_base) {
+            copied.push((hash.to_string(), subject.to_string()));
+        }
+    }
+    copied
+}
+
+/// A `.chuckrc` for a project copied from the template at `url`, pinned to `base`
+/// when it's known
+pub fn config(url: &str, base: Option<&str>, target: Option<&str>) -> String {
+    let quote = |s: &str| toml::Value::String(s.to_string()).to_string();
+    let mut config = format!("[template]\nurl = {}\n", quote(url));
+    if let Some(base) = base {
+        config.push_str("# The template commit this project was copied from\n");
+        config.push_str(&format!("base = {}\n", quote(base)));
+    }
+    if let Some(target) = target {
+        config.push_str(&format!("\n[pr]\ntarget = {}\n", quote(target)));
+    }
+    config.push_str("\n[state]\nshared = true\n");
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    fn tree(files: &[(&str, &str)]) -> Blobs {
+        files
+            .iter()
+            .map(|(path, blob)| (path.to_string(), blob.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn picks_the_template_commit_the_project_is_closest_to() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git rev-list", "ccc\nbbb\naaa\n")
+                .on(
+                    "git ls-tree -r -z ccc",
+                    "100644 blob r2\tREADME.md\x00100644 blob l1\tsrc/lib.rs\x00100644 blob n1\tsrc/new.rs\0",
+                )
+                .on(
+                    "git ls-tree -r -z bbb",
+                    "100644 blob r1\tREADME.md\x00100644 blob l1\tsrc/lib.rs\0",
+                )
+                .on("git ls-tree -r -z aaa", "100644 blob r1\tREADME.md\0"),
+        );
+        let project = tree(&[("README.md", "r1"), ("src/lib.rs", "l1"), ("app.rs", "a1")]);
+
+        let best = process::with_runner(fake, || closest_base("template/main", &project));
+        assert_eq!(
+            best.unwrap(),
+            Some(Match {
+                commit: "bbb".to_string(),
+                identical: 2,
+                differing: 0
+            })
+        );
+    }
+
+    #[test]
+    fn finds_commits_that_only_copied_template_files() {
+        let base = tree(&[("README.md", "r1"), ("src/lib.rs", "l1")]);
+        let log = format!(
+            "\x1eeee\x1fAdd app\n\n:000000 100644 {0} a1 A\tapp.rs\n\
+             \x1eddd\x1fCopy the template\n\n:000000 100644 {0} r1 A\tREADME.md\n:000000 100644 {0} l1 A\tsrc/lib.rs\n\
+             \x1eccc\x1fDrop the lib\n\n:100644 000000 l1 {0} D\tsrc/lib.rs\n",
+            NO_BLOB
+        );
+        assert_eq!(
+            parse_copied(&log, &base),
+            [("ddd".to_string(), "Copy the template".to_string())]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+use crate::forge::{self, Response};
+use crate::journal;
+use crate::network;
+use crate::paths;
+use crate::process::{self, Pending};
+use crate::screen;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// A response kept in `.git/chuck/api-cache` to revalidate next time
+#[derive(Debug, Deserialize, Serialize)]
+struct Cached {
+    path: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    fetched_at: DateTime<Utc>,
+    body: String,
+}
+
+/// What `prefetch` is getting or got
+enum Prefetched {
+    Running(Pending<Result<String, String>>),
+    Done(String),
+}
+
+/// Responses asked for ahead of time, by path, kept for the rest of the run
+static PREFETCHED: Mutex<Vec<(String, Prefetched)>> = Mutex::new(Vec::new());
+
+/// Starts getting `path` in the background, so a `get` of it later in the run picks
+/// the response up instead of waiting for GitHub then. Faked and recorded runs ask
+/// when asked, so they run the same commands in the same order.
+pub fn prefetch(path: &str) {
+    if process::is_faked() || process::is_observed() {
+        return;
+    }
+    let owned = path.to_string();
+    let pending = process::in_background(move || {
+        fetch(&owned, "Failed to prefetch").map_err(|e| e.to_string())
+    });
+    if let Ok(mut prefetched) = PREFETCHED.lock() {
+        prefetched.push((path.to_string(), Prefetched::Running(pending)));
+    }
+}
+
+/// The response `prefetch` got for `path`, waiting for it if it's still coming
+fn prefetched(path: &str) -> Option<String> {
+    let entry = {
+        let mut prefetched = PREFETCHED.lock().ok()?;
+        let i = prefetched.iter().position(|(p, _)| p == path)?;
+        prefetched.swap_remove(i).1
+    };
+    // A failed prefetch gets asked again, for the caller's own error
+    let body = match entry {
+        Prefetched::Running(pending) => pending.wait().ok()?,
+        Prefetched::Done(body) => body,
+    };
+    if let Ok(mut prefetched) = PREFETCHED.lock() {
+        prefetched.push((path.to_string(), Prefetched::Done(body.clone())));
+    }
+    Some(body)
+}
+
+/// GETs `path` from the GitHub REST API through the `forge` backend, e.g.
+/// `repos/acme/app/commits`, returning the JSON body. Responses are kept in
+/// `.git/chuck/api-cache` and revalidated with their ETag or Last-Modified date;
+/// an unchanged one comes back as a 304, which GitHub doesn't count against the
+/// rate limit. Once the limit is used up anyway, the kept response is used as is.
+/// `context` starts the error message.
+pub fn get(path: &str, context: &str) -> Result<String> {
+    match prefetched(path) {
+        Some(body) => Ok(body),
+        None => fetch(path, context),
+    }
+}
+
+fn fetch(path: &str, context: &str) -> Result<String> {
+    let cache_path = cache_path(path).ok();
+    let cached = cache_path.as_ref().and_then(|file| {
+        let content = fs::read_to_string(file).ok()?;
+        serde_json::from_str::<Cached>(&content)
+            .ok()
+            .filter(|cached| cached.path == path)
+    });
+
+    let mut headers = Vec::new();
+    if let Some(cached) = &cached {
+        if let Some(etag) = &cached.etag {
+            headers.push(format!("If-None-Match: {}", etag));
+        } else if let Some(date) = &cached.last_modified {
+            headers.push(format!("If-Modified-Since: {}", date));
+        }
+    }
+    let response = forge::get(path, &headers, context)?;
+
+    match cached {
+        Some(cached) if response.status == 304 => return Ok(cached.body),
+        Some(cached) if response.rate_limited() => {
+            screen::say(&format!(
+                "🧔 GitHub's API rate limit is used up{}, going with {} as of {}",
+                response
+                    .reset()
+                    .map(|reset| format!(" until it resets {}", reset))
+                    .unwrap_or_default(),
+                path,
+                cached
+                    .fetched_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+            ));
+            return Ok(cached.body);
+        }
+        _ if response.rate_limited() => {
+            return Err(anyhow!(
+                "{}: GitHub's API rate limit is used up{}",
+                context,
+                response
+                    .reset()
+                    .map(|reset| format!(", it resets {}", reset))
+                    .unwrap_or_default()
+            ));
+        }
+        _ if response.is_success() => {
+            if let Some(file) = cache_path {
+                save(&file, path, &response);
+            }
+            return Ok(response.body);
+        }
+        _ => {}
+    }
+
+    Err(anyhow!(network::describe_failure(
+        context,
+        &response.error()
+    )))
+}
+
+/// Where the response for `path` is kept, one file per URL
+fn cache_path(path: &str) -> Result<PathBuf> {
+    let name: String = path
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    Ok(paths::cache_dir(&journal::git_dir()?).join(format!("{}.json", name)))
+}
+
+/// Keeps `response` if it can be revalidated. The cache is only a saving, so
+/// failing to write it isn't worth mentioning.
+fn save(file: &Path, path: &str, response: &Response) {
+    let etag = response.header("etag").map(str::to_string);
+    let last_modified = response.header("last-modified").map(str::to_string);
+    if etag.is_none() && last_modified.is_none() {
+        return;
+    }
+    let cached = Cached {
+        path: path.to_string(),
+        etag,
+        last_modified,
+        fetched_at: Utc::now(),
+        body: response.body.clone(),
+    };
+    if let (Some(dir), Ok(content)) = (file.parent(), serde_json::to_string(&cached)) {
+        let _ = fs::create_dir_all(dir).and_then(|_| fs::write(file, content));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    #[test]
+    fn revalidates_the_kept_response_with_its_etag() {
+        let dir = std::env::temp_dir().join(format!("chuck-api-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let git_dir = format!("{}\n", dir.display());
+        let path = "repos/acme/template/commits/main";
+
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git rev-parse --git-dir", &git_dir)
+                .on(
+                    "gh api",
+                    "HTTP/2.0 200 OK\r\nEtag: W/\"abc\"\r\n\r\n{\"sha\":\"1234\"}",
+                ),
+        );
+        let body = process::with_runner(fake, || get(path, "Failed"));
+        assert_eq!(body.unwrap(), "{\"sha\":\"1234\"}");
+
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git rev-parse --git-dir", &git_dir)
+                .on("gh api", "HTTP/2.0 304 Not Modified\r\n\r\n"),
+        );
+        let body = process::with_runner(fake.clone(), || get(path, "Failed"));
+        assert_eq!(body.unwrap(), "{\"sha\":\"1234\"}");
+        assert_eq!(
+            fake.calls_to("gh"),
+            ["gh api repos/acme/template/commits/main --include -H If-None-Match: W/\"abc\""]
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn says_when_the_rate_limit_is_used_up() {
+        let fake = Rc::new(FakeRunner::new().fail("git rev-parse", "").on(
+            "gh api",
+            "HTTP/2.0 403 Forbidden\nX-Ratelimit-Remaining: 0\nX-Ratelimit-Reset: 1737374400\n\n{\"message\":\"API rate limit exceeded\"}",
+        ));
+        let error = process::with_runner(fake, || get("repos/acme/app/commits", "Failed"));
+        assert!(error
+            .unwrap_err()
+            .to_string()
+            .starts_with("Failed: GitHub's API rate limit is used up, it resets at "));
+    }
+}
@@ -0,0 +1,134 @@
+use crate::network;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::process::Command;
+
+/// How far a downstream project has caught up with the template
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Synced {
+    /// The last template commit in its history, when it was cloned or merges the template
+    Commit(String),
+    /// When it started, for a project generated from the template with its own history
+    Date(String),
+}
+
+/// The branch `url`'s HEAD points at, which is where its pull requests go
+pub fn default_branch(url: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["ls-remote", "--symref", url, "HEAD"])
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to execute git ls-remote"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(network::describe_failure(
+            &format!("Failed to reach {}", url),
+            &error
+        )));
+    }
+
+    // ref: refs/heads/main	HEAD
+    String::from_utf8(output.stdout)?
+        .lines()
+        .find_map(|line| {
+            line.strip_prefix("ref: refs/heads/")?
+                .split_once('\t')
+                .map(|(branch, _)| branch.to_string())
+        })
+        .ok_or_else(|| anyhow!("{} has no default branch", url))
+}
+
+/// Where `downstream` last took the template in from `template_head`'s history
+pub fn synced(template_head: &str, downstream: &str) -> Result<Synced> {
+    let base = git(&["merge-base", template_head, downstream]).unwrap_or_default();
+    if !base.is_empty() {
+        return Ok(Synced::Commit(base));
+    }
+    let roots = git(&["log", "--max-parents=0", "--format=%aI", downstream])?;
+    roots
+        .lines()
+        .min()
+        .map(|date| Synced::Date(date.to_string()))
+        .ok_or_else(|| anyhow!("{} has no commits", downstream))
+}
+
+/// Template commits on `template_head` that `synced` doesn't have, newest first
+pub fn candidates(template_head: &str, synced: &Synced) -> Result<Vec<String>> {
+    let output = match synced {
+        Synced::Commit(base) => git(&["rev-list", &format!("{}..{}", base, template_head)])?,
+        Synced::Date(date) => git(&["rev-list", &format!("--since={}", date), template_head])?,
+    };
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// Directories holding a `.chuckrc` in `downstream`, with its contents, `""` for the root
+pub fn chuckrc_files(downstream: &str) -> Result<Vec<(String, String)>> {
+    git(&["ls-tree", "-r", "--name-only", downstream])?
+        .lines()
+        .filter(|path| *path == ".chuckrc" || path.ends_with("/.chuckrc"))
+        .map(|path| {
+            let dir = path.strip_suffix(".chuckrc").unwrap_or(path);
+            let content = git(&["show", &format!("{}:{}", downstream, path)])?;
+            Ok((dir.trim_end_matches('/').to_string(), content))
+        })
+        .collect()
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn reads_the_default_branch() {
+        let fake = Rc::new(FakeRunner::new().on(
+            "git ls-remote --symref",
+            "ref: refs/heads/trunk\tHEAD\n1234567890abcdef\tHEAD\n",
+        ));
+        let branch =
+            process::with_runner(fake, || default_branch("https://github.com/acme/app.git"));
+        assert_eq!(branch.unwrap(), "trunk");
+    }
+
+    #[test]
+    fn falls_back_to_when_a_generated_project_started() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .fail("git merge-base", "")
+                .on(
+                    "git log --max-parents=0",
+                    "2025-01-09T12:00:00+00:00\n2025-01-03T12:00:00+00:00\n",
+                )
+                .on("git rev-list", "bbb2222\naaa1111\n"),
+        );
+        let (synced, commits) = process::with_runner(fake.clone(), || {
+            let synced = synced("HEAD", "FETCH_HEAD").unwrap();
+            let commits = candidates("HEAD", &synced).unwrap();
+            (synced, commits)
+        });
+
+        assert_eq!(
+            synced,
+            Synced::Date("2025-01-03T12:00:00+00:00".to_string())
+        );
+        assert_eq!(commits, ["bbb2222", "aaa1111"]);
+        assert_eq!(
+            fake.calls_to("git rev-list"),
+            ["git rev-list --since=2025-01-03T12:00:00+00:00 HEAD"]
+        );
+    }
+}
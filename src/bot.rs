@@ -0,0 +1,166 @@
+use crate::glob::gitignore_match;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// `[bot]` in `.chuckrc`: the commits `chuck bot` contributes without asking. Every
+/// kind of rule that's set has to match, any one entry within a kind will do.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct BotRules {
+    /// Every file the commit touches matches one of these (gitignore-style)
+    pub paths: Vec<String>,
+    /// Author name or email
+    pub authors: Vec<String>,
+    /// Trailers such as `Upstream: yes`, or just `Upstream` for any value
+    pub trailers: Vec<String>,
+}
+
+/// What the rules look at beyond the commit's files
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CommitMeta {
+    pub author_name: String,
+    pub author_email: String,
+    /// `(key, value)` of the message's trailers
+    pub trailers: Vec<(String, String)>,
+}
+
+impl BotRules {
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && self.authors.is_empty() && self.trailers.is_empty()
+    }
+
+    pub fn matches(&self, files: &[&str], meta: &CommitMeta) -> bool {
+        let paths = self.paths.is_empty()
+            || (!files.is_empty()
+                && files.iter().all(|file| {
+                    self.paths
+                        .iter()
+                        .any(|pattern| gitignore_match(pattern, file))
+                }));
+        let authors = self.authors.is_empty()
+            || self.authors.iter().any(|author| {
+                author.eq_ignore_ascii_case(&meta.author_name)
+                    || author.eq_ignore_ascii_case(&meta.author_email)
+            });
+        let trailers = self.trailers.is_empty()
+            || self
+                .trailers
+                .iter()
+                .any(|rule| has_trailer(&meta.trailers, rule));
+        !self.is_empty() && paths && authors && trailers
+    }
+}
+
+fn has_trailer(trailers: &[(String, String)], rule: &str) -> bool {
+    let (key, value) = match rule.split_once(':') {
+        Some((key, value)) => (key.trim(), Some(value.trim())),
+        None => (rule.trim(), None),
+    };
+    trailers.iter().any(|(k, v)| {
+        k.eq_ignore_ascii_case(key) && value.is_none_or(|value| v.eq_ignore_ascii_case(value))
+    })
+}
+
+/// Author and trailers of each of `hashes`, by hash
+pub fn commit_meta(hashes: &[&str]) -> Result<HashMap<String, CommitMeta>> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let output = Command::new("git")
+        .args([
+            "show",
+            "-s",
+            "--no-walk",
+            "--format=%H%x1f%an%x1f%ae%x1f%(trailers:only,unfold)%x1e",
+        ])
+        .args(hashes)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git show"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to read commit trailers: {}", error.trim()));
+    }
+    Ok(parse_meta(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub fn parse_meta(output: &str) -> HashMap<String, CommitMeta> {
+    output
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').split('\x1f');
+            let hash = fields.next()?.to_string();
+            let author_name = fields.next()?.to_string();
+            let author_email = fields.next()?.to_string();
+            let trailers = fields
+                .next()?
+                .lines()
+                .filter_map(|line| line.split_once(':'))
+                .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+                .collect();
+            Some((
+                hash,
+                CommitMeta {
+                    author_name,
+                    author_email,
+                    trailers,
+                },
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn meta(trailers: &[(&str, &str)]) -> CommitMeta {
+        CommitMeta {
+            author_name: "Jane Doe".to_string(),
+            author_email: "jane@example.com".to_string(),
+            trailers: trailers
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn needs_every_kind_of_rule_to_match() {
+        let rules = BotRules {
+            paths: vec!["src/shared/".to_string()],
+            authors: vec!["jane@example.com".to_string()],
+            trailers: vec!["Upstream: yes".to_string()],
+        };
+        let tagged = meta(&[("upstream", "Yes")]);
+
+        assert!(rules.matches(&["src/shared/auth.rs"], &tagged));
+        assert!(!rules.matches(&["src/shared/auth.rs", "src/app.rs"], &tagged));
+        assert!(!rules.matches(&["src/shared/auth.rs"], &meta(&[("Upstream", "no")])));
+        assert!(!BotRules::default().matches(&["src/shared/auth.rs"], &tagged));
+
+        let any_value = BotRules {
+            trailers: vec!["Upstream".to_string()],
+            ..BotRules::default()
+        };
+        assert!(any_value.matches(&[], &meta(&[("Upstream", "later")])));
+    }
+
+    #[test]
+    fn reads_authors_and_trailers() {
+        let output = "aaa\x1fJane Doe\x1fjane@example.com\x1fUpstream: yes\nSigned-off-by: Jane <jane@example.com>\n\x1e\nbbb\x1fJoe\x1fjoe@example.com\x1f\x1e\n";
+        let meta = parse_meta(output);
+
+        assert_eq!(meta.len(), 2);
+        assert_eq!(
+            meta["aaa"].trailers[0],
+            ("Upstream".to_string(), "yes".to_string())
+        );
+        assert_eq!(meta["aaa"].trailers.len(), 2);
+        assert_eq!(meta["bbb"].author_email, "joe@example.com");
+        assert!(meta["bbb"].trailers.is_empty());
+    }
+}
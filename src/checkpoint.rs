@@ -0,0 +1,122 @@
+use crate::journal;
+use crate::process::{self, CommandExt};
+use crate::read_only;
+use crate::selection::Step;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const CHECKPOINT_FILE: &str = "checkpoint.toml";
+
+/// Plans longer than this are checkpointed, about this many steps apart
+pub const EVERY: usize = 10;
+
+const WIP_PREFIX: &str = "chuck/wip-";
+
+/// How far a branch build got, in `.git/chuck/checkpoint.toml`. The branch
+/// `chuck/wip-<N>` holds the first N steps applied, so building the same plan again
+/// starts from there.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Checkpoint {
+    /// Template commit the branch was built on
+    pub base: String,
+    pub branch: String,
+    /// The steps applied, in order
+    pub steps: Vec<Step>,
+}
+
+impl Checkpoint {
+    pub fn path() -> Result<PathBuf> {
+        Ok(journal::chuck_dir()?.join(CHECKPOINT_FILE))
+    }
+
+    /// `None` when no build has stopped partway
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map(Some)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    /// Points `chuck/wip-<N>` at `head`, which has `steps` applied on `base`, and
+    /// records it
+    pub fn save(base: &str, steps: &[Step], head: &str) -> Result<Self> {
+        let checkpoint = Self {
+            base: base.to_string(),
+            branch: format!("{}{}", WIP_PREFIX, steps.len()),
+            steps: steps.to_vec(),
+        };
+        let output = Command::new("git")
+            .args(["branch", "-f", &checkpoint.branch, head])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git branch"))?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "Failed to create {}: {}",
+                checkpoint.branch,
+                error.trim()
+            ));
+        }
+
+        let path = Self::path()?;
+        if read_only::withheld(&format!("written {}", path.display())) {
+            return Ok(checkpoint);
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(&checkpoint)?)?;
+        Ok(checkpoint)
+    }
+
+    /// How many of `steps` on `base` this has applied already, 0 when it's from
+    /// building something else
+    pub fn covers(&self, base: &str, steps: &[Step]) -> usize {
+        if self.base == base && steps.starts_with(&self.steps) {
+            self.steps.len()
+        } else {
+            0
+        }
+    }
+
+    /// Forgets the checkpoint and deletes every `chuck/wip-*` branch
+    pub fn clear() -> Result<()> {
+        let output = Command::new("git")
+            .args([
+                "for-each-ref",
+                "--format=%(refname:short)",
+                &format!("refs/heads/{}*", WIP_PREFIX),
+            ])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git for-each-ref"))?;
+        let branches = String::from_utf8_lossy(&output.stdout);
+        let branches: Vec<&str> = branches.lines().filter(|b| !b.is_empty()).collect();
+        if !branches.is_empty() {
+            let output = Command::new("git")
+                .args(["branch", "-D"])
+                .args(&branches)
+                .output_local()
+                .map_err(|e| process::command_error(e, "Failed to execute git branch"))?;
+
+            if !output.status.success() {
+                let error = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow!(
+                    "Failed to delete the checkpoints: {}",
+                    error.trim()
+                ));
+            }
+        }
+
+        match fs::remove_file(Self::path()?) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
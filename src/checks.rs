@@ -0,0 +1,183 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::process::Command;
+
+/// `[checks]` in .chuckrc: the template's own formatter and linter, run on the chuck
+/// branch so contributions match its style without cleaning them up by hand
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ChecksConfig {
+    /// Formats the work tree in place, e.g. "cargo fmt"
+    pub format: Option<String>,
+    /// Fixes what it can in place, e.g. "eslint --fix ."
+    pub lint: Option<String>,
+    /// Amend each pick with what the checks change in it, instead of one commit on top
+    #[serde(default)]
+    pub amend: bool,
+}
+
+impl ChecksConfig {
+    /// The configured commands, formatter first
+    pub fn commands(&self) -> Vec<&str> {
+        [&self.format, &self.lint]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands().is_empty()
+    }
+}
+
+/// Runs the checks on the work tree and commits what they change in the files changed
+/// since `since`, amending HEAD or as a commit of its own. What they change elsewhere
+/// is thrown away, that's not the contribution's to fix. Returns how many files the
+/// checks changed.
+pub fn apply(config: &ChecksConfig, since: &str, amend: bool, verbose: bool) -> Result<usize> {
+    let root = git(".", &["rev-parse", "--show-toplevel"])?;
+    let root = Path::new(root.trim());
+    let git = |args: &[&str]| git(root, args);
+    // Uncommitted changes carried over to the branch would end up in the commit
+    if !git(&["status", "--porcelain", "--untracked-files=no"])?
+        .trim()
+        .is_empty()
+    {
+        return Err(anyhow!(
+            "there are uncommitted changes, commit or stash them first"
+        ));
+    }
+
+    let picked = git(&["diff", "--name-only", "--no-renames", "-z", since, "HEAD"])?;
+    let picked: HashSet<&str> = picked.split('\0').filter(|p| !p.is_empty()).collect();
+    if picked.is_empty() {
+        return Ok(0);
+    }
+
+    for command in config.commands() {
+        if verbose {
+            println!("🧔 VERBOSE: Running {}", command);
+        }
+        let output = Command::new("sh")
+            .args(["-c", command])
+            .current_dir(root)
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to run the checks"));
+        let failure = match output {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => Some(anyhow!(
+                "{} failed: {}",
+                command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => Some(e),
+        };
+        if let Some(e) = failure {
+            git(&["checkout", "--", "."])?;
+            return Err(e);
+        }
+    }
+
+    let changed = git(&["diff", "--name-only", "-z"])?;
+    let (kept, others): (Vec<&str>, Vec<&str>) = changed
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .partition(|path| picked.contains(path));
+    if !others.is_empty() {
+        let mut checkout = vec!["checkout", "--"];
+        checkout.extend(&others);
+        git(&checkout)?;
+    }
+    if kept.is_empty() {
+        return Ok(0);
+    }
+
+    let mut add = vec!["add", "--"];
+    add.extend(&kept);
+    git(&add)?;
+    let message = format!("Run {}", config.commands().join(" and "));
+    if amend {
+        git(&["commit", "--amend", "--no-edit", "--no-verify"])?;
+    } else {
+        git(&["commit", "--no-verify", "-m", &message])?;
+    }
+    Ok(kept.len())
+}
+
+fn git(dir: impl AsRef<Path>, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn commits_only_what_the_checks_change_in_the_picks() {
+        let config = ChecksConfig {
+            format: Some("cargo fmt".to_string()),
+            lint: Some("cargo clippy --fix".to_string()),
+            amend: false,
+        };
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git rev-parse --show-toplevel", "/repo\n")
+                .on("git diff --name-only --no-renames", "src/a.rs\0src/b.rs\0")
+                .on("git diff --name-only -z", "src/a.rs\0src/template.rs\0"),
+        );
+
+        let changed = process::with_runner(fake.clone(), || apply(&config, "base", false, false));
+
+        assert_eq!(changed.unwrap(), 1);
+        assert_eq!(
+            fake.calls_to("sh"),
+            ["sh -c cargo fmt", "sh -c cargo clippy --fix"]
+        );
+        assert_eq!(
+            fake.calls_to("git checkout"),
+            ["git checkout -- src/template.rs"]
+        );
+        assert_eq!(fake.calls_to("git add"), ["git add -- src/a.rs"]);
+        assert_eq!(
+            fake.calls_to("git commit"),
+            ["git commit --no-verify -m Run cargo fmt and cargo clippy --fix"]
+        );
+    }
+
+    #[test]
+    fn undoes_a_failed_check() {
+        let config = ChecksConfig {
+            format: Some("cargo fmt".to_string()),
+            ..ChecksConfig::default()
+        };
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git diff --name-only --no-renames", "src/a.rs\0")
+                .fail("sh -c cargo fmt", "error: expected `;`"),
+        );
+
+        let result = process::with_runner(fake.clone(), || apply(&config, "HEAD^", true, false));
+
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "cargo fmt failed: error: expected `;`"
+        );
+        assert_eq!(fake.calls_to("git checkout"), ["git checkout -- ."]);
+        assert!(fake.calls_to("git commit").is_empty());
+    }
+}
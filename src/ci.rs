@@ -0,0 +1,156 @@
+use crate::forge;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Combined result of a commit's checks and statuses on GitHub
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CiStatus {
+    Passing,
+    Failing,
+    Pending,
+}
+
+impl CiStatus {
+    /// GitHub's `StatusState`. `None` for commits without any checks.
+    fn parse(state: &str) -> Option<Self> {
+        match state {
+            "SUCCESS" => Some(CiStatus::Passing),
+            "FAILURE" | "ERROR" => Some(CiStatus::Failing),
+            "PENDING" | "EXPECTED" => Some(CiStatus::Pending),
+            _ => None,
+        }
+    }
+
+    pub fn icon(&self) -> &'static str {
+        match self {
+            CiStatus::Passing => "✅",
+            CiStatus::Failing => "❌",
+            CiStatus::Pending => "●",
+        }
+    }
+
+    pub fn describe(&self) -> &'static str {
+        match self {
+            CiStatus::Passing => "checks passed",
+            CiStatus::Failing => "checks failed",
+            CiStatus::Pending => "checks still running",
+        }
+    }
+}
+
+/// Commits looked up per GraphQL query, well within GitHub's size and node limits
+const PER_QUERY: usize = 100;
+
+/// Looks up the check status of `hashes` in `repo` (`owner/name`) with a GraphQL query
+/// per `PER_QUERY` of them. Commits without checks, or that GitHub doesn't have, are
+/// left out.
+pub fn statuses(repo: &str, hashes: &[&str]) -> Result<HashMap<String, CiStatus>> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let (owner, name) = repo
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Expected owner/name, got {}", repo))?;
+
+    let mut found = HashMap::new();
+    for chunk in hashes.chunks(PER_QUERY) {
+        let json = forge::graphql(
+            &query(chunk),
+            &[("owner", owner), ("name", name)],
+            "Failed to query check status",
+        )?;
+        found.extend(parse(&json, chunk));
+    }
+    Ok(found)
+}
+
+/// One aliased `object` lookup per commit, `c0`, `c1`...
+fn query(hashes: &[&str]) -> String {
+    let objects: String = hashes
+        .iter()
+        .enumerate()
+        .map(|(i, hash)| {
+            format!(
+                "c{}: object(oid: \"{}\") {{ ... on Commit {{ statusCheckRollup {{ state }} }} }} ",
+                i, hash
+            )
+        })
+        .collect();
+    format!(
+        "query($owner: String!, $name: String!) {{ repository(owner: $owner, name: $name) {{ {}}} }}",
+        objects
+    )
+}
+
+fn parse(json: &Value, hashes: &[&str]) -> HashMap<String, CiStatus> {
+    let repository = &json["data"]["repository"];
+    hashes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, hash)| {
+            let state = repository[format!("c{}", i)]["statusCheckRollup"]["state"].as_str()?;
+            Some((hash.to_string(), CiStatus::parse(state)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    #[test]
+    fn reads_rollup_state_per_commit() {
+        let response = r#"{"data":{"repository":{
+            "c0":{"statusCheckRollup":{"state":"SUCCESS"}},
+            "c1":{"statusCheckRollup":{"state":"FAILURE"}},
+            "c2":{"statusCheckRollup":null},
+            "c3":null,
+            "c4":{"statusCheckRollup":{"state":"PENDING"}}
+        }}}"#;
+        let fake = Rc::new(FakeRunner::new().on("gh api graphql", response));
+        let found = process::with_runner(fake.clone(), || {
+            statuses("acme/app", &["a", "b", "c", "d", "e"]).unwrap()
+        });
+
+        assert_eq!(found.len(), 3);
+        assert_eq!(found["a"], CiStatus::Passing);
+        assert_eq!(found["b"], CiStatus::Failing);
+        assert_eq!(found["e"], CiStatus::Pending);
+
+        let call = &fake.calls()[0];
+        assert!(call.contains("c1: object(oid: \"b\")"), "{}", call);
+        assert!(call.contains("owner=acme"), "{}", call);
+        assert!(call.contains("name=app"), "{}", call);
+    }
+
+    #[test]
+    fn splits_long_lists_across_queries() {
+        let hashes: Vec<String> = (0..250).map(|i| format!("{:040x}", i)).collect();
+        let hashes: Vec<&str> = hashes.iter().map(String::as_str).collect();
+        let fake = Rc::new(FakeRunner::new().on(
+            "gh api graphql",
+            r#"{"data":{"repository":{"c0":{"statusCheckRollup":{"state":"SUCCESS"}}}}}"#,
+        ));
+        let found = process::with_runner(fake.clone(), || statuses("acme/app", &hashes).unwrap());
+
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 3);
+        assert!(calls[0].contains(&format!("c99: object(oid: \"{}\")", hashes[99])));
+        assert!(!calls[0].contains("c100:"));
+        assert!(calls[2].contains(&format!("c0: object(oid: \"{}\")", hashes[200])));
+        assert!(!calls[2].contains("c50:"));
+        // The first commit of each query
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[hashes[100]], CiStatus::Passing);
+    }
+
+    #[test]
+    fn reports_api_errors() {
+        let fake = FakeRunner::new().fail("gh api graphql", "HTTP 401: Bad credentials");
+        let result = process::with_runner(Rc::new(fake), || statuses("acme/app", &["a"]));
+        assert!(result.unwrap_err().to_string().contains("Bad credentials"));
+    }
+}
@@ -0,0 +1,78 @@
+use crate::glob::gitignore_match;
+use crate::process::CommandExt;
+use std::collections::BTreeMap;
+use std::process::Command;
+
+/// Where GitHub looks for CODEOWNERS, in priority order
+const LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+pub struct CodeOwners {
+    rules: Vec<(String, Vec<String>)>,
+}
+
+impl CodeOwners {
+    /// Reads CODEOWNERS from the template at `rev`, if the template has one
+    pub fn load(rev: &str) -> Option<Self> {
+        LOCATIONS.iter().find_map(|location| {
+            let output = Command::new("git")
+                .args(["show", &format!("{}:{}", rev, location)])
+                .output_local()
+                .ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            Some(Self::parse(&String::from_utf8_lossy(&output.stdout)))
+        })
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                let pattern = parts.next()?.to_string();
+                let owners = parts
+                    .take_while(|part| !part.starts_with('#'))
+                    .map(|owner| owner.to_string())
+                    .collect();
+                Some((pattern, owners))
+            })
+            .collect();
+
+        Self { rules }
+    }
+
+    /// Owners of `path`. As on GitHub, the last matching rule wins.
+    pub fn owners_for(&self, path: &str) -> &[String] {
+        self.rules
+            .iter()
+            .rev()
+            .find(|(pattern, _)| gitignore_match(pattern, path))
+            .map(|(_, owners)| owners.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// Owner -> files they own, for everything in `paths`
+    pub fn owners_of<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a str>,
+    ) -> BTreeMap<String, Vec<String>> {
+        let mut owners: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for path in paths {
+            for owner in self.owners_for(path) {
+                let files = owners.entry(owner.clone()).or_default();
+                if !files.iter().any(|f| f == path) {
+                    files.push(path.to_string());
+                }
+            }
+        }
+        owners
+    }
+}
+
+/// `@user` and `@org/team` can be requested as reviewers through gh; email owners can't
+pub fn reviewer_handle(owner: &str) -> Option<&str> {
+    owner.strip_prefix('@')
+}
@@ -0,0 +1,136 @@
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+const DEFAULT_PATTERN: &str = "%Y-%m-%d %H:%M";
+
+/// How commit dates are shown, `[ui] date_format` in the global config: `"absolute"`
+/// (the default), `"relative"` for "3 days ago", or a strftime pattern. Absolute dates
+/// are always in the local timezone, whatever offset the author committed with.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DateFormat {
+    #[default]
+    Absolute,
+    Relative,
+    Pattern(String),
+}
+
+impl TryFrom<String> for DateFormat {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "absolute" => Ok(Self::Absolute),
+            "relative" => Ok(Self::Relative),
+            _ if StrftimeItems::new(&value).any(|item| item == Item::Error) => Err(format!(
+                "date_format must be \"absolute\", \"relative\" or a strftime pattern, got \"{}\"",
+                value
+            )),
+            _ => Ok(Self::Pattern(value)),
+        }
+    }
+}
+
+impl From<DateFormat> for String {
+    fn from(format: DateFormat) -> Self {
+        match format {
+            DateFormat::Absolute => "absolute".to_string(),
+            DateFormat::Relative => "relative".to_string(),
+            DateFormat::Pattern(pattern) => pattern,
+        }
+    }
+}
+
+impl DateFormat {
+    /// `date` as of `now`, which only matters for relative dates
+    pub fn render(&self, date: &DateTime<FixedOffset>, now: DateTime<Utc>) -> String {
+        match self {
+            Self::Absolute => date
+                .with_timezone(&Local)
+                .format(DEFAULT_PATTERN)
+                .to_string(),
+            Self::Pattern(pattern) => date.with_timezone(&Local).format(pattern).to_string(),
+            Self::Relative => relative(now.signed_duration_since(date).num_seconds()),
+        }
+    }
+}
+
+fn relative(seconds: i64) -> String {
+    const MINUTE: i64 = 60;
+    const HOUR: i64 = 60 * MINUTE;
+    const DAY: i64 = 24 * HOUR;
+
+    let ago = |n: i64, unit: &str| format!("{} {}{} ago", n, unit, if n == 1 { "" } else { "s" });
+    match seconds {
+        s if s < MINUTE => "just now".to_string(),
+        s if s < HOUR => ago(s / MINUTE, "minute"),
+        s if s < DAY => ago(s / HOUR, "hour"),
+        s if s < 2 * DAY => "yesterday".to_string(),
+        s if s < 14 * DAY => ago(s / DAY, "day"),
+        s if s < 60 * DAY => ago(s / (7 * DAY), "week"),
+        s if s < 365 * DAY => ago(s / (30 * DAY), "month"),
+        s => ago(s / (365 * DAY), "year"),
+    }
+}
+
+static FORMAT: OnceLock<DateFormat> = OnceLock::new();
+
+pub fn configure(format: DateFormat) {
+    let _ = FORMAT.set(format);
+}
+
+/// `date` for display, in the configured format
+pub fn display(date: &DateTime<FixedOffset>) -> String {
+    FORMAT
+        .get_or_init(DateFormat::default)
+        .render(date, Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(date).unwrap()
+    }
+
+    #[test]
+    fn same_instant_shows_the_same_whatever_the_offset() {
+        let utc = at("2025-01-20T14:30:00Z");
+        let berlin = at("2025-01-20T15:30:00+01:00");
+        let now = Utc::now();
+        assert_eq!(
+            DateFormat::Absolute.render(&utc, now),
+            DateFormat::Absolute.render(&berlin, now)
+        );
+    }
+
+    #[test]
+    fn relative_dates() {
+        let date = at("2025-01-20T12:00:00Z");
+        let now = |later: &str| at(later).with_timezone(&Utc);
+        let render = |later| DateFormat::Relative.render(&date, now(later));
+
+        assert_eq!(render("2025-01-20T12:00:30Z"), "just now");
+        assert_eq!(render("2025-01-20T12:01:00Z"), "1 minute ago");
+        assert_eq!(render("2025-01-20T15:00:00Z"), "3 hours ago");
+        assert_eq!(render("2025-01-21T13:00:00Z"), "yesterday");
+        assert_eq!(render("2025-01-23T12:00:00Z"), "3 days ago");
+        assert_eq!(render("2025-02-10T12:00:00Z"), "3 weeks ago");
+        assert_eq!(render("2025-04-20T12:00:00Z"), "3 months ago");
+        assert_eq!(render("2027-01-21T12:00:00Z"), "2 years ago");
+    }
+
+    #[test]
+    fn parses_config_values() {
+        let parse = |value: &str| DateFormat::try_from(value.to_string());
+        assert_eq!(parse("relative"), Ok(DateFormat::Relative));
+        assert_eq!(
+            parse("%d.%m.%Y"),
+            Ok(DateFormat::Pattern("%d.%m.%Y".to_string()))
+        );
+        assert!(parse("%Q").is_err());
+    }
+}
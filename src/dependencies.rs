@@ -0,0 +1,447 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Cargo tables whose entries are packages
+const CARGO_TABLES: &[&str] = &[
+    "dependencies",
+    "dev-dependencies",
+    "build-dependencies",
+    "workspace.dependencies",
+];
+
+/// package.json objects whose entries are packages
+const NPM_OBJECTS: &[&str] = &[
+    "dependencies",
+    "devDependencies",
+    "peerDependencies",
+    "optionalDependencies",
+];
+
+/// Manifest formats Chuck can merge by package name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Cargo,
+    Npm,
+}
+
+impl Format {
+    /// The format of the manifest at `path`, if it's one
+    pub fn of(path: &str) -> Option<Self> {
+        match path.rsplit('/').next()? {
+            "Cargo.toml" => Some(Format::Cargo),
+            "package.json" => Some(Format::Npm),
+            _ => None,
+        }
+    }
+}
+
+/// One package's entry, with the comments and blank lines in front of it
+#[derive(Debug, Clone, PartialEq)]
+struct Entry {
+    name: String,
+    text: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Packages { table: String, entries: Vec<Entry> },
+}
+
+/// A manifest split into its package lists and everything else, kept as written
+#[derive(Debug, Clone, PartialEq)]
+struct Manifest {
+    format: Format,
+    segments: Vec<Segment>,
+}
+
+impl Manifest {
+    fn parse(content: &str, format: Format) -> Option<Self> {
+        match format {
+            Format::Cargo => Some(parse_cargo(content)),
+            Format::Npm => parse_npm(content),
+        }
+    }
+
+    /// Everything but the packages, to tell whether a side changed anything else
+    fn skeleton(&self) -> Vec<&str> {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Text(text) => text.as_str(),
+                Segment::Packages { table, .. } => table.as_str(),
+            })
+            .collect()
+    }
+
+    fn packages(&self, table: &str) -> HashMap<&str, &str> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Packages { table: t, entries } if t == table => Some(entries),
+                _ => None,
+            })
+            .flatten()
+            .map(|entry| (entry.name.as_str(), entry.text.as_str()))
+            .collect()
+    }
+
+    fn names(&self, table: &str) -> Vec<&str> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Packages { table: t, entries } if t == table => Some(entries),
+                _ => None,
+            })
+            .flatten()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Packages { entries, .. } if self.format == Format::Npm => {
+                    if !entries.is_empty() {
+                        let texts: Vec<&str> = entries.iter().map(|e| e.text.as_str()).collect();
+                        out.push_str(&texts.join(",\n"));
+                        out.push('\n');
+                    }
+                }
+                Segment::Packages { entries, .. } => {
+                    for entry in entries {
+                        out.push_str(&entry.text);
+                    }
+                }
+            }
+        }
+        out
+    }
+}
+
+fn parse_cargo(content: &str) -> Manifest {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut table: Option<(String, Vec<Entry>)> = None;
+    // Comments and blank lines wait to see whether another entry follows
+    let mut pending = String::new();
+    let mut lines = content.split_inclusive('\n');
+
+    while let Some(line) = lines.next() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            if let Some((name, entries)) = table.take() {
+                segments.push(Segment::Packages {
+                    table: name,
+                    entries,
+                });
+            }
+            text.push_str(&pending);
+            pending.clear();
+            text.push_str(line);
+
+            let header = trimmed
+                .trim_start_matches('[')
+                .split(']')
+                .next()
+                .unwrap_or("");
+            let header = header.trim();
+            let target_table = header
+                .strip_prefix("target.")
+                .and_then(|rest| rest.rsplit_once('.'))
+                .map(|(_, table)| table);
+            if CARGO_TABLES.contains(&header)
+                || target_table.is_some_and(|t| CARGO_TABLES.contains(&t))
+            {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+                table = Some((header.to_string(), Vec::new()));
+            }
+            continue;
+        }
+
+        let Some((_, entries)) = &mut table else {
+            text.push_str(line);
+            continue;
+        };
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            pending.push_str(line);
+            continue;
+        }
+
+        let mut entry = std::mem::take(&mut pending);
+        entry.push_str(line);
+        // Inline tables and arrays can go on over several lines
+        let mut depth = nesting(line);
+        while depth > 0 {
+            let Some(next) = lines.next() else { break };
+            entry.push_str(next);
+            depth += nesting(next);
+        }
+        let name = trimmed
+            .split('=')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .trim_matches(['"', '\''])
+            .to_string();
+        entries.push(Entry { name, text: entry });
+    }
+
+    if let Some((name, entries)) = table {
+        segments.push(Segment::Packages {
+            table: name,
+            entries,
+        });
+    }
+    text.push_str(&pending);
+    segments.push(Segment::Text(text));
+    Manifest {
+        format: Format::Cargo,
+        segments,
+    }
+}
+
+/// How many more brackets `line` opens than it closes, strings aside
+fn nesting(line: &str) -> i32 {
+    let mut depth = 0;
+    let mut quote = None;
+    for c in line.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            (None, '#') => break,
+            (None, '[' | '{') => depth += 1,
+            (None, ']' | '}') => depth -= 1,
+            _ => {}
+        }
+    }
+    depth
+}
+
+/// Only handles the usual layout, one package per line. `None` for anything else.
+fn parse_npm(content: &str) -> Option<Manifest> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut table: Option<(String, Vec<Entry>)> = None;
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some((name, entries)) = &mut table {
+            if trimmed.starts_with('}') {
+                segments.push(Segment::Packages {
+                    table: std::mem::take(name),
+                    entries: std::mem::take(entries),
+                });
+                table = None;
+                text.push_str(line);
+                continue;
+            }
+            let (key, value) = trimmed.split_once(':')?;
+            let key = key.trim();
+            if !key.starts_with('"') || value.contains(['{', '[']) {
+                return None;
+            }
+            entries.push(Entry {
+                name: key.trim_matches('"').to_string(),
+                text: line.trim_end().trim_end_matches(',').to_string(),
+            });
+            continue;
+        }
+
+        text.push_str(line);
+        let opens = trimmed
+            .strip_suffix('{')
+            .and_then(|key| key.trim_end().strip_suffix(':'))
+            .map(|key| key.trim().trim_matches('"'));
+        if let Some(key) = opens.filter(|key| NPM_OBJECTS.contains(key)) {
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+            table = Some((key.to_string(), Vec::new()));
+        }
+    }
+
+    if table.is_some() {
+        return None;
+    }
+    segments.push(Segment::Text(text));
+    Some(Manifest {
+        format: Format::Npm,
+        segments,
+    })
+}
+
+/// Merges the package changes `theirs` made to `base` into `ours`, package by
+/// package. `None` when both sides changed the same package differently, or when
+/// both changed something besides the package lists.
+pub fn merge(base: &str, ours: &str, theirs: &str, format: Format) -> Option<String> {
+    let base = Manifest::parse(base, format)?;
+    let ours = Manifest::parse(ours, format)?;
+    let theirs = Manifest::parse(theirs, format)?;
+
+    let mut merged = if theirs.skeleton() == base.skeleton() {
+        ours.clone()
+    } else if ours.skeleton() == base.skeleton() {
+        theirs.clone()
+    } else {
+        return None;
+    };
+
+    for segment in &mut merged.segments {
+        let Segment::Packages { table, entries } = segment else {
+            continue;
+        };
+        let (b, o, t) = (
+            base.packages(table),
+            ours.packages(table),
+            theirs.packages(table),
+        );
+
+        // The order as it is, with packages only the other side has added in place
+        // when the list is sorted, or at the end
+        let mut names: Vec<String> = entries.iter().map(|e| e.name.clone()).collect();
+        let sorted = names.windows(2).all(|pair| pair[0] <= pair[1]);
+        for name in ours.names(table).into_iter().chain(theirs.names(table)) {
+            if names.iter().any(|n| n == name) {
+                continue;
+            }
+            let at = if sorted {
+                names.partition_point(|n| n.as_str() < name)
+            } else {
+                names.len()
+            };
+            names.insert(at, name.to_string());
+        }
+
+        let mut result = Vec::new();
+        for name in names {
+            let (b, o, t) = (b.get(&*name), o.get(&*name), t.get(&*name));
+            let text = if t == b {
+                o
+            } else if o == b || o == t {
+                t
+            } else {
+                return None;
+            };
+            if let Some(text) = text {
+                result.push(Entry {
+                    name,
+                    text: text.to_string(),
+                });
+            }
+        }
+        *entries = result;
+    }
+
+    Some(merged.render())
+}
+
+/// Resolves the conflicted `Cargo.toml` and `package.json` files of a stopped pick
+/// whose sides only disagree on packages, staging the result. Returns the ones it did.
+pub fn resolve_conflicts(unmerged: &[String], verbose: bool) -> Result<Vec<String>> {
+    let mut resolved = Vec::new();
+    let mut root = None;
+    for path in unmerged {
+        let Some(format) = Format::of(path) else {
+            continue;
+        };
+        let (Some(base), Some(ours), Some(theirs)) =
+            (stage(1, path)?, stage(2, path)?, stage(3, path)?)
+        else {
+            continue;
+        };
+        let Some(content) = merge(&base, &ours, &theirs, format) else {
+            if verbose {
+                println!(
+                    "🧔 VERBOSE: {} changes more than packages on both sides",
+                    path
+                );
+            }
+            continue;
+        };
+
+        let root = match &root {
+            Some(root) => root,
+            None => root.insert(toplevel()?),
+        };
+        fs::write(Path::new(root).join(path), content)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path, e))?;
+        let output = Command::new("git")
+            .args(["-C", root, "add", "--", path])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git add"))?;
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to stage {}: {}", path, error.trim()));
+        }
+        resolved.push(path.clone());
+    }
+    Ok(resolved)
+}
+
+/// `path` as it is in conflict stage `n`: 1 the common ancestor, 2 ours, 3 theirs
+fn stage(n: u8, path: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["show", &format!(":{}:{}", n, path)])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git show"))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(String::from_utf8(output.stdout).ok())
+}
+
+fn toplevel() -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_CARGO: &str = "[package]\nname = \"app\"\n\n[dependencies]\nanyhow = \"1.0\"\nserde = \"1.0\"\n\n[dev-dependencies]\ncriterion = \"0.5\"\n";
+
+    #[test]
+    fn merges_cargo_dependencies_by_name() {
+        // The template bumped serde and added clap, the commit adds tokio and drops criterion
+        let ours = "[package]\nname = \"app\"\n\n[dependencies]\nanyhow = \"1.0\"\nclap = { version = \"4\", features = [\n    \"derive\",\n] }\nserde = \"1.0.200\"\n\n[dev-dependencies]\ncriterion = \"0.5\"\n";
+        let theirs = "[package]\nname = \"app\"\n\n[dependencies]\nanyhow = \"1.0\"\nserde = \"1.0\"\n# Async runtime\ntokio = \"1\"\n\n[dev-dependencies]\n";
+
+        assert_eq!(
+            merge(BASE_CARGO, ours, theirs, Format::Cargo).unwrap(),
+            "[package]\nname = \"app\"\n\n[dependencies]\nanyhow = \"1.0\"\nclap = { version = \"4\", features = [\n    \"derive\",\n] }\nserde = \"1.0.200\"\n# Async runtime\ntokio = \"1\"\n\n[dev-dependencies]\n"
+        );
+    }
+
+    #[test]
+    fn leaves_real_conflicts_alone() {
+        let ours = BASE_CARGO.replace("serde = \"1.0\"", "serde = \"1.0.200\"");
+        let theirs = BASE_CARGO.replace("serde = \"1.0\"", "serde = \"1.0.150\"");
+        assert_eq!(merge(BASE_CARGO, &ours, &theirs, Format::Cargo), None);
+
+        // Both sides changed the package itself
+        let ours = BASE_CARGO.replace("name = \"app\"", "name = \"web\"");
+        let theirs = BASE_CARGO.replace("name = \"app\"", "name = \"api\"");
+        assert_eq!(merge(BASE_CARGO, &ours, &theirs, Format::Cargo), None);
+    }
+
+    #[test]
+    fn merges_package_json_dependencies() {
+        let base = "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"express\": \"^4.18.0\",\n    \"react\": \"^18.2.0\"\n  }\n}\n";
+        let ours = "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"express\": \"^4.19.0\",\n    \"react\": \"^18.2.0\"\n  }\n}\n";
+        let theirs = "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"express\": \"^4.18.0\",\n    \"react\": \"^18.2.0\",\n    \"zod\": \"^3.22.0\"\n  }\n}\n";
+
+        assert_eq!(
+            merge(base, ours, theirs, Format::Npm).unwrap(),
+            "{\n  \"name\": \"app\",\n  \"dependencies\": {\n    \"express\": \"^4.19.0\",\n    \"react\": \"^18.2.0\",\n    \"zod\": \"^3.22.0\"\n  }\n}\n"
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/// A directory the candidate commits touch, as a row of the sidebar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dir {
+    pub path: String,
+    /// 0 for top-level directories
+    pub depth: usize,
+    /// How many commits touch something under it
+    pub commits: usize,
+}
+
+impl Dir {
+    /// Last component, what the row shows under its parent
+    pub fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// Every directory holding a file of `commits`, each given as its paths. Parents
+/// come right before their children, siblings in name order.
+pub fn build<'a, C, P>(commits: C) -> Vec<Dir>
+where
+    C: IntoIterator<Item = P>,
+    P: IntoIterator<Item = &'a str>,
+{
+    // Keyed by components, so "a/b" sorts right after "a" and before "a-b"
+    let mut counts: BTreeMap<Vec<&str>, usize> = BTreeMap::new();
+    for paths in commits {
+        let mut dirs: BTreeSet<Vec<&str>> = BTreeSet::new();
+        for path in paths {
+            let components: Vec<&str> = path.split('/').collect();
+            for end in 1..components.len() {
+                dirs.insert(components[..end].to_vec());
+            }
+        }
+        for dir in dirs {
+            *counts.entry(dir).or_default() += 1;
+        }
+    }
+
+    counts
+        .into_iter()
+        .map(|(components, commits)| Dir {
+            path: components.join("/"),
+            depth: components.len() - 1,
+            commits,
+        })
+        .collect()
+}
+
+/// Whether any of `paths` is under one of `dirs`. No directories means no filter.
+pub fn touches<'a>(mut paths: impl Iterator<Item = &'a str>, dirs: &BTreeSet<String>) -> bool {
+    dirs.is_empty()
+        || paths.any(|path| {
+            dirs.iter().any(|dir| {
+                path.strip_prefix(dir.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lists_parents_before_children() {
+        let dirs = build([
+            vec!["src/ui/list.rs", "src/main.rs"],
+            vec!["src/ui/help.rs", "src-gen/schema.rs", "README.md"],
+        ]);
+        let rows: Vec<(&str, usize, usize)> = dirs
+            .iter()
+            .map(|d| (d.path.as_str(), d.depth, d.commits))
+            .collect();
+        assert_eq!(rows, [("src", 0, 2), ("src/ui", 1, 2), ("src-gen", 0, 1)]);
+        assert_eq!(dirs[1].name(), "ui");
+    }
+
+    #[test]
+    fn filters_by_directory() {
+        let dirs: BTreeSet<String> = ["src/ui".to_string()].into();
+        assert!(touches(["README.md", "src/ui/list.rs"].into_iter(), &dirs));
+        assert!(!touches(["src/ui.rs", "src/uikit/a.rs"].into_iter(), &dirs));
+        assert!(touches(["README.md"].into_iter(), &BTreeSet::new()));
+    }
+}
@@ -0,0 +1,321 @@
+use crate::partial;
+use crate::process::{self, CommandExt};
+use crate::profile;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, FixedOffset};
+use std::collections::HashSet;
+use std::process::Command;
+
+#[derive(Debug, Clone)]
+pub struct FileChange {
+    /// Single-letter `git diff --name-status` code (A, M, D, R, C, T)
+    pub status: char,
+    pub path: String,
+    /// Source path for renames and copies
+    pub old_path: Option<String>,
+}
+
+impl FileChange {
+    pub fn display(&self) -> String {
+        match &self.old_path {
+            Some(old) => format!("{} → {}", old, self.path),
+            None => self.path.clone(),
+        }
+    }
+}
+
+/// Where a changed file stands on the template, which tells a clean pick from a
+/// conflict before anything is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Upstream {
+    /// The template has the file the change applies to
+    Exists,
+    /// The change creates the file upstream
+    New,
+    /// The change edits, deletes or renames a file the template doesn't have
+    Missing,
+}
+
+impl Upstream {
+    pub fn badge(self) -> &'static str {
+        match self {
+            Upstream::Exists => "✔ exists",
+            Upstream::New => "✚ new upstream",
+            Upstream::Missing => "✖ missing path",
+        }
+    }
+}
+
+impl FileChange {
+    /// Where this file stands on a template with `template_files`
+    pub fn upstream(&self, template_files: &HashSet<String>) -> Upstream {
+        let (path, creates) = match self.status {
+            'A' | 'C' => (&self.path, true),
+            'R' => (self.old_path.as_ref().unwrap_or(&self.path), false),
+            _ => (&self.path, false),
+        };
+        if template_files.contains(path) {
+            Upstream::Exists
+        } else if creates {
+            Upstream::New
+        } else {
+            Upstream::Missing
+        }
+    }
+}
+
+/// One commit from `git log`, before its files are looked up
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub hash: String,
+    pub parents: Vec<String>,
+    pub author: String,
+    pub date: DateTime<FixedOffset>,
+    pub subject: String,
+}
+
+pub const LOG_FORMAT: &str = "--format=%H%x1f%P%x1f%an%x1f%aI%x1f%s%x1e";
+
+/// Runs `git log` with `rev_args`, newest first like the GitHub API
+pub fn read_log(rev_args: &[&str]) -> Result<Vec<LogEntry>> {
+    let mut args = vec!["log", LOG_FORMAT];
+    args.extend_from_slice(rev_args);
+    args.push("--");
+
+    let output = Command::new("git")
+        .args(&args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to read commits: {}", error.trim()));
+    }
+
+    parse_log(&String::from_utf8(output.stdout)?)
+}
+
+/// Parses output of `git log` in `LOG_FORMAT`
+pub fn parse_log(log: &str) -> Result<Vec<LogEntry>> {
+    let mut entries = Vec::new();
+    for record in log.split('\x1e') {
+        let fields: Vec<&str> = record.trim_start_matches('\n').split('\x1f').collect();
+        let [hash, parents, author, date, subject] = fields[..] else {
+            continue;
+        };
+        entries.push(LogEntry {
+            hash: hash.to_string(),
+            parents: parents.split_whitespace().map(str::to_string).collect(),
+            author: author.to_string(),
+            date: DateTime::parse_from_rfc3339(date)?,
+            subject: subject.to_string(),
+        });
+    }
+    Ok(entries)
+}
+
+pub fn get_commit_files(sha: &str, is_merge: bool) -> Result<Vec<FileChange>> {
+    let _phase = profile::phase("file-list fetch");
+
+    // Finding renames compares file contents, which a partial clone would download
+    let renames = if partial::setup().is_partial() {
+        "--no-renames"
+    } else {
+        "-M"
+    };
+    // `git show` on a merge only lists files with conflicts, so diff against the first parent
+    let output = if is_merge {
+        Command::new("git")
+            .args(["diff", "--name-status", renames, &format!("{}^1", sha), sha])
+            .output_local()
+    } else {
+        Command::new("git")
+            .args(["show", "--name-status", renames, "--format=", sha])
+            .output_local()
+    }
+    .map_err(|e| process::command_error(e, "Failed to execute git show"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to get commit files: {}", error));
+    }
+
+    Ok(parse_name_status(&String::from_utf8(output.stdout)?))
+}
+
+pub fn parse_name_status(output: &str) -> Vec<FileChange> {
+    output.lines().filter_map(parse_name_status_line).collect()
+}
+
+/// Parses `M\tpath` or `R087\told\tnew` from `git diff --name-status`
+pub fn parse_name_status_line(line: &str) -> Option<FileChange> {
+    let mut parts = line.trim().split('\t');
+    let status = parts.next()?.chars().next()?;
+    let first = parts.next()?.to_string();
+
+    match parts.next() {
+        Some(second) => Some(FileChange {
+            status,
+            path: second.to_string(),
+            old_path: Some(first),
+        }),
+        None => Some(FileChange {
+            status,
+            path: first,
+            old_path: None,
+        }),
+    }
+}
+
+/// `(patch-id, commit)` pairs for the patches `git <log_args>` prints
+pub fn patch_ids(log_args: &[&str]) -> Result<Vec<(String, String)>> {
+    let log = partial::local_only(Command::new("git").args(log_args))
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+
+    if !log.status.success() {
+        let error = String::from_utf8_lossy(&log.stderr);
+        if let Some(reason) = partial::missing_contents(&error) {
+            return Err(anyhow!("{}", reason));
+        }
+        return Err(anyhow!("Failed to read commit patches: {}", error));
+    }
+
+    patch_ids_of(&log.stdout)
+}
+
+/// `(patch-id, commit)` pairs for `git log -p` output that was already read
+pub fn patch_ids_of(log: &[u8]) -> Result<Vec<(String, String)>> {
+    let output = Command::new("git")
+        .args(["patch-id", "--stable"])
+        .output_local_with_input(log)
+        .map_err(|e| process::command_error(e, "Failed to execute git patch-id"))?;
+
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            Some((parts.next()?.to_string(), parts.next()?.to_string()))
+        })
+        .collect())
+}
+
+/// Every file path in `rev`'s tree
+pub fn tree_paths(rev: &str) -> Result<HashSet<String>> {
+    let output = Command::new("git")
+        .args(["ls-tree", "-r", "-z", "--name-only", rev])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git ls-tree"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to list files in {}: {}", rev, error));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn tells_where_files_stand_upstream() {
+        let template: HashSet<String> = ["src/lib.rs", "README.md"]
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let change = |status, path: &str, old_path: Option<&str>| FileChange {
+            status,
+            path: path.to_string(),
+            old_path: old_path.map(str::to_string),
+        };
+        let upstream = |file: FileChange| file.upstream(&template);
+
+        assert_eq!(upstream(change('M', "src/lib.rs", None)), Upstream::Exists);
+        assert_eq!(upstream(change('A', "README.md", None)), Upstream::Exists);
+        assert_eq!(upstream(change('A', "src/new.rs", None)), Upstream::New);
+        assert_eq!(upstream(change('M', "src/app.rs", None)), Upstream::Missing);
+        assert_eq!(upstream(change('D', "src/app.rs", None)), Upstream::Missing);
+        assert_eq!(
+            upstream(change('R', "src/core.rs", Some("src/lib.rs"))),
+            Upstream::Exists
+        );
+        assert_eq!(
+            upstream(change('R', "src/core.rs", Some("src/app.rs"))),
+            Upstream::Missing
+        );
+        assert_eq!(
+            upstream(change('C', "src/copy.rs", Some("src/app.rs"))),
+            Upstream::New
+        );
+    }
+
+    #[test]
+    fn reads_log_records() {
+        let log = "aaa\x1fp1\x1fJane\x1f2025-01-20T14:30:00+01:00\x1fFirst\x1e\n\
+                   bbb\x1fp1 p2\x1fJoe\x1f2025-01-21T09:00:00+00:00\x1fMerge\x1e\n";
+        let fake = Rc::new(FakeRunner::new().on("git log", log));
+        let entries = process::with_runner(fake.clone(), || read_log(&["main"]).unwrap());
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].hash, "aaa");
+        assert_eq!(entries[0].parents, ["p1"]);
+        assert_eq!(entries[1].subject, "Merge");
+        assert_eq!(entries[1].parents, ["p1", "p2"]);
+        assert_eq!(fake.calls(), [format!("git log {} main --", LOG_FORMAT)]);
+    }
+
+    #[test]
+    fn lists_files_with_renames() {
+        let fake = FakeRunner::new().on(
+            "git show --name-status",
+            "M\tsrc/a.rs\nR087\told.rs\tnew.rs\n",
+        );
+        let files = process::with_runner(Rc::new(fake), || get_commit_files("abc", false).unwrap());
+
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/a.rs");
+        assert_eq!(files[1].status, 'R');
+        assert_eq!(files[1].display(), "old.rs → new.rs");
+    }
+
+    #[test]
+    fn diffs_merges_against_first_parent() {
+        let fake = Rc::new(FakeRunner::new());
+        process::with_runner(fake.clone(), || get_commit_files("abc", true).unwrap());
+        assert_eq!(fake.calls(), ["git diff --name-status -M abc^1 abc"]);
+    }
+
+    #[test]
+    fn reports_git_errors() {
+        let fake = FakeRunner::new().fail("git show", "fatal: bad object abc");
+        let result = process::with_runner(Rc::new(fake), || get_commit_files("abc", false));
+        assert!(result.unwrap_err().to_string().contains("bad object"));
+    }
+
+    #[test]
+    fn pipes_log_into_patch_id() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git log -p", "diff --git a/x b/x\n")
+                .on("git patch-id", "p1 c1\np2 c2\n"),
+        );
+        let ids = process::with_runner(fake.clone(), || patch_ids(&["log", "-p", "main"]).unwrap());
+
+        assert_eq!(
+            ids,
+            [
+                ("p1".to_string(), "c1".to_string()),
+                ("p2".to_string(), "c2".to_string())
+            ]
+        );
+        assert_eq!(fake.inputs(), [b"diff --git a/x b/x\n".to_vec()]);
+    }
+}
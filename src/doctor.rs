@@ -0,0 +1,344 @@
+use crate::access;
+use crate::forge::{self, Backend};
+use crate::process::CommandExt;
+use crate::repo_state;
+use std::process::{Command, Output};
+
+/// Oldest git with everything Chuck runs (`cherry-pick --quit`, `ls-tree -z`, ...)
+const MIN_GIT: (u32, u32) = (2, 20);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Pass,
+    Fail,
+    /// Couldn't be checked because an earlier check failed
+    Skip,
+}
+
+/// One line of the checklist, with how to fix it when it fails
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub status: Status,
+    pub summary: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(summary: impl Into<String>) -> Self {
+        Self {
+            status: Status::Pass,
+            summary: summary.into(),
+            fix: None,
+        }
+    }
+
+    fn fail(summary: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            status: Status::Fail,
+            summary: summary.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn skip(summary: impl Into<String>) -> Self {
+        Self {
+            status: Status::Skip,
+            summary: summary.into(),
+            fix: None,
+        }
+    }
+}
+
+/// The template as `.chuckrc` (or a template remote) describes it
+pub struct Template {
+    pub url: String,
+    pub repo: String,
+}
+
+/// Runs every check, prints the checklist and returns how many failed.
+/// `template` is the error reading `.chuckrc` when it couldn't be read.
+pub fn run(template: Result<Template, String>) -> usize {
+    println!("🧔 Checking your setup...\n");
+    let checks = checks(&template);
+    for check in &checks {
+        let icon = match check.status {
+            Status::Pass => "✅",
+            Status::Fail => "❌",
+            Status::Skip => "➖",
+        };
+        println!("  {} {}", icon, check.summary);
+        if let Some(fix) = &check.fix {
+            println!("     → {}", fix);
+        }
+    }
+
+    let failed = checks.iter().filter(|c| c.status == Status::Fail).count();
+    println!();
+    match failed {
+        0 => println!("🧔 \"Everything checks out. Go make that pull request, kiddo!\""),
+        1 => println!("🧔 \"One thing to sort out first.\""),
+        n => println!("🧔 \"{} things to sort out first.\"", n),
+    }
+    failed
+}
+
+pub fn checks(template: &Result<Template, String>) -> Vec<Check> {
+    let gh = check_gh();
+    let gh_ok = gh.status == Status::Pass;
+    let mut checks = vec![check_git(), gh, check_chuckrc(template)];
+
+    match template {
+        Ok(template) => {
+            checks.push(check_credentials(&template.url));
+            checks.push(if gh_ok {
+                check_write_access(&template.repo)
+            } else {
+                Check::skip("Write access to the template: needs access to GitHub")
+            });
+        }
+        Err(_) => {
+            checks.push(Check::skip("Credentials for the template: needs .chuckrc"));
+            checks.push(Check::skip("Write access to the template: needs .chuckrc"));
+        }
+    }
+
+    checks.push(check_work_tree());
+    checks
+}
+
+fn run_local(program: &str, args: &[&str]) -> Option<Output> {
+    Command::new(program).args(args).output_local().ok()
+}
+
+fn run_network(program: &str, args: &[&str]) -> Option<Output> {
+    Command::new(program).args(args).output_network().ok()
+}
+
+fn check_git() -> Check {
+    let Some(output) = run_local("git", &["--version"]) else {
+        return Check::fail("git not found", "Install git 2.20 or newer");
+    };
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    match parse_git_version(&version) {
+        Some(found) if found >= MIN_GIT => Check::pass(version),
+        Some(_) => Check::fail(
+            format!("{} is too old", version),
+            format!("Upgrade git to {}.{} or newer", MIN_GIT.0, MIN_GIT.1),
+        ),
+        None => Check::fail(
+            format!("Can't tell the git version from \"{}\"", version),
+            "Check that `git --version` runs",
+        ),
+    }
+}
+
+/// `(major, minor)` from `git version 2.39.3 (Apple Git-146)`
+pub fn parse_git_version(output: &str) -> Option<(u32, u32)> {
+    let version = output
+        .strip_prefix("git version ")?
+        .split_whitespace()
+        .next()?;
+    let mut parts = version.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+fn check_gh() -> Check {
+    match forge::backend() {
+        Ok(Backend::Gh) => {}
+        Ok(Backend::Api { .. }) => {
+            return Check::pass("Talking to the GitHub API with the token from the environment")
+        }
+        Err(e) => {
+            return Check::fail(
+                e.to_string(),
+                "Export GH_TOKEN with a token that has repo scope, or set [forge] client = \"gh\"",
+            )
+        }
+    }
+    if run_local("gh", &["--version"]).is_none() {
+        return Check::fail(
+            "GitHub CLI (gh) not found",
+            "Install it: brew install gh (or see https://cli.github.com)",
+        );
+    }
+    match run_network("gh", &["auth", "status"]) {
+        Some(output) if output.status.success() => Check::pass("gh is logged in"),
+        _ => Check::fail("gh isn't logged in", "Run: gh auth login"),
+    }
+}
+
+fn check_chuckrc(template: &Result<Template, String>) -> Check {
+    match template {
+        Ok(template) => Check::pass(format!("Template: {}", template.repo)),
+        Err(e) => Check::fail(
+            format!("No usable template config: {}", e),
+            "Add a .chuckrc with [template] url = \"git@github.com:owner/template.git\"",
+        ),
+    }
+}
+
+/// SSH URLs need a key in the agent that GitHub accepts, HTTPS ones a credential helper
+fn check_credentials(url: &str) -> Check {
+    if !url.starts_with("git@") {
+        let helper = run_local("git", &["config", "--get", "credential.helper"])
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .unwrap_or_default();
+        return if helper.is_empty() {
+            Check::fail(
+                "No git credential helper for HTTPS pushes",
+                "Run: gh auth setup-git",
+            )
+        } else {
+            Check::pass(format!("git credential helper: {}", helper))
+        };
+    }
+
+    let host = url
+        .strip_prefix("git@")
+        .and_then(|rest| rest.split(':').next())
+        .unwrap_or("github.com");
+    match run_local("ssh-add", &["-l"]).map(|output| output.status.code()) {
+        Some(Some(0)) => {}
+        Some(Some(1)) => {
+            return Check::fail(
+                "The ssh agent has no keys",
+                "Add yours: ssh-add ~/.ssh/id_ed25519",
+            )
+        }
+        _ => {
+            return Check::fail(
+                "No ssh agent running",
+                "Start one and add your key: eval \"$(ssh-agent)\" && ssh-add",
+            )
+        }
+    }
+
+    // GitHub greets a known key by name and then refuses the shell, so the exit code
+    // says nothing; the greeting does
+    let greeting = run_network(
+        "ssh",
+        &[
+            "-T",
+            "-o",
+            "BatchMode=yes",
+            "-o",
+            "ConnectTimeout=10",
+            &format!("git@{}", host),
+        ],
+    )
+    .map(|output| String::from_utf8_lossy(&output.stderr).into_owned())
+    .unwrap_or_default();
+    if greeting.contains("successfully authenticated") {
+        Check::pass(format!("{} accepts a key from your ssh agent", host))
+    } else {
+        Check::fail(
+            format!("{} doesn't accept any key in your ssh agent", host),
+            format!(
+                "Add your public key at https://{}/settings/keys, or check with: ssh -T git@{}",
+                host, host
+            ),
+        )
+    }
+}
+
+fn check_write_access(repo: &str) -> Check {
+    match access::can_push(repo) {
+        Ok(true) => Check::pass(format!("You can push to {}", repo)),
+        // Not a problem, runs push to a fork instead
+        Ok(false) => Check::pass(format!(
+            "No write access to {}, branches go through your fork",
+            repo
+        )),
+        Err(_) => Check::fail(
+            format!("Couldn't look up {}", repo),
+            "Check the template URL and that your gh account can see the repository",
+        ),
+    }
+}
+
+fn check_work_tree() -> Check {
+    if let Err(e) = repo_state::ensure_idle() {
+        return Check::fail("Repository is busy", e.to_string());
+    }
+    let Some(output) = run_local("git", &["status", "--porcelain", "--untracked-files=no"]) else {
+        return Check::fail(
+            "Couldn't run git status",
+            "Run chuck inside a git repository",
+        );
+    };
+    if !output.status.success() {
+        return Check::fail(
+            "Not inside a git repository",
+            "Run chuck from your project's checkout",
+        );
+    }
+    let changes = String::from_utf8_lossy(&output.stdout).lines().count();
+    if changes == 0 {
+        Check::pass("Working tree is clean")
+    } else {
+        Check::fail(
+            format!(
+                "{} uncommitted change{}",
+                changes,
+                if changes == 1 { "" } else { "s" }
+            ),
+            "Commit or stash them: git stash",
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    #[test]
+    fn reads_git_versions() {
+        assert_eq!(parse_git_version("git version 2.43.0"), Some((2, 43)));
+        assert_eq!(
+            parse_git_version("git version 2.39.3 (Apple Git-146)"),
+            Some((2, 39))
+        );
+        assert_eq!(parse_git_version("nope"), None);
+    }
+
+    #[test]
+    fn explains_what_to_fix() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git --version", "git version 2.43.0\n")
+                .fail(
+                    "gh auth status",
+                    "You are not logged into any GitHub hosts.",
+                )
+                .fail("ssh-add -l", "The agent has no identities.")
+                .on("git status --porcelain", " M src/lib.rs\n"),
+        );
+        let template = Ok(Template {
+            url: "git@github.com:acme/template.git".to_string(),
+            repo: "acme/template".to_string(),
+        });
+
+        let checks = process::with_runner(fake.clone(), || checks(&template));
+
+        let statuses: Vec<&Status> = checks.iter().map(|c| &c.status).collect();
+        assert_eq!(
+            statuses,
+            [
+                &Status::Pass,
+                &Status::Fail,
+                &Status::Pass,
+                &Status::Fail,
+                &Status::Skip,
+                &Status::Fail
+            ]
+        );
+        assert_eq!(checks[1].fix.as_deref(), Some("Run: gh auth login"));
+        assert_eq!(checks[3].summary, "The ssh agent has no keys");
+        assert_eq!(checks[5].summary, "1 uncommitted change");
+        assert!(fake.calls_to("gh api").is_empty());
+    }
+}
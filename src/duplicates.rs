@@ -0,0 +1,419 @@
+use crate::discovery;
+use crate::forge::{self, Backend};
+use crate::metadata::PrMetadata;
+use crate::network;
+use crate::partial;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+/// Share of changed lines two commits need in common to count as the same change
+const SIMILAR: f64 = 0.8;
+
+/// A pull request on the template
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PullRequest {
+    pub number: u64,
+    pub title: String,
+    pub url: String,
+    #[serde(rename = "headRefOid")]
+    pub head: String,
+    /// Branch it was opened from
+    #[serde(rename = "headRefName", default)]
+    pub branch: String,
+    #[serde(default)]
+    pub body: String,
+    #[serde(rename = "createdAt", default)]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Likeness {
+    /// Listed in the metadata Chuck put in its body
+    Recorded,
+    /// Same `git patch-id`
+    SamePatch,
+    /// Percentage of changed lines in common
+    Similar(u32),
+}
+
+/// A candidate commit that an open pull request seems to contain already
+#[derive(Debug)]
+pub struct Duplicate<'a> {
+    pub commit: String,
+    pub pr: &'a PullRequest,
+    pub likeness: Likeness,
+}
+
+impl PullRequest {
+    /// From a pull request as the REST API lists it, rather than `gh pr list`
+    fn from_rest(pr: &Value) -> Option<Self> {
+        Some(Self {
+            number: pr.get("number")?.as_u64()?,
+            title: pr.get("title")?.as_str()?.to_string(),
+            url: pr.get("html_url")?.as_str()?.to_string(),
+            head: pr.pointer("/head/sha")?.as_str()?.to_string(),
+            branch: pr
+                .pointer("/head/ref")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            body: pr
+                .get("body")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            created_at: pr
+                .get("created_at")
+                .and_then(Value::as_str)
+                .and_then(|date| date.parse().ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    /// The metadata Chuck put in its body when it opened it
+    pub fn metadata(&self) -> Option<PrMetadata> {
+        PrMetadata::extract(&self.body)
+    }
+}
+
+impl Duplicate<'_> {
+    pub fn describe(&self) -> String {
+        match self.likeness {
+            Likeness::Recorded => format!(
+                "already proposed in #{} \"{}\"",
+                self.pr.number, self.pr.title
+            ),
+            Likeness::SamePatch => format!(
+                "looks already proposed in #{} \"{}\"",
+                self.pr.number, self.pr.title
+            ),
+            Likeness::Similar(percent) => format!(
+                "{}% like a commit in #{} \"{}\"",
+                percent, self.pr.number, self.pr.title
+            ),
+        }
+    }
+}
+
+/// The template's latest 100 pull requests in `state`, "open" or "all"
+pub fn pull_requests(template_repo: &str, state: &str) -> Result<Vec<PullRequest>> {
+    if let Backend::Api { .. } = forge::backend()? {
+        let json = forge::get_json(
+            &format!("repos/{}/pulls?state={}&per_page=100", template_repo, state),
+            "Failed to list pull requests",
+        )?;
+        return Ok(json
+            .as_array()
+            .map(|prs| prs.iter().filter_map(PullRequest::from_rest).collect())
+            .unwrap_or_default());
+    }
+
+    let output = Command::new("gh")
+        .args([
+            "pr",
+            "list",
+            "--repo",
+            template_repo,
+            "--state",
+            state,
+            "--limit",
+            "100",
+            "--json",
+            "number,title,url,headRefOid,headRefName,body,createdAt",
+        ])
+        .output_network()
+        .map_err(|e| {
+            process::command_error(e, "GitHub CLI not found. Install with: brew install gh")
+        })?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(network::describe_failure(
+            "Failed to list pull requests",
+            &error
+        )));
+    }
+
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// Fetches the pull requests' head commits from `remote` so they can be compared locally
+pub fn fetch_heads(remote: &str, prs: &[PullRequest]) -> Result<()> {
+    if prs.is_empty() {
+        return Ok(());
+    }
+    let mut args = vec![
+        "fetch".to_string(),
+        "--no-tags".to_string(),
+        "--quiet".to_string(),
+    ];
+    if let Some(filter) = partial::setup().filter() {
+        args.push(format!("--filter={}", filter));
+    }
+    args.push(remote.to_string());
+    args.extend(prs.iter().map(|pr| format!("refs/pull/{}/head", pr.number)));
+
+    let output = Command::new("git")
+        .args(&args)
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to execute git fetch"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(network::describe_failure(
+            "Failed to fetch open pull requests",
+            &error
+        )));
+    }
+    Ok(())
+}
+
+/// Which of `commits` an open pull request already has: listed in its metadata, the
+/// same patch, or failing that, mostly the same changed lines. Only the pull
+/// requests' commits that aren't in `base` are compared. One match per commit, the
+/// first pull request wins.
+pub fn find<'a>(
+    commits: &[&str],
+    prs: &'a [PullRequest],
+    base: &str,
+) -> Result<Vec<Duplicate<'a>>> {
+    let mut args = vec!["--no-walk"];
+    args.extend_from_slice(commits);
+    let log = patches(&args)?;
+    let ids: HashMap<String, String> = discovery::patch_ids_of(log.as_bytes())?
+        .into_iter()
+        .map(|(id, sha)| (sha, id))
+        .collect();
+    let lines = changed_lines(&log);
+
+    let mut duplicates: Vec<Duplicate> = Vec::new();
+    for pr in prs {
+        let pr_log = patches(&[&pr.head, "--not", base])?;
+        let pr_ids: HashSet<String> = discovery::patch_ids_of(pr_log.as_bytes())?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect();
+        let pr_lines = changed_lines(&pr_log);
+        let metadata = pr.metadata();
+
+        for commit in commits {
+            if duplicates.iter().any(|d| d.commit == *commit) {
+                continue;
+            }
+            let id = ids.get(*commit).map(String::as_str);
+            let likeness = if metadata.as_ref().is_some_and(|m| m.carries(commit, id)) {
+                Some(Likeness::Recorded)
+            } else if id.is_some_and(|id| pr_ids.contains(id)) {
+                Some(Likeness::SamePatch)
+            } else {
+                lines.get(*commit).and_then(|ours| {
+                    pr_lines
+                        .values()
+                        .map(|theirs| similarity(ours, theirs))
+                        .filter(|&s| s >= SIMILAR)
+                        .max_by(f64::total_cmp)
+                        .map(|s| Likeness::Similar((s * 100.0).round() as u32))
+                })
+            };
+            if let Some(likeness) = likeness {
+                duplicates.push(Duplicate {
+                    commit: commit.to_string(),
+                    pr,
+                    likeness,
+                });
+            }
+        }
+    }
+    Ok(duplicates)
+}
+
+/// `git log -p` for non-merge commits, in the format `git patch-id` reads
+fn patches(rev_args: &[&str]) -> Result<String> {
+    let output = partial::local_only(
+        Command::new("git")
+            .args(["log", "-p", "--no-merges", "--no-color", "--format=medium"])
+            .args(rev_args),
+    )
+    .output_local()
+    .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if let Some(reason) = partial::missing_contents(&error) {
+            return Err(anyhow!("{}", reason));
+        }
+        return Err(anyhow!("Failed to read commit patches: {}", error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Commit -> its added and removed lines, with their `+`/`-`, from `git log -p`
+pub fn changed_lines(log: &str) -> HashMap<String, HashSet<String>> {
+    let mut commits: HashMap<String, HashSet<String>> = HashMap::new();
+    let mut current: Option<&str> = None;
+    // `--- a/file` headers look like removed lines, so only count lines inside hunks
+    let mut in_hunk = false;
+    for line in log.lines() {
+        if let Some(rest) = line.strip_prefix("commit ") {
+            current = rest.split_whitespace().next();
+            in_hunk = false;
+        } else if line.starts_with("diff --git ") {
+            in_hunk = false;
+        } else if line.starts_with("@@ ") {
+            in_hunk = true;
+        } else if let (true, Some(commit)) = (in_hunk, current) {
+            if line.starts_with('+') || line.starts_with('-') {
+                commits
+                    .entry(commit.to_string())
+                    .or_default()
+                    .insert(line.to_string());
+            }
+        }
+    }
+    commits
+}
+
+/// Lines in common over lines in either
+fn similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    const OURS: &str = "\
+commit aaa
+Author: Jane <jane@example.com>
+
+    Fix auth
+
+diff --git a/src/auth.rs b/src/auth.rs
+--- a/src/auth.rs
++++ b/src/auth.rs
+@@ -1,5 +1,5 @@
+-let a = 1;
++let a = 2;
+-let b = 1;
++let b = 2;
+-let c = 1;
++let c = 2;
+-let d = 1;
++let d = 2;
+-let e = 1;
++let e = 2;
+";
+
+    #[test]
+    fn reads_pull_requests_from_gh_and_the_api_alike() {
+        let via_gh: Vec<PullRequest> = serde_json::from_str(
+            r#"[{"number":7,"title":"Fix auth","url":"https://github.com/acme/template/pull/7","headRefOid":"abc","headRefName":"fix-auth","body":"","createdAt":"2025-01-20T14:30:00Z"}]"#,
+        )
+        .unwrap();
+        let rest = serde_json::json!([{
+            "number": 7,
+            "title": "Fix auth",
+            "html_url": "https://github.com/acme/template/pull/7",
+            "url": "https://api.github.com/repos/acme/template/pulls/7",
+            "head": { "sha": "abc", "ref": "fix-auth" },
+            "body": null,
+            "created_at": "2025-01-20T14:30:00Z"
+        }]);
+        let via_api: Vec<PullRequest> = rest
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(PullRequest::from_rest)
+            .collect();
+        assert_eq!(via_gh, via_api);
+    }
+
+    #[test]
+    fn collects_changed_lines_per_commit() {
+        let log = format!("{}commit bbb\n\n    Other\n\ndiff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-old\n+new\n", OURS);
+        let lines = changed_lines(&log);
+        assert_eq!(lines["aaa"].len(), 10);
+        assert!(!lines["aaa"].contains("--- a/src/auth.rs"));
+        assert_eq!(
+            lines["bbb"],
+            HashSet::from(["-old".to_string(), "+new".to_string()])
+        );
+    }
+
+    #[test]
+    fn flags_same_patches_and_similar_changes() {
+        let pr = |number: u64, title: &str| PullRequest {
+            number,
+            title: title.to_string(),
+            url: format!("https://github.com/acme/template/pull/{}", number),
+            head: format!("pr{}", number),
+            branch: String::new(),
+            body: String::new(),
+            created_at: DateTime::default(),
+        };
+        // PR 90 says it carries eee, which it was squashed from
+        let metadata = PrMetadata::new(
+            "acme/app",
+            "Jane Doe",
+            vec![crate::metadata::SourceCommit {
+                sha: "eee".to_string(),
+                patch_id: None,
+                subject: "Tweak auth".to_string(),
+            }],
+        );
+        let prs = vec![
+            pr(87, "Fix auth"),
+            PullRequest {
+                body: metadata.embed("Tweaks auth"),
+                ..pr(90, "Tweak auth")
+            },
+        ];
+        // ddd makes the same change as aaa in another file, PR 90 has it with one line different
+        let ddd = OURS
+            .replace("commit aaa", "commit ddd")
+            .replace("auth.rs", "session.rs");
+        let tweaked = ddd
+            .replace("commit ddd", "commit ccc")
+            .replace("+let e = 2;", "+let e = 3;");
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on(
+                    "git log -p --no-merges --no-color --format=medium --no-walk",
+                    &format!("{}{}", OURS, ddd),
+                )
+                .on(
+                    "git log -p --no-merges --no-color --format=medium pr90",
+                    &tweaked,
+                )
+                // Every patch-id lookup answers this, so PR 87 has aaa's patch
+                .on("git patch-id", "p1 aaa\n"),
+        );
+
+        let found = process::with_runner(fake.clone(), || {
+            find(&["aaa", "ddd", "eee"], &prs, "base").unwrap()
+        });
+
+        assert_eq!(found.len(), 3);
+        assert_eq!((found[0].commit.as_str(), found[0].pr.number), ("aaa", 87));
+        assert_eq!(found[0].likeness, Likeness::SamePatch);
+        assert_eq!((found[1].commit.as_str(), found[1].pr.number), ("ddd", 90));
+        assert_eq!(found[1].likeness, Likeness::Similar(82));
+        assert_eq!((found[2].commit.as_str(), found[2].pr.number), ("eee", 90));
+        assert_eq!(found[2].likeness, Likeness::Recorded);
+        assert_eq!(
+            fake.calls_to("git log")[1],
+            "git log -p --no-merges --no-color --format=medium pr87 --not base"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// `[emails]` in .chuckrc: which author and committer emails may go upstream, for
+/// organisations that only publish under company addresses
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct EmailConfig {
+    /// Domains the emails on the chuck branch have to be at, subdomains included
+    #[serde(default)]
+    pub allowed_domains: Vec<String>,
+    /// Rewrite emails outside them without asking, where there's one to rewrite to
+    #[serde(default)]
+    pub rewrite: bool,
+}
+
+impl EmailConfig {
+    pub fn is_empty(&self) -> bool {
+        self.allowed_domains.is_empty()
+    }
+
+    pub fn allows(&self, email: &str) -> bool {
+        let Some((_, domain)) = email.rsplit_once('@') else {
+            return false;
+        };
+        let domain = domain.to_ascii_lowercase();
+        self.allowed_domains.iter().any(|allowed| {
+            let allowed = allowed.trim_start_matches('@').to_ascii_lowercase();
+            domain == allowed || domain.ends_with(&format!(".{}", allowed))
+        })
+    }
+}
+
+/// An email on the chuck branch outside the allowed domains
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub short_hash: String,
+    /// "author" or "committer"
+    pub role: &'static str,
+    pub name: String,
+    pub email: String,
+    /// The allowed email it can be rewritten to, if there is one
+    pub fix: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ident {
+    name: String,
+    email: String,
+    date: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BranchCommit {
+    hash: String,
+    author: Ident,
+    committer: Ident,
+}
+
+const FORMAT: &str = "--format=%H%x1f%an%x1f%ae%x1f%aI%x1f%cn%x1f%ce%x1f%cI";
+
+/// Every email outside `config`'s domains on the commits since `base`. An email is
+/// rewritten to what `.mailmap` maps it to, or for the user's own commits to their
+/// configured email, when that one is allowed.
+pub fn check(config: &EmailConfig, base: &str) -> Result<Vec<Violation>> {
+    let user = user_email();
+    let mut fixes: HashMap<(String, String), Option<String>> = HashMap::new();
+    let mut violations = Vec::new();
+    for commit in branch_commits(base)? {
+        for (role, ident) in [("author", &commit.author), ("committer", &commit.committer)] {
+            if config.allows(&ident.email) {
+                continue;
+            }
+            let key = (ident.name.clone(), ident.email.clone());
+            let fix = fixes
+                .entry(key)
+                .or_insert_with(|| {
+                    mailmapped(ident)
+                        .filter(|email| config.allows(email))
+                        .or_else(|| {
+                            user.as_ref()
+                                .filter(|(name, _)| *name == ident.name)
+                                .map(|(_, email)| email.clone())
+                                .filter(|email| config.allows(email))
+                        })
+                })
+                .clone();
+            violations.push(Violation {
+                short_hash: commit.hash[..7.min(commit.hash.len())].to_string(),
+                role,
+                name: ident.name.clone(),
+                email: ident.email.clone(),
+                fix,
+            });
+        }
+    }
+    Ok(violations)
+}
+
+/// Recreates the commits since `base` with the fixes of `violations` applied, keeping
+/// names, dates, messages and trees, and moves the checked-out branch to them
+pub fn rewrite(base: &str, violations: &[Violation]) -> Result<()> {
+    // Keyed like `check`, so one name's fix doesn't rewrite another's same address
+    let fixes: HashMap<(&str, &str), &str> = violations
+        .iter()
+        .filter_map(|v| Some(((v.name.as_str(), v.email.as_str()), v.fix.as_deref()?)))
+        .collect();
+    let fixed = |name: &str, email: &str| {
+        fixes
+            .get(&(name, email))
+            .copied()
+            .unwrap_or(email)
+            .to_string()
+    };
+    recreate(base, fixed, str::to_string)?;
+    Ok(())
+}
+
+/// Recreates the commits since `base` with `email` applied to each author's and
+/// committer's name and email and `message` to each message, keeping names, dates and
+/// trees, and moves the checked-out branch to them. Returns how many messages changed.
+pub fn recreate(
+    base: &str,
+    email: impl Fn(&str, &str) -> String,
+    message: impl Fn(&str) -> String,
+) -> Result<usize> {
+    let mut parent = git(&["rev-parse", base])?.trim().to_string();
+    let mut changed = 0;
+    for commit in branch_commits(base)? {
+        let tree = git(&["rev-parse", &format!("{}^{{tree}}", commit.hash)])?;
+        // The message exactly, after the headers
+        let raw = git(&["cat-file", "commit", &commit.hash])?;
+        let original = raw.split_once("\n\n").map_or("", |(_, message)| message);
+        let new_message = message(original);
+        if new_message != original {
+            changed += 1;
+        }
+        let output = Command::new("git")
+            .args(["commit-tree", tree.trim(), "-p", &parent, "-F", "-"])
+            .env("GIT_AUTHOR_NAME", &commit.author.name)
+            .env(
+                "GIT_AUTHOR_EMAIL",
+                email(&commit.author.name, &commit.author.email),
+            )
+            .env("GIT_AUTHOR_DATE", &commit.author.date)
+            .env("GIT_COMMITTER_NAME", &commit.committer.name)
+            .env(
+                "GIT_COMMITTER_EMAIL",
+                email(&commit.committer.name, &commit.committer.email),
+            )
+            .env("GIT_COMMITTER_DATE", &commit.committer.date)
+            .output_local_with_input(new_message.as_bytes())
+            .map_err(|e| process::command_error(e, "Failed to execute git commit-tree"))?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "Failed to rewrite {}: {}",
+                commit.hash,
+                error.trim()
+            ));
+        }
+        parent = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    }
+    // Same trees, so the work tree stays as it is
+    git(&["reset", "-q", "--soft", &parent])?;
+    Ok(changed)
+}
+
+fn branch_commits(base: &str) -> Result<Vec<BranchCommit>> {
+    let log = git(&["log", "--reverse", FORMAT, &format!("{}..HEAD", base)])?;
+    Ok(log
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split('\x1f').collect();
+            let [hash, an, ae, ad, cn, ce, cd] = fields[..] else {
+                return None;
+            };
+            let ident = |name: &str, email: &str, date: &str| Ident {
+                name: name.to_string(),
+                email: email.to_string(),
+                date: date.to_string(),
+            };
+            Some(BranchCommit {
+                hash: hash.to_string(),
+                author: ident(an, ae, ad),
+                committer: ident(cn, ce, cd),
+            })
+        })
+        .collect())
+}
+
+/// The email `.mailmap` gives `ident`, if it maps it to another one
+fn mailmapped(ident: &Ident) -> Option<String> {
+    let mapped = git(&[
+        "check-mailmap",
+        &format!("{} <{}>", ident.name, ident.email),
+    ])
+    .ok()?;
+    let email = mapped.trim().rsplit_once('<')?.1.trim_end_matches('>');
+    (email != ident.email).then(|| email.to_string())
+}
+
+/// `(name, email)` the user commits as
+fn user_email() -> Option<(String, String)> {
+    let ident = git(&["var", "GIT_COMMITTER_IDENT"]).ok()?;
+    let (name, rest) = ident.split_once('<')?;
+    let email = rest.split_once('>')?.0;
+    Some((name.trim().to_string(), email.to_string()))
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn finds_emails_outside_the_allowed_domains() {
+        let config = EmailConfig {
+            allowed_domains: vec!["acme.com".to_string()],
+            rewrite: false,
+        };
+        assert!(config.allows("jane@acme.com"));
+        assert!(config.allows("jane@eu.ACME.com"));
+        assert!(!config.allows("jane@notacme.com"));
+        assert!(!config.allows("jane"));
+
+        let log = "aaaaaaaaaa\x1fJane\x1fjane@gmail.com\x1f2025-01-01T00:00:00Z\x1fJane\x1fjane@acme.com\x1f2025-01-02T00:00:00Z\n\
+                   bbbbbbbbbb\x1fBob\x1fbob@home.org\x1f2025-01-01T00:00:00Z\x1fJane\x1fjane@acme.com\x1f2025-01-02T00:00:00Z\n\
+                   cccccccccc\x1fJoe\x1fjoe@acme.com\x1f2025-01-01T00:00:00Z\x1fJane\x1fjane@acme.com\x1f2025-01-02T00:00:00Z\n";
+        let fake = FakeRunner::new()
+            .on(
+                "git var GIT_COMMITTER_IDENT",
+                "Jane <jane@acme.com> 1700000000 +0000\n",
+            )
+            .on("git log --reverse", log)
+            .on("git check-mailmap Jane", "Jane <jane@gmail.com>\n")
+            .on("git check-mailmap Bob", "Bob <bob@acme.com>\n");
+        let violations = process::with_runner(Rc::new(fake), || check(&config, "base").unwrap());
+
+        assert_eq!(
+            violations,
+            [
+                Violation {
+                    short_hash: "aaaaaaa".to_string(),
+                    role: "author",
+                    name: "Jane".to_string(),
+                    email: "jane@gmail.com".to_string(),
+                    fix: Some("jane@acme.com".to_string()),
+                },
+                Violation {
+                    short_hash: "bbbbbbb".to_string(),
+                    role: "author",
+                    name: "Bob".to_string(),
+                    email: "bob@home.org".to_string(),
+                    fix: Some("bob@acme.com".to_string()),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use crate::process;
+use std::fmt;
+use std::process::ExitCode;
+
+/// What a run ended with, as the process exit code. Wrappers and CI can branch on
+/// these instead of parsing output. 2 is left to clap for usage errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Code {
+    Success = 0,
+    /// Anything without a more specific code below
+    Error = 1,
+    /// No `.chuckrc` or template remote, or the config couldn't be read
+    ConfigMissing = 3,
+    NotARepo = 4,
+    /// Nothing newer than the template to contribute
+    NoCandidates = 5,
+    /// Quit without selecting anything, or cancelled with Ctrl-C
+    Aborted = 6,
+    /// A selected commit didn't apply on the template base
+    Conflict = 7,
+    /// The branch was built but couldn't be pushed to the template
+    PushFailed = 8,
+    /// The branch was pushed but the pull request couldn't be opened
+    PrFailed = 9,
+}
+
+impl From<Code> for ExitCode {
+    fn from(code: Code) -> Self {
+        ExitCode::from(code as u8)
+    }
+}
+
+/// An error that ends the run with a specific exit code
+#[derive(Debug)]
+struct Failure {
+    code: Code,
+    error: anyhow::Error,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+impl std::error::Error for Failure {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
+/// Tags `error` with the exit code it should end the run with
+pub fn fail(code: Code, error: anyhow::Error) -> anyhow::Error {
+    anyhow::Error::new(Failure { code, error })
+}
+
+/// The exit code for an error: the one it was tagged with by `fail`, `Code::Aborted`
+/// for commands cancelled with Ctrl-C, otherwise `Code::Error`
+pub fn code_of(error: &anyhow::Error) -> Code {
+    match error.chain().find_map(|e| e.downcast_ref::<Failure>()) {
+        Some(failure) => failure.code,
+        None if error.to_string().contains(process::CANCELLED) => Code::Aborted,
+        None => Code::Error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+
+    #[test]
+    fn tagged_errors_keep_their_message() {
+        let error = fail(Code::Conflict, anyhow!("Cherry-pick failed"));
+        assert_eq!(error.to_string(), "Cherry-pick failed");
+        assert_eq!(code_of(&error), Code::Conflict);
+    }
+
+    #[test]
+    fn untagged_errors_are_generic() {
+        assert_eq!(code_of(&anyhow!("boom")), Code::Error);
+    }
+
+    #[test]
+    fn cancelled_commands_are_aborts() {
+        let error = anyhow!("git fetch: {}", process::CANCELLED);
+        assert_eq!(code_of(&error), Code::Aborted);
+    }
+
+    #[test]
+    fn codes_survive_added_context() {
+        let error = fail(Code::NotARepo, anyhow!("no .git")).context("while starting");
+        assert_eq!(code_of(&error), Code::NotARepo);
+    }
+}
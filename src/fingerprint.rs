@@ -0,0 +1,152 @@
+use crate::adopt;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+const NO_BLOB: &str = "0000000000000000000000000000000000000000";
+
+/// How candidate commits are told apart from what the project got from the template
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Commits newer than the template's last update, going by their dates
+    #[default]
+    Dates,
+    /// Every commit of the project, less those that only write files exactly as some
+    /// version of the template had them. Works when the project's history has nothing
+    /// in common with the template's, like a squashed import or a copy.
+    Content,
+}
+
+/// `[discovery]` in .chuckrc
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct DiscoveryConfig {
+    #[serde(default)]
+    pub strategy: Strategy,
+}
+
+/// Where a commit's changes came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Origin {
+    /// Every file it writes is byte for byte a version the template has had
+    Template,
+    /// It writes something the template never had, or only deletes
+    New,
+}
+
+/// The blob of every version of every file the template has had
+pub struct Fingerprint {
+    blobs: HashSet<String>,
+}
+
+impl Fingerprint {
+    /// Everything up to `base`, in any of its commits and at any path, so content
+    /// counts as the template's wherever the project put it
+    pub fn of_template(base: &str) -> Result<Self> {
+        let mut blobs: HashSet<String> = adopt::blobs(base)?.into_values().collect();
+        for (_, written) in raw_log(&["--no-merges", base])? {
+            blobs.extend(written);
+        }
+        Ok(Self { blobs })
+    }
+
+    /// Where a commit writing `written` got them from
+    pub fn origin(&self, written: &[String]) -> Origin {
+        if !written.is_empty() && written.iter().all(|blob| self.blobs.contains(blob)) {
+            Origin::Template
+        } else {
+            Origin::New
+        }
+    }
+}
+
+/// The origin of every non-merge commit `rev_args` lists, by hash
+pub fn classify(rev_args: &[&str], template: &Fingerprint) -> Result<HashMap<String, Origin>> {
+    let mut args = vec!["--no-merges"];
+    args.extend_from_slice(rev_args);
+    Ok(raw_log(&args)?
+        .into_iter()
+        .map(|(hash, written)| {
+            let origin = template.origin(&written);
+            (hash, origin)
+        })
+        .collect())
+}
+
+/// `(hash, blobs written)` for each commit `git log <rev_args>` lists
+fn raw_log(rev_args: &[&str]) -> Result<Vec<(String, Vec<String>)>> {
+    let mut args = vec![
+        "log",
+        "--no-renames",
+        "--raw",
+        "--no-abbrev",
+        "--format=%x1e%H",
+    ];
+    args.extend_from_slice(rev_args);
+    args.push("--");
+
+    let output = Command::new("git")
+        .args(&args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to read the history: {}", error.trim()));
+    }
+
+    Ok(parse_raw(&String::from_utf8_lossy(&output.stdout)))
+}
+
+fn parse_raw(log: &str) -> Vec<(String, Vec<String>)> {
+    log.split('\x1e')
+        .filter(|r| !r.trim().is_empty())
+        .filter_map(|record| {
+            let mut lines = record.lines();
+            let hash = lines.next()?.trim().to_string();
+            // ":<old mode> <new mode> <old sha> <new sha> <status>\t<path>", deletions
+            // write nothing
+            let written = lines
+                .filter(|l| l.starts_with(':'))
+                .filter_map(|l| l.split_once('\t')?.0.split(' ').nth(3))
+                .filter(|blob| *blob != NO_BLOB)
+                .map(str::to_string)
+                .collect();
+            Some((hash, written))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tells_template_content_from_new_code() {
+        let template = Fingerprint {
+            blobs: ["r1", "r2", "l1"].map(str::to_string).into(),
+        };
+        let log = format!(
+            "\x1eeee\n\n:000000 100644 {0} a1 A\tapp.rs\n:100644 100644 r1 r2 M\tREADME.md\n\
+             \x1eddd\n\n:100644 100644 r1 r2 M\tREADME.md\n\
+             \x1eccc\n\n:000000 100644 {0} r1 A\tdocs/README.md\n:000000 100644 {0} l1 A\tlib.rs\n\
+             \x1ebbb\n\n:100644 000000 l1 {0} D\tlib.rs\n",
+            NO_BLOB
+        );
+        let origins: Vec<(String, Origin)> = parse_raw(&log)
+            .into_iter()
+            .map(|(hash, written)| (hash, template.origin(&written)))
+            .collect();
+        assert_eq!(
+            origins,
+            [
+                ("eee".to_string(), Origin::New),
+                ("ddd".to_string(), Origin::Template),
+                ("ccc".to_string(), Origin::Template),
+                ("bbb".to_string(), Origin::New),
+            ]
+        );
+    }
+}
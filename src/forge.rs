@@ -0,0 +1,442 @@
+use crate::network;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::env;
+use std::path::Path;
+use std::process::Command;
+use std::sync::OnceLock;
+
+const API_URL: &str = "https://api.github.com";
+
+/// Checked in this order, like gh does
+const TOKEN_VARS: [&str; 2] = ["GH_TOKEN", "GITHUB_TOKEN"];
+
+/// `[forge]` in the global config: how Chuck talks to GitHub
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct ForgeConfig {
+    #[serde(default)]
+    pub client: Client,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Client {
+    /// gh when it's installed, otherwise the API with a token from the environment
+    #[default]
+    Auto,
+    /// The GitHub CLI, with its login and SSO handling
+    Gh,
+    /// The REST and GraphQL APIs through curl, with `GH_TOKEN` or `GITHUB_TOKEN`
+    Api,
+}
+
+static CONFIG: OnceLock<ForgeConfig> = OnceLock::new();
+
+pub fn configure(config: ForgeConfig) {
+    let _ = CONFIG.set(config);
+}
+
+fn config() -> &'static ForgeConfig {
+    CONFIG.get_or_init(ForgeConfig::default)
+}
+
+/// How requests to GitHub go out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Backend {
+    Gh,
+    Api { token: String },
+}
+
+/// The backend `[forge] client` settles on here
+pub fn backend() -> Result<Backend> {
+    match config().client {
+        Client::Gh => Ok(Backend::Gh),
+        Client::Api => token().map(|token| Backend::Api { token }).ok_or_else(|| {
+            anyhow!("[forge] client = \"api\" needs a token in GH_TOKEN or GITHUB_TOKEN")
+        }),
+        Client::Auto => Ok(match token() {
+            // A fake runner stands in for gh whatever is installed
+            Some(token) if !on_path("gh") && !process::is_faked() => Backend::Api { token },
+            // Without either, gh's "not found" says best what's missing
+            _ => Backend::Gh,
+        }),
+    }
+}
+
+/// What a GitHub-only feature without an API path says under `client = "api"`
+pub fn require_gh(what: &str) -> Result<()> {
+    match backend()? {
+        Backend::Gh => Ok(()),
+        Backend::Api { .. } => Err(anyhow!(
+            "{} needs the GitHub CLI, and [forge] client is \"api\"",
+            what
+        )),
+    }
+}
+
+fn token() -> Option<String> {
+    TOKEN_VARS
+        .iter()
+        .find_map(|var| env::var(var).ok().filter(|token| !token.is_empty()))
+}
+
+/// Looks through `PATH` rather than running the program, so it costs nothing
+fn on_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path).any(|dir| Path::new(&dir).join(program).is_file())
+    })
+}
+
+/// An HTTP response as `gh api --include` and `curl --include` print it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Response {
+    fn parse(output: &str) -> Option<Self> {
+        let mut rest = output;
+        // Proxies and `100 Continue` put a response of their own in front
+        let mut response = None;
+        while rest.starts_with("HTTP/") {
+            let (head, body) = rest
+                .split_once("\r\n\r\n")
+                .or_else(|| rest.split_once("\n\n"))
+                .unwrap_or((rest, ""));
+            let mut lines = head.lines();
+            let status = lines.next()?.split(' ').nth(1)?.parse().ok()?;
+            let headers = lines
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+                .collect();
+            response = Some(Self {
+                status,
+                headers,
+                body: body.to_string(),
+            });
+            rest = body;
+        }
+        response
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn rate_limited(&self) -> bool {
+        matches!(self.status, 403 | 429) && self.header("x-ratelimit-remaining") == Some("0")
+    }
+
+    /// When the rate limit resets, e.g. "at 14:05"
+    pub fn reset(&self) -> Option<String> {
+        let epoch = self.header("x-ratelimit-reset")?.parse().ok()?;
+        let reset = DateTime::from_timestamp(epoch, 0)?;
+        Some(format!(
+            "at {}",
+            reset.with_timezone(&Local).format("%H:%M")
+        ))
+    }
+
+    /// GitHub's message for a failed request, e.g. "Not Found (HTTP 404)"
+    pub fn error(&self) -> String {
+        let message = serde_json::from_str::<Value>(&self.body)
+            .ok()
+            .and_then(|json| json.get("message")?.as_str().map(str::to_string));
+        match message {
+            Some(message) => format!("{} (HTTP {})", message, self.status),
+            None if !self.body.trim().is_empty() => self.body.trim().to_string(),
+            None => format!("HTTP {}", self.status),
+        }
+    }
+
+    pub fn json(&self) -> Result<Value> {
+        Ok(serde_json::from_str(&self.body)?)
+    }
+}
+
+/// GETs `path` from the REST API (e.g. `repos/acme/app`) with extra `headers`,
+/// whatever the status. Only failing to get an answer at all is an error, starting
+/// with `context`.
+pub fn get(path: &str, headers: &[String], context: &str) -> Result<Response> {
+    request(&backend()?, "GET", path, headers, None, context)
+}
+
+/// GETs `path` as JSON, failing on anything but a 2xx
+pub fn get_json(path: &str, context: &str) -> Result<Value> {
+    let response = get(path, &[], context)?;
+    if !response.is_success() {
+        return Err(anyhow!(network::describe_failure(
+            context,
+            &response.error()
+        )));
+    }
+    response.json()
+}
+
+/// Sends `body` to `path` with `method` (e.g. "POST"), returning the JSON answer
+pub fn send(method: &str, path: &str, body: &Value, context: &str) -> Result<Value> {
+    let response = request(&backend()?, method, path, &[], Some(body), context)?;
+    if !response.is_success() {
+        return Err(anyhow!(network::describe_failure(
+            context,
+            &response.error()
+        )));
+    }
+    response.json()
+}
+
+/// Runs a GraphQL `query` with string `variables`, returning the JSON answer
+pub fn graphql(query: &str, variables: &[(&str, &str)], context: &str) -> Result<Value> {
+    match backend()? {
+        Backend::Gh => {
+            let mut command = Command::new("gh");
+            command.args(["api", "graphql", "-f", &format!("query={}", query)]);
+            for (name, value) in variables {
+                command.args(["-f", &format!("{}={}", name, value)]);
+            }
+            let output = command
+                .output_network()
+                .map_err(|e| process::command_error(e, context))?;
+            if !output.status.success() {
+                let error = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow!(network::describe_failure(context, &error)));
+            }
+            Ok(serde_json::from_slice(&output.stdout)?)
+        }
+        api @ Backend::Api { .. } => {
+            let variables: serde_json::Map<String, Value> = variables
+                .iter()
+                .map(|(name, value)| (name.to_string(), Value::from(*value)))
+                .collect();
+            let body = serde_json::json!({ "query": query, "variables": variables });
+            let response = request(&api, "POST", "graphql", &[], Some(&body), context)?;
+            let json = response.json().ok();
+            // GraphQL reports its errors with a 200
+            let errors = json.as_ref().and_then(|json| json.get("errors"));
+            match (response.is_success(), errors) {
+                (true, None) => Ok(json.unwrap_or_default()),
+                (true, Some(errors)) => Err(anyhow!(network::describe_failure(
+                    context,
+                    &errors.to_string()
+                ))),
+                (false, _) => Err(anyhow!(network::describe_failure(
+                    context,
+                    &response.error()
+                ))),
+            }
+        }
+    }
+}
+
+fn request(
+    backend: &Backend,
+    method: &str,
+    path: &str,
+    headers: &[String],
+    body: Option<&Value>,
+    context: &str,
+) -> Result<Response> {
+    let (mut command, input) = match backend {
+        Backend::Gh => {
+            let mut command = Command::new("gh");
+            command.args(["api", path, "--include"]);
+            if method != "GET" {
+                command.args(["--method", method]);
+            }
+            for header in headers {
+                command.args(["-H", header]);
+            }
+            let input = body.map(|body| {
+                command.args(["--input", "-"]);
+                body.to_string()
+            });
+            (command, input)
+        }
+        Backend::Api { token } => {
+            let mut command = Command::new("curl");
+            command.args([
+                "--silent",
+                "--show-error",
+                "--include",
+                "--request",
+                method,
+                "--config",
+                "-",
+                &format!("{}/{}", API_URL, path),
+            ]);
+            (command, Some(curl_config(token, headers, body)))
+        }
+    };
+
+    let output = match &input {
+        Some(input) => command.output_network_with_input(input.as_bytes()),
+        None => command.output_network(),
+    }
+    .map_err(|e| process::command_error(e, context))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if let Some(response) = Response::parse(&stdout) {
+        return Ok(response);
+    }
+    let error = String::from_utf8_lossy(&output.stderr);
+    match backend {
+        // Not what gh prints with --include, take it as the body
+        Backend::Gh if output.status.success() => Ok(Response {
+            status: 200,
+            headers: Vec::new(),
+            body: stdout.into_owned(),
+        }),
+        // gh says "gh: Not Found (HTTP 404)" when it couldn't print the response
+        Backend::Gh => match http_status(&error) {
+            Some(status) => Ok(Response {
+                status,
+                headers: Vec::new(),
+                body: error.trim().to_string(),
+            }),
+            None => Err(anyhow!(network::describe_failure(context, &error))),
+        },
+        Backend::Api { .. } => Err(anyhow!(network::describe_failure(context, &error))),
+    }
+}
+
+/// curl options read from stdin, so the token stays out of the process list
+fn curl_config(token: &str, headers: &[String], body: Option<&Value>) -> String {
+    // Double-quoted values take backslash escapes
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let mut config = String::new();
+    for header in [
+        format!("Authorization: Bearer {}", token),
+        "Accept: application/vnd.github+json".to_string(),
+        "User-Agent: chuck".to_string(),
+    ]
+    .iter()
+    .chain(headers)
+    {
+        config.push_str(&format!("header = {}\n", quote(header)));
+    }
+    if let Some(body) = body {
+        config.push_str(&format!("data-binary = {}\n", quote(&body.to_string())));
+    }
+    config
+}
+
+fn http_status(stderr: &str) -> Option<u16> {
+    let start = stderr.find("(HTTP ")? + "(HTTP ".len();
+    stderr[start..].get(..3)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    const TOKEN: &str = "ghp_secret";
+
+    fn api() -> Backend {
+        Backend::Api {
+            token: TOKEN.to_string(),
+        }
+    }
+
+    /// The same request through gh and through curl, each answered like it would be
+    fn both(
+        gh_output: &str,
+        curl_output: &str,
+        request: impl Fn(&Backend) -> Result<Response>,
+    ) -> (Response, Response, Rc<FakeRunner>) {
+        let fake = Rc::new(FakeRunner::new().on("gh api", gh_output));
+        let via_gh = process::with_runner(fake, || request(&Backend::Gh)).unwrap();
+        let fake = Rc::new(FakeRunner::new().on("curl", curl_output));
+        let via_api = process::with_runner(fake.clone(), || request(&api())).unwrap();
+        (via_gh, via_api, fake)
+    }
+
+    #[test]
+    fn gh_and_the_api_agree_on_a_get() {
+        let (via_gh, via_api, curl) = both(
+            "HTTP/2.0 200 OK\r\nEtag: \"abc\"\r\n\r\n{\"full_name\":\"acme/template\"}",
+            "HTTP/2 200 \r\netag: \"abc\"\r\n\r\n{\"full_name\":\"acme/template\"}",
+            |backend| {
+                request(
+                    backend,
+                    "GET",
+                    "repos/acme/template",
+                    &["If-None-Match: \"xyz\"".to_string()],
+                    None,
+                    "Failed",
+                )
+            },
+        );
+        assert_eq!(via_gh, via_api);
+        assert_eq!(via_api.header("ETag"), Some("\"abc\""));
+
+        let call = &curl.calls()[0];
+        assert!(call.ends_with(" https://api.github.com/repos/acme/template"));
+        assert!(!call.contains(TOKEN), "{}", call);
+        let config = String::from_utf8(curl.inputs()[0].clone()).unwrap();
+        assert!(config.contains("header = \"Authorization: Bearer ghp_secret\"\n"));
+        assert!(config.contains("header = \"If-None-Match: \\\"xyz\\\"\"\n"));
+    }
+
+    #[test]
+    fn gh_and_the_api_agree_on_failures() {
+        let not_found =
+            "{\"message\":\"Not Found\",\"documentation_url\":\"https://docs.github.com\"}";
+        let (via_gh, via_api, _) = both(
+            &format!("HTTP/2.0 404 Not Found\r\n\r\n{}", not_found),
+            &format!(
+                "HTTP/1.1 200 Connection established\r\n\r\nHTTP/2 404 \r\n\r\n{}",
+                not_found
+            ),
+            |backend| request(backend, "GET", "repos/acme/gone", &[], None, "Failed"),
+        );
+        assert_eq!(via_gh.status, 404);
+        assert_eq!(via_gh.error(), "Not Found (HTTP 404)");
+        assert_eq!((via_api.status, via_api.error()), (404, via_gh.error()));
+
+        // Older gh only says so on stderr
+        let fake = Rc::new(FakeRunner::new().fail("gh api", "gh: Not Found (HTTP 404)"));
+        let response = process::with_runner(fake, || {
+            request(&Backend::Gh, "GET", "x", &[], None, "Failed")
+        });
+        assert_eq!(response.unwrap().status, 404);
+    }
+
+    #[test]
+    fn sends_bodies_on_stdin() {
+        let body = serde_json::json!({ "title": "Say \"hi\"", "draft": true });
+        let created = "HTTP/2 201 Created\r\n\r\n{\"number\":7}";
+        let (via_gh, via_api, curl) = both(created, created, |backend| {
+            request(
+                backend,
+                "POST",
+                "repos/acme/template/pulls",
+                &[],
+                Some(&body),
+                "Failed",
+            )
+        });
+        assert_eq!(via_gh, via_api);
+
+        assert!(curl.calls()[0].contains("--request POST"));
+        let config = String::from_utf8(curl.inputs()[0].clone()).unwrap();
+        assert!(
+            config.contains(r#"data-binary = "{\"draft\":true,\"title\":\"Say \\\"hi\\\"\"}""#),
+            "{}",
+            config
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// `[formatting]` in .chuckrc, for templates and projects that only differ in how
+/// their code is laid out
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct FormattingConfig {
+    /// Whitespace-only changes don't count when looking for template fixes, and commits
+    /// that only change whitespace are left out of "select all"
+    #[serde(default)]
+    pub ignore_whitespace: bool,
+    /// Line endings for the files the picks change on the chuck branch
+    pub line_endings: Option<LineEndings>,
+    /// Run on the chuck branch before pushing, with the files the picks change as
+    /// arguments, e.g. "prettier --write"
+    pub formatter: Option<String>,
+}
+
+impl FormattingConfig {
+    /// Whether there's anything to do to the chuck branch
+    pub fn tidies(&self) -> bool {
+        self.line_endings.is_some() || self.formatter.is_some()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEndings {
+    Lf,
+    Crlf,
+}
+
+/// `content` with every line ending as `endings`, `None` when it already is or looks
+/// binary
+pub fn normalize(content: &[u8], endings: LineEndings) -> Option<Vec<u8>> {
+    if content.contains(&0) {
+        return None;
+    }
+    let mut normalized = Vec::with_capacity(content.len());
+    for (i, &byte) in content.iter().enumerate() {
+        match byte {
+            b'\r' if content.get(i + 1) == Some(&b'\n') => {}
+            b'\n' => {
+                if endings == LineEndings::Crlf {
+                    normalized.push(b'\r');
+                }
+                normalized.push(b'\n');
+            }
+            byte => normalized.push(byte),
+        }
+    }
+    (normalized != content).then_some(normalized)
+}
+
+/// Normalizes line endings and runs the formatter on the files changed since `base`,
+/// then commits whatever that changed on top of the picks. Other files are left as
+/// they were, so a formatter that does the whole tree doesn't drag the template's
+/// unrelated files into the PR. Returns how many files the commit changes.
+pub fn tidy(base: &str, config: &FormattingConfig, verbose: bool) -> Result<usize> {
+    // Paths are relative to the top of the work tree, so that's where this runs
+    let root = git(".", &["rev-parse", "--show-toplevel"])?;
+    let root = Path::new(root.trim());
+    let git = |args: &[&str]| git(root, args);
+    // Uncommitted changes carried over to the branch would end up in the commit
+    if !git(&["status", "--porcelain", "--untracked-files=no"])?
+        .trim()
+        .is_empty()
+    {
+        return Err(anyhow!(
+            "there are uncommitted changes, commit or stash them first"
+        ));
+    }
+
+    let changed = git(&[
+        "diff",
+        "--name-only",
+        "--no-renames",
+        "--diff-filter=AM",
+        "-z",
+        base,
+        "HEAD",
+    ])?;
+    // Outside a sparse checkout's cone there's nothing on disk to tidy
+    let files: Vec<&str> = changed
+        .split('\0')
+        .filter(|path| !path.is_empty() && root.join(path).is_file())
+        .collect();
+    if files.is_empty() {
+        return Ok(0);
+    }
+
+    let mut done = Vec::new();
+    if let Some(endings) = config.line_endings {
+        for path in &files {
+            let file = root.join(path);
+            let content = fs::read(&file).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?;
+            if let Some(normalized) = normalize(&content, endings) {
+                if verbose {
+                    println!("🧔 VERBOSE: Normalizing line endings in {}", path);
+                }
+                fs::write(&file, normalized)
+                    .map_err(|e| anyhow!("Failed to write {}: {}", path, e))?;
+            }
+        }
+        done.push("line endings".to_string());
+    }
+
+    if let Some(formatter) = &config.formatter {
+        if verbose {
+            println!("🧔 VERBOSE: Running {} on {} files", formatter, files.len());
+        }
+        let output = Command::new("sh")
+            .args(["-c", &format!("{} \"$@\"", formatter), "chuck-formatter"])
+            .args(&files)
+            .current_dir(root)
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to run the formatter"))?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("{} failed: {}", formatter, error.trim()));
+        }
+        done.push(formatter.clone());
+    }
+
+    let mut add = vec!["add", "--"];
+    add.extend(&files);
+    git(&add)?;
+    // Whatever else the formatter touched goes back to how the picks left it
+    git(&["checkout", "--", "."])?;
+
+    let tidied = git(&["diff", "--cached", "--name-only", "-z"])?;
+    let count = tidied.split('\0').filter(|path| !path.is_empty()).count();
+    if count > 0 {
+        let message = format!("Normalize formatting ({})", done.join(", "));
+        git(&["commit", "--no-verify", "-m", &message])?;
+    }
+    Ok(count)
+}
+
+fn git(dir: impl AsRef<Path>, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_line_endings() {
+        assert_eq!(
+            normalize(b"a\r\nb\nc\r\n", LineEndings::Lf).unwrap(),
+            b"a\nb\nc\n"
+        );
+        assert_eq!(
+            normalize(b"a\nb\r\n", LineEndings::Crlf).unwrap(),
+            b"a\r\nb\r\n"
+        );
+        // A lone carriage return isn't a line ending
+        assert_eq!(normalize(b"a\rb\n", LineEndings::Lf), None);
+        assert_eq!(normalize(b"\0\r\n", LineEndings::Lf), None);
+    }
+}
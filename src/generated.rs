@@ -0,0 +1,197 @@
+use crate::glob::gitignore_match;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// How a conflict in a generated file gets settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// Keep the chuck branch's version, which is the template's
+    Ours,
+    /// Take the picked commit's version
+    Theirs,
+    /// Run `command` and keep what it writes
+    Regenerate,
+}
+
+/// `[[generated]]` in .chuckrc: files that only conflict because they're generated,
+/// and what to do when they do
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct GeneratedConfig {
+    /// `.gitignore` pattern, relative to the template's root, e.g. "src/schema.rs"
+    pub path: String,
+    pub resolve: Strategy,
+    /// With `resolve = "regenerate"`: run at the repository root once the other
+    /// strategies are applied, e.g. "cargo run --bin gen-schema"
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+/// Settles the conflicts in `unmerged` that a `[[generated]]` entry covers and stages
+/// them. Files whose strategy can't be applied, like taking a side that deleted the
+/// file or a failing command, stay unmerged. Returns the files settled.
+pub fn resolve_conflicts(
+    configs: &[GeneratedConfig],
+    unmerged: &[String],
+    verbose: bool,
+) -> Result<Vec<String>> {
+    let matched: Vec<(&String, &GeneratedConfig)> = unmerged
+        .iter()
+        .filter_map(|path| {
+            configs
+                .iter()
+                .find(|config| gitignore_match(&config.path, path))
+                .map(|config| (path, config))
+        })
+        .collect();
+    if matched.is_empty() {
+        return Ok(Vec::new());
+    }
+    let root = git(".", &["rev-parse", "--show-toplevel"])?;
+    let root = Path::new(root.trim());
+    let git = |args: &[&str]| git(root, args);
+
+    let mut resolved = Vec::new();
+    let mut commands: Vec<(&str, Vec<&String>)> = Vec::new();
+    for (path, config) in matched {
+        let side = match config.resolve {
+            Strategy::Ours | Strategy::Regenerate => "--ours",
+            Strategy::Theirs => "--theirs",
+        };
+        // Regenerating starts from the branch's copy, without conflict markers
+        if let Err(e) = git(&["checkout", side, "--", path]) {
+            if verbose {
+                println!("🧔 VERBOSE: Leaving {} unresolved: {}", path, e);
+            }
+            continue;
+        }
+        match (config.resolve, config.command.as_deref()) {
+            (Strategy::Regenerate, Some(command)) => {
+                match commands.iter_mut().find(|(c, _)| *c == command) {
+                    Some((_, paths)) => paths.push(path),
+                    None => commands.push((command, vec![path])),
+                }
+            }
+            (Strategy::Regenerate, None) => {
+                println!(
+                    "🧔 {} is set to regenerate without a command, leaving it unresolved",
+                    config.path
+                );
+            }
+            _ => {
+                git(&["add", "--", path])?;
+                resolved.push(path.clone());
+            }
+        }
+    }
+
+    for (command, paths) in commands {
+        if verbose {
+            println!("🧔 VERBOSE: Running {} in {}", command, root.display());
+        }
+        let output = Command::new("sh")
+            .args(["-c", command])
+            .current_dir(root)
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to regenerate files"))?;
+        if !output.status.success() {
+            println!(
+                "🧔 {} failed, leaving {} unresolved: {}",
+                command,
+                paths
+                    .iter()
+                    .map(|p| p.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            continue;
+        }
+        for path in paths {
+            git(&["add", "--", path])?;
+            resolved.push(path.clone());
+        }
+    }
+    Ok(resolved)
+}
+
+fn git(dir: impl AsRef<Path>, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    fn generated(path: &str, resolve: Strategy, command: Option<&str>) -> GeneratedConfig {
+        GeneratedConfig {
+            path: path.to_string(),
+            resolve,
+            command: command.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn settles_generated_files_by_strategy() {
+        let fake = Rc::new(FakeRunner::new().on("git rev-parse --show-toplevel", "/repo\n"));
+        let configs = [
+            generated("openapi.yaml", Strategy::Theirs, None),
+            generated("schema.rs", Strategy::Regenerate, Some("make schema")),
+        ];
+        let unmerged = [
+            "openapi.yaml".to_string(),
+            "src/schema.rs".to_string(),
+            "src/lib.rs".to_string(),
+        ];
+
+        let resolved = process::with_runner(fake.clone(), || {
+            resolve_conflicts(&configs, &unmerged, false)
+        });
+
+        assert_eq!(resolved.unwrap(), ["openapi.yaml", "src/schema.rs"]);
+        assert_eq!(
+            fake.calls_to("git checkout"),
+            [
+                "git checkout --theirs -- openapi.yaml",
+                "git checkout --ours -- src/schema.rs"
+            ]
+        );
+        assert_eq!(fake.calls_to("sh"), ["sh -c make schema"]);
+        assert_eq!(
+            fake.calls_to("git add"),
+            ["git add -- openapi.yaml", "git add -- src/schema.rs"]
+        );
+    }
+
+    #[test]
+    fn leaves_files_a_failing_command_was_to_regenerate() {
+        let fake = Rc::new(FakeRunner::new().fail("sh -c make schema", "no rule"));
+        let configs = [generated(
+            "schema.rs",
+            Strategy::Regenerate,
+            Some("make schema"),
+        )];
+
+        let resolved = process::with_runner(fake.clone(), || {
+            resolve_conflicts(&configs, &["schema.rs".to_string()], false)
+        });
+
+        assert!(resolved.unwrap().is_empty());
+        assert!(fake.calls_to("git add").is_empty());
+    }
+}
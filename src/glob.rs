@@ -0,0 +1,61 @@
+/// Matches `path` against a glob pattern where `*` and `?` stay within one path
+/// segment and `**` spans any number of directories (`src/**/*.rs`, `docs/**`).
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    match_from(pattern.as_bytes(), path.as_bytes())
+}
+
+fn match_from(pattern: &[u8], path: &[u8]) -> bool {
+    if pattern.is_empty() {
+        return path.is_empty();
+    }
+
+    if let Some(rest) = pattern.strip_prefix(b"**") {
+        if rest.is_empty() {
+            return true;
+        }
+        // `**/` matches zero or more whole directories
+        if let Some(rest) = rest.strip_prefix(b"/") {
+            return (0..=path.len())
+                .filter(|&i| i == 0 || path[i - 1] == b'/')
+                .any(|i| match_from(rest, &path[i..]));
+        }
+        return (0..=path.len()).any(|i| match_from(rest, &path[i..]));
+    }
+
+    match pattern[0] {
+        b'*' => {
+            for i in 0..=path.len() {
+                if match_from(&pattern[1..], &path[i..]) {
+                    return true;
+                }
+                if i < path.len() && path[i] == b'/' {
+                    break;
+                }
+            }
+            false
+        }
+        b'?' => !path.is_empty() && path[0] != b'/' && match_from(&pattern[1..], &path[1..]),
+        c => !path.is_empty() && path[0] == c && match_from(&pattern[1..], &path[1..]),
+    }
+}
+
+/// Matches with gitignore/CODEOWNERS semantics: a pattern without a slash matches
+/// at any depth, a leading `/` anchors to the root, and a pattern naming a
+/// directory matches everything inside it.
+pub fn gitignore_match(pattern: &str, path: &str) -> bool {
+    let directory_only = pattern.ends_with('/');
+    let trimmed = pattern.trim_end_matches('/');
+    let anchored = trimmed.starts_with('/') || trimmed.contains('/');
+    let trimmed = trimmed.trim_start_matches('/');
+
+    let base = if anchored {
+        trimmed.to_string()
+    } else {
+        format!("**/{}", trimmed)
+    };
+
+    if !directory_only && glob_match(&base, path) {
+        return true;
+    }
+    glob_match(&format!("{}/**", base), path)
+}
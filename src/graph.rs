@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+/// Draws a `git log --graph` style gutter for commits listed newest first, given as
+/// `(hash, parents)`. Returns one equally wide string per commit, ending in a space so
+/// it can go straight in front of the row. Only edges between
+/// listed commits are drawn, so history outside the list doesn't leave dangling lanes.
+pub fn layout(commits: &[(&str, &[String])]) -> Vec<String> {
+    let listed: HashSet<&str> = commits.iter().map(|(hash, _)| *hash).collect();
+    // Each lane holds the hash of the commit it's heading down to
+    let mut lanes: Vec<Option<&str>> = Vec::new();
+    let mut rows: Vec<Vec<char>> = Vec::new();
+
+    for (hash, parents) in commits {
+        let col = match lanes.iter().position(|lane| *lane == Some(*hash)) {
+            Some(col) => col,
+            None => free_lane(&mut lanes),
+        };
+
+        // Other lanes heading to this commit end here
+        let joins: Vec<usize> = (0..lanes.len())
+            .filter(|&i| i != col && lanes[i] == Some(*hash))
+            .collect();
+        for &i in &joins {
+            lanes[i] = None;
+        }
+
+        let mut parents = parents
+            .iter()
+            .map(String::as_str)
+            .filter(|p| listed.contains(p));
+        lanes[col] = parents.next();
+        let mut forks = Vec::new();
+        for parent in parents {
+            let lane = match lanes.iter().position(|lane| *lane == Some(parent)) {
+                Some(lane) => lane,
+                None => {
+                    let lane = free_lane(&mut lanes);
+                    lanes[lane] = Some(parent);
+                    lane
+                }
+            };
+            forks.push(lane);
+        }
+
+        let width = lanes
+            .len()
+            .max(joins.iter().chain(&forks).max().map_or(0, |m| m + 1));
+        let mut row = vec![' '; width * 2];
+        for (i, lane) in lanes.iter().enumerate() {
+            if lane.is_some() {
+                row[i * 2] = '│';
+            }
+        }
+        for (lanes, left, right) in [(&joins, '╰', '╯'), (&forks, '╭', '╮')] {
+            for &i in lanes.iter() {
+                let (from, to) = if i < col { (i, col) } else { (col, i) };
+                for cell in row.iter_mut().take(to * 2).skip(from * 2 + 1) {
+                    if *cell == ' ' {
+                        *cell = '─';
+                    }
+                }
+                row[i * 2] = if i < col { left } else { right };
+            }
+        }
+        row[col * 2] = '●';
+        rows.push(row);
+    }
+
+    let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+    rows.into_iter()
+        .map(|mut row| {
+            row.resize(width, ' ');
+            row.into_iter().collect()
+        })
+        .collect()
+}
+
+fn free_lane(lanes: &mut Vec<Option<&str>>) -> usize {
+    match lanes.iter().position(Option::is_none) {
+        Some(i) => i,
+        None => {
+            lanes.push(None);
+            lanes.len() - 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph(commits: &[(&str, &[&str])]) -> Vec<String> {
+        let parents: Vec<Vec<String>> = commits
+            .iter()
+            .map(|(_, parents)| parents.iter().map(|p| p.to_string()).collect())
+            .collect();
+        let commits: Vec<(&str, &[String])> = commits
+            .iter()
+            .zip(&parents)
+            .map(|((hash, _), parents)| (*hash, parents.as_slice()))
+            .collect();
+        layout(&commits)
+            .into_iter()
+            .map(|row| row.trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn linear_history_is_one_lane() {
+        assert_eq!(
+            graph(&[("c", &["b"]), ("b", &["a"]), ("a", &["base"])]),
+            ["●", "●", "●"]
+        );
+    }
+
+    #[test]
+    fn merges_fork_and_join_lanes() {
+        // m merges feature f into main b, both off a
+        assert_eq!(
+            graph(&[
+                ("m", &["b", "f"]),
+                ("f", &["a"]),
+                ("b", &["a"]),
+                ("a", &["base"]),
+            ]),
+            ["●─╮", "│ ●", "● │", "●─╯"]
+        );
+    }
+
+    #[test]
+    fn unlisted_parents_end_their_lane() {
+        assert_eq!(
+            graph(&[("m", &["b", "outside"]), ("b", &["a"]), ("a", &[])]),
+            ["●", "●", "●"]
+        );
+    }
+}
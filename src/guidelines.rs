@@ -0,0 +1,202 @@
+use crate::process::CommandExt;
+use std::process::Command;
+
+/// Where GitHub looks for contribution guidelines, in priority order
+const LOCATIONS: [&str; 3] = [
+    ".github/CONTRIBUTING.md",
+    "CONTRIBUTING.md",
+    "docs/CONTRIBUTING.md",
+];
+
+/// Marks the list of requirements meant for tools like Chuck, around a plain Markdown
+/// list so people reading the file see it too
+const START: &str = "<!-- chuck:requirements -->";
+const END: &str = "<!-- /chuck:requirements -->";
+
+/// Headings whose list is taken for the requirements when nothing is marked
+const CHECKLIST_HEADINGS: [&str; 5] = [
+    "requirement",
+    "checklist",
+    "before you submit",
+    "before submitting",
+    "pull request",
+];
+
+/// Most requirements and sections shown, the rest is behind the link
+const SHOWN: usize = 8;
+
+/// The template's CONTRIBUTING.md, condensed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Guidelines {
+    /// Where in the template it is, like `.github/CONTRIBUTING.md`
+    pub path: String,
+    /// What contributions have to do, from the marked list or a checklist section
+    pub requirements: Vec<String>,
+    /// The section headings, for when there's no list of requirements
+    pub sections: Vec<String>,
+}
+
+impl Guidelines {
+    /// Reads the contribution guidelines from the template at `rev`, if it has any
+    pub fn load(rev: &str) -> Option<Self> {
+        LOCATIONS.iter().find_map(|location| {
+            let output = Command::new("git")
+                .args(["show", &format!("{}:{}", rev, location)])
+                .output_local()
+                .ok()?;
+            if !output.status.success() {
+                return None;
+            }
+            Some(Self::parse(
+                location,
+                &String::from_utf8_lossy(&output.stdout),
+            ))
+        })
+    }
+
+    pub fn parse(path: &str, content: &str) -> Self {
+        let requirements = match marked(content) {
+            Some(marked) => list_items(marked),
+            None => checklist(content),
+        };
+        let sections = content
+            .lines()
+            .filter_map(|line| line.strip_prefix("## "))
+            .map(plain)
+            .take(SHOWN)
+            .collect();
+        Self {
+            path: path.to_string(),
+            requirements: requirements.into_iter().take(SHOWN).collect(),
+            sections,
+        }
+    }
+
+    /// The file on GitHub as it is at `rev` in `repo`
+    pub fn url(&self, repo: &str, rev: &str) -> String {
+        format!("https://github.com/{}/blob/{}/{}", repo, rev, self.path)
+    }
+}
+
+/// What's between the requirement markers
+fn marked(content: &str) -> Option<&str> {
+    let start = content.find(START)? + START.len();
+    let end = content[start..]
+        .find(END)
+        .map_or(content.len(), |end| start + end);
+    Some(&content[start..end])
+}
+
+/// The list under the first heading that sounds like a checklist
+fn checklist(content: &str) -> Vec<String> {
+    let mut lines = content.lines();
+    let found = lines.by_ref().any(|line| {
+        let heading = line.trim_start_matches('#');
+        heading.len() < line.len()
+            && CHECKLIST_HEADINGS
+                .iter()
+                .any(|name| heading.to_lowercase().contains(name))
+    });
+    if !found {
+        return Vec::new();
+    }
+    let section: Vec<&str> = lines.take_while(|line| !line.starts_with('#')).collect();
+    list_items(&section.join("\n"))
+}
+
+/// The top-level items of the Markdown lists in `text`, task boxes and all
+fn list_items(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| {
+            let item = line
+                .strip_prefix("- ")
+                .or_else(|| line.strip_prefix("* "))
+                .or_else(|| {
+                    let (number, rest) = line.split_once(". ")?;
+                    number.parse::<u32>().ok().map(|_| rest)
+                })?;
+            let item = ["[ ] ", "[x] ", "[X] "]
+                .iter()
+                .find_map(|task| item.strip_prefix(task))
+                .unwrap_or(item);
+            Some(plain(item))
+        })
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// Markdown emphasis and links reduced to their text
+fn plain(text: &str) -> String {
+    let mut plain = String::new();
+    let mut rest = text.trim();
+    while let Some(open) = rest.find('[') {
+        let Some((label, after)) = rest[open + 1..].split_once("](") else {
+            break;
+        };
+        let Some(close) = after.find(')') else {
+            break;
+        };
+        plain.push_str(&rest[..open]);
+        plain.push_str(label);
+        rest = &after[close + 1..];
+    }
+    plain.push_str(rest);
+    plain.replace("**", "").replace("__", "").trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn condenses_contributing_md() {
+        let marked = "\
+# Contributing
+
+Thanks for helping out!
+
+## Pull requests
+
+<!-- chuck:requirements -->
+- **Sign off** every commit ([DCO](https://developercertificate.org))
+- [ ] Add an entry to `CHANGELOG.md`
+1. Keep it to one change
+<!-- /chuck:requirements -->
+
+## Style
+
+Run `make fmt`.
+";
+        let guidelines = Guidelines::parse("CONTRIBUTING.md", marked);
+        assert_eq!(
+            guidelines.requirements,
+            [
+                "Sign off every commit (DCO)",
+                "Add an entry to `CHANGELOG.md`",
+                "Keep it to one change"
+            ]
+        );
+        assert_eq!(guidelines.sections, ["Pull requests", "Style"]);
+        assert_eq!(
+            guidelines.url("acme/template", "abc123"),
+            "https://github.com/acme/template/blob/abc123/CONTRIBUTING.md"
+        );
+
+        let unmarked = "\
+## Setup
+
+- Install Rust
+
+## Before you submit
+
+* Tests pass
+* Docs updated
+
+## Style
+";
+        assert_eq!(
+            Guidelines::parse("CONTRIBUTING.md", unmarked).requirements,
+            ["Tests pass", "Docs updated"]
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use crate::journal;
+use crate::read_only;
+use crate::state::{PendingChanges, SharedState};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const IGNORED_FILE: &str = "ignored.toml";
+
+/// A commit the user asked never to be offered again
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IgnoredCommit {
+    pub hash: String,
+    /// Still matches after the commit is rebased or cherry-picked elsewhere
+    pub patch_id: Option<String>,
+    pub message: String,
+    pub ignored_at: DateTime<Utc>,
+}
+
+/// `.git/chuck/ignored.toml`, or the `ignored` list of the team's `.chuck/state.toml`
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct IgnoreList {
+    #[serde(default, rename = "commit")]
+    pub commits: Vec<IgnoredCommit>,
+}
+
+impl IgnoreList {
+    pub fn path() -> Result<PathBuf> {
+        Ok(journal::chuck_dir()?.join(IGNORED_FILE))
+    }
+
+    /// The saved list, empty if there isn't one yet. With `shared`, the team's list
+    /// with the changes runs haven't written to it yet.
+    pub fn load(shared: Option<&PendingChanges>) -> Result<Self> {
+        if let Some(pending) = shared {
+            let mut state = SharedState::load()?;
+            pending.apply_to(&mut state);
+            return Ok(Self {
+                commits: state.ignored,
+            });
+        }
+
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    /// Writes the clone's own list. Changes to the shared one go through
+    /// `PendingChanges`.
+    pub fn save(&self) -> Result<()> {
+        // Only for this run under --read-only, said while the TUI is up
+        if read_only::is_enabled() {
+            return Ok(());
+        }
+
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, hash: &str, patch_id: Option<&str>) -> bool {
+        self.commits
+            .iter()
+            .any(|c| c.hash == hash || (patch_id.is_some() && c.patch_id.as_deref() == patch_id))
+    }
+
+    pub fn add(&mut self, hash: &str, patch_id: Option<&str>, message: &str) {
+        if !self.contains(hash, patch_id) {
+            self.commits.push(IgnoredCommit {
+                hash: hash.to_string(),
+                patch_id: patch_id.map(str::to_string),
+                message: message.to_string(),
+                ignored_at: Utc::now(),
+            });
+        }
+    }
+
+    pub fn remove(&mut self, hash: &str, patch_id: Option<&str>) {
+        self.commits.retain(|c| {
+            c.hash != hash && (patch_id.is_none() || c.patch_id.as_deref() != patch_id)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_rebased_commits_by_patch_id() {
+        let mut list = IgnoreList::default();
+        list.add("aaa", Some("p1"), "Bump local config");
+        list.add("bbb", None, "Merge branch 'x'");
+
+        assert!(list.contains("aaa", None));
+        assert!(list.contains("rebased", Some("p1")));
+        assert!(list.contains("bbb", Some("p2")));
+        assert!(!list.contains("ccc", Some("p3")));
+
+        list.remove("rebased", Some("p1"));
+        assert!(!list.contains("aaa", None));
+        assert_eq!(list.commits.len(), 1);
+    }
+
+    #[test]
+    fn round_trips_through_toml() {
+        let mut list = IgnoreList::default();
+        list.add("aaa", Some("p1"), "Bump local config");
+        let parsed = IgnoreList::parse(&toml::to_string(&list).unwrap()).unwrap();
+        assert_eq!(parsed.commits, list.commits);
+    }
+}
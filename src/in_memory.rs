@@ -0,0 +1,221 @@
+use crate::paths;
+use crate::preview;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// How picking a commit without a worktree went
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    /// The new commit on top of the head
+    Picked(String),
+    /// Nothing left of it once merged, the head stays where it is
+    Empty,
+    /// The merge needs a person or a cherry-pick, for the reason given
+    NeedsWorktree(String),
+}
+
+/// Picks `commit`, relative to `parent`, onto `head` with a three-way merge done in
+/// a scratch index: no worktree, no checkout and the repository's index left alone.
+/// Author and date are the commit's, the message is `message` of its own. Files
+/// changed on both sides are merged like `git merge-file` does. Anything else, like
+/// conflicts, renames or binary files changed on both sides, needs the worktree.
+pub fn pick(
+    head: &str,
+    parent: &str,
+    commit: &str,
+    sign: bool,
+    message: impl FnOnce(&str) -> String,
+) -> Result<Outcome> {
+    let index = paths::temp_dir().join(format!("chuck-pick-{}.index", std::process::id()));
+    let tree = merge(&index, head, parent, commit);
+    let _ = fs::remove_file(&index);
+    let tree = match tree? {
+        Ok(tree) => tree,
+        Err(reason) => return Ok(Outcome::NeedsWorktree(reason)),
+    };
+    if tree == git(None, &["rev-parse", &format!("{}^{{tree}}", head)])? {
+        return Ok(Outcome::Empty);
+    }
+
+    let original = git(
+        None,
+        &[
+            "log",
+            "-1",
+            "--date=raw",
+            "--format=%an%x00%ae%x00%ad%x00%B",
+            commit,
+        ],
+    )?;
+    let mut fields = original.splitn(4, '\0');
+    let (name, email, date, original_message) = (
+        fields.next().unwrap_or_default(),
+        fields.next().unwrap_or_default(),
+        fields.next().unwrap_or_default(),
+        fields.next().unwrap_or_default(),
+    );
+    let mut command = Command::new("git");
+    command
+        .env("GIT_AUTHOR_NAME", name)
+        .env("GIT_AUTHOR_EMAIL", email)
+        .env("GIT_AUTHOR_DATE", date)
+        .args(["commit-tree", &tree, "-p", head, "-F", "-"]);
+    if sign {
+        command.arg("-S");
+    }
+    let message = format!("{}\n", message(original_message).trim_end());
+    let output = command
+        .output_local_with_input(message.as_bytes())
+        .map_err(|e| process::command_error(e, "Failed to execute git commit-tree"))?;
+    Ok(Outcome::Picked(stdout(output, "commit the merged tree")?))
+}
+
+/// The merged tree, or why it takes a worktree to get it
+fn merge(index: &Path, head: &str, parent: &str, commit: &str) -> Result<Result<String, String>> {
+    let index = Some(index);
+    let output = git_output(
+        index,
+        &[
+            "read-tree",
+            "-i",
+            "-m",
+            "--aggressive",
+            parent,
+            head,
+            commit,
+        ],
+    )?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Ok(Err(error.trim().to_string()));
+    }
+
+    for (path, stages) in unmerged(&git(index, &["ls-files", "-u", "-z"])?) {
+        // Added, deleted or renamed on one side only: git's merge machinery decides
+        let [Some(base), Some(ours), Some(theirs)] = stages else {
+            return Ok(Err(format!("{} was added or deleted on one side", path)));
+        };
+        let mode = &ours.0;
+        if !(base.0 == *mode && theirs.0 == *mode && is_file(mode)) {
+            return Ok(Err(format!("{} changed type on both sides", path)));
+        }
+        let [base, ours, theirs] = [&base.1, &ours.1, &theirs.1].map(|sha| blob(sha));
+        let merged = match preview::merge(&ours?, &base?, &theirs?) {
+            Ok((_, true)) => return Ok(Err(format!("conflicts in {}", path))),
+            Ok((merged, false)) => merged,
+            Err(e) => return Ok(Err(format!("can't merge {}: {}", path, e))),
+        };
+        let sha = stdout(
+            Command::new("git")
+                .args(["hash-object", "-w", "--stdin"])
+                .output_local_with_input(&merged)
+                .map_err(|e| process::command_error(e, "Failed to execute git hash-object"))?,
+            "store the merged file",
+        )?;
+        git(
+            index,
+            &[
+                "update-index",
+                "--cacheinfo",
+                &format!("{},{},{}", mode, sha, path),
+            ],
+        )?;
+    }
+    Ok(Ok(git(index, &["write-tree"])?))
+}
+
+/// `(mode, sha)` of each conflict stage: base, ours and theirs
+type Stages = [Option<(String, String)>; 3];
+
+/// The conflicted paths in `git ls-files -u -z` output, with their stages
+fn unmerged(listing: &str) -> BTreeMap<String, Stages> {
+    let mut paths: BTreeMap<String, Stages> = BTreeMap::new();
+    for entry in listing.split('\0').filter(|entry| !entry.is_empty()) {
+        // "<mode> <sha> <stage>\t<path>"
+        let Some((info, path)) = entry.split_once('\t') else {
+            continue;
+        };
+        let mut info = info.split(' ');
+        let (Some(mode), Some(sha), Some(stage)) = (info.next(), info.next(), info.next()) else {
+            continue;
+        };
+        let Some(slot) = stage
+            .parse::<usize>()
+            .ok()
+            .and_then(|stage| stage.checked_sub(1))
+            .filter(|slot| *slot < 3)
+        else {
+            continue;
+        };
+        paths.entry(path.to_string()).or_default()[slot] =
+            Some((mode.to_string(), sha.to_string()));
+    }
+    paths
+}
+
+/// Regular and executable files, not symlinks or submodules
+fn is_file(mode: &str) -> bool {
+    matches!(mode, "100644" | "100755")
+}
+
+fn blob(sha: &str) -> Result<Vec<u8>> {
+    let output = git_output(None, &["cat-file", "blob", sha])?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to read {}: {}", sha, error.trim()));
+    }
+    Ok(output.stdout)
+}
+
+fn git_output(index: Option<&Path>, args: &[&str]) -> Result<Output> {
+    let mut command = Command::new("git");
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+    command
+        .args(args)
+        .output_local()
+        .map_err(|e| process::command_error(e, &format!("Failed to execute git {}", args[0])))
+}
+
+fn git(index: Option<&Path>, args: &[&str]) -> Result<String> {
+    stdout(git_output(index, args)?, &format!("run git {}", args[0]))
+}
+
+fn stdout(output: Output, what: &str) -> Result<String> {
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to {}: {}", what, error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_conflict_stages_by_path() {
+        let listing = concat!(
+            "100644 aaa 1\tsrc/a.rs\x00",
+            "100644 bbb 2\tsrc/a.rs\x00",
+            "100644 ccc 3\tsrc/a.rs\x00",
+            "100644 ddd 1\tsrc/gone.rs\x00",
+            "100644 eee 3\tsrc/gone.rs\x00",
+        );
+        let stage = |sha: &str| Some(("100644".to_string(), sha.to_string()));
+        let paths = unmerged(listing);
+        assert_eq!(
+            paths["src/a.rs"],
+            [stage("aaa"), stage("bbb"), stage("ccc")]
+        );
+        assert_eq!(paths["src/gone.rs"], [stage("ddd"), None, stage("eee")]);
+        assert!(!is_file("120000"));
+    }
+}
@@ -0,0 +1,177 @@
+use crate::forge;
+use crate::triage;
+use anyhow::{anyhow, Result};
+
+/// Labels about the issue rather than the change, left off the pull request
+const ISSUE_ONLY: [&str; 10] = [
+    "good first issue",
+    "help wanted",
+    "question",
+    "duplicate",
+    "invalid",
+    "wontfix",
+    "needs triage",
+    "needs info",
+    "needs reproduction",
+    "stale",
+];
+
+/// Label prefixes that say where the issue stands, like `status: accepted`
+const ISSUE_ONLY_PREFIXES: [&str; 2] = ["status", "triage"];
+
+/// A template issue a contribution is for, from `chuck for-issue`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub number: u64,
+    pub title: String,
+    pub labels: Vec<String>,
+    pub closed: bool,
+}
+
+impl Issue {
+    /// Looks up issue `number` in `repo`. Pull requests share the numbering, but
+    /// aren't issues to fix.
+    pub fn fetch(repo: &str, number: u64) -> Result<Self> {
+        let json = forge::get_json(
+            &format!("repos/{}/issues/{}", repo, number),
+            &format!("Failed to look up {}#{}", repo, number),
+        )?;
+        if json.get("pull_request").is_some() {
+            return Err(anyhow!("{}#{} is a pull request", repo, number));
+        }
+        Ok(Self {
+            number,
+            title: json["title"].as_str().unwrap_or_default().to_string(),
+            labels: json["labels"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|label| label["name"].as_str().map(str::to_string))
+                .collect(),
+            closed: json["state"].as_str() == Some("closed"),
+        })
+    }
+
+    /// Whether `subject`, from a commit in `commit_repo`, refers to this issue in
+    /// `repo`: as `owner/name#12` or the issue's URL, or as a bare `#12` only when the
+    /// commit is in `repo` too, since elsewhere that's the other repository's own #12
+    pub fn is_mentioned_in(&self, subject: &str, repo: &str, commit_repo: &str) -> bool {
+        let subject = subject.to_lowercase();
+        let repo = repo.to_lowercase();
+        let mut tags = vec![
+            format!("{}#{}", repo, self.number),
+            format!("github.com/{}/issues/{}", repo, self.number),
+        ];
+        if commit_repo.eq_ignore_ascii_case(&repo) {
+            tags.push(format!("#{}", self.number));
+        }
+        tags.iter().any(|tag| {
+            subject.match_indices(tag.as_str()).any(|(at, _)| {
+                let before = &subject[..at];
+                let after = &subject[at + tag.len()..];
+                // Not #123 for #12, nor another repository's #12
+                !after.starts_with(|c: char| c.is_ascii_digit())
+                    && (tag.starts_with("github.com/")
+                        || before.is_empty()
+                        || before.ends_with(|c: char| !c.is_alphanumeric() && !"/-_.".contains(c)))
+            })
+        })
+    }
+
+    /// The pull request's title, after the issue
+    pub fn pr_title(&self) -> String {
+        format!("{} (fixes #{})", self.title.trim(), self.number)
+    }
+
+    /// `body` starting with the line that closes the issue in `repo` on merge
+    pub fn link(&self, repo: &str, body: &str) -> String {
+        format!("Fixes {}#{}\n\n{}", repo, self.number, body)
+    }
+
+    /// The issue's labels that say something about the change, like its area or kind
+    pub fn change_labels(&self) -> Vec<&str> {
+        self.labels
+            .iter()
+            .map(String::as_str)
+            .filter(|label| {
+                let label = label.to_lowercase();
+                !ISSUE_ONLY.contains(&label.as_str())
+                    && !ISSUE_ONLY_PREFIXES.iter().any(|prefix| {
+                        label.strip_prefix(prefix).is_some_and(|rest| {
+                            rest.is_empty() || rest.starts_with([':', '/', ' '])
+                        })
+                    })
+            })
+            .collect()
+    }
+
+    /// Puts `change_labels` on the pull request at `url` in `repo`, returning them
+    pub fn copy_labels(&self, repo: &str, url: &str) -> Result<Vec<&str>> {
+        let labels = self.change_labels();
+        if labels.is_empty() {
+            return Ok(labels);
+        }
+        let number = triage::pr_number(url)
+            .ok_or_else(|| anyhow!("Couldn't tell the pull request number from {}", url))?;
+        // Pull requests are issues as far as labels go
+        forge::send(
+            "POST",
+            &format!("repos/{}/issues/{}/labels", repo, number),
+            &serde_json::json!({ "labels": labels }),
+            "Failed to label the pull request",
+        )?;
+        Ok(labels)
+    }
+}
+
+/// An issue number from the command line, given as `12`, `#12` or the issue's URL
+pub fn parse_number(arg: &str) -> Result<u64, String> {
+    let number = arg.trim_end_matches('/').rsplit('/').next().unwrap_or(arg);
+    number
+        .trim_start_matches('#')
+        .parse()
+        .map_err(|_| format!("expected an issue number, #12 or its URL, got {}", arg))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    #[test]
+    fn carries_the_issue_over_to_the_pull_request() {
+        let fake = Rc::new(FakeRunner::new().on(
+            "gh api repos/acme/template/issues/12",
+            r#"{"title":"Login fails on Safari ","state":"open","labels":[{"name":"bug"},{"name":"area: auth"},{"name":"good first issue"},{"name":"Status: Accepted"}]}"#,
+        ));
+        let issue = process::with_runner(fake, || Issue::fetch("acme/template", 12)).unwrap();
+
+        assert_eq!(issue.pr_title(), "Login fails on Safari (fixes #12)");
+        assert!(issue
+            .link("acme/template", "Contributed from acme/app.")
+            .starts_with("Fixes acme/template#12\n\n"));
+        assert_eq!(issue.change_labels(), ["bug", "area: auth"]);
+
+        let mentioned =
+            |subject, commit_repo| issue.is_mentioned_in(subject, "acme/template", commit_repo);
+        assert!(mentioned("Fix Safari login (#12)", "acme/template"));
+        assert!(!mentioned("Fix Safari login (#12)", "acme/app"));
+        assert!(mentioned("Fix Acme/Template#12", "acme/app"));
+        assert!(mentioned(
+            "Fix https://github.com/acme/template/issues/12",
+            "acme/app"
+        ));
+        assert!(!mentioned("Fix acme/template#123", "acme/app"));
+        assert!(!mentioned("Fix #123", "acme/template"));
+        assert!(!mentioned("Fix acme/app#12", "acme/template"));
+        assert!(!mentioned("Fix notacme/template#12", "acme/app"));
+
+        assert_eq!(parse_number("#12"), Ok(12));
+        assert_eq!(
+            parse_number("https://github.com/acme/template/issues/12"),
+            Ok(12)
+        );
+        assert!(parse_number("twelve").is_err());
+    }
+}
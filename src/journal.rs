@@ -0,0 +1,103 @@
+use crate::paths;
+use crate::process::{self, CommandExt};
+use crate::read_only;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+const JOURNAL_FILE: &str = "journal.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Pushed,
+    PushFailed,
+    Conflict,
+    Failed,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JournalCommit {
+    pub hash: String,
+    pub message: String,
+    pub author: String,
+    pub files: Vec<String>,
+}
+
+/// One line of `.git/chuck/journal.jsonl`, written at the end of every run
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub repo: String,
+    pub template: String,
+    pub branch: Option<String>,
+    pub remote_branch: Option<String>,
+    pub outcome: Outcome,
+    pub commits: Vec<JournalCommit>,
+}
+
+/// Chuck's per-clone state directory, `.git/chuck` unless `[paths] state` moves it
+pub fn chuck_dir() -> Result<PathBuf> {
+    Ok(paths::state_dir(&git_dir()?))
+}
+
+/// The clone's git directory, as git gives it
+pub fn git_dir() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-dir"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("Not inside a git repository"));
+    }
+
+    let git_dir = String::from_utf8(output.stdout)?.trim().to_string();
+    Ok(PathBuf::from(git_dir))
+}
+
+pub fn journal_path() -> Result<PathBuf> {
+    Ok(chuck_dir()?.join(JOURNAL_FILE))
+}
+
+pub fn append(entry: &JournalEntry) -> Result<()> {
+    let path = journal_path()?;
+    if read_only::withheld(&format!("written the run to {}", path.display())) {
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+/// When Chuck last pushed a branch from `repo` to `template` in this clone
+pub fn last_push(repo: &str, template: &str) -> Result<Option<DateTime<Utc>>> {
+    Ok(read_all()?
+        .into_iter()
+        .filter(|e| e.outcome == Outcome::Pushed && e.repo == repo && e.template == template)
+        .map(|e| e.timestamp)
+        .max())
+}
+
+pub fn read_all() -> Result<Vec<JournalEntry>> {
+    let path = journal_path()?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(_) => return Ok(Vec::new()),
+    };
+
+    // Skip lines we can't parse rather than refusing to read the whole journal
+    Ok(content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
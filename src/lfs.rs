@@ -0,0 +1,314 @@
+use crate::glob::gitignore_match;
+use crate::network;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
+use std::process::Command;
+
+/// First line of every Git LFS pointer file
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+/// Pointers are around 130 bytes, anything much bigger is the real content
+const MAX_POINTER_SIZE: u64 = 1024;
+
+/// A file committed as a Git LFS pointer
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pointer {
+    pub path: String,
+    /// `sha256:...` of the content on the LFS server
+    pub oid: String,
+    pub size: u64,
+}
+
+/// The LFS pointers among the files `commit` adds or changes relative to `parent`
+pub fn pointers(parent: &str, commit: &str) -> Result<Vec<Pointer>> {
+    let raw = git(
+        &["diff-tree", "-r", "-z", "--no-renames", parent, commit],
+        None,
+    )?;
+    let blobs = parse_raw(&String::from_utf8_lossy(&raw));
+    if blobs.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Only read what's small enough to be a pointer
+    let sizes = git(
+        &["cat-file", "--batch-check=%(objectname) %(objectsize)"],
+        Some(batch_input(blobs.iter().map(|(_, oid)| oid.as_str())).as_bytes()),
+    )?;
+    let small: BTreeSet<&str> = std::str::from_utf8(&sizes)?
+        .lines()
+        .filter_map(|line| {
+            let (oid, size) = line.split_once(' ')?;
+            (size.parse::<u64>().ok()? <= MAX_POINTER_SIZE).then_some(oid)
+        })
+        .collect();
+    let candidates: Vec<&(String, String)> = blobs
+        .iter()
+        .filter(|(_, oid)| small.contains(oid.as_str()))
+        .collect();
+    if candidates.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let contents = read_objects(candidates.iter().map(|(_, oid)| oid.as_str()))?;
+    Ok(candidates
+        .iter()
+        .zip(contents)
+        .filter_map(|((path, _), content)| {
+            let (oid, size) = parse_pointer(&String::from_utf8_lossy(&content?))?;
+            Some(Pointer {
+                path: path.clone(),
+                oid,
+                size,
+            })
+        })
+        .collect())
+}
+
+/// `(path, blob)` of the regular files added or changed in `git diff-tree -r -z` output
+pub fn parse_raw(raw: &str) -> Vec<(String, String)> {
+    let mut fields = raw.split('\0');
+    let mut blobs = Vec::new();
+    while let (Some(meta), Some(path)) = (fields.next(), fields.next()) {
+        let meta: Vec<&str> = meta.trim_start_matches(':').split(' ').collect();
+        let [_, new_mode, _, new_oid, status] = meta[..] else {
+            continue;
+        };
+        if status != "D" && new_mode.starts_with("100") {
+            blobs.push((path.to_string(), new_oid.to_string()));
+        }
+    }
+    blobs
+}
+
+/// `(oid, size)` from a pointer file's text
+pub fn parse_pointer(text: &str) -> Option<(String, u64)> {
+    let mut lines = text.lines();
+    if lines.next()? != POINTER_VERSION {
+        return None;
+    }
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("oid", value)) => oid = Some(value.to_string()),
+            Some(("size", value)) => size = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some((oid?, size?))
+}
+
+/// The `paths` that the `.gitattributes` files in `rev` don't send through LFS, so
+/// pointers committed there would stay pointer files
+pub fn untracked(rev: &str, paths: &[&str]) -> Result<Vec<String>> {
+    // Sorted, a directory comes before those inside it, whose rules override its own
+    let mut dirs = BTreeSet::from([String::new()]);
+    for path in paths {
+        let mut dir = *path;
+        while let Some((parent, _)) = dir.rsplit_once('/') {
+            dirs.insert(parent.to_string());
+            dir = parent;
+        }
+    }
+
+    let names: Vec<String> = dirs
+        .iter()
+        .map(|dir| match dir.as_str() {
+            "" => format!("{}:.gitattributes", rev),
+            dir => format!("{}:{}/.gitattributes", rev, dir),
+        })
+        .collect();
+    let files = read_objects(names.iter().map(String::as_str))?;
+
+    let mut rules = Vec::new();
+    for (dir, content) in dirs.iter().zip(files) {
+        if let Some(content) = content {
+            rules.extend(
+                parse_attributes(&String::from_utf8_lossy(&content))
+                    .into_iter()
+                    .map(|(pattern, lfs)| (dir.as_str(), pattern, lfs)),
+            );
+        }
+    }
+
+    Ok(paths
+        .iter()
+        .filter(|path| !tracks(&rules, path))
+        .map(|path| path.to_string())
+        .collect())
+}
+
+/// `(pattern, uses LFS)` of the lines in a `.gitattributes` that set or unset `filter`
+pub fn parse_attributes(content: &str) -> Vec<(String, bool)> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?;
+            let lfs = parts.fold(None, |lfs, attribute| match attribute {
+                "filter=lfs" => Some(true),
+                "-filter" | "!filter" => Some(false),
+                other if other.starts_with("filter=") => Some(false),
+                _ => lfs,
+            })?;
+            Some((pattern.to_string(), lfs))
+        })
+        .collect()
+}
+
+/// Whether the last rule matching `path` sends it through LFS
+fn tracks(rules: &[(&str, String, bool)], path: &str) -> bool {
+    rules
+        .iter()
+        .rev()
+        .find(|(dir, pattern, _)| {
+            let relative = match *dir {
+                "" => Some(path),
+                dir => path.strip_prefix(dir).and_then(|p| p.strip_prefix('/')),
+            };
+            relative.is_some_and(|relative| gitignore_match(pattern, relative))
+        })
+        .is_some_and(|(_, _, lfs)| *lfs)
+}
+
+/// Uploads the LFS objects `branch` refers to that `remote`, a remote name or URL,
+/// doesn't have yet
+pub fn push(remote: &str, branch: &str) -> Result<()> {
+    let output = Command::new("git")
+        .args(["lfs", "push", remote, branch])
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to execute git lfs push"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if error.contains("'lfs' is not a git command") {
+            return Err(anyhow!(
+                "The picks include Git LFS files and git-lfs isn't installed. Install it from https://git-lfs.com"
+            ));
+        }
+        return Err(anyhow!(network::describe_failure(
+            "Failed to push Git LFS objects",
+            &error
+        )));
+    }
+    Ok(())
+}
+
+/// Contents of each object `names` refers to, `None` for those that don't exist
+pub(crate) fn read_objects<'a>(
+    names: impl Iterator<Item = &'a str>,
+) -> Result<Vec<Option<Vec<u8>>>> {
+    let output = git(
+        &["cat-file", "--batch"],
+        Some(batch_input(names).as_bytes()),
+    )?;
+    let mut objects = Vec::new();
+    let mut rest = output.as_slice();
+    while let Some(end) = rest.iter().position(|&b| b == b'\n') {
+        let header = String::from_utf8_lossy(&rest[..end]).into_owned();
+        rest = &rest[end + 1..];
+        if header.ends_with(" missing") || header.ends_with(" ambiguous") {
+            objects.push(None);
+            continue;
+        }
+        let size: usize = header
+            .rsplit(' ')
+            .next()
+            .and_then(|size| size.parse().ok())
+            .ok_or_else(|| anyhow!("Unexpected git cat-file output: {}", header))?;
+        let content = rest
+            .get(..size)
+            .ok_or_else(|| anyhow!("Truncated git cat-file output"))?;
+        objects.push(Some(content.to_vec()));
+        // The content is followed by a newline
+        rest = rest.get(size + 1..).unwrap_or_default();
+    }
+    Ok(objects)
+}
+
+fn batch_input<'a>(names: impl Iterator<Item = &'a str>) -> String {
+    names.map(|name| format!("{}\n", name)).collect()
+}
+
+fn git(args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut command = Command::new("git");
+    command.args(args);
+    let output = match input {
+        Some(input) => command.output_local_with_input(input),
+        None => command.output_local(),
+    }
+    .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    const POINTER: &str =
+        "version https://git-lfs.github.com/spec/v1\noid sha256:4d7a\nsize 12345\n";
+
+    #[test]
+    fn finds_pointers_among_changed_files() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on(
+                    "git diff-tree",
+                    ":100644 100644 aaa bbb M\0assets/logo.png\0:000000 100644 000 ccc A\0src/app.rs\0:100644 000000 ddd 000 D\0old.png\0",
+                )
+                .on("git cat-file --batch-check", "bbb 87\nccc 4096\n")
+                .on(
+                    "git cat-file --batch",
+                    &format!("bbb blob {}\n{}\n", POINTER.len(), POINTER),
+                ),
+        );
+
+        let pointers = process::with_runner(fake.clone(), || pointers("c^1", "c")).unwrap();
+
+        assert_eq!(
+            pointers,
+            [Pointer {
+                path: "assets/logo.png".to_string(),
+                oid: "sha256:4d7a".to_string(),
+                size: 12345,
+            }]
+        );
+        // Only the small file was read
+        assert_eq!(fake.inputs()[1], b"bbb\n");
+    }
+
+    #[test]
+    fn reads_lfs_tracking_from_gitattributes() {
+        let root = "*.png filter=lfs diff=lfs merge=lfs -text\n# comment\n*.txt text\n";
+        let docs = "*.png -filter\n";
+        let fake = Rc::new(FakeRunner::new().on(
+            "git cat-file --batch",
+            &format!(
+                "r1 blob {}\n{}\nbase:assets/.gitattributes missing\nr2 blob {}\n{}\nbase:docs/x/.gitattributes missing\n",
+                root.len(),
+                root,
+                docs.len(),
+                docs
+            ),
+        ));
+
+        let untracked = process::with_runner(fake.clone(), || {
+            untracked("base", &["assets/logo.png", "docs/x/a.png", "data.bin"])
+        });
+
+        assert_eq!(untracked.unwrap(), ["docs/x/a.png", "data.bin"]);
+        assert_eq!(
+            fake.inputs()[0],
+            b"base:.gitattributes\nbase:assets/.gitattributes\nbase:docs/.gitattributes\nbase:docs/x/.gitattributes\n"
+        );
+    }
+}
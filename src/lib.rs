@@ -0,0 +1,73 @@
+//! Chuck's building blocks outside the TUI, shared by the binary and the benchmarks
+
+pub mod access;
+pub mod action;
+pub mod adopt;
+pub mod api;
+pub mod backport;
+pub mod bot;
+pub mod checkpoint;
+pub mod checks;
+pub mod ci;
+pub mod clipboard;
+pub mod codeowners;
+pub mod dates;
+pub mod dependencies;
+pub mod dirtree;
+pub mod discovery;
+pub mod doctor;
+pub mod duplicates;
+pub mod emails;
+pub mod exit;
+pub mod fingerprint;
+pub mod forge;
+pub mod formatting;
+pub mod generated;
+pub mod glob;
+pub mod graph;
+pub mod guidelines;
+pub mod ignored;
+pub mod in_memory;
+pub mod issue;
+pub mod journal;
+pub mod leak;
+pub mod lfs;
+pub mod lock;
+pub mod lockfile;
+pub mod manifest;
+pub mod mergetool;
+pub mod metadata;
+pub mod network;
+pub mod notify;
+pub mod palette;
+pub mod partial;
+pub mod paths;
+pub mod policy;
+pub mod precheck;
+pub mod prefs;
+pub mod preview;
+pub mod process;
+pub mod profile;
+pub mod provenance;
+pub mod push;
+pub mod read_only;
+pub mod rebase;
+pub mod recording;
+pub mod relocation;
+pub mod repo_state;
+pub mod report;
+pub mod risk;
+pub mod screen;
+pub mod selection;
+pub mod self_update;
+pub mod serve;
+pub mod signature;
+pub mod size;
+pub mod split;
+pub mod squash;
+pub mod state;
+pub mod stats;
+pub mod subscription;
+pub mod trailer;
+pub mod triage;
+pub mod workspace;
@@ -0,0 +1,241 @@
+use crate::journal;
+use crate::process::CommandExt;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const LOCK_FILE: &str = "lock";
+
+/// Locks older than this are considered abandoned even if we can't check the process
+const STALE_AFTER_HOURS: i64 = 12;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct LockInfo {
+    pid: u32,
+    host: String,
+    started: DateTime<Utc>,
+}
+
+/// Held for the duration of a run so two chuck processes can't build branches
+/// in the same checkout at once. Released when dropped.
+pub struct RepoLock {
+    path: PathBuf,
+    /// What this run wrote, so dropping it never removes another run's lock
+    content: String,
+}
+
+impl RepoLock {
+    pub fn acquire(verbose: bool) -> Result<Self> {
+        let dir = journal::chuck_dir()?;
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(LOCK_FILE);
+
+        let info = LockInfo {
+            pid: std::process::id(),
+            host: hostname(),
+            started: Utc::now(),
+        };
+        let content = serde_json::to_string(&info)?;
+
+        // Written in full before it's linked into place, so nobody reads half a lock
+        let staged = dir.join(format!("{}.{}", LOCK_FILE, info.pid));
+        fs::write(&staged, &content)?;
+        let result = Self::link(&staged, path, content, &info.host, verbose);
+        let _ = fs::remove_file(&staged);
+        result
+    }
+
+    fn link(
+        staged: &Path,
+        path: PathBuf,
+        content: String,
+        host: &str,
+        verbose: bool,
+    ) -> Result<Self> {
+        // One retry: the first attempt may find a stale lock that we clear
+        for _ in 0..2 {
+            match fs::hard_link(staged, &path) {
+                Ok(()) => {
+                    if verbose {
+                        println!("🧔 VERBOSE: Acquired lock {}", path.display());
+                    }
+                    return Ok(Self { path, content });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let existing = fs::read_to_string(&path).unwrap_or_default();
+                    let abandoned = match serde_json::from_str::<LockInfo>(&existing) {
+                        Ok(lock) => is_stale(&lock, host),
+                        // Unreadable locks are held until they're as old as a stale one
+                        Err(_) => is_old(&path),
+                    };
+                    if !abandoned || !take_over(&path, &existing) {
+                        return Err(held(&path, &existing));
+                    }
+                    println!("🧔 Removing stale lock from an earlier chuck run");
+                }
+                Err(e) => return Err(anyhow!("Failed to create {}: {}", path.display(), e)),
+            }
+        }
+
+        Err(anyhow!("Failed to acquire {}", path.display()))
+    }
+}
+
+impl Drop for RepoLock {
+    fn drop(&mut self) {
+        if fs::read_to_string(&self.path).is_ok_and(|content| content == self.content) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Moves the stale lock at `path` out of the way, as long as it's still the one that
+/// held `stale`. Another run clearing it at the same time gets there first or finds
+/// the new lock in its place, which it leaves alone.
+fn take_over(path: &Path, stale: &str) -> bool {
+    let aside = path.with_extension(format!("stale.{}", std::process::id()));
+    if fs::rename(path, &aside).is_err() {
+        // Gone already, the retry finds out who has it now
+        return true;
+    }
+    let moved = fs::read_to_string(&aside).unwrap_or_default();
+    if moved == stale {
+        let _ = fs::remove_file(&aside);
+        return true;
+    }
+    // A fresh lock taken in the meantime: put it back
+    let _ = fs::hard_link(&aside, path);
+    let _ = fs::remove_file(&aside);
+    false
+}
+
+fn held(path: &Path, content: &str) -> anyhow::Error {
+    let owner = match serde_json::from_str::<LockInfo>(content) {
+        Ok(lock) => format!(
+            "pid {} on {}, started {}",
+            lock.pid,
+            lock.host,
+            lock.started.format("%Y-%m-%d %H:%M UTC")
+        ),
+        Err(_) => "its lock can't be read".to_string(),
+    };
+    anyhow!(
+        "Another chuck run is active in this repository ({}).\n  \
+         If that's not true, delete {} and try again.",
+        owner,
+        path.display()
+    )
+}
+
+/// Whether the file at `path` was last written longer ago than a stale lock's age
+fn is_old(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age.as_secs() > STALE_AFTER_HOURS as u64 * 3600)
+}
+
+fn is_stale(lock: &LockInfo, this_host: &str) -> bool {
+    if Utc::now() - lock.started > chrono::Duration::hours(STALE_AFTER_HOURS) {
+        return true;
+    }
+    // We can only check whether the owner is alive when it ran on this machine
+    lock.host == this_host && !process_alive(lock.pid)
+}
+
+fn process_alive(pid: u32) -> bool {
+    let output = if cfg!(windows) {
+        Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH"])
+            .output_local()
+    } else {
+        Command::new("kill")
+            .args(["-0", &pid.to_string()])
+            .output_local()
+    };
+
+    match output {
+        Ok(output) if cfg!(windows) => {
+            String::from_utf8_lossy(&output.stdout).contains(&pid.to_string())
+        }
+        // EPERM means the process exists but belongs to someone else, e.g. a teammate
+        Ok(output) => {
+            output.status.success()
+                || String::from_utf8_lossy(&output.stderr).contains("not permitted")
+        }
+        // Can't tell, so assume it's alive rather than stealing the lock
+        Err(_) => true,
+    }
+}
+
+fn hostname() -> String {
+    if let Ok(name) = std::env::var("HOSTNAME").or_else(|_| std::env::var("COMPUTERNAME")) {
+        return name;
+    }
+    Command::new("hostname")
+        .output_local()
+        .ok()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    fn in_repo<T>(name: &str, f: impl FnOnce() -> T) -> T {
+        let dir = std::env::temp_dir().join(format!("chuck-lock-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let fake = Rc::new(
+            FakeRunner::new().on("git rev-parse --git-dir", &format!("{}\n", dir.display())),
+        );
+        let result = process::with_runner(fake, f);
+        let _ = fs::remove_dir_all(&dir);
+        result
+    }
+
+    #[test]
+    fn refuses_while_another_run_holds_it() {
+        in_repo("held", || {
+            let first = RepoLock::acquire(false).unwrap();
+            let error = RepoLock::acquire(false).err().unwrap().to_string();
+            assert!(
+                error.starts_with("Another chuck run is active"),
+                "{}",
+                error
+            );
+            drop(first);
+            assert!(RepoLock::acquire(false).is_ok());
+        });
+    }
+
+    #[test]
+    fn treats_an_unreadable_lock_as_held() {
+        in_repo("unreadable", || {
+            let dir = journal::chuck_dir().unwrap();
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join(LOCK_FILE), "{\"pid\":").unwrap();
+            let error = RepoLock::acquire(false).err().unwrap().to_string();
+            assert!(error.contains("its lock can't be read"), "{}", error);
+        });
+    }
+
+    #[test]
+    fn leaves_a_lock_it_no_longer_owns() {
+        in_repo("replaced", || {
+            let lock = RepoLock::acquire(false).unwrap();
+            fs::write(&lock.path, "someone else's").unwrap();
+            let path = lock.path.clone();
+            drop(lock);
+            assert_eq!(fs::read_to_string(path).unwrap(), "someone else's");
+        });
+    }
+}
@@ -0,0 +1,193 @@
+use crate::glob::gitignore_match;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::process::Command;
+
+/// `[[lockfiles]]` in .chuckrc: how to rebuild a lockfile against the template's
+/// dependencies instead of contributing the project's copy
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LockfileConfig {
+    /// Manifests that make the lockfile stale, as a `.gitignore` pattern, e.g. "Cargo.toml"
+    pub manifest: String,
+    /// The lockfile, next to the manifest, e.g. "Cargo.lock"
+    pub lockfile: String,
+    /// Rebuilds it, run in the manifest's directory, e.g. "cargo generate-lockfile"
+    pub command: String,
+}
+
+/// For every manifest changed since `base`, puts the template's lockfile back and
+/// regenerates it with the configured command, committing the results on top. Other
+/// files the commands touch are thrown away. Lockfiles the template doesn't have are
+/// left alone. Returns the lockfiles committed.
+pub fn regenerate(configs: &[LockfileConfig], base: &str, verbose: bool) -> Result<Vec<String>> {
+    let root = git(".", &["rev-parse", "--show-toplevel"])?;
+    let root = Path::new(root.trim());
+    let git = |args: &[&str]| git(root, args);
+    if !git(&["status", "--porcelain", "--untracked-files=no"])?
+        .trim()
+        .is_empty()
+    {
+        return Err(anyhow!(
+            "there are uncommitted changes, commit or stash them first"
+        ));
+    }
+
+    let changed = git(&["diff", "--name-only", "--no-renames", "-z", base, "HEAD"])?;
+    let mut jobs: Vec<(String, &LockfileConfig)> = Vec::new();
+    for path in changed.split('\0').filter(|p| !p.is_empty()) {
+        for config in configs {
+            if !gitignore_match(&config.manifest, path) {
+                continue;
+            }
+            let dir = path.rsplit_once('/').map_or("", |(dir, _)| dir);
+            let lockfile = if dir.is_empty() {
+                config.lockfile.clone()
+            } else {
+                format!("{}/{}", dir, config.lockfile)
+            };
+            if !jobs.iter().any(|(existing, _)| *existing == lockfile) {
+                jobs.push((lockfile, config));
+            }
+        }
+    }
+
+    let mut regenerated = Vec::new();
+    for (lockfile, config) in jobs {
+        let tracked = git(&["ls-tree", "--name-only", base, "--", &lockfile])?;
+        if tracked.trim().is_empty() {
+            if verbose {
+                println!(
+                    "🧔 VERBOSE: The template has no {}, not regenerating it",
+                    lockfile
+                );
+            }
+            continue;
+        }
+
+        git(&["checkout", base, "--", &lockfile])?;
+        let dir = root.join(Path::new(&lockfile).parent().unwrap_or(Path::new("")));
+        if verbose {
+            println!(
+                "🧔 VERBOSE: Running {} in {}",
+                config.command,
+                dir.display()
+            );
+        }
+        let output = Command::new("sh")
+            .args(["-c", &config.command])
+            .current_dir(&dir)
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to regenerate the lockfile"));
+        let failure = match output {
+            Ok(output) if output.status.success() => None,
+            Ok(output) => Some(anyhow!(
+                "{} failed: {}",
+                config.command,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => Some(e),
+        };
+        if let Some(e) = failure {
+            git(&["checkout", "HEAD", "--", "."])?;
+            return Err(e);
+        }
+
+        git(&["add", "--", &lockfile])?;
+        git(&["checkout", "--", "."])?;
+        regenerated.push(lockfile);
+    }
+
+    let staged = git(&["diff", "--cached", "--name-only", "-z"])?;
+    let committed: Vec<String> = regenerated
+        .into_iter()
+        .filter(|lockfile| staged.split('\0').any(|path| path == lockfile))
+        .collect();
+    if committed.is_empty() {
+        return Ok(committed);
+    }
+    git(&[
+        "commit",
+        "--no-verify",
+        "-m",
+        &format!("Regenerate {}", committed.join(", ")),
+    ])?;
+    Ok(committed)
+}
+
+fn git(dir: impl AsRef<Path>, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    fn cargo() -> LockfileConfig {
+        LockfileConfig {
+            manifest: "Cargo.toml".to_string(),
+            lockfile: "Cargo.lock".to_string(),
+            command: "cargo generate-lockfile".to_string(),
+        }
+    }
+
+    #[test]
+    fn regenerates_lockfiles_next_to_changed_manifests() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git rev-parse --show-toplevel", "/repo\n")
+                .on(
+                    "git diff --name-only --no-renames",
+                    "Cargo.lock\0crates/cli/Cargo.toml\0src/lib.rs\0",
+                )
+                .on("git ls-tree", "crates/cli/Cargo.lock\n")
+                .on("git diff --cached", "crates/cli/Cargo.lock\0"),
+        );
+
+        let committed =
+            process::with_runner(fake.clone(), || regenerate(&[cargo()], "base", false));
+
+        assert_eq!(committed.unwrap(), ["crates/cli/Cargo.lock"]);
+        assert_eq!(
+            fake.calls_to("git checkout"),
+            [
+                "git checkout base -- crates/cli/Cargo.lock",
+                "git checkout -- ."
+            ]
+        );
+        assert_eq!(fake.calls_to("sh"), ["sh -c cargo generate-lockfile"]);
+        assert_eq!(
+            fake.calls_to("git commit"),
+            ["git commit --no-verify -m Regenerate crates/cli/Cargo.lock"]
+        );
+    }
+
+    #[test]
+    fn leaves_lockfiles_the_template_does_not_track() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git diff --name-only --no-renames", "Cargo.toml\0")
+                .on("git ls-tree", ""),
+        );
+
+        let committed =
+            process::with_runner(fake.clone(), || regenerate(&[cargo()], "base", false));
+
+        assert!(committed.unwrap().is_empty());
+        assert!(fake.calls_to("sh").is_empty());
+        assert!(fake.calls_to("git commit").is_empty());
+    }
+}
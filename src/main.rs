@@ -1,13 +1,13 @@
 use anyhow::{anyhow, Result};
-use clap::Parser;
+use clap::{Args, Parser, Subcommand};
 use crossterm::{
     event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
-    backend::CrosstermBackend,
-    layout::{Constraint, Direction, Layout, Rect},
+    backend::{Backend, CrosstermBackend},
+    layout::{Alignment, Constraint, Direction, Layout, Margin, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span, Text},
     widgets::{
@@ -19,8 +19,44 @@ use ratatui::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::fs;
-use std::io::{self, Write};
-use std::process::Command;
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, ExitCode};
+
+use access::PushAccess;
+use bot::BotRules;
+use chuck::{
+    access, action, adopt, api, backport, bot, checkpoint, checks, ci, clipboard, codeowners,
+    dates, dependencies, dirtree, discovery, doctor, duplicates, emails, exit, fingerprint, forge,
+    formatting, generated, glob, graph, guidelines, ignored, in_memory, issue, journal, leak, lfs,
+    lock, lockfile, manifest, mergetool, metadata, network, notify, palette, partial, paths,
+    policy, precheck, prefs, preview, process, profile, provenance, push, read_only, rebase,
+    recording, relocation, repo_state, report, risk, screen, selection, self_update, serve,
+    signature, size, split, squash, state, stats, subscription, trailer, triage, workspace,
+};
+use ci::CiStatus;
+use dates::DateFormat;
+use discovery::{FileChange, Upstream};
+use exit::Code;
+use forge::ForgeConfig;
+use ignored::IgnoreList;
+use journal::{JournalCommit, JournalEntry, Outcome};
+use manifest::{Manifest, Ownership};
+use mergetool::MergeConfig;
+use network::NetworkConfig;
+use process::{CommandExt, TimeoutConfig};
+use provenance::Provenance;
+use push::PushConfig;
+use report::ReportFormat;
+use signature::Signature;
+use state::{Contribution, PendingChanges, SharedState};
+use stats::StatsFormat;
+use subscription::Subscription;
+use trailer::Mark;
+
+#[cfg(test)]
+mod ui_tests;
+#[cfg(test)]
+mod workflow_tests;
 
 #[derive(Parser)]
 #[command(name = "chuck")]
@@ -28,8 +64,245 @@ use std::process::Command;
 #[command(version)]
 struct Cli {
     /// Show verbose output
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Time each phase of the run and print a breakdown at the end
+    #[arg(long, global = true)]
+    profile: bool,
+
+    /// Write the commands run, their output and the keys pressed to FILE, secrets
+    /// redacted, for chuck replay and bug reports
+    #[arg(long, global = true, value_name = "FILE")]
+    record: Option<std::path::PathBuf>,
+
+    /// Never change the repository, GitHub or Chuck's state: print each command that
+    /// would have, and what would have been built, instead
+    #[arg(long, global = true)]
+    read_only: bool,
+
+    /// Print where the global config is read from and exit
+    #[arg(long)]
+    print_config_path: bool,
+
+    /// Hide commits the template already contains instead of badging them
+    #[arg(long)]
+    hide_upstreamed: bool,
+
+    /// Don't count whitespace-only changes when looking for template fixes, overriding .chuckrc
+    #[arg(long)]
+    ignore_whitespace: bool,
+
+    /// Template ref (branch, tag or SHA) to build on, overriding .chuckrc
+    #[arg(long, value_name = "REF")]
+    base: Option<String>,
+
+    /// Choose the template base from recent tags and branches
+    #[arg(long, conflicts_with = "base")]
+    pick_base: bool,
+
+    /// Template branch to open the pull request against, overriding .chuckrc
+    #[arg(long, value_name = "BRANCH")]
+    target: Option<String>,
+
+    /// Choose the pull request's target branch from the template's branches
+    #[arg(long, conflicts_with = "target")]
+    pick_target: bool,
+
+    /// Open the pull request as a draft, overriding .chuckrc
+    #[arg(long, overrides_with = "no_draft")]
+    draft: bool,
+
+    /// Open the pull request ready for review, overriding .chuckrc
+    #[arg(long, overrides_with = "draft")]
+    no_draft: bool,
+
+    /// Start the plan with every selected commit squashed into one
+    #[arg(long, overrides_with = "no_squash")]
+    squash: bool,
+
+    /// Start the plan with every selected commit picked on its own
+    #[arg(long, overrides_with = "squash")]
+    no_squash: bool,
+
+    /// Only offer commits whose author name contains this, case-insensitively; "" for everyone
+    #[arg(long, value_name = "NAME")]
+    author: Option<String>,
+
+    /// Offer commits from this local ref instead of the repo's default branch on GitHub
+    #[arg(long, value_name = "REF")]
+    from: Option<String>,
+
+    /// Offer exactly the commits in a local range, e.g. v1.0..feature
+    #[arg(long, value_name = "A..B", conflicts_with = "from")]
+    range: Option<String>,
+
+    /// Only allow selecting commits with a verified GPG or SSH signature
+    #[arg(long)]
+    verified_only: bool,
+
+    /// List commits you pressed x on too, so they can be brought back
+    #[arg(long)]
+    show_ignored: bool,
+
+    /// Push a private project's commits to a public repository without typing yes
+    #[arg(long)]
+    push_public: bool,
+
+    /// Leave the project's own files, per [split] and the template's policy, out of
+    /// commits that mix them with shared code
+    #[arg(long)]
+    auto_split: bool,
+
+    /// Offer every commit since the template's last update, not just those since your
+    /// last push
+    #[arg(long)]
+    since_template: bool,
+
+    /// Skip the TUI and pick these commits: comma-separated SHA prefixes, or "all"
+    #[arg(long, value_name = "SHAS", value_delimiter = ',')]
+    select: Vec<String>,
+
+    /// Skip the TUI and build the plan in a file written by chuck export-selection
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["select", "action", "base", "pick_base"])]
+    selection: Option<std::path::PathBuf>,
+
+    /// The plan read from --selection
+    #[arg(skip)]
+    planned: Option<selection::Selection>,
+
+    /// Keep template files that selected commits delete, instead of deleting them upstream
+    #[arg(long)]
+    keep_template_files: bool,
+
+    /// Delete files the template's policy requires when selected commits delete them,
+    /// instead of stopping
+    #[arg(long, conflicts_with = "keep_template_files")]
+    delete_required_files: bool,
+
+    /// Run in GitHub Actions: report what could be contributed in the job summary
+    /// instead of contributing it
+    #[arg(long, conflicts_with_all = ["select", "pick_base", "pick_target"])]
+    action: bool,
+
+    /// Write the commits discovery found to FILE as JSON instead of starting the TUI,
+    /// for chuck serve
+    #[arg(long, hide = true, value_name = "FILE", conflicts_with_all = ["select", "selection", "action"])]
+    candidates: Option<std::path::PathBuf>,
+
+    /// With --action, keep an issue on the template listing the commits up to date
+    #[arg(long, requires = "action")]
+    tracking_issue: bool,
+
+    /// Work on the app in this directory, the one with its own .chuckrc
+    #[arg(long, value_name = "DIR")]
+    workspace: Option<String>,
+
+    /// Go through every directory with a .chuckrc, one after the other
+    #[arg(long, conflicts_with = "workspace")]
+    all_workspaces: bool,
+
+    #[command(flatten)]
+    cherry_pick: CherryPickArgs,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Update chuck to the latest GitHub release
+    SelfUpdate {
+        /// Only check whether a newer release exists
+        #[arg(long)]
+        check: bool,
+    },
+    /// Show contribution statistics from the local journal
+    Stats {
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: StatsFormat,
+    },
+    /// Generate a shareable report of upstream contributions
+    Report {
+        /// Only include contributions on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<String>,
+        /// Output format
+        #[arg(long, value_enum, default_value = "md")]
+        format: ReportFormat,
+    },
+    /// Record which files are template-managed, project-managed or diverged
+    Manifest,
+    /// Check git, gh, credentials and access to the template, and say how to fix problems
+    Doctor,
+    /// Print where Chuck keeps its config, state, journal, API cache and scratch files
+    Paths,
+    /// Contribute the commits matching the [bot] rules in .chuckrc and open the PR, unattended
+    Bot,
+    /// Tag HEAD with a "Chuck: upstream" or "Chuck: skip" trailer, amending it
+    Mark {
+        #[arg(value_enum)]
+        mark: Mark,
+    },
+    /// Record the template's current head as synced, later runs say what's new since
+    Subscribe,
+    /// Show how many commits the template has had since `chuck subscribe`
+    Status,
+    /// Set up Chuck in a project copied from a template by hand: find the template
+    /// commit it started from and write .chuckrc
+    Adopt {
+        /// URL of the template repository
+        #[arg(long, value_name = "URL")]
+        template: String,
+    },
+    /// Browse the commits since the template, their diffs and files, without selecting,
+    /// pushing or writing anything
+    Review,
+    /// Plan a contribution as usual, but write it to a file for review instead of
+    /// building it. chuck --selection <FILE> builds it later.
+    ExportSelection {
+        #[arg(value_name = "FILE")]
+        file: std::path::PathBuf,
+    },
+    /// Contribute the fix for a template issue: the commits mentioning it start out
+    /// selected, and the PR is titled after it, closes it and gets its labels
+    ForIssue {
+        /// Issue number on the template, `#12` or the issue's URL
+        #[arg(value_name = "ISSUE", value_parser = issue::parse_number)]
+        number: u64,
+    },
+    /// For template maintainers: pick template commits and open a PR with them on each
+    /// project built from the template
+    Backport {
+        /// Downstream repository, `owner/name` or URL. Repeat for several.
+        #[arg(long, value_name = "REPO", required = true)]
+        to: Vec<String>,
+        /// Offer the template commits after this ref instead of working out what each
+        /// project is missing
+        #[arg(long, value_name = "REF")]
+        since: Option<String>,
+    },
+    /// Run a session written with --record again, answering every command from the
+    /// recording and pressing the recorded keys
+    Replay {
+        #[arg(value_name = "FILE")]
+        file: std::path::PathBuf,
+    },
+    /// Rebase a pushed chuck branch onto the template's latest head and force-push it,
+    /// keeping its PR mergeable
+    Rebase {
+        /// PR number or URL, or the name of the branch on the template
+        #[arg(value_name = "PR|BRANCH")]
+        pr: String,
+    },
+    /// Answer JSON-RPC requests from an editor plugin: discover the commits and
+    /// contribute a selection, with the plugin's UI instead of the TUI
+    Serve {
+        /// Talk over stdin and stdout, one JSON message per line
+        #[arg(long, required = true)]
+        stdio: bool,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -37,28 +310,603 @@ struct Commit {
     hash: String,
     short_hash: String,
     message: String,
-    files: Vec<String>,
+    files: Vec<FileChange>,
     selected: bool,
     author: String,
     date: String,
+    parents: Vec<String>,
+    already_upstream: bool,
+    /// Reasons the template's contribution policy rejects this commit's files
+    policy_issues: Vec<String>,
+    /// CI config, lockfiles, manifests or sensitive files it changes, per `[risk]`
+    risks: Vec<risk::Finding>,
+    /// Every file it touches is project-managed according to the manifest
+    project_only: bool,
+    /// `None` until signatures have been checked
+    signature: Option<Signature>,
+    /// `None` if the commit has no checks on GitHub or they couldn't be looked up
+    ci: Option<CiStatus>,
+    /// Whether the lines it changes came from the template, `None` for merges
+    provenance: Option<Provenance>,
+    /// Files it deletes that the template base still has
+    deleted_template_files: Vec<String>,
+    /// Project files left out of the pick, per `[split]`, because the commit mixes
+    /// them with shared code
+    split_off: Vec<String>,
+    /// `git patch-id`, `None` for merges or if it couldn't be computed
+    patch_id: Option<String>,
+    /// The user asked never to be offered this commit again
+    ignored: bool,
+    /// When and where it was already proposed upstream
+    contributed: Option<Contribution>,
+    /// `Chuck: upstream` or `Chuck: skip` in its message
+    mark: Option<Mark>,
+}
+
+impl Commit {
+    fn is_merge(&self) -> bool {
+        self.parents.len() > 1
+    }
+
+    fn is_verified(&self) -> bool {
+        self.signature.as_ref().is_some_and(Signature::is_verified)
+    }
+
+    fn bulk_selectable(&self) -> bool {
+        !self.is_merge()
+            && !self.ignored
+            && self.mark != Some(Mark::Skip)
+            && self.contributed.is_none()
+            && !self.already_upstream
+            && self.policy_issues.is_empty()
+            && !self.project_only
+            && !self.provenance.is_some_and(|p| p.whitespace_only)
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct ChuckConfig {
     template: TemplateConfig,
+    #[serde(default)]
+    discovery: fingerprint::DiscoveryConfig,
+    #[serde(default)]
+    merge_commits: MergeCommitConfig,
+    #[serde(default)]
+    cherry_pick: CherryPickConfig,
+    #[serde(default)]
+    pr: PrConfig,
+    #[serde(default)]
+    notify: notify::WebhookConfig,
+    #[serde(default)]
+    emails: emails::EmailConfig,
+    #[serde(default)]
+    state: StateConfig,
+    #[serde(default)]
+    bot: BotRules,
+    #[serde(default)]
+    formatting: formatting::FormattingConfig,
+    #[serde(default)]
+    checks: checks::ChecksConfig,
+    #[serde(default)]
+    squash: squash::SquashConfig,
+    #[serde(default)]
+    split: split::SplitConfig,
+    #[serde(default)]
+    risk: risk::RiskConfig,
+    #[serde(default)]
+    leak_guard: leak::LeakGuardConfig,
+    #[serde(default)]
+    lockfiles: Vec<lockfile::LockfileConfig>,
+    #[serde(default)]
+    generated: Vec<generated::GeneratedConfig>,
+    /// The `.chuckrc` this was read from, `None` when it came from a remote
+    #[serde(skip)]
+    path: Option<std::path::PathBuf>,
+}
+
+/// User-wide settings from the global `config.toml`, as opposed to the per-repo `.chuckrc`
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct GlobalConfig {
+    #[serde(default)]
+    network: NetworkConfig,
+    #[serde(default)]
+    timeouts: TimeoutConfig,
+    #[serde(default)]
+    ui: UiConfig,
+    #[serde(default)]
+    merge: MergeConfig,
+    #[serde(default)]
+    push: PushConfig,
+    #[serde(default)]
+    forge: ForgeConfig,
+    #[serde(default)]
+    notify: notify::NotifyConfig,
+    #[serde(default)]
+    paths: paths::PathsConfig,
+}
+
+/// `[ui]` in the global config
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct UiConfig {
+    #[serde(default)]
+    date_format: DateFormat,
+    /// Start with the commit graph shown
+    #[serde(default)]
+    graph: bool,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
 struct TemplateConfig {
     url: String,
+    /// Name of the git remote Chuck adds for the template
+    #[serde(default = "default_remote_name")]
+    remote: String,
+    /// Leave the remote in place after the run instead of removing it
+    #[serde(default)]
+    keep_remote: bool,
+    /// Template ref (branch, tag or SHA) to build the chuck branch on
+    base: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct PrConfig {
+    /// Template branch the pull request is opened against
+    target: Option<String>,
+    /// Open the pull request with `gh pr create` instead of printing a URL
+    #[serde(default)]
+    create: bool,
+    /// Request review from the template's CODEOWNERS for the touched files
+    #[serde(default)]
+    request_codeowners: bool,
+    /// Open the pull request as a draft
+    #[serde(default)]
+    draft: bool,
+    /// Milestone and project the pull request goes in, for upstream triage
+    #[serde(flatten)]
+    triage: triage::Triage,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct StateConfig {
+    /// Keep ignored and contributed commits in `.chuck/state.toml` for the whole team
+    #[serde(default)]
+    shared: bool,
+}
+
+impl ChuckConfig {
+    /// Settings for `template` when there's no .chuckrc to read them from
+    fn new(template: TemplateConfig) -> Self {
+        Self {
+            template,
+            discovery: fingerprint::DiscoveryConfig::default(),
+            merge_commits: MergeCommitConfig::default(),
+            cherry_pick: CherryPickConfig::default(),
+            pr: PrConfig::default(),
+            notify: notify::WebhookConfig::default(),
+            emails: emails::EmailConfig::default(),
+            state: StateConfig::default(),
+            bot: BotRules::default(),
+            formatting: formatting::FormattingConfig::default(),
+            checks: checks::ChecksConfig::default(),
+            squash: squash::SquashConfig::default(),
+            split: split::SplitConfig::default(),
+            risk: risk::RiskConfig::default(),
+            leak_guard: leak::LeakGuardConfig::default(),
+            lockfiles: Vec::new(),
+            generated: Vec::new(),
+            path: None,
+        }
+    }
+
+    fn target_branch(&self) -> &str {
+        self.pr.target.as_deref().unwrap_or("main")
+    }
+
+    /// The chuck branch is built on the pinned base if there is one, otherwise on
+    /// the head of the branch the PR targets
+    fn base_ref(&self) -> &str {
+        self.template
+            .base
+            .as_deref()
+            .unwrap_or_else(|| self.target_branch())
+    }
+}
+
+fn default_remote_name() -> String {
+    "chuck-template".to_string()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum MergeMode {
+    /// `git cherry-pick -m <mainline>`
+    Mainline,
+    /// Apply the merge's diff against its mainline parent as a plain commit
+    Flatten,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct MergeCommitConfig {
+    #[serde(default = "default_mainline")]
+    mainline: u32,
+    #[serde(default = "default_merge_mode")]
+    mode: MergeMode,
+}
+
+impl Default for MergeCommitConfig {
+    fn default() -> Self {
+        Self {
+            mainline: default_mainline(),
+            mode: default_merge_mode(),
+        }
+    }
+}
+
+/// How the chuck branch's commits get made
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Engine {
+    /// `git cherry-pick` in the worktree, one commit after the other
+    #[default]
+    Worktree,
+    /// Three-way merges in a scratch index, handing over to the worktree for the
+    /// first pick that needs it
+    InMemory,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct CherryPickConfig {
+    /// Append "(cherry picked from commit ...)" like `git cherry-pick -x`
+    #[serde(default)]
+    record_origin: bool,
+    /// Passed as `--strategy-option`, e.g. "theirs" or "ours"
+    strategy_option: Option<String>,
+    /// Rename similarity percentage for the merge machinery
+    rename_threshold: Option<u8>,
+    /// Retry a failed cherry-pick as `git apply --3way` before giving up
+    #[serde(default)]
+    three_way_fallback: bool,
+    #[serde(default)]
+    engine: Engine,
+    /// Workspace directory picks are re-rooted from, set by --workspace
+    #[serde(skip)]
+    subtree: Option<String>,
+}
+
+impl Cli {
+    /// The remembered options passed this time
+    fn prefs(&self) -> prefs::Prefs {
+        let switch = |on: bool, off: bool| (on || off).then_some(on);
+        prefs::Prefs {
+            squash: switch(self.squash, self.no_squash),
+            target: self.target.clone(),
+            draft: switch(self.draft, self.no_draft),
+            author: self.author.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct CherryPickArgs {
+    /// Append "(cherry picked from commit ...)" to each commit message
+    #[arg(short = 'x', long)]
+    record_origin: bool,
+
+    /// Strategy option passed to git cherry-pick (e.g. theirs, ours)
+    #[arg(long, value_name = "OPTION")]
+    strategy_option: Option<String>,
+
+    /// Rename detection similarity threshold in percent
+    #[arg(long, value_name = "PERCENT", value_parser = clap::value_parser!(u8).range(1..=100))]
+    rename_threshold: Option<u8>,
+
+    /// Retry failed cherry-picks as a three-way apply
+    #[arg(long)]
+    three_way_fallback: bool,
+
+    /// Build the branch's commits without a worktree where no one's needed to merge
+    #[arg(long)]
+    in_memory: bool,
+}
+
+impl CherryPickArgs {
+    /// Command line flags win over .chuckrc
+    fn apply_to(&self, config: &mut CherryPickConfig) {
+        if self.record_origin {
+            config.record_origin = true;
+        }
+        if let Some(option) = &self.strategy_option {
+            config.strategy_option = Some(option.clone());
+        }
+        if let Some(threshold) = self.rename_threshold {
+            config.rename_threshold = Some(threshold);
+        }
+        if self.three_way_fallback {
+            config.three_way_fallback = true;
+        }
+        if self.in_memory {
+            config.engine = Engine::InMemory;
+        }
+    }
+}
+
+fn default_mainline() -> u32 {
+    1
+}
+
+fn default_merge_mode() -> MergeMode {
+    MergeMode::Mainline
+}
+
+/// What to do with a commit when building the chuck branch, as in `git rebase -i`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlanAction {
+    Pick,
+    /// Fold into the previous commit, combining the messages
+    Squash,
+    /// Pick with a new subject line
+    Reword,
+    Drop,
+}
+
+impl PlanAction {
+    fn label(&self) -> &'static str {
+        match self {
+            PlanAction::Pick => "pick",
+            PlanAction::Squash => "squash",
+            PlanAction::Reword => "reword",
+            PlanAction::Drop => "drop",
+        }
+    }
+
+    fn color(&self) -> Color {
+        match self {
+            PlanAction::Pick => Color::Green,
+            PlanAction::Squash => Color::Magenta,
+            PlanAction::Reword => Color::Cyan,
+            PlanAction::Drop => Color::DarkGray,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PlanStep {
+    /// Index into `App::commits`
+    commit: usize,
+    action: PlanAction,
+    /// Subject used when the action is `Reword`
+    message: String,
+    /// Restore the template files the commit deletes instead of deleting them upstream
+    keep_template_files: bool,
+    /// Files left out of the pick, as they are on the branch so far
+    leave_out: Vec<String>,
+}
+
+impl PlanStep {
+    /// This step of the plan for `commit` as written to a selection file
+    fn export(&self, commit: &Commit) -> selection::Step {
+        selection::Step {
+            hash: commit.hash.clone(),
+            subject: commit.message.clone(),
+            action: match self.action {
+                PlanAction::Pick => selection::Action::Pick,
+                PlanAction::Squash => selection::Action::Squash,
+                PlanAction::Reword => selection::Action::Reword,
+                PlanAction::Drop => selection::Action::Drop,
+            },
+            message: (self.action == PlanAction::Reword).then(|| self.message.clone()),
+            keep_template_files: self.keep_template_files,
+            leave_out: self.leave_out.clone(),
+        }
+    }
+}
+
+/// Orders the commit list can be sorted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sort {
+    /// Newest first, as `git log` has them
+    History,
+    Oldest,
+    Author,
+    Subject,
+}
+
+/// Everything the `:` palette can do, found by typing part of its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PaletteCommand {
+    SelectAll,
+    SelectNone,
+    InvertSelection,
+    SelectByPath,
+    FilterByDirectory,
+    ClearDirectoryFilter,
+    Sort(Sort),
+    ToggleGraph,
+    ToggleSquash,
+    SetPrTitle,
+    ShowDiff,
+    CopyHash,
+    CopySelected,
+    NeverSuggest,
+    Mark(Mark),
+    ReviewPlan,
+    Help,
+    Quit,
+}
+
+impl PaletteCommand {
+    const ALL: [PaletteCommand; 22] = [
+        PaletteCommand::SelectAll,
+        PaletteCommand::SelectNone,
+        PaletteCommand::InvertSelection,
+        PaletteCommand::SelectByPath,
+        PaletteCommand::FilterByDirectory,
+        PaletteCommand::ClearDirectoryFilter,
+        PaletteCommand::Sort(Sort::History),
+        PaletteCommand::Sort(Sort::Oldest),
+        PaletteCommand::Sort(Sort::Author),
+        PaletteCommand::Sort(Sort::Subject),
+        PaletteCommand::ToggleGraph,
+        PaletteCommand::ToggleSquash,
+        PaletteCommand::SetPrTitle,
+        PaletteCommand::ShowDiff,
+        PaletteCommand::CopyHash,
+        PaletteCommand::CopySelected,
+        PaletteCommand::NeverSuggest,
+        PaletteCommand::Mark(Mark::Upstream),
+        PaletteCommand::Mark(Mark::Skip),
+        PaletteCommand::ReviewPlan,
+        PaletteCommand::Help,
+        PaletteCommand::Quit,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PaletteCommand::SelectAll => "Select all commits",
+            PaletteCommand::SelectNone => "Select none",
+            PaletteCommand::InvertSelection => "Invert the selection",
+            PaletteCommand::SelectByPath => "Select commits by path",
+            PaletteCommand::FilterByDirectory => "Filter by directory",
+            PaletteCommand::ClearDirectoryFilter => "Clear the directory filter",
+            PaletteCommand::Sort(Sort::History) => "Sort newest first",
+            PaletteCommand::Sort(Sort::Oldest) => "Sort oldest first",
+            PaletteCommand::Sort(Sort::Author) => "Sort by author",
+            PaletteCommand::Sort(Sort::Subject) => "Sort by subject",
+            PaletteCommand::ToggleGraph => "Show or hide the commit graph",
+            PaletteCommand::ToggleSquash => "Toggle squashing into one commit",
+            PaletteCommand::SetPrTitle => "Set the pull request title",
+            PaletteCommand::ShowDiff => "Compare with the template",
+            PaletteCommand::CopyHash => "Copy the commit's hash",
+            PaletteCommand::CopySelected => "Copy the selected hashes",
+            PaletteCommand::NeverSuggest => "Never suggest this commit again",
+            PaletteCommand::Mark(Mark::Upstream) => "Tag HEAD \"Chuck: upstream\"",
+            PaletteCommand::Mark(Mark::Skip) => "Tag HEAD \"Chuck: skip\"",
+            PaletteCommand::ReviewPlan => "Review the plan",
+            PaletteCommand::Help => "Help",
+            PaletteCommand::Quit => "Quit",
+        }
+    }
+
+    /// Whether `chuck review` offers it, which it doesn't for anything building up
+    /// a contribution
+    fn browsing(self) -> bool {
+        matches!(
+            self,
+            PaletteCommand::FilterByDirectory
+                | PaletteCommand::ClearDirectoryFilter
+                | PaletteCommand::Sort(_)
+                | PaletteCommand::ToggleGraph
+                | PaletteCommand::ShowDiff
+                | PaletteCommand::CopyHash
+                | PaletteCommand::Help
+                | PaletteCommand::Quit
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Select,
+    Plan,
+}
+
+/// What the diff view compares a commit's files against
+struct DiffSource {
+    template_base: String,
+    /// Workspace the commits come from, `""` for the repository root
+    subtree: String,
+    /// Parent of merge commits their changes are taken against
+    mainline: u32,
+}
+
+/// One of a commit's files, as the template has it next to after the pick
+struct DiffView {
+    /// Index into `App::commits`
+    commit: usize,
+    /// Index into the commit's files
+    file: usize,
+    preview: Result<preview::Preview, String>,
+    scroll: usize,
 }
 
 struct App {
     commits: Vec<Commit>,
     list_state: ListState,
-    scroll_state: ScrollbarState,
     should_quit: bool,
     show_help: bool,
+    screen: Screen,
+    plan: Vec<PlanStep>,
+    plan_state: ListState,
+    /// Subject being typed while rewording the current plan step
+    editing: Option<String>,
+    /// Glob being typed to select commits by the files they touch
+    pattern: Option<String>,
+    /// Unverified commits can't be selected
+    verified_only: bool,
+    /// First commit shown in the list window
+    list_offset: usize,
+    /// Rendered rows, rebuilt when the commit's selection changes
+    line_cache: Vec<Option<(bool, Line<'static>)>>,
+    /// Graph gutter for each commit, like `git log --graph`
+    graph: Vec<String>,
+    show_graph: bool,
+    /// `None` when there's no template to compare with
+    diff_source: Option<DiffSource>,
+    diff: Option<DiffView>,
+    /// The diff view shows both versions next to each other instead of interleaved
+    side_by_side: bool,
+    /// Lines each planned commit changes, by index into `commits`. `None` when they
+    /// couldn't be counted.
+    line_counts: std::collections::HashMap<usize, Option<size::LineCount>>,
+    /// The template's idea of a PR too big to review in one go
+    soft_limits: size::SoftLimits,
+    /// The plan starts with every commit squashed into the first
+    squash: bool,
+    /// The plan starts with `fixup!` and `squash!` commits folded into what they name
+    autosquash: bool,
+    /// `chuck review`: browsing only, nothing can be selected
+    read_only: bool,
+    /// Directories the candidates touch, built when the sidebar is first opened
+    dirs: Vec<dirtree::Dir>,
+    dir_state: ListState,
+    /// The sidebar is open and has the keyboard
+    show_dirs: bool,
+    /// Only commits touching these directories are listed
+    dir_filter: std::collections::BTreeSet<String>,
+    /// Hashes waiting for the event loop to put them on the clipboard
+    clipboard: Option<String>,
+    /// Shown in the footer until the next key
+    notice: Option<String>,
+    /// Position of each commit in history, which the plan applies them in whatever
+    /// order the list is sorted in
+    rank: Vec<usize>,
+    sort: Sort,
+    /// Command name being typed in the `:` palette
+    palette: Option<String>,
+    /// Index into the palette's matches
+    palette_cursor: usize,
+    /// Pull request title being typed
+    titling: Option<String>,
+    /// Replaces the title Chuck would give the pull request
+    pr_title: Option<String>,
+    /// Errors shown in a panel over everything until dismissed
+    error: Option<String>,
+    /// First line of the error panel shown
+    error_scroll: usize,
+    /// Files on the template base, to tell which changed files exist upstream. `None`
+    /// when they couldn't be listed.
+    template_files: Option<std::collections::HashSet<String>>,
+}
+
+/// Whether `commit` touches one of `dirs`, renames' old paths included
+fn in_dirs(commit: &Commit, dirs: &std::collections::BTreeSet<String>) -> bool {
+    dirtree::touches(
+        commit
+            .files
+            .iter()
+            .flat_map(|f| std::iter::once(f.path.as_str()).chain(f.old_path.as_deref())),
+        dirs,
+    )
 }
 
 impl App {
@@ -67,932 +915,7026 @@ impl App {
         if !commits.is_empty() {
             list_state.select(Some(0));
         }
+        let rank = (0..commits.len()).collect();
+
+        let nodes: Vec<(&str, &[String])> = commits
+            .iter()
+            .map(|c| (c.hash.as_str(), c.parents.as_slice()))
+            .collect();
 
         Self {
-            scroll_state: ScrollbarState::new(commits.len()),
+            line_cache: vec![None; commits.len()],
+            graph: graph::layout(&nodes),
+            show_graph: false,
             commits,
             list_state,
             should_quit: false,
             show_help: false,
+            screen: Screen::Select,
+            plan: Vec::new(),
+            plan_state: ListState::default(),
+            editing: None,
+            pattern: None,
+            verified_only: false,
+            list_offset: 0,
+            diff_source: None,
+            diff: None,
+            side_by_side: false,
+            line_counts: std::collections::HashMap::new(),
+            soft_limits: size::SoftLimits::default(),
+            squash: false,
+            autosquash: true,
+            read_only: false,
+            dirs: Vec::new(),
+            dir_state: ListState::default(),
+            show_dirs: false,
+            dir_filter: std::collections::BTreeSet::new(),
+            clipboard: None,
+            notice: None,
+            rank,
+            sort: Sort::History,
+            palette: None,
+            palette_cursor: 0,
+            titling: None,
+            pr_title: None,
+            error: None,
+            error_scroll: 0,
+            template_files: None,
         }
     }
 
-    fn next(&mut self) {
-        if self.commits.is_empty() {
-            return;
-        }
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i >= self.commits.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
+    fn cached_line(&mut self, i: usize) -> Line<'static> {
+        let commit = &self.commits[i];
+        match &self.line_cache[i] {
+            Some((selected, line)) if *selected == commit.selected => line.clone(),
+            _ => {
+                let gutter = self.show_graph.then(|| self.graph[i].as_str());
+                let line = commit_line(commit, gutter);
+                self.line_cache[i] = Some((commit.selected, line.clone()));
+                line
             }
-            None => 0,
-        };
-        self.list_state.select(Some(i));
-        self.scroll_state = self.scroll_state.position(i);
+        }
     }
 
-    fn previous(&mut self) {
-        if self.commits.is_empty() {
-            return;
-        }
-        let i = match self.list_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.commits.len() - 1
-                } else {
-                    i - 1
-                }
-            }
-            None => 0,
-        };
-        self.list_state.select(Some(i));
-        self.scroll_state = self.scroll_state.position(i);
+    /// Whether the directory filter lets commit `i` into the list
+    fn shown(&self, i: usize) -> bool {
+        in_dirs(&self.commits[i], &self.dir_filter)
     }
 
-    fn toggle_current(&mut self) {
-        if let Some(i) = self.list_state.selected() {
-            if i < self.commits.len() {
-                self.commits[i].selected = !self.commits[i].selected;
-            }
+    /// Commits in the list, by index into `commits`
+    fn shown_commits(&self) -> Vec<usize> {
+        (0..self.commits.len()).filter(|&i| self.shown(i)).collect()
+    }
+
+    fn next(&mut self) {
+        let n = self.commits.len();
+        let Some(current) = self.list_state.selected() else {
+            return self
+                .list_state
+                .select(self.shown_commits().first().copied());
+        };
+        // Wrapping around, past the commits the filter hides
+        if let Some(i) = (1..=n).map(|k| (current + k) % n).find(|&i| self.shown(i)) {
+            self.list_state.select(Some(i));
         }
     }
 
-    fn select_all(&mut self) {
-        for commit in &mut self.commits {
-            commit.selected = true;
+    fn previous(&mut self) {
+        let n = self.commits.len();
+        let Some(current) = self.list_state.selected() else {
+            return self
+                .list_state
+                .select(self.shown_commits().first().copied());
+        };
+        if let Some(i) = (1..=n)
+            .map(|k| (current + n - k) % n)
+            .find(|&i| self.shown(i))
+        {
+            self.list_state.select(Some(i));
         }
     }
 
-    fn select_none(&mut self) {
-        for commit in &mut self.commits {
-            commit.selected = false;
+    fn copy_current(&mut self) {
+        if let Some(commit) = self.list_state.selected().map(|i| &self.commits[i]) {
+            self.notice = Some(format!("Copied {} to the clipboard", commit.short_hash));
+            self.clipboard = Some(commit.hash.clone());
         }
     }
 
-    fn invert_selection(&mut self) {
-        for commit in &mut self.commits {
-            commit.selected = !commit.selected;
+    /// Selected hashes one per line, in list order
+    fn copy_selected(&mut self) {
+        let hashes: Vec<&str> = self
+            .commits
+            .iter()
+            .filter(|c| c.selected)
+            .map(|c| c.hash.as_str())
+            .collect();
+        if hashes.is_empty() {
+            self.notice = Some("No commits selected to copy".to_string());
+            return;
         }
+        self.notice = Some(format!(
+            "Copied {} selected hashes to the clipboard",
+            hashes.len()
+        ));
+        self.clipboard = Some(hashes.join("\n"));
     }
 
-    fn get_selected(&self) -> Vec<&Commit> {
-        self.commits.iter().filter(|c| c.selected).collect()
+    /// Adds the errors reported since the last frame to the error panel
+    fn show_errors(&mut self) {
+        for error in screen::take_errors() {
+            self.error = Some(match self.error.take() {
+                Some(shown) => format!("{}\n\n{}", shown, error),
+                None => error,
+            });
+        }
     }
 
-    fn selected_count(&self) -> usize {
-        self.commits.iter().filter(|c| c.selected).count()
+    fn scroll_error(&mut self, offset: isize) {
+        let lines = self.error.as_deref().map_or(0, |e| e.lines().count());
+        self.error_scroll = self
+            .error_scroll
+            .saturating_add_signed(offset)
+            .min(lines.saturating_sub(1));
     }
 
-    fn current_commit(&self) -> Option<&Commit> {
-        self.list_state.selected().and_then(|i| self.commits.get(i))
+    fn copy_error(&mut self) {
+        if let Some(error) = &self.error {
+            self.notice = Some("Copied the error to the clipboard".to_string());
+            self.clipboard = Some(error.clone());
+        }
     }
-}
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    run_app(cli)
-}
+    fn dismiss_error(&mut self) {
+        self.error = None;
+        self.error_scroll = 0;
+    }
 
-fn run_app(cli: Cli) -> Result<()> {
-    println!("🧔 Chuck: Let's see what you've been working on...\n");
+    fn open_palette(&mut self) {
+        self.palette = Some(String::new());
+        self.palette_cursor = 0;
+    }
 
-    // Find the template repository
-    let template_repo =
-        find_template_repo().map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+    /// Commands matching what's typed in the palette, best first
+    fn palette_matches(&self) -> Vec<PaletteCommand> {
+        let offered: Vec<PaletteCommand> = PaletteCommand::ALL
+            .into_iter()
+            .filter(|command| !self.read_only || command.browsing())
+            .collect();
+        let names: Vec<&str> = offered.iter().map(|command| command.name()).collect();
+        palette::rank(self.palette.as_deref().unwrap_or_default(), &names)
+            .into_iter()
+            .map(|i| offered[i])
+            .collect()
+    }
 
-    if cli.verbose {
-        println!("🧔 VERBOSE: Template repository: {}", template_repo);
+    fn palette_next(&mut self) {
+        let n = self.palette_matches().len();
+        if n > 0 {
+            self.palette_cursor = (self.palette_cursor + 1) % n;
+        }
     }
-    println!("🧔 Found template: {}", template_repo);
 
-    // Get current repository
-    let current_repo =
-        get_current_repo().map_err(|e| anyhow!("🧔 \"Can't figure out current repo\": {}", e))?;
+    fn palette_previous(&mut self) {
+        let n = self.palette_matches().len();
+        if n > 0 {
+            self.palette_cursor = (self.palette_cursor + n - 1) % n;
+        }
+    }
 
-    if cli.verbose {
-        println!("🧔 VERBOSE: Current repository: {}", current_repo);
+    /// Closes the palette and runs the command under its cursor. Returns whether the
+    /// selection is over, like a key that does the same.
+    fn run_palette(&mut self) -> bool {
+        let command = self.palette_matches().get(self.palette_cursor).copied();
+        self.palette = None;
+        let Some(command) = command else {
+            return false;
+        };
+        match command {
+            PaletteCommand::SelectAll => self.select_all(),
+            PaletteCommand::SelectNone => self.select_none(),
+            PaletteCommand::InvertSelection => self.invert_selection(),
+            PaletteCommand::SelectByPath => self.pattern = Some(String::new()),
+            PaletteCommand::FilterByDirectory => self.open_dirs(),
+            PaletteCommand::ClearDirectoryFilter => self.clear_dirs(),
+            PaletteCommand::Sort(sort) => self.sort_by(sort),
+            PaletteCommand::ToggleGraph => self.toggle_graph(),
+            PaletteCommand::ToggleSquash => {
+                self.squash = !self.squash;
+                self.notice = Some(
+                    if self.squash {
+                        "The plan will squash the selected commits into one"
+                    } else {
+                        "The plan will pick the selected commits one by one"
+                    }
+                    .to_string(),
+                );
+            }
+            PaletteCommand::SetPrTitle => {
+                self.titling = Some(self.pr_title.clone().unwrap_or_default());
+            }
+            PaletteCommand::ShowDiff => self.open_diff(),
+            PaletteCommand::CopyHash => self.copy_current(),
+            PaletteCommand::CopySelected => self.copy_selected(),
+            PaletteCommand::NeverSuggest => self.toggle_ignored(),
+            PaletteCommand::Mark(mark) => self.mark_current(mark),
+            PaletteCommand::ReviewPlan => return self.proceed(),
+            PaletteCommand::Help => self.show_help = true,
+            PaletteCommand::Quit => {
+                self.should_quit = true;
+                return true;
+            }
+        }
+        false
     }
 
-    // Get commits since template
-    let commits = get_commits_since_template(&current_repo, &template_repo)
-        .map_err(|e| anyhow!("🧔 \"Can't seem to get those commits\": {}", e))?;
+    /// Moves on to the plan, or ends the selection when there's nothing to plan
+    fn proceed(&mut self) -> bool {
+        if self.selected_count() == 0 {
+            return true;
+        }
+        self.start_plan();
+        self.count_lines();
+        false
+    }
 
-    if commits.is_empty() {
-        println!("🧔 \"Looks like you haven't made any commits since the template. Get to work!\"");
-        return Ok(());
+    fn finish_title(&mut self) {
+        let title = self.titling.take().unwrap_or_default();
+        let title = title.trim();
+        if title.is_empty() {
+            self.pr_title = None;
+            self.notice = Some("The pull request gets the usual title".to_string());
+        } else {
+            self.notice = Some(format!("The pull request will be titled \"{}\"", title));
+            self.pr_title = Some(title.to_string());
+        }
     }
 
-    if cli.verbose {
-        println!("🧔 VERBOSE: Found {} commits to review", commits.len());
-        for commit in &commits {
-            println!(
-                "🧔 VERBOSE: {} - {} (files: {})",
-                commit.short_hash,
-                commit.message,
-                commit.files.len()
+    fn open_dirs(&mut self) {
+        if self.dirs.is_empty() {
+            self.dirs = dirtree::build(
+                self.commits
+                    .iter()
+                    .map(|c| c.files.iter().map(|f| f.path.as_str())),
             );
         }
+        if self.dir_state.selected().is_none() && !self.dirs.is_empty() {
+            self.dir_state.select(Some(0));
+        }
+        self.show_dirs = true;
     }
 
-    // Setup terminal for TUI
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
-
-    // Run interactive selection
-    let mut app = App::new(commits);
-
-    loop {
-        terminal.draw(|frame| render_ui(frame, &mut app))?;
-
-        if let Event::Key(key) = event::read()? {
-            if handle_key_event(&mut app, key) {
-                break;
-            }
+    fn next_dir(&mut self) {
+        if let Some(i) = self.dir_state.selected() {
+            self.dir_state.select(Some((i + 1) % self.dirs.len()));
         }
     }
 
-    // Restore terminal properly
-    disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
-    terminal.show_cursor()?;
-
-    // Ensure terminal is fully restored and flushed
-    drop(terminal);
-    io::stdout().flush()?;
-
-    // Get selected commits after TUI exits
-    let selected_commits = app.get_selected();
-
-    // Print clear separator and status
-    println!("\n🧔 Exiting interactive mode...");
-    println!(
-        "🧔 Selected {} commits for contribution",
-        selected_commits.len()
-    );
+    fn previous_dir(&mut self) {
+        if let Some(i) = self.dir_state.selected() {
+            self.dir_state
+                .select(Some((i + self.dirs.len() - 1) % self.dirs.len()));
+        }
+    }
 
-    if selected_commits.is_empty() {
-        println!("🧔 \"No commits selected. That's fine, take your time.\"");
-        return Ok(());
+    /// Adds the directory under the cursor to the filter, or takes it out
+    fn toggle_dir(&mut self) {
+        let Some(dir) = self.dir_state.selected().and_then(|i| self.dirs.get(i)) else {
+            return;
+        };
+        if !self.dir_filter.remove(&dir.path) {
+            self.dir_filter.insert(dir.path.clone());
+        }
+        self.keep_cursor_shown();
     }
 
-    // Show what commits were selected
-    println!("\n🧔 Selected commits:");
-    for commit in &selected_commits {
-        println!("  • {} - {}", commit.short_hash, commit.message);
+    fn clear_dirs(&mut self) {
+        self.dir_filter.clear();
+        self.keep_cursor_shown();
     }
 
-    if cli.verbose {
-        println!(
-            "\n🧔 VERBOSE: About to process {} commits",
-            selected_commits.len()
-        );
+    /// Moves the cursor off a commit the filter just hid
+    fn keep_cursor_shown(&mut self) {
+        if self.list_state.selected().is_some_and(|i| !self.shown(i)) {
+            self.list_state
+                .select(self.shown_commits().first().copied());
+        }
     }
 
-    println!("\n🧔 Creating branch and processing commits...");
+    fn toggle_current(&mut self) {
+        if let Some(i) = self.list_state.selected() {
+            if let Some(commit) = self.commits.get_mut(i) {
+                commit.selected = !commit.selected
+                    && !commit.ignored
+                    && (!self.verified_only || commit.is_verified());
+            }
+        }
+    }
 
-    // Create branch with selected commits
-    let (branch_name, timestamp) =
-        create_branch_with_commits(&selected_commits, cli.verbose, &template_repo)?;
+    /// Marks the current commit to never be offered again, or takes that back
+    fn toggle_ignored(&mut self) {
+        if let Some(i) = self.list_state.selected() {
+            if let Some(commit) = self.commits.get_mut(i) {
+                commit.ignored = !commit.ignored;
+                commit.selected = false;
+                self.line_cache[i] = None;
+            }
+        }
+    }
 
-    // Get template URL for pushing
-    let config = read_chuck_config()?;
+    /// Tags the highlighted commit with `mark` for the next runs, amending it. Only
+    /// HEAD can be amended like that, older commits would need a rebase.
+    fn mark_current(&mut self, mark: Mark) {
+        let Some(i) = self.list_state.selected() else {
+            return;
+        };
+        if read_only::is_enabled() {
+            self.notice = Some("Read-only, HEAD stays as it is".to_string());
+            return;
+        }
+        match trailer::head() {
+            Ok(head) if head == self.commits[i].hash => {}
+            Ok(_) => {
+                self.notice = Some(format!(
+                    "Only HEAD can be tagged here, add \"{}: {}\" when committing instead",
+                    trailer::KEY,
+                    mark.value()
+                ));
+                return;
+            }
+            Err(e) => {
+                self.notice = Some(format!("Couldn't find HEAD: {}", e));
+                return;
+            }
+        }
+        let hash = match trailer::mark_head(mark) {
+            Ok(hash) => hash,
+            Err(e) => {
+                self.notice = Some(format!("Couldn't tag it: {}", e));
+                return;
+            }
+        };
 
-    println!("\n🧔 Attempting to push to template repository...");
+        // A new commit as far as signatures and CI go
+        let signature = signature::verify(&[&hash])
+            .ok()
+            .and_then(|mut signatures| signatures.remove(&hash));
+        let verified_only = self.verified_only;
+        let commit = &mut self.commits[i];
+        commit.short_hash = hash.chars().take(commit.short_hash.len()).collect();
+        commit.hash = hash;
+        commit.signature = signature;
+        commit.ci = None;
+        commit.mark = Some(mark);
+        commit.selected = mark == Mark::Upstream
+            && commit.bulk_selectable()
+            && (!verified_only || commit.is_verified());
+        self.line_cache[i] = None;
+        self.notice = Some(format!(
+            "Amended {} with \"{}: {}\"",
+            commit.short_hash,
+            trailer::KEY,
+            mark.value()
+        ));
+    }
 
-    // Push to template and create PR
-    match push_to_template_and_create_pr(
-        &branch_name,
-        &config.template.url,
-        &current_repo,
-        &timestamp,
-    ) {
-        Ok(()) => {
-            println!("\n🧔 ✅ SUCCESS! All operations completed successfully.");
-            println!("🧔 Check the URL above to create your pull request.");
+    // Merge commits and policy violations are never bulk-selected, they have to be picked
+    // deliberately. Commits the directory filter hides keep their selection.
+    fn select_all(&mut self) {
+        for commit in &mut self.commits {
+            if in_dirs(commit, &self.dir_filter) {
+                commit.selected =
+                    commit.bulk_selectable() && (!self.verified_only || commit.is_verified());
+            }
         }
-        Err(e) => {
-            println!("\n🧔 ⚠️  Branch created but couldn't auto-push: {}", e);
-            println!("\n🧔 Manual commands to complete the process:");
-            println!(
-                "   git push {} {}:chuck-from-{}",
-                config.template.url,
-                branch_name,
-                current_repo.replace("/", "-")
-            );
-            let template_repo_name = extract_repo_name_from_url(&config.template.url)?;
-            let remote_branch_name = format!("chuck-from-{}", current_repo.replace("/", "-"));
-            println!(
-                "   Then create PR at: https://github.com/{}/pull/new/{}",
-                template_repo_name, remote_branch_name
-            );
+    }
+
+    fn select_none(&mut self) {
+        for commit in &mut self.commits {
+            commit.selected = false;
         }
     }
 
-    Ok(())
-}
+    fn invert_selection(&mut self) {
+        for commit in &mut self.commits {
+            if !in_dirs(commit, &self.dir_filter) {
+                continue;
+            }
+            commit.selected = !commit.selected
+                && commit.bulk_selectable()
+                && (!self.verified_only || commit.is_verified());
+        }
+    }
 
-fn handle_key_event(app: &mut App, key: KeyEvent) -> bool {
-    match key.code {
-        KeyCode::Char('q') | KeyCode::Esc => {
-            if app.show_help {
-                app.show_help = false;
-            } else {
-                app.should_quit = true;
-                return true;
+    /// Whether every file `commit` touches matches `pattern`. A trailing `/` means
+    /// everything under the directory.
+    fn within(commit: &Commit, pattern: &str) -> bool {
+        let pattern = match pattern.strip_suffix('/') {
+            Some(dir) => format!("{}/**", dir),
+            None => pattern.to_string(),
+        };
+        !commit.files.is_empty()
+            && commit.files.iter().all(|f| {
+                std::iter::once(&f.path)
+                    .chain(&f.old_path)
+                    .all(|path| glob::glob_match(&pattern, path))
+            })
+    }
+
+    /// Commits `a` would select that only touch files matching `pattern`
+    fn matching(&self, pattern: &str) -> Vec<usize> {
+        if pattern.trim().is_empty() {
+            return Vec::new();
+        }
+        (0..self.commits.len())
+            .filter(|&i| {
+                let commit = &self.commits[i];
+                commit.bulk_selectable()
+                    && (!self.verified_only || commit.is_verified())
+                    && Self::within(commit, pattern.trim())
+            })
+            .collect()
+    }
+
+    /// Adds the commits matching the pattern being typed to the selection
+    fn finish_pattern(&mut self) {
+        if let Some(pattern) = self.pattern.take() {
+            for i in self.matching(&pattern) {
+                self.commits[i].selected = true;
             }
         }
-        KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-            app.should_quit = true;
-            return true;
+    }
+
+    /// Selects commits by SHA prefix for runs without the TUI. `all` selects what `a` would.
+    fn select_by_hash(&mut self, wanted: &[String]) -> Result<()> {
+        if wanted.iter().any(|w| w == "all") {
+            self.select_all();
+            return Ok(());
         }
-        KeyCode::Down | KeyCode::Char('j') => app.next(),
-        KeyCode::Up | KeyCode::Char('k') => app.previous(),
-        KeyCode::Char(' ') => app.toggle_current(),
-        KeyCode::Char('a') => app.select_all(),
-        KeyCode::Char('n') => app.select_none(),
-        KeyCode::Char('i') => app.invert_selection(),
-        KeyCode::Char('h') | KeyCode::Char('?') => app.show_help = !app.show_help,
-        KeyCode::Enter => {
-            if !app.show_help {
-                return true; // Proceed with selected commits
+
+        for prefix in wanted {
+            let matches: Vec<usize> = (0..self.commits.len())
+                .filter(|&i| self.commits[i].hash.starts_with(prefix.as_str()))
+                .collect();
+            let i = match matches[..] {
+                [i] => i,
+                [] => return Err(anyhow!("No candidate commit matches {}", prefix)),
+                _ => {
+                    return Err(anyhow!(
+                        "{} is ambiguous, it matches {} commits",
+                        prefix,
+                        matches.len()
+                    ))
+                }
+            };
+            let commit = &mut self.commits[i];
+            if self.verified_only && !commit.is_verified() {
+                return Err(anyhow!(
+                    "{} doesn't have a verified signature",
+                    commit.short_hash
+                ));
             }
+            commit.selected = true;
         }
-        _ => {}
+        Ok(())
     }
-    false
-}
 
-fn render_ui(frame: &mut Frame, app: &mut App) {
-    let area = frame.area();
+    /// Replaces the plan with the one from a selection file, in its order. Commits
+    /// that can't be selected any more, e.g. because the template has them now, drop out.
+    fn apply_selection(&mut self, planned: &selection::Selection) {
+        self.plan = planned
+            .steps
+            .iter()
+            .filter_map(|step| {
+                let i = self
+                    .commits
+                    .iter()
+                    .position(|c| c.selected && c.hash.starts_with(&step.hash))?;
+                Some(PlanStep {
+                    commit: i,
+                    action: match step.action {
+                        selection::Action::Pick => PlanAction::Pick,
+                        selection::Action::Squash => PlanAction::Squash,
+                        selection::Action::Reword => PlanAction::Reword,
+                        selection::Action::Drop => PlanAction::Drop,
+                    },
+                    message: step
+                        .message
+                        .clone()
+                        .unwrap_or_else(|| self.commits[i].message.clone()),
+                    keep_template_files: step.keep_template_files,
+                    leave_out: step.leave_out.clone(),
+                })
+            })
+            .collect();
+    }
 
-    // Create main layout
-    let main_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Length(3),
-            Constraint::Min(0),
-            Constraint::Length(3),
-        ])
-        .split(area);
+    fn toggle_graph(&mut self) {
+        // The graph only makes sense in history order
+        if !self.show_graph {
+            self.sort_by(Sort::History);
+        }
+        self.show_graph = !self.show_graph;
+        self.line_cache.fill(None);
+    }
 
-    let header_area = main_layout[0];
-    let main_area = main_layout[1];
-    let footer_area = main_layout[2];
+    /// Reorders the list, keeping the cursor on the same commit
+    fn sort_by(&mut self, sort: Sort) {
+        let mut order: Vec<usize> = (0..self.commits.len()).collect();
+        match sort {
+            Sort::History => order.sort_by_key(|&i| self.rank[i]),
+            Sort::Oldest => order.sort_by_key(|&i| std::cmp::Reverse(self.rank[i])),
+            Sort::Author => order.sort_by_key(|&i| (&self.commits[i].author, self.rank[i])),
+            Sort::Subject => {
+                order.sort_by_key(|&i| (self.commits[i].message.to_lowercase(), self.rank[i]))
+            }
+        }
+        let current = self.list_state.selected();
+
+        let mut commits: Vec<Option<Commit>> = self.commits.drain(..).map(Some).collect();
+        self.commits = order.iter().map(|&i| commits[i].take().unwrap()).collect();
+        self.line_cache = order.iter().map(|&i| self.line_cache[i].clone()).collect();
+        self.graph = order.iter().map(|&i| self.graph[i].clone()).collect();
+        self.rank = order.iter().map(|&i| self.rank[i]).collect();
+        self.list_state
+            .select(current.and_then(|c| order.iter().position(|&i| i == c)));
+        // Counted by index, which just moved
+        self.line_counts.clear();
+
+        if sort != Sort::History && self.show_graph {
+            self.show_graph = false;
+            self.line_cache.fill(None);
+        }
+        self.sort = sort;
+    }
 
-    // Render header
-    render_header(frame, header_area, app);
+    fn selected_count(&self) -> usize {
+        self.commits.iter().filter(|c| c.selected).count()
+    }
 
-    // Render main content
-    if app.show_help {
-        render_help(frame, main_area);
-    } else {
-        let content_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(60), Constraint::Percentage(40)])
-            .split(main_area);
+    fn current_commit(&self) -> Option<&Commit> {
+        if self.screen == Screen::Plan {
+            return self
+                .plan_state
+                .selected()
+                .and_then(|i| self.plan.get(i))
+                .and_then(|step| self.commits.get(step.commit));
+        }
+        self.list_state.selected().and_then(|i| self.commits.get(i))
+    }
 
-        let list_area = content_layout[0];
-        let details_area = content_layout[1];
+    /// Switches to the plan screen with every selected commit picked, or squashed into
+    /// the first with `squash`, oldest first since that's the order they have to be
+    /// applied in
+    fn start_plan(&mut self) {
+        let mut selected: Vec<usize> = (0..self.commits.len())
+            .filter(|&i| self.commits[i].selected)
+            .collect();
+        selected.sort_by_key(|&i| std::cmp::Reverse(self.rank[i]));
+        self.plan = selected
+            .into_iter()
+            .map(|i| (i, &self.commits[i]))
+            .map(|(i, c)| PlanStep {
+                commit: i,
+                action: PlanAction::Pick,
+                message: c.message.clone(),
+                keep_template_files: false,
+                leave_out: Vec::new(),
+            })
+            .collect();
+        if self.autosquash {
+            let subjects: Vec<(&str, &str)> = self
+                .plan
+                .iter()
+                .map(|step| {
+                    let commit = &self.commits[step.commit];
+                    (commit.hash.as_str(), commit.message.as_str())
+                })
+                .collect();
+            let order = squash::autosquash(&subjects);
+            let mut steps: Vec<Option<PlanStep>> = self.plan.drain(..).map(Some).collect();
+            self.plan = order
+                .into_iter()
+                .filter_map(|(i, fold)| {
+                    let mut step = steps[i].take()?;
+                    if fold.is_some() {
+                        step.action = PlanAction::Squash;
+                    }
+                    Some(step)
+                })
+                .collect();
+        }
+        if self.squash {
+            for step in self.plan.iter_mut().skip(1) {
+                step.action = PlanAction::Squash;
+            }
+        }
+        self.plan_state.select(Some(0));
+        self.screen = Screen::Plan;
+    }
 
-        render_commit_list(frame, list_area, app);
-        render_commit_details(frame, details_area, app);
+    /// Counts the lines of planned commits that haven't been counted yet. Counting
+    /// needs the repository the diff view reads from.
+    fn count_lines(&mut self) {
+        let Some(source) = &self.diff_source else {
+            return;
+        };
+        for step in &self.plan {
+            if !self.line_counts.contains_key(&step.commit) {
+                let commit = &self.commits[step.commit];
+                let mainline = if commit.is_merge() {
+                    source.mainline
+                } else {
+                    1
+                };
+                let count = match size::line_count(&commit.hash, mainline, &source.subtree) {
+                    Ok(count) => Some(count),
+                    Err(e) => {
+                        screen::error(&format!(
+                            "Couldn't count the lines {} changes: {}",
+                            commit.short_hash, e
+                        ));
+                        None
+                    }
+                };
+                self.line_counts.insert(step.commit, count);
+            }
+        }
     }
 
-    // Render footer
-    render_footer(frame, footer_area, app);
-}
+    /// How big a PR the kept plan steps make
+    fn estimate(&self) -> size::Estimate {
+        let kept: Vec<usize> = self
+            .plan
+            .iter()
+            .filter(|s| s.action != PlanAction::Drop)
+            .map(|s| s.commit)
+            .collect();
+        let changed = kept
+            .iter()
+            .try_fold(size::LineCount::default(), |total, i| {
+                let count = (*self.line_counts.get(i)?)?;
+                Some(size::LineCount {
+                    added: total.added + count.added,
+                    removed: total.removed + count.removed,
+                })
+            });
+        let paths = kept
+            .iter()
+            .flat_map(|&i| &self.commits[i].files)
+            .map(|f| f.path.as_str());
+        size::Estimate::new(paths, changed)
+    }
 
-fn render_header(frame: &mut Frame, area: Rect, app: &App) {
-    let selected_count = app.selected_count();
-    let total_count = app.commits.len();
+    fn plan_next(&mut self) {
+        if let Some(i) = self.plan_state.selected() {
+            self.plan_state.select(Some((i + 1) % self.plan.len()));
+        }
+    }
 
-    let title = if selected_count > 0 {
-        format!(
-            "🧔 Chuck: {} of {} commits selected",
-            selected_count, total_count
-        )
-    } else {
-        format!("🧔 Chuck: {} commits found since template", total_count)
-    };
+    fn plan_previous(&mut self) {
+        if let Some(i) = self.plan_state.selected() {
+            self.plan_state
+                .select(Some((i + self.plan.len() - 1) % self.plan.len()));
+        }
+    }
 
-    let header = Paragraph::new(title)
-        .style(
-            Style::default()
-                .fg(Color::Cyan)
-                .add_modifier(Modifier::BOLD),
-        )
-        .block(Block::default().borders(Borders::BOTTOM));
+    fn set_action(&mut self, action: PlanAction) {
+        if let Some(i) = self.plan_state.selected() {
+            // There's nothing to squash into above the first kept step
+            if action == PlanAction::Squash
+                && !self.plan[..i].iter().any(|s| s.action != PlanAction::Drop)
+            {
+                return;
+            }
+            self.plan[i].action = action;
+        }
+    }
 
-    frame.render_widget(header, area);
-}
+    /// Moves the current plan step by `offset` (-1 up, 1 down)
+    fn move_step(&mut self, offset: isize) {
+        if let Some(i) = self.plan_state.selected() {
+            let j = i as isize + offset;
+            if j >= 0 && (j as usize) < self.plan.len() {
+                self.plan.swap(i, j as usize);
+                self.plan_state.select(Some(j as usize));
+            }
+        }
+        // A squash may have ended up first
+        if let Some(first) = self.plan.iter_mut().find(|s| s.action != PlanAction::Drop) {
+            if first.action == PlanAction::Squash {
+                first.action = PlanAction::Pick;
+            }
+        }
+    }
 
-fn render_commit_list(frame: &mut Frame, area: Rect, app: &mut App) {
-    let items: Vec<ListItem> = app
-        .commits
-        .iter()
-        .map(|commit| {
-            let checkbox = if commit.selected { "✓" } else { " " };
-            let style = if commit.selected {
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD)
-            } else {
-                Style::default()
-            };
+    fn toggle_keep_template_files(&mut self) {
+        if let Some(step) = self
+            .plan_state
+            .selected()
+            .and_then(|i| self.plan.get_mut(i))
+        {
+            step.keep_template_files = !step.keep_template_files
+                && !self.commits[step.commit].deleted_template_files.is_empty();
+        }
+    }
 
-            let content = Line::from(vec![
-                Span::styled(format!("[{}] ", checkbox), style),
-                Span::styled(&commit.short_hash, Style::default().fg(Color::Yellow)),
-                Span::raw(" - "),
-                Span::styled(&commit.message, style),
-            ]);
+    fn start_reword(&mut self) {
+        if let Some(step) = self.plan_state.selected().and_then(|i| self.plan.get(i)) {
+            self.editing = Some(step.message.clone());
+        }
+    }
 
-            ListItem::new(content)
-        })
-        .collect();
+    fn finish_reword(&mut self) {
+        if let (Some(message), Some(i)) = (self.editing.take(), self.plan_state.selected()) {
+            let message = message.trim().to_string();
+            if !message.is_empty() {
+                self.plan[i].message = message;
+                self.plan[i].action = PlanAction::Reword;
+            }
+        }
+    }
 
-    let list = List::new(items)
-        .block(
-            Block::default()
-                .title("Commits")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Gray)),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::DarkGray)
-                .add_modifier(Modifier::BOLD),
-        )
-        .highlight_symbol("► ");
+    /// Opens the diff view on the current commit's first file
+    fn open_diff(&mut self) {
+        let commit = if self.screen == Screen::Plan {
+            self.plan_state
+                .selected()
+                .and_then(|i| self.plan.get(i))
+                .map(|step| step.commit)
+        } else {
+            self.list_state.selected()
+        };
+        if let Some(commit) = commit.filter(|&i| !self.commits[i].files.is_empty()) {
+            self.show_file(commit, 0);
+        }
+    }
 
-    frame.render_stateful_widget(list, area, &mut app.list_state);
+    fn show_file(&mut self, commit: usize, file: usize) {
+        let preview = match &self.diff_source {
+            Some(source) => {
+                let c = &self.commits[commit];
+                let change = &c.files[file];
+                let mainline = if c.is_merge() { source.mainline } else { 1 };
+                preview::load(
+                    &source.template_base,
+                    &format!("{}^{}", c.hash, mainline),
+                    &c.hash,
+                    &change.path,
+                    change.old_path.as_deref(),
+                    &source.subtree,
+                )
+                .map_err(|e| e.to_string())
+            }
+            None => Err("no template to compare with".to_string()),
+        };
+        self.diff = Some(DiffView {
+            commit,
+            file,
+            preview,
+            scroll: 0,
+        });
+    }
 
-    // Render scrollbar
-    let scrollbar = Scrollbar::default()
-        .orientation(ScrollbarOrientation::VerticalRight)
-        .begin_symbol(Some("↑"))
-        .end_symbol(Some("↓"));
+    /// Moves the diff view `offset` files along, wrapping around
+    fn next_file(&mut self, offset: isize) {
+        if let Some(view) = &self.diff {
+            let count = self.commits[view.commit].files.len() as isize;
+            let file = (view.file as isize + offset).rem_euclid(count) as usize;
+            self.show_file(view.commit, file);
+        }
+    }
 
-    let scrollbar_area = Rect {
-        x: area.x + area.width - 1,
-        y: area.y + 1,
-        width: 1,
-        height: area.height - 2,
-    };
+    fn scroll_diff(&mut self, offset: isize) {
+        if let Some(view) = &mut self.diff {
+            let rows = view.preview.as_ref().map_or(0, |p| p.rows.len());
+            view.scroll = view.scroll.saturating_add_signed(offset).min(rows);
+        }
+    }
 
-    frame.render_stateful_widget(scrollbar, scrollbar_area, &mut app.scroll_state);
+    /// The confirmed plan without dropped commits. Empty if the user quit.
+    fn planned(&self) -> Vec<(&Commit, &PlanStep)> {
+        if self.should_quit {
+            return Vec::new();
+        }
+        self.plan
+            .iter()
+            .filter(|step| step.action != PlanAction::Drop)
+            .map(|step| (&self.commits[step.commit], step))
+            .collect()
+    }
 }
 
-fn render_commit_details(frame: &mut Frame, area: Rect, app: &App) {
-    let content = if let Some(commit) = app.current_commit() {
-        let mut text = vec![
-            Line::from(vec![
-                Span::styled("Hash: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::styled(&commit.hash, Style::default().fg(Color::Yellow)),
-            ]),
-            Line::from(vec![
-                Span::styled("Author: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(&commit.author),
-            ]),
-            Line::from(vec![
-                Span::styled("Date: ", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(&commit.date),
-            ]),
-            Line::raw(""),
-            Line::from(vec![Span::styled(
-                "Message:",
-                Style::default().add_modifier(Modifier::BOLD),
-            )]),
-            Line::raw(&commit.message),
-            Line::raw(""),
-        ];
-
-        if !commit.files.is_empty() {
-            text.push(Line::from(vec![Span::styled(
-                "Files:",
-                Style::default().add_modifier(Modifier::BOLD),
-            )]));
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    if cli.profile {
+        profile::enable();
+    }
+    if cli.read_only {
+        read_only::enable();
+    }
 
-            for file in &commit.files {
-                text.push(Line::from(vec![
-                    Span::raw("  • "),
-                    Span::styled(file, Style::default().fg(Color::Cyan)),
-                ]));
-            }
+    let result = match cli.record.clone() {
+        Some(path) => {
+            let args: Vec<String> = std::env::args().collect();
+            recording::record(&path, &args, || run(cli))
         }
-
-        Text::from(text)
-    } else {
-        Text::from("No commit selected")
+        None => run(cli),
     };
+    profile::report();
 
-    let details = Paragraph::new(content)
-        .block(
-            Block::default()
-                .title("Details")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Gray)),
-        )
-        .wrap(Wrap { trim: true });
-
-    frame.render_widget(details, area);
+    match result {
+        Ok(code) => code.into(),
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            exit::code_of(&e).into()
+        }
+    }
 }
 
-fn render_footer(frame: &mut Frame, area: Rect, app: &App) {
-    let help_text = if app.show_help {
-        "Press 'h' or '?' to close help"
-    } else {
-        "↑/↓/j/k: navigate │ Space: toggle │ a: all │ n: none │ i: invert │ h/?: help │ Enter: proceed │ q: quit"
-    };
+fn run(mut cli: Cli) -> Result<Code> {
+    // Before reading it, since it may be what's broken
+    if cli.print_config_path {
+        let Some(path) = paths::config_file() else {
+            return Err(anyhow!(
+                "🧔 \"No place like home\": set HOME or {}",
+                paths::CONFIG_DIR_VAR
+            ));
+        };
+        println!("{}", path.display());
+        return Ok(Code::Success);
+    }
 
-    let footer = Paragraph::new(help_text)
-        .style(Style::default().fg(Color::Gray))
-        .block(Block::default().borders(Borders::TOP));
+    let global_config = read_global_config()?;
+    paths::configure(global_config.paths.clone());
+    network::apply(&global_config.network, cli.verbose)?;
+    process::configure(global_config.timeouts.clone());
+    dates::configure(global_config.ui.date_format.clone());
+    mergetool::configure(global_config.merge.clone());
+    push::configure(global_config.push.clone());
+    forge::configure(global_config.forge.clone());
+    notify::configure(global_config.notify.clone());
+
+    if let Some(path) = &cli.selection {
+        let planned = selection::Selection::load(path)
+            .map_err(|e| anyhow!("🧔 \"Which ones, exactly?\": {}", e))?;
+        cli.select = planned.hashes();
+        cli.base = Some(planned.base.clone());
+        cli.target.get_or_insert_with(|| planned.target.clone());
+        cli.planned = Some(planned);
+    }
 
-    frame.render_widget(footer, area);
+    let done = |result: Result<()>| result.map(|_| Code::Success);
+    let unattended = cli.action || matches!(cli.command, Some(Commands::Bot));
+    match cli.command {
+        Some(Commands::SelfUpdate { check }) => done(self_update::run(check, cli.verbose)),
+        Some(Commands::Stats { format }) => done(stats::run(format)),
+        Some(Commands::Report { ref since, format }) => {
+            done(report::run(since.as_deref(), format, cli.verbose))
+        }
+        Some(Commands::Manifest) => done(run_manifest(&cli)),
+        Some(Commands::Subscribe) => done(run_subscribe(&cli)),
+        Some(Commands::Status) => done(run_status(&cli)),
+        Some(Commands::Adopt { ref template }) => done(run_adopt(&cli, template)),
+        Some(Commands::Rebase { ref pr }) => done(run_rebase(&cli, pr)),
+        Some(Commands::Replay { ref file }) => run_replay(file),
+        Some(Commands::Serve { .. }) => done(run_serve(&cli)),
+        Some(Commands::Backport { ref to, ref since }) => {
+            run_backport(&cli, &global_config.ui, to, since.as_deref())
+        }
+        Some(Commands::Doctor) => Ok(run_doctor()),
+        Some(Commands::Paths) => {
+            print_paths();
+            Ok(Code::Success)
+        }
+        Some(Commands::Mark { mark }) => done(
+            trailer::mark_head(mark)
+                .map(|_| println!("🧔 Tagged HEAD with \"{}: {}\"", trailer::KEY, mark.value()))
+                .map_err(|e| anyhow!("🧔 \"Couldn't tag it\": {}", e)),
+        ),
+        Some(Commands::ExportSelection { .. }) if cli.all_workspaces => Err(anyhow!(
+            "🧔 \"One at a time\": export-selection plans a single workspace, pick it with --workspace"
+        )),
+        Some(
+            Commands::Bot
+            | Commands::Review
+            | Commands::ExportSelection { .. }
+            | Commands::ForIssue { .. },
+        )
+        | None => {
+            let code = run_workspaces(&cli, &global_config.ui, unattended)?;
+            // Nothing to contribute isn't a failed job
+            Ok(if unattended && code == Code::NoCandidates {
+                Code::Success
+            } else {
+                code
+            })
+        }
+    }
+}
+
+/// Runs chuck for the workspace the user picks, or each in turn. Without workspaces,
+/// that's just the repository root.
+fn run_workspaces(cli: &Cli, ui: &UiConfig, unattended: bool) -> Result<Code> {
+    let found = workspace::find().unwrap_or_else(|e| {
+        println!("🧔 Couldn't look for workspaces: {}", e);
+        Vec::new()
+    });
+
+    let chosen = if let Some(dir) = &cli.workspace {
+        let dir = workspace::normalize(dir);
+        if !found.contains(&dir) {
+            return Err(exit::fail(
+                Code::ConfigMissing,
+                anyhow!(
+                    "🧔 \"Hmm, having trouble here\": No {} in {}",
+                    workspace::CONFIG_FILE,
+                    workspace::display(&dir)
+                ),
+            ));
+        }
+        vec![dir]
+    } else if found.iter().all(|dir| dir.is_empty()) {
+        // A plain repo, or one with only a root .chuckrc
+        return run_app(cli, ui, "");
+    } else if cli.all_workspaces || unattended {
+        found
+    } else if !cli.select.is_empty() || cli.candidates.is_some() {
+        return Err(anyhow!(
+            "🧔 \"Which app, exactly?\": {} has a {}, pass --workspace or --all-workspaces",
+            found
+                .iter()
+                .map(|dir| workspace::display(dir))
+                .collect::<Vec<_>>()
+                .join(", "),
+            workspace::CONFIG_FILE
+        ));
+    } else {
+        let all = "All of them, one after the other".to_string();
+        let mut choices: Vec<String> = found
+            .iter()
+            .map(|dir| workspace::display(dir).to_string())
+            .collect();
+        choices.push(all.clone());
+        match pick_from_list("Which workspace?", &choices, "")? {
+            Some(choice) if choice == all => found,
+            Some(choice) => vec![workspace::normalize(&choice)],
+            None => {
+                println!("🧔 \"No workspace picked. Come back when you've made up your mind.\"");
+                return Ok(Code::Aborted);
+            }
+        }
+    };
+
+    if chosen.len() == 1 {
+        return run_app(cli, ui, &chosen[0]);
+    }
+
+    // Each run ends on its chuck branch, the next one starts where the user was
+    let original_ref = current_ref()?;
+    let mut codes = Vec::new();
+    for dir in &chosen {
+        println!("\n🧔 ── Workspace {} ──", workspace::display(dir));
+        let code = run_app(cli, ui, dir)?;
+        if current_ref().is_ok_and(|r| r != original_ref) {
+            let _ = Command::new("git")
+                .args(["checkout", "-q", &original_ref])
+                .output_local();
+        }
+        codes.push(code);
+    }
+
+    // Success if any workspace contributed, otherwise what they had in common
+    Ok(if codes.contains(&Code::Success) {
+        Code::Success
+    } else if codes.iter().all(|code| *code == codes[0]) {
+        codes[0]
+    } else {
+        Code::NoCandidates
+    })
+}
+
+/// The options remembered from earlier runs with the ones passed now on top, which
+/// are remembered in turn
+fn remembered_prefs(cli: &Cli) -> prefs::Prefs {
+    let given = cli.prefs();
+    let saved = prefs::Prefs::load().unwrap_or_else(|e| {
+        println!("🧔 Ignoring the options remembered from last time: {}", e);
+        prefs::Prefs::default()
+    });
+    let flags = saved.flags_besides(&given);
+    if !flags.is_empty() {
+        println!("🧔 Using {} from last time", flags.join(" "));
+    }
+    let prefs = saved.with(&given);
+    if prefs != saved {
+        if let Err(e) = prefs.save() {
+            println!("🧔 Couldn't remember these options: {}", e);
+        }
+    }
+    prefs
+}
+
+/// `chuck replay`: the recorded run again, with the recorded command line
+fn run_replay(file: &std::path::Path) -> Result<Code> {
+    let replay =
+        recording::Replay::load(file).map_err(|e| anyhow!("🧔 \"Can't play that back\": {}", e))?;
+    let mut cli = Cli::try_parse_from(&replay.args)
+        .map_err(|e| anyhow!("🧔 \"Can't play that back\": {}", e))?;
+    if matches!(cli.command, Some(Commands::Replay { .. })) {
+        return Err(anyhow!(
+            "🧔 \"Can't play that back\": {} records a replay",
+            file.display()
+        ));
+    }
+    cli.record = None;
+    if replay.version != env!("CARGO_PKG_VERSION") {
+        println!(
+            "🧔 Recorded with chuck {}, this is {}, so the replay may drift",
+            replay.version,
+            env!("CARGO_PKG_VERSION")
+        );
+    }
+    println!("🧔 Replaying {}", replay.args.join(" "));
+    replay.run(|| run(cli))
+}
+
+/// One chuck run for the app in `dir`, `""` being the repository root
+fn run_app(cli: &Cli, ui: &UiConfig, dir: &str) -> Result<Code> {
+    println!("🧔 Chuck: Let's see what you've been working on...\n");
+
+    journal::chuck_dir()
+        .map_err(|e| exit::fail(Code::NotARepo, anyhow!("🧔 \"Where are we?\": {}", e)))?;
+    let _lock = lock::RepoLock::acquire(cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Hold your horses\": {}", e))?;
+
+    let started_on = current_ref().ok();
+    let mut pending = PendingChanges::default();
+    let code = contribute(cli, ui, dir, &mut pending);
+    if !pending.is_empty() {
+        let on_users_branch = started_on.is_some() && current_ref().ok() == started_on;
+        write_pending(&pending, on_users_branch);
+    }
+    code
+}
+
+/// What [`run_app`] does under the lock. Changes to the team's `.chuck/state.toml`
+/// go into `pending`, to be written once the run is over.
+fn contribute(cli: &Cli, ui: &UiConfig, dir: &str, pending: &mut PendingChanges) -> Result<Code> {
+    let started = std::time::Instant::now();
+    repo_state::ensure_idle().map_err(|e| anyhow!("🧔 \"One thing at a time\": {}", e))?;
+
+    partial::configure(partial::detect());
+    let setup = partial::setup();
+    if let Some((remote, filter)) = setup.promisors.first() {
+        println!(
+            "🧔 Partial clone ({} from {}): checks that need file contents only use what's here",
+            filter, remote
+        );
+    }
+    if setup.sparse && cli.verbose {
+        println!("🧔 VERBOSE: Sparse checkout, picks outside it stay out of the work tree");
+    }
+
+    // Find the template repository
+    let mut config = find_template_config(dir).map_err(|e| {
+        exit::fail(
+            Code::ConfigMissing,
+            anyhow!("🧔 \"Hmm, having trouble here\": {}", e),
+        )
+    })?;
+    let template_repo = extract_repo_name_from_url(&config.template.url).map_err(|e| {
+        exit::fail(
+            Code::ConfigMissing,
+            anyhow!("🧔 \"Hmm, having trouble here\": {}", e),
+        )
+    })?;
+
+    if cli.verbose {
+        println!("🧔 VERBOSE: Template repository: {}", template_repo);
+    }
+    println!("🧔 Found template: {}", template_repo);
+
+    // Get current repository
+    let current_repo =
+        get_current_repo().map_err(|e| anyhow!("🧔 \"Can't figure out current repo\": {}", e))?;
+
+    if cli.verbose {
+        println!("🧔 VERBOSE: Current repository: {}", current_repo);
+    }
+
+    cli.cherry_pick.apply_to(&mut config.cherry_pick);
+    config.squash.repo = current_repo.clone();
+    if cli.ignore_whitespace {
+        config.formatting.ignore_whitespace = true;
+    }
+    if !dir.is_empty() {
+        config.cherry_pick.subtree = Some(dir.to_string());
+    }
+    let bot = matches!(cli.command, Some(Commands::Bot));
+    if bot && config.bot.is_empty() {
+        return Err(exit::fail(
+            Code::ConfigMissing,
+            anyhow!("🧔 \"Which ones, exactly?\": chuck bot needs [bot] rules in .chuckrc"),
+        ));
+    }
+    let review = matches!(cli.command, Some(Commands::Review));
+    if review && !io::stdin().is_terminal() {
+        return Err(anyhow!(
+            "🧔 \"Nothing to look at\": chuck review needs a terminal"
+        ));
+    }
+    // Before any picking, so a wrong number doesn't cost a selection
+    let issue = match &cli.command {
+        Some(Commands::ForIssue { number }) => {
+            let issue = issue::Issue::fetch(&template_repo, *number)
+                .map_err(|e| anyhow!("🧔 \"Which issue was that?\": {}", e))?;
+            println!(
+                "🧔 Working on {}#{}: {}",
+                template_repo, issue.number, issue.title
+            );
+            if issue.closed {
+                println!("🧔 ⚠️  It's closed already");
+            }
+            config.pr.create = true;
+            Some(issue)
+        }
+        _ => None,
+    };
+    let interactive = !bot && !cli.action && cli.select.is_empty() && io::stdin().is_terminal();
+    // The template's base SHA and date come from GitHub, ask while git fetches
+    if !cli.pick_base {
+        let base = cli.base.as_deref().unwrap_or(config.base_ref());
+        api::prefetch(&format!("repos/{}/commits/{}", template_repo, base));
+    }
+    let template_remote = fetch_template_remote(&mut config, interactive, cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Can't reach the template\": {}", e))?;
+    // The template may have moved
+    let template_repo = extract_repo_name_from_url(&config.template.url)?;
+    if let Some(planned) = &cli.planned {
+        if !planned.template.eq_ignore_ascii_case(&template_repo) {
+            return Err(anyhow!(
+                "🧔 \"Wrong template, kiddo\": the selection was planned against {}, not {}",
+                planned.template,
+                template_repo
+            ));
+        }
+    }
+
+    // Find out now, not at the push, whether the branch has to go through a fork
+    // --action, review and listing candidates never push
+    let listing = cli.candidates.is_some();
+    let access = if cli.action || review || listing {
+        PushAccess::Direct
+    } else {
+        check_token(&template_repo)?;
+        access::preflight(&template_repo, cli.verbose).unwrap_or_else(|e| {
+            println!("🧔 Couldn't check push access to {}: {}", template_repo, e);
+            PushAccess::Direct
+        })
+    };
+    if let PushAccess::Fork { repo } = &access {
+        println!(
+            "🧔 You can't push to {}, so the branch goes to your fork {}",
+            template_repo, repo
+        );
+    }
+
+    // Unattended runs and reviews go by .chuckrc and leave what's remembered alone
+    let prefs = if bot || review || cli.action || listing || cli.planned.is_some() {
+        cli.prefs()
+    } else {
+        remembered_prefs(cli)
+    };
+    if let Some(target) = &prefs.target {
+        config.pr.target = Some(target.clone());
+    }
+    if let Some(draft) = prefs.draft {
+        config.pr.draft = draft;
+    }
+    if cli.pick_target {
+        match pick_target_branch(&template_repo, config.target_branch())? {
+            Some(target) => config.pr.target = Some(target),
+            None => {
+                println!("🧔 \"No target picked. Come back when you've made up your mind.\"");
+                return Ok(Code::Aborted);
+            }
+        }
+    }
+    let target_branch = config.target_branch().to_string();
+
+    if !cli.action {
+        if let Err(e) = announce_template_updates(&config, &template_repo, 0) {
+            println!("🧔 Couldn't check the template for new commits: {}", e);
+        }
+    }
+
+    if let Some(base) = &cli.base {
+        config.template.base = Some(base.clone());
+    }
+    if cli.pick_base {
+        match pick_template_base(&template_repo, config.base_ref())? {
+            Some(base) => config.template.base = Some(base),
+            None => {
+                println!("🧔 \"No base picked. Come back when you've made up your mind.\"");
+                return Ok(Code::Aborted);
+            }
+        }
+    }
+    let base_ref = config.base_ref().to_string();
+    if base_ref == target_branch {
+        println!("🧔 Targeting template branch {}", target_branch);
+    } else {
+        println!(
+            "🧔 Building on template {}, targeting {}",
+            base_ref, target_branch
+        );
+    }
+
+    let template_base_sha = get_template_base_commit(&template_repo, &base_ref)
+        .map_err(|e| anyhow!("🧔 \"Can't find that template base\": {}", e))?;
+
+    // Regulars mostly want what's new since they last ran Chuck
+    let last_push = if cli.since_template || cli.range.is_some() || cli.from.is_some() {
+        None
+    } else {
+        journal::last_push(&current_repo, &template_repo).unwrap_or_else(|e| {
+            println!("🧔 Couldn't read the journal: {}", e);
+            None
+        })
+    };
+
+    // Get commits since template
+    let discovery_phase = profile::phase("commit discovery");
+    let mut commits = match (&cli.range, &cli.from) {
+        (Some(range), _) => get_commits_in_range(range),
+        (None, from) if config.discovery.strategy == fingerprint::Strategy::Content => {
+            get_commits_by_content(
+                from.as_deref().unwrap_or("HEAD"),
+                &template_base_sha,
+                cli.verbose,
+            )
+        }
+        (None, Some(from)) => get_commits_from_ref(from, &template_repo, &base_ref),
+        (None, None) => {
+            get_commits_since_template(&current_repo, &template_repo, &base_ref, last_push)
+        }
+    }
+    .map_err(|e| anyhow!("🧔 \"Can't seem to get those commits\": {}", e))?;
+
+    if !dir.is_empty() {
+        scope_to_workspace(&mut commits, dir);
+        println!(
+            "🧔 {} commits touch {}, the rest belong to other apps",
+            commits.len(),
+            dir
+        );
+    }
+
+    if let Some(author) = prefs.author.as_deref().filter(|a| !a.is_empty()) {
+        let pattern = author.to_lowercase();
+        let before = commits.len();
+        commits.retain(|c| c.author.to_lowercase().contains(&pattern));
+        println!(
+            "🧔 {} of {} commits are by authors matching \"{}\"",
+            commits.len(),
+            before,
+            author
+        );
+    }
+
+    if commits.is_empty() {
+        if last_push.is_some() {
+            println!(
+                "🧔 \"Nothing new since your last push. --since-template looks further back.\""
+            );
+        } else {
+            println!(
+                "🧔 \"Looks like you haven't made any commits since the template. Get to work!\""
+            );
+        }
+        return Ok(Code::NoCandidates);
+    }
+
+    if let Err(e) = mark_already_upstream(&mut commits, &template_base_sha, cli.verbose) {
+        // Not fatal, empty cherry-picks are still skipped when the branch is built
+        println!(
+            "🧔 Couldn't check which commits upstream already has: {}",
+            e
+        );
+    }
+
+    // Reviews leave the file as it is, so they only look at what earlier runs left
+    let mut looking = PendingChanges::default();
+    let pending = if review { &mut looking } else { pending };
+    if config.state.shared {
+        match PendingChanges::load() {
+            Ok(earlier) => *pending = earlier,
+            Err(e) => println!(
+                "🧔 Couldn't read what earlier runs left for {}: {}",
+                state::STATE_FILE,
+                e
+            ),
+        }
+    }
+    let ignore_list = match IgnoreList::load(config.state.shared.then_some(&*pending)) {
+        Ok(list) => Some(list),
+        Err(e) => {
+            println!("🧔 Couldn't read the ignore list: {}", e);
+            None
+        }
+    };
+    if let Some(list) = &ignore_list {
+        for commit in &mut commits {
+            commit.ignored = list.contains(&commit.hash, commit.patch_id.as_deref());
+        }
+        let ignored = commits.iter().filter(|c| c.ignored).count();
+        if ignored > 0 && !cli.show_ignored {
+            commits.retain(|c| !c.ignored);
+            println!(
+                "🧔 Hiding {} commits you asked never to see again (--show-ignored lists them)",
+                ignored
+            );
+            if commits.is_empty() {
+                println!("🧔 \"Nothing left but the stuff you told me to forget.\"");
+                return Ok(Code::NoCandidates);
+            }
+        }
+    }
+
+    let hashes: Vec<&str> = commits.iter().map(|c| c.hash.as_str()).collect();
+    match bot::commit_meta(&hashes) {
+        Ok(meta) => {
+            for commit in &mut commits {
+                commit.mark = meta
+                    .get(&commit.hash)
+                    .and_then(|meta| Mark::from_trailers(&meta.trailers));
+            }
+        }
+        Err(e) => println!("🧔 Couldn't read commit trailers: {}", e),
+    }
+    let skipped = commits
+        .iter()
+        .filter(|c| c.mark == Some(Mark::Skip))
+        .count();
+    if skipped > 0 && !cli.show_ignored {
+        commits.retain(|c| c.mark != Some(Mark::Skip));
+        println!(
+            "🧔 Hiding {} commits marked \"{}: skip\" (--show-ignored lists them)",
+            skipped,
+            trailer::KEY
+        );
+        if commits.is_empty() {
+            println!("🧔 \"Nothing left but the stuff you told me to forget.\"");
+            return Ok(Code::NoCandidates);
+        }
+    }
+
+    mark_contributed(
+        &mut commits,
+        &current_repo,
+        &template_repo,
+        config.state.shared,
+        pending,
+    );
+
+    if let Some(manifest) =
+        refresh_manifest(&template_repo, &template_base_sha, !review, cli.verbose)
+    {
+        for commit in &mut commits {
+            commit.project_only = !commit.files.is_empty()
+                && commit
+                    .files
+                    .iter()
+                    .all(|f| manifest.ownership(&f.path) == Some(Ownership::Project));
+        }
+    }
+
+    let policy = match policy::Policy::load(&template_base_sha) {
+        Ok(policy) => policy,
+        Err(e) => {
+            println!("🧔 Ignoring the template's contribution policy: {}", e);
+            None
+        }
+    };
+    if cli.auto_split || config.split.auto {
+        for commit in &mut commits {
+            commit.split_off = config.split.downstream_part(
+                commit
+                    .files
+                    .iter()
+                    .flat_map(|f| std::iter::once(f.path.as_str()).chain(f.old_path.as_deref())),
+                policy.as_ref(),
+            );
+        }
+        let split = commits.iter().filter(|c| !c.split_off.is_empty()).count();
+        if split > 0 {
+            println!(
+                "🧔 {} commits mix project files with shared code, they go upstream without them",
+                split
+            );
+        }
+    }
+    if let Some(policy) = &policy {
+        println!(
+            "🧔 Template publishes a contribution policy{}",
+            if policy.enforce { " (enforced)" } else { "" }
+        );
+        for commit in &mut commits {
+            commit.policy_issues = commit
+                .files
+                .iter()
+                .filter(|f| !commit.split_off.contains(&f.path))
+                .filter_map(|f| policy.path_violation(&f.path))
+                .collect();
+        }
+    }
+    for commit in &mut commits {
+        commit.risks = config
+            .risk
+            .assess(commit.files.iter().map(|f| f.path.as_str()));
+    }
+
+    let template_files = match discovery::tree_paths(&template_base_sha) {
+        Ok(template_files) => {
+            for commit in &mut commits {
+                commit.deleted_template_files = commit
+                    .files
+                    .iter()
+                    .filter(|f| f.status == 'D' && template_files.contains(&f.path))
+                    .map(|f| f.path.clone())
+                    .collect();
+            }
+            Some(template_files)
+        }
+        Err(e) => {
+            println!(
+                "🧔 Couldn't check which files the template still has: {}",
+                e
+            );
+            None
+        }
+    };
+
+    let hashes: Vec<&str> = commits.iter().map(|c| c.hash.as_str()).collect();
+    let signatures = signature::verify(&hashes);
+    let statuses = ci::statuses(&current_repo, &hashes);
+
+    match signatures {
+        Ok(mut signatures) => {
+            for commit in &mut commits {
+                commit.signature = signatures.remove(&commit.hash);
+            }
+        }
+        Err(e) => println!("🧔 Couldn't check commit signatures: {}", e),
+    }
+
+    match statuses {
+        Ok(mut statuses) => {
+            for commit in &mut commits {
+                commit.ci = statuses.remove(&commit.hash);
+            }
+        }
+        Err(e) => println!("🧔 Couldn't check CI status: {}", e),
+    }
+
+    let mut tracer =
+        provenance::Tracer::new(&template_base_sha, config.formatting.ignore_whitespace);
+    for commit in commits.iter_mut().filter(|c| !c.is_merge()) {
+        match tracer.trace(&commit.hash) {
+            Ok(provenance) => commit.provenance = Some(provenance),
+            Err(e) => {
+                if cli.verbose {
+                    println!(
+                        "🧔 VERBOSE: Couldn't trace where {} came from: {}",
+                        commit.short_hash, e
+                    );
+                }
+            }
+        }
+    }
+
+    let verified_only =
+        cli.verified_only || policy.as_ref().is_some_and(|policy| policy.require_signed);
+    if verified_only {
+        let unverified = commits.iter().filter(|c| !c.is_verified()).count();
+        println!(
+            "🧔 Only commits with a verified signature can be selected ({} aren't)",
+            unverified
+        );
+    }
+
+    let contributed = commits.iter().filter(|c| c.contributed.is_some()).count();
+    if contributed > 0 {
+        println!("🧔 {} of these were already proposed upstream", contributed);
+    }
+
+    let upstreamed = commits.iter().filter(|c| c.already_upstream).count();
+    if upstreamed > 0 {
+        println!("🧔 Template already has {} of these commits", upstreamed);
+        if cli.hide_upstreamed {
+            commits.retain(|c| !c.already_upstream);
+            if commits.is_empty() {
+                println!("🧔 \"Upstream already has everything you've got. Nice work!\"");
+                return Ok(Code::NoCandidates);
+            }
+        }
+    }
+
+    if cli.verbose {
+        println!("🧔 VERBOSE: Found {} commits to review", commits.len());
+        for commit in &commits {
+            println!(
+                "🧔 VERBOSE: {} - {} (files: {}{})",
+                commit.short_hash,
+                commit.message,
+                commit.files.len(),
+                if commit.is_merge() { ", merge" } else { "" }
+            );
+        }
+    }
+
+    drop(discovery_phase);
+    notify::finished(
+        &format!("Found {} commits to look at", commits.len()),
+        started.elapsed(),
+    );
+
+    if cli.action {
+        return run_action(cli, &commits, &current_repo, &template_repo);
+    }
+    if let Some(path) = &cli.candidates {
+        let discovered = serve::Discovered {
+            repo: current_repo,
+            template: template_repo,
+            base: template_base_sha,
+            target: target_branch,
+            commits: commits
+                .iter()
+                .map(|commit| serve::Candidate {
+                    hash: commit.hash.clone(),
+                    short_hash: commit.short_hash.clone(),
+                    subject: commit.message.clone(),
+                    author: commit.author.clone(),
+                    date: commit.date.clone(),
+                    files: commit.files.iter().map(|f| f.path.clone()).collect(),
+                    merge: commit.is_merge(),
+                    selectable: commit.bulk_selectable()
+                        && (!verified_only || commit.is_verified()),
+                    notes: candidate_notes(commit),
+                })
+                .collect(),
+        };
+        fs::write(path, serde_json::to_string(&discovered)?)
+            .map_err(|e| anyhow!("🧔 \"Couldn't write it down\": {}", e))?;
+        return Ok(Code::Success);
+    }
+
+    let mut selection = if review {
+        Vec::new()
+    } else {
+        cli.select.clone()
+    };
+    if bot {
+        selection = bot_selection(&commits, &config.bot, verified_only)
+            .map_err(|e| anyhow!("🧔 \"Can't tell which ones you tagged\": {}", e))?;
+        if selection.is_empty() {
+            println!("🧔 \"Nothing matches the [bot] rules. Back to my nap.\"");
+            return Ok(Code::NoCandidates);
+        }
+        println!("🧔 {} commits match the [bot] rules", selection.len());
+        config.pr.create = true;
+    }
+
+    // Only the TUI starts from the trailers' picks, --select and bot mean exactly those
+    if selection.is_empty() && !review {
+        let mut marked = 0;
+        for commit in &mut commits {
+            if commit.mark == Some(Mark::Upstream)
+                && commit.bulk_selectable()
+                && (!verified_only || commit.is_verified())
+            {
+                commit.selected = true;
+                marked += 1;
+            }
+        }
+        if marked > 0 {
+            println!(
+                "🧔 Pre-selected {} commits marked \"{}: upstream\"",
+                marked,
+                trailer::KEY
+            );
+        }
+        if let Some(issue) = &issue {
+            let mut mentioning = 0;
+            for commit in &mut commits {
+                if !commit.selected
+                    && issue.is_mentioned_in(&commit.message, &template_repo, &current_repo)
+                    && commit.bulk_selectable()
+                    && (!verified_only || commit.is_verified())
+                {
+                    commit.selected = true;
+                    mentioning += 1;
+                }
+            }
+            if mentioning > 0 {
+                println!(
+                    "🧔 Pre-selected {} commits mentioning #{}",
+                    mentioning, issue.number
+                );
+            }
+        }
+    }
+
+    let mut app = App::new(commits);
+    app.verified_only = verified_only;
+    app.diff_source = Some(DiffSource {
+        template_base: template_base_sha.clone(),
+        subtree: dir.to_string(),
+        mainline: config.merge_commits.mainline,
+    });
+    if let Some(policy) = &policy {
+        app.soft_limits = policy.soft_limits.clone();
+    }
+    app.squash = prefs.squash == Some(true);
+    app.autosquash = config.squash.autosquash;
+    app.show_graph = ui.graph;
+    app.read_only = review;
+    app.template_files = template_files;
+
+    let tui_phase = profile::phase("TUI session");
+    if selection.is_empty() {
+        select_interactively(&mut app)?;
+    } else {
+        app.select_by_hash(&selection)
+            .map_err(|e| anyhow!("🧔 \"Which ones, exactly?\": {}", e))?;
+        if app.selected_count() > 0 {
+            app.start_plan();
+            for step in &mut app.plan {
+                step.keep_template_files = cli.keep_template_files
+                    && !app.commits[step.commit].deleted_template_files.is_empty();
+            }
+            if let Some(planned) = &cli.planned {
+                app.apply_selection(planned);
+            }
+        }
+    }
+    drop(tui_phase);
+    let building = std::time::Instant::now();
+    if review {
+        return Ok(Code::Success);
+    }
+
+    // Ignoring is remembered whether or not anything gets contributed
+    if let Some(mut list) = ignore_list {
+        let changed: Vec<&Commit> = app
+            .commits
+            .iter()
+            .filter(|c| c.ignored != list.contains(&c.hash, c.patch_id.as_deref()))
+            .collect();
+        if !changed.is_empty() {
+            for commit in changed {
+                let patch_id = commit.patch_id.as_deref();
+                match (config.state.shared, commit.ignored) {
+                    (true, true) => pending.ignore(&commit.hash, patch_id, &commit.message),
+                    (true, false) => pending.unignore(&commit.hash, patch_id, &commit.message),
+                    (false, true) => list.add(&commit.hash, patch_id, &commit.message),
+                    (false, false) => list.remove(&commit.hash, patch_id),
+                }
+            }
+            if !config.state.shared {
+                match list.save() {
+                    Ok(()) => println!(
+                        "🧔 Updated the ignore list, {} commits won't be suggested again",
+                        list.commits.len()
+                    ),
+                    Err(e) => println!("🧔 Couldn't save the ignore list: {}", e),
+                }
+            }
+        }
+    }
+
+    // Before anything reads the picks' files, which would fetch them one by one
+    if !app.should_quit && partial::setup().is_partial() {
+        let picks: Vec<(String, String)> = app
+            .planned()
+            .iter()
+            .map(|(commit, _)| {
+                let parent = if commit.is_merge() {
+                    config.merge_commits.mainline
+                } else {
+                    1
+                };
+                (format!("{}^{}", commit.hash, parent), commit.hash.clone())
+            })
+            .collect();
+        match partial::prefetch(
+            partial::setup(),
+            &picks,
+            &template_remote.name,
+            &template_base_sha,
+            config.cherry_pick.subtree.as_deref(),
+        ) {
+            Ok(0) => {}
+            Ok(fetched) => println!("🧔 Fetched {} files the picks need", fetched),
+            Err(e) => println!(
+                "🧔 Couldn't fetch the picks' files up front, git fetches them as it goes: {}",
+                e
+            ),
+        }
+    }
+
+    if !app.should_quit {
+        check_picks(
+            &mut app,
+            &template_base_sha,
+            &config,
+            selection.is_empty() && io::stdin().is_terminal(),
+        )?;
+    }
+
+    let push_lfs = !app.should_quit
+        && check_lfs(
+            &mut app,
+            &template_base_sha,
+            &config,
+            selection.is_empty() && io::stdin().is_terminal(),
+        )?;
+
+    // Get the confirmed plan after TUI exits
+    let plan = app.planned();
+    let selected_commits: Vec<&Commit> = plan.iter().map(|(commit, _)| *commit).collect();
+
+    println!(
+        "🧔 Selected {} commits for contribution",
+        selected_commits.len()
+    );
+
+    if selected_commits.is_empty() {
+        println!("🧔 \"No commits selected. That's fine, take your time.\"");
+        // With --select, nothing selectable matched rather than the user backing out
+        return Ok(if selection.is_empty() {
+            Code::Aborted
+        } else {
+            Code::NoCandidates
+        });
+    }
+
+    // Show what commits were selected
+    println!("\n🧔 Selected commits:");
+    for (commit, step) in &plan {
+        match step.action {
+            PlanAction::Reword => println!(
+                "  • {} - {} (reworded: {})",
+                commit.short_hash, commit.message, step.message
+            ),
+            PlanAction::Squash => println!(
+                "  • {} - {} (squashed into previous)",
+                commit.short_hash, commit.message
+            ),
+            _ => println!("  • {} - {}", commit.short_hash, commit.message),
+        }
+    }
+
+    let deleting: Vec<&Commit> = plan
+        .iter()
+        .filter(|(commit, step)| {
+            !commit.deleted_template_files.is_empty() && !step.keep_template_files
+        })
+        .map(|(commit, _)| *commit)
+        .collect();
+    if !deleting.is_empty() {
+        println!("\n🧔 ⚠️  These commits delete files the template still has:");
+        for commit in &deleting {
+            println!(
+                "  • {} - {}",
+                commit.short_hash,
+                commit.deleted_template_files.join(", ")
+            );
+        }
+        println!("🧔 Press t on the plan screen or pass --keep-template-files to keep them");
+
+        let required: Vec<(&Commit, Vec<&str>)> = policy
+            .iter()
+            .flat_map(|policy| {
+                deleting
+                    .iter()
+                    .map(|commit| (*commit, policy.required(&commit.deleted_template_files)))
+            })
+            .filter(|(_, required)| !required.is_empty())
+            .collect();
+        if !required.is_empty() && !cli.delete_required_files {
+            println!("\n🧔 ⛔ The template requires every project to keep these:");
+            for (commit, paths) in &required {
+                println!("  • {} - {}", commit.short_hash, paths.join(", "));
+            }
+            let interactive = selection.is_empty() && io::stdin().is_terminal();
+            if !interactive {
+                return Err(anyhow!(
+                    "🧔 \"Those stay, kiddo\": pass --keep-template-files to keep them, or --delete-required-files to delete them upstream anyway"
+                ));
+            }
+            if !mergetool::confirm("Delete them upstream anyway?")? {
+                println!("🧔 \"Good call.\" Press t on the plan screen to keep them");
+                return Ok(Code::Aborted);
+            }
+        }
+    }
+
+    warn_about_open_duplicates(
+        &selected_commits,
+        &template_repo,
+        &template_remote.name,
+        &template_base_sha,
+        cli.verbose,
+    );
+
+    if let Some(policy) = &policy {
+        let violations = policy.violations(
+            selected_commits.iter().flat_map(|c| {
+                c.files
+                    .iter()
+                    .map(|f| f.path.as_str())
+                    .filter(|path| !c.split_off.iter().any(|split| split == path))
+            }),
+            selected_commits.len(),
+        );
+        if !violations.is_empty() {
+            println!("\n🧔 ⚠️  This doesn't follow the template's contribution policy:");
+            for violation in &violations {
+                println!("  • {}", violation);
+            }
+            if policy.enforce {
+                return Err(anyhow!(
+                    "🧔 \"Rules are rules, kiddo\": the template enforces its contribution policy"
+                ));
+            }
+        }
+        if !policy.required_checks.is_empty() {
+            println!(
+                "\n🧔 The template requires these checks to pass: {}",
+                policy.required_checks.join(", ")
+            );
+        }
+    }
+
+    let code_owners = codeowners::CodeOwners::load(&template_base_sha);
+    let owners = code_owners
+        .as_ref()
+        .map(|owners| {
+            owners.owners_of(
+                selected_commits
+                    .iter()
+                    .flat_map(|c| c.files.iter().map(|f| f.path.as_str())),
+            )
+        })
+        .unwrap_or_default();
+
+    if !owners.is_empty() {
+        println!("\n🧔 Template code owners for these changes:");
+        for (owner, files) in &owners {
+            println!("  • {} ({} files)", owner, files.len());
+        }
+    }
+
+    if let Some(Commands::ExportSelection { file }) = &cli.command {
+        selection::Selection {
+            template: template_repo.clone(),
+            base: template_base_sha.clone(),
+            target: target_branch.clone(),
+            steps: plan
+                .iter()
+                .map(|(commit, step)| step.export(commit))
+                .collect(),
+        }
+        .save(file)
+        .map_err(|e| anyhow!("🧔 \"Couldn't write that down\": {}", e))?;
+        println!(
+            "\n🧔 Wrote the plan to {}. Once it's reviewed, build it with chuck --selection {}",
+            file.display(),
+            file.display()
+        );
+        return Ok(Code::Success);
+    }
+
+    if withhold_build(&plan, &template_base_sha, &template_repo) {
+        return Ok(Code::Success);
+    }
+
+    if cli.verbose {
+        println!(
+            "\n🧔 VERBOSE: About to process {} commits",
+            selected_commits.len()
+        );
+    }
+
+    println!("\n🧔 Creating branch and processing commits...");
+
+    // Create branch with selected commits
+    let (branch_name, timestamp) = match create_branch_with_commits(
+        &plan,
+        cli.verbose,
+        &template_base_sha,
+        &config,
+    ) {
+        Ok(result) => result,
+        Err(e) => {
+            if plan.len() > checkpoint::EVERY {
+                if let Ok(Some(saved)) = checkpoint::Checkpoint::load() {
+                    println!(
+                            "🧔 {} has the first {} commits. Run the same selection again to pick up from there.",
+                            saved.branch,
+                            saved.steps.len()
+                        );
+                }
+            }
+            let conflict = e.to_string().contains("Cherry-pick failed");
+            let outcome = if conflict {
+                Outcome::Conflict
+            } else {
+                Outcome::Failed
+            };
+            record_run(
+                &current_repo,
+                &template_repo,
+                None,
+                None,
+                outcome,
+                &selected_commits,
+                cli.verbose,
+            );
+            return Err(if conflict {
+                exit::fail(Code::Conflict, e)
+            } else {
+                e
+            });
+        }
+    };
+
+    if !config.checks.is_empty() && !config.checks.amend {
+        match checks::apply(&config.checks, &template_base_sha, false, cli.verbose) {
+            Ok(0) => {}
+            Ok(count) => println!("🧔 The checks changed {} files, committed on top", count),
+            Err(e) => println!("🧔 Couldn't run the checks on the branch: {}", e),
+        }
+    }
+
+    if !config.lockfiles.is_empty() {
+        match lockfile::regenerate(&config.lockfiles, &template_base_sha, cli.verbose) {
+            Ok(lockfiles) if lockfiles.is_empty() => {}
+            Ok(lockfiles) => println!(
+                "🧔 Regenerated {} against the template's dependencies",
+                lockfiles.join(", ")
+            ),
+            Err(e) => println!("🧔 Couldn't regenerate the lockfiles: {}", e),
+        }
+    }
+
+    if config.formatting.tidies() {
+        match formatting::tidy(&template_base_sha, &config.formatting, cli.verbose) {
+            Ok(0) => {}
+            Ok(count) => println!("🧔 Normalized formatting in {} files", count),
+            Err(e) => println!("🧔 Couldn't normalize the branch's formatting: {}", e),
+        }
+    }
+
+    if !config.emails.is_empty() {
+        let interactive = selection.is_empty() && io::stdin().is_terminal();
+        enforce_emails(
+            &config.emails,
+            &template_base_sha,
+            &branch_name,
+            interactive,
+        )?;
+    }
+
+    println!("\n🧔 Attempting to push to template repository...");
+
+    let push_repo = match &access {
+        PushAccess::Fork { repo } => repo.clone(),
+        PushAccess::Direct => template_repo.clone(),
+    };
+    let exposure = exposes(&current_repo, &push_repo);
+    let exposed = exposure != Some(false);
+    let guarded = exposed && config.leak_guard.enabled;
+    let redact_words = config.leak_guard.words(&current_repo);
+    if guarded {
+        guard_leaks(
+            &config.leak_guard,
+            &current_repo,
+            &template_base_sha,
+            &branch_name,
+        )?;
+    }
+
+    // Named after the first commit where the template's policy asks for a topic, and
+    // not after a private project
+    let subject = selected_commits[0]
+        .message
+        .lines()
+        .next()
+        .unwrap_or_default();
+    let (source, subject) = if guarded {
+        (leak::UNNAMED, leak::redact(subject, &redact_words))
+    } else {
+        (current_repo.as_str(), subject.to_string())
+    };
+    let remote_branch_name = match &policy {
+        Some(policy) => policy.remote_branch(source, &subject, &timestamp),
+        None => policy::Policy::default().remote_branch(source, &subject, &timestamp),
+    };
+
+    // Push through a remote the user already had so its push URL and credentials apply
+    let push_target = if let Some(url) = access.fork_url(&config.template.url) {
+        url
+    } else if template_remote.temporary {
+        config.template.url.clone()
+    } else {
+        template_remote.name.clone()
+    };
+    let pr_head = access.pr_head(&remote_branch_name);
+
+    show_push(
+        &push_repo,
+        &push_target,
+        &branch_name,
+        &remote_branch_name,
+        &template_base_sha,
+    );
+    let guidelines = guidelines::Guidelines::load(&template_base_sha);
+    if let Some(guidelines) = &guidelines {
+        show_guidelines(guidelines, &template_repo, &template_base_sha);
+    }
+    let go_ahead = if exposed {
+        confirm_exposure(
+            &current_repo,
+            &push_repo,
+            exposure.is_some(),
+            interactive,
+            cli.push_public,
+        )?
+    } else if interactive && guidelines.is_some() {
+        mergetool::confirm("Push it?")?
+    } else {
+        true
+    };
+    if !go_ahead {
+        println!(
+            "🧔 \"Good call.\" Nothing was pushed, {} is still here",
+            branch_name
+        );
+        return Ok(Code::Aborted);
+    }
+
+    // Push to template and create PR, the LFS objects first so the branch never
+    // points at content the server doesn't have
+    let pushed = if push_lfs {
+        println!("🧔 Pushing Git LFS objects...");
+        lfs::push(&push_target, &branch_name)
+    } else {
+        Ok(())
+    }
+    .and_then(|()| {
+        push_to_template_and_create_pr(
+            &branch_name,
+            &template_base_sha,
+            &push_target,
+            &push_repo,
+            &target_branch,
+            &remote_branch_name,
+        )
+    });
+    notify::finished(
+        &match &pushed {
+            Ok(()) => format!("Pushed {} to {}", remote_branch_name, push_repo),
+            Err(_) => format!("Couldn't push to {}", push_repo),
+        },
+        building.elapsed(),
+    );
+    let code = match pushed {
+        Ok(()) => {
+            record_run(
+                &current_repo,
+                &template_repo,
+                Some(&branch_name),
+                Some(&remote_branch_name),
+                Outcome::Pushed,
+                &selected_commits,
+                cli.verbose,
+            );
+            if config.state.shared {
+                println!(
+                    "🧔 These commits go into {} on your next run from your own branch",
+                    state::STATE_FILE
+                );
+            }
+
+            let pr_url = pull_request_url(&template_repo, &target_branch, &pr_head);
+            let (code, link) = if config.pr.create {
+                let reviewers: Vec<&str> = if config.pr.request_codeowners {
+                    owners
+                        .keys()
+                        .filter_map(|owner| codeowners::reviewer_handle(owner))
+                        .collect()
+                } else {
+                    Vec::new()
+                };
+
+                let (title, body) = contribution_text(&current_repo, &selected_commits);
+                let (title, body) = match &issue {
+                    Some(issue) => (issue.pr_title(), issue.link(&template_repo, &body)),
+                    None => (title, body),
+                };
+                let title = app.pr_title.clone().unwrap_or(title);
+                let (title, body) = if guarded {
+                    (
+                        leak::redact(&title, &redact_words),
+                        leak::redact(&body, &redact_words),
+                    )
+                } else {
+                    (title, body)
+                };
+                match open_pull_request(
+                    &template_repo,
+                    &target_branch,
+                    &pr_head,
+                    &title,
+                    &body,
+                    &reviewers,
+                    config.pr.draft,
+                ) {
+                    Ok(url) => {
+                        println!("\n🧔 ✅ SUCCESS! Pull request opened: {}", url);
+                        if !reviewers.is_empty() {
+                            println!("🧔 Requested review from: {}", reviewers.join(", "));
+                        }
+                        if !config.pr.triage.is_empty() {
+                            for error in config.pr.triage.apply(&template_repo, &url) {
+                                println!("🧔 {}", error);
+                            }
+                        }
+                        if let Some(issue) = &issue {
+                            match issue.copy_labels(&template_repo, &url) {
+                                Ok(labels) if labels.is_empty() => {}
+                                Ok(labels) => {
+                                    println!("🧔 Labeled it like the issue: {}", labels.join(", "))
+                                }
+                                Err(e) => println!("🧔 Couldn't copy the issue's labels: {}", e),
+                            }
+                        }
+                        (Code::Success, url)
+                    }
+                    Err(e) => {
+                        println!("\n🧔 ⚠️  Branch pushed but couldn't open the PR: {}", e);
+                        println!("🧔 Create it at: {}", pr_url);
+                        (Code::PrFailed, pr_url)
+                    }
+                }
+            } else {
+                println!("\n🧔 📝 Next step: Create your pull request");
+                println!("🧔 PR URL: {}", pr_url);
+                println!("🧔 \"Now go make that pull request, kiddo!\"");
+                println!("\n🧔 ✅ SUCCESS! All operations completed successfully.");
+                println!("🧔 Check the URL above to create your pull request.");
+                (Code::Success, pr_url)
+            };
+
+            if let (Some(webhook), Code::Success) = (&config.notify.webhook, &code) {
+                let commits: Vec<(&str, &str)> = selected_commits
+                    .iter()
+                    .map(|c| (c.short_hash.as_str(), c.message.as_str()))
+                    .collect();
+                let text = notify::summary(&current_repo, &template_repo, &commits, &link);
+                if let Err(e) = notify::post(webhook, &text) {
+                    println!("🧔 Couldn't notify the webhook: {}", e);
+                }
+            }
+            code
+        }
+        Err(e) => {
+            record_run(
+                &current_repo,
+                &template_repo,
+                Some(&branch_name),
+                None,
+                Outcome::PushFailed,
+                &selected_commits,
+                cli.verbose,
+            );
+            println!("\n🧔 ⚠️  Branch created but couldn't auto-push: {}", e);
+            println!("\n🧔 Manual commands to complete the process:");
+            // Same remote branch, so whatever already made it isn't sent again
+            println!(
+                "   git push {} {}:{}",
+                access
+                    .fork_url(&config.template.url)
+                    .unwrap_or(config.template.url),
+                branch_name,
+                remote_branch_name
+            );
+            println!(
+                "   Then create PR at: {}",
+                pull_request_url(&template_repo, &target_branch, &pr_head)
+            );
+            Code::PushFailed
+        }
+    };
+
+    Ok(code)
+}
+
+/// Refuses to go on while the chuck branch has emails outside `[emails]`
+/// allowed_domains, unless they're rewritten with the user's consent
+fn enforce_emails(
+    config: &emails::EmailConfig,
+    base: &str,
+    branch_name: &str,
+    interactive: bool,
+) -> Result<()> {
+    let violations =
+        emails::check(config, base).map_err(|e| anyhow!("🧔 \"Can't vouch for those\": {}", e))?;
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    println!(
+        "\n🧔 ⚠️  These emails aren't at {}:",
+        config.allowed_domains.join(" or ")
+    );
+    for v in &violations {
+        match &v.fix {
+            Some(fix) => println!(
+                "  • {} {} {} <{}> → {}",
+                v.short_hash, v.role, v.name, v.email, fix
+            ),
+            None => println!("  • {} {} {} <{}>", v.short_hash, v.role, v.name, v.email),
+        }
+    }
+    if violations.iter().any(|v| v.fix.is_none()) {
+        return Err(exit::fail(
+            Code::PushFailed,
+            anyhow!(
+                "🧔 \"Not with those emails\": there's no allowed email to rewrite some of them to, map them in .mailmap or fix the commits ({} is left as it is)",
+                branch_name
+            ),
+        ));
+    }
+    let consent = config.rewrite || (interactive && mergetool::confirm("Rewrite them?")?);
+    if !consent {
+        return Err(exit::fail(
+            Code::PushFailed,
+            anyhow!(
+                "🧔 \"Not with those emails\": set rewrite = true under [emails] to rewrite them ({} is left as it is)",
+                branch_name
+            ),
+        ));
+    }
+    emails::rewrite(base, &violations)
+        .map_err(|e| anyhow!("🧔 \"Couldn't fix those emails\": {}", e))?;
+    println!("🧔 Rewrote {} emails on the chuck branch", violations.len());
+    Ok(())
+}
+
+/// Flags commits that an earlier run pushed to the template. With `shared` the team's
+/// `.chuck/state.toml` counts too, and this clone's pushes go into `pending` for it.
+fn mark_contributed(
+    commits: &mut [Commit],
+    current_repo: &str,
+    template_repo: &str,
+    shared: bool,
+    pending: &mut PendingChanges,
+) {
+    let mut state = if shared {
+        match SharedState::load() {
+            Ok(state) => state,
+            Err(e) => {
+                println!("🧔 Couldn't read {}: {}", state::STATE_FILE, e);
+                return;
+            }
+        }
+    } else {
+        SharedState::default()
+    };
+    pending.apply_to(&mut state);
+    let recorded = state.contributed.len();
+
+    let patch_ids: std::collections::HashMap<String, String> = commits
+        .iter()
+        .filter_map(|c| Some((c.hash.clone(), c.patch_id.clone()?)))
+        .collect();
+    let journal = journal::read_all().unwrap_or_default();
+    state.record_pushed(&journal, &patch_ids, &state::user_name());
+
+    // Pull requests opened from other clones say which commits they carry
+    if shared {
+        match duplicates::pull_requests(template_repo, "all") {
+            Ok(prs) => {
+                state.record_proposed(&prs, current_repo);
+            }
+            Err(e) => println!(
+                "🧔 Couldn't check the template's pull requests for contributions: {}",
+                e
+            ),
+        }
+    }
+
+    if shared {
+        pending
+            .contributed
+            .extend_from_slice(&state.contributed[recorded..]);
+    }
+
+    for commit in commits {
+        commit.contributed = state
+            .contribution(&commit.hash, commit.patch_id.as_deref())
+            .cloned();
+    }
+}
+
+/// Writes what the run changed in `.chuck/state.toml` when it ended on the branch it
+/// started from. Anywhere else the file may not even exist, so the changes wait for
+/// the next run that does.
+fn write_pending(pending: &PendingChanges, on_users_branch: bool) {
+    if read_only::withheld(&format!("written {}", state::STATE_FILE)) {
+        return;
+    }
+    match pending.write(on_users_branch) {
+        Ok(()) if on_users_branch => {
+            if !pending.ignored.commits.is_empty() || !pending.unignored.commits.is_empty() {
+                println!(
+                    "🧔 Updated the ignore list in {}, commit it so your team skips them too",
+                    state::STATE_FILE
+                );
+            }
+            if !pending.contributed.is_empty() {
+                println!(
+                    "🧔 Added {} contributed commits to {}, commit it so your team sees them",
+                    pending.contributed.len(),
+                    state::STATE_FILE
+                );
+            }
+        }
+        Ok(()) => println!(
+            "🧔 {} changes go in on your next run from your own branch",
+            state::STATE_FILE
+        ),
+        Err(e) => println!("🧔 Couldn't update {}: {}", state::STATE_FILE, e),
+    }
+}
+
+/// Warns when an open pull request on the template already seems to have some of
+/// the selected commits. Only a warning, the run goes on either way.
+fn warn_about_open_duplicates(
+    commits: &[&Commit],
+    template_repo: &str,
+    remote: &str,
+    template_base: &str,
+    verbose: bool,
+) {
+    let _phase = profile::phase("duplicate check");
+    let hashes: Vec<&str> = commits.iter().map(|c| c.hash.as_str()).collect();
+    let result = duplicates::pull_requests(template_repo, "open").and_then(|prs| {
+        if verbose {
+            println!(
+                "🧔 VERBOSE: Comparing with {} open pull requests on the template",
+                prs.len()
+            );
+        }
+        duplicates::fetch_heads(remote, &prs)?;
+        let found = duplicates::find(&hashes, &prs, template_base)?;
+        Ok(found
+            .iter()
+            .map(|d| (d.commit.clone(), d.describe(), d.pr.url.clone()))
+            .collect::<Vec<_>>())
+    });
+
+    match result {
+        Ok(found) if !found.is_empty() => {
+            println!("\n🧔 ⚠️  Somebody may have beaten you to it:");
+            for (hash, description, url) in &found {
+                if let Some(commit) = commits.iter().find(|c| &c.hash == hash) {
+                    println!(
+                        "  • {} - {}: {} ({})",
+                        commit.short_hash, commit.message, description, url
+                    );
+                }
+            }
+        }
+        Ok(_) => {}
+        Err(e) => println!("🧔 Couldn't check the template's open pull requests: {}", e),
+    }
+}
+
+/// The manifest brought up to date with the template base, if the project keeps one.
+/// Only in memory: the run checks out the template base with the manifest tracked, so
+/// writing it is left to `chuck manifest`.
+fn refresh_manifest(
+    template_repo: &str,
+    template_base_sha: &str,
+    mention: bool,
+    verbose: bool,
+) -> Option<Manifest> {
+    let existing = match Manifest::load() {
+        Ok(Some(existing)) => existing,
+        Ok(None) => return None,
+        Err(e) => {
+            println!("🧔 Ignoring the manifest: {}", e);
+            return None;
+        }
+    };
+
+    let manifest = match Manifest::generate(template_repo, template_base_sha) {
+        Ok(manifest) => manifest,
+        Err(e) => {
+            println!("🧔 Couldn't refresh {}: {}", manifest::MANIFEST_FILE, e);
+            return Some(existing);
+        }
+    };
+
+    if manifest.same_files(&existing) {
+        if verbose {
+            println!("🧔 VERBOSE: {} is up to date", manifest::MANIFEST_FILE);
+        }
+    } else if mention {
+        println!(
+            "🧔 {} is behind the template, run `chuck manifest` and commit it to catch up",
+            manifest::MANIFEST_FILE
+        );
+    }
+
+    Some(manifest)
+}
+
+/// Keeps the commits that touch `dir` and only their files in it, with paths relative
+/// to it as they are in the template
+fn scope_to_workspace(commits: &mut Vec<Commit>, dir: &str) {
+    for commit in commits.iter_mut() {
+        commit.files.retain_mut(|file| {
+            let Some(path) = workspace::relative(dir, &file.path) else {
+                return false;
+            };
+            file.path = path.to_string();
+            file.old_path = file
+                .old_path
+                .as_deref()
+                .and_then(|old| workspace::relative(dir, old))
+                .map(str::to_string);
+            true
+        });
+    }
+    commits.retain(|commit| !commit.files.is_empty());
+}
+
+/// Shows how each pick would go on the template base by itself before anything gets
+/// built. At a terminal, conflicts are a chance to go back and change the selection.
+fn check_picks(
+    app: &mut App,
+    template_base: &str,
+    config: &ChuckConfig,
+    interactive: bool,
+) -> Result<()> {
+    loop {
+        let plan = app.planned();
+        let picks: Vec<precheck::Pick> = plan
+            .iter()
+            .map(|(commit, step)| {
+                let mut leave_out = step.leave_out.clone();
+                if step.keep_template_files {
+                    leave_out.extend(commit.deleted_template_files.iter().cloned());
+                }
+                leave_out.extend(commit.split_off.iter().cloned());
+                precheck::Pick {
+                    parent: format!("{}^{}", commit.hash, mainline(commit, config)),
+                    commit: commit.hash.clone(),
+                    leave_out,
+                }
+            })
+            .collect();
+        if picks.is_empty() {
+            return Ok(());
+        }
+
+        println!("\n🧔 How each pick goes on the template base:");
+        let outcomes =
+            precheck::check(&picks, template_base, config.cherry_pick.subtree.as_deref());
+        let mut conflicts = 0;
+        for ((commit, _), outcome) in plan.iter().zip(outcomes) {
+            match outcome {
+                Ok(outcome) => {
+                    let detail = match &outcome {
+                        precheck::Outcome::Conflicts(detail) => {
+                            conflicts += 1;
+                            format!(" ({})", detail)
+                        }
+                        _ => String::new(),
+                    };
+                    println!(
+                        "  {} {} - {}{}",
+                        outcome.badge(),
+                        commit.short_hash,
+                        commit.message,
+                        detail
+                    );
+                }
+                Err(e) => println!(
+                    "  ? {} - {} (couldn't check: {})",
+                    commit.short_hash, commit.message, e
+                ),
+            }
+        }
+        if conflicts == 0 {
+            return Ok(());
+        }
+        println!(
+            "🧔 That's each commit by itself. One that builds on an earlier pick can still go cleanly after it."
+        );
+        if !interactive || !mergetool::confirm("Change the selection first?")? {
+            return Ok(());
+        }
+        select_interactively(app)?;
+    }
+}
+
+/// Looks for Git LFS pointers in the plan. Those the template tracks with LFS too get
+/// their objects pushed with the branch. The rest would land there as pointer files,
+/// so they're left out unless someone at the terminal says otherwise. Returns whether
+/// there are LFS objects to push.
+fn check_lfs(
+    app: &mut App,
+    template_base: &str,
+    config: &ChuckConfig,
+    interactive: bool,
+) -> Result<bool> {
+    let subtree = config.cherry_pick.subtree.as_deref().unwrap_or("");
+    let mut found: Vec<(usize, Vec<String>)> = Vec::new();
+    for (i, step) in app.plan.iter().enumerate() {
+        let commit = &app.commits[step.commit];
+        if step.action == PlanAction::Drop {
+            continue;
+        }
+        let parent = if commit.is_merge() {
+            config.merge_commits.mainline
+        } else {
+            1
+        };
+        let pointers = match lfs::pointers(&format!("{}^{}", commit.hash, parent), &commit.hash) {
+            Ok(pointers) => pointers,
+            Err(e) => {
+                println!("🧔 Couldn't look for Git LFS files: {}", e);
+                return Ok(false);
+            }
+        };
+        // As they'll be in the template
+        let paths: Vec<String> = pointers
+            .iter()
+            .filter_map(|pointer| workspace::relative(subtree, &pointer.path))
+            .map(str::to_string)
+            .collect();
+        if !paths.is_empty() {
+            found.push((i, paths));
+        }
+    }
+    if found.is_empty() {
+        return Ok(false);
+    }
+
+    let paths: std::collections::BTreeSet<&str> = found
+        .iter()
+        .flat_map(|(_, paths)| paths.iter().map(String::as_str))
+        .collect();
+    println!("\n🧔 The picks include {} Git LFS files", paths.len());
+    let paths: Vec<&str> = paths.into_iter().collect();
+    let untracked = match lfs::untracked(template_base, &paths) {
+        Ok(untracked) => untracked,
+        Err(e) => {
+            println!(
+                "🧔 Couldn't check which files the template keeps in LFS: {}",
+                e
+            );
+            return Ok(true);
+        }
+    };
+    if untracked.is_empty() {
+        println!(
+            "🧔 The template tracks them with LFS too, their objects get pushed with the branch"
+        );
+        return Ok(true);
+    }
+
+    println!(
+        "🧔 ⚠️  The template doesn't use Git LFS for these, they'd land there as pointer files:"
+    );
+    for path in &untracked {
+        println!("  • {}", path);
+    }
+    // Unattended, broken files upstream are the worse outcome
+    if interactive && !mergetool::confirm("Leave them out of the picks?")? {
+        println!("🧔 Keeping them as pointer files");
+        return Ok(true);
+    }
+    for (i, paths) in &found {
+        app.plan[*i].leave_out = paths
+            .iter()
+            .filter(|path| untracked.contains(path))
+            .cloned()
+            .collect();
+    }
+    println!("🧔 Leaving them out, track them with LFS in the template to contribute them");
+    Ok(untracked.len() < paths.len())
+}
+
+fn run_manifest(cli: &Cli) -> Result<()> {
+    let _lock = lock::RepoLock::acquire(cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Hold your horses\": {}", e))?;
+
+    let mut config =
+        find_template_config("").map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+    if let Some(base) = &cli.base {
+        config.template.base = Some(base.clone());
+    }
+    extract_repo_name_from_url(&config.template.url)
+        .map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+
+    let _template_remote =
+        fetch_template_remote(&mut config, io::stdin().is_terminal(), cli.verbose)
+            .map_err(|e| anyhow!("🧔 \"Can't reach the template\": {}", e))?;
+    let template_repo = extract_repo_name_from_url(&config.template.url)?;
+    let template_base_sha = get_template_base_commit(&template_repo, config.base_ref())
+        .map_err(|e| anyhow!("🧔 \"Can't find that template base\": {}", e))?;
+
+    let manifest = Manifest::generate(&template_repo, &template_base_sha)?;
+    let path = manifest.save()?;
+
+    println!("🧔 Wrote {}", path.display());
+    println!(
+        "🧔 {} template-managed, {} project-managed, {} diverged",
+        manifest.count(Ownership::Template),
+        manifest.count(Ownership::Project),
+        manifest.count(Ownership::Diverged)
+    );
+    Ok(())
+}
+
+/// Fetches the template for a command that only looks at it, returning its
+/// `owner/repo`. The config's url follows the template if it moved.
+fn fetch_template_for(config: &mut ChuckConfig, verbose: bool) -> Result<(String, TemplateRemote)> {
+    extract_repo_name_from_url(&config.template.url)
+        .map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+    let template_remote = fetch_template_remote(config, io::stdin().is_terminal(), verbose)
+        .map_err(|e| anyhow!("🧔 \"Can't reach the template\": {}", e))?;
+    let template_repo = extract_repo_name_from_url(&config.template.url)?;
+    Ok((template_repo, template_remote))
+}
+
+fn run_subscribe(cli: &Cli) -> Result<()> {
+    let _lock = lock::RepoLock::acquire(cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Hold your horses\": {}", e))?;
+    let mut config =
+        find_template_config("").map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+    if let Some(target) = &cli.target {
+        config.pr.target = Some(target.clone());
+    }
+    let (template_repo, _template_remote) = fetch_template_for(&mut config, cli.verbose)?;
+    let branch = config.target_branch();
+    let head = get_template_base_commit(&template_repo, branch)
+        .map_err(|e| anyhow!("🧔 \"Can't find the template's head\": {}", e))?;
+
+    Subscription::new(&template_repo, branch, &head).save()?;
+    println!(
+        "🧔 Subscribed to {} ({}) at {}. Chuck will say when it has new commits.",
+        template_repo,
+        branch,
+        &head[..head.len().min(7)]
+    );
+    Ok(())
+}
+
+fn run_status(cli: &Cli) -> Result<()> {
+    let _lock = lock::RepoLock::acquire(cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Hold your horses\": {}", e))?;
+    let mut config =
+        find_template_config("").map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+    if let Some(target) = &cli.target {
+        config.pr.target = Some(target.clone());
+    }
+    let (template_repo, _template_remote) = fetch_template_for(&mut config, cli.verbose)?;
+
+    match announce_template_updates(&config, &template_repo, 10)? {
+        None => println!(
+            "🧔 Not subscribed to {} ({}) yet. Run `chuck subscribe` once you're in sync with it.",
+            template_repo,
+            config.target_branch()
+        ),
+        Some(0) => println!(
+            "🧔 Up to date with {} ({})",
+            template_repo,
+            config.target_branch()
+        ),
+        Some(_) => {}
+    }
+
+    if let Err(e) = check_required_files(&template_repo, config.base_ref()) {
+        println!("🧔 Couldn't check the files the template requires: {}", e);
+    }
+    Ok(())
+}
+
+/// Warns about files the template's policy requires that the project removed or
+/// renamed since
+fn check_required_files(template_repo: &str, base_ref: &str) -> Result<()> {
+    let base = get_template_base_commit(template_repo, base_ref)?;
+    let Some(policy) = policy::Policy::load(&base)? else {
+        return Ok(());
+    };
+    if policy.required_files.is_empty() {
+        return Ok(());
+    }
+    let missing = policy.missing_files(&adopt::blobs(&base)?, &adopt::blobs("HEAD")?);
+    if missing.is_empty() {
+        println!(
+            "🧔 All {} files the template requires are here",
+            policy.required_files.len()
+        );
+        return Ok(());
+    }
+    println!("🧔 ⚠️  The template requires files this project doesn't have:");
+    for file in &missing {
+        match &file.moved_to {
+            Some(path) => println!("  • {} (renamed to {}?)", file.path, path),
+            None => println!("  • {} (removed)", file.path),
+        }
+    }
+    Ok(())
+}
+
+/// `chuck adopt`: for a project that started as a copy of the template rather than
+/// from it, pins the template commit it was copied from in a new `.chuckrc` and
+/// records the commits that brought the copy in as contributed, so they're never offered
+fn run_adopt(cli: &Cli, url: &str) -> Result<()> {
+    let _lock = lock::RepoLock::acquire(cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Hold your horses\": {}", e))?;
+    let path = std::path::Path::new(workspace::CONFIG_FILE);
+    if path.exists() {
+        return Err(anyhow!(
+            "🧔 \"Already adopted\": {} exists, and chuck adopt won't overwrite it",
+            path.display()
+        ));
+    }
+    let mut config: ChuckConfig = toml::from_str(&adopt::config(url, None, cli.target.as_deref()))
+        .map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+    let (template_repo, template_remote) = fetch_template_for(&mut config, cli.verbose)?;
+    let branch = config.target_branch().to_string();
+
+    println!(
+        "🧔 Looking for the commit of {} ({}) this project was copied from...",
+        template_repo, branch
+    );
+    let project = adopt::blobs("HEAD")?;
+    let base = adopt::closest_base(&format!("{}/{}", template_remote.name, branch), &project)
+        .map_err(|e| anyhow!("🧔 \"Can't compare with the template\": {}", e))?
+        .ok_or_else(|| {
+            anyhow!(
+                "🧔 \"Never seen it before\": this project has no file in common with {} ({})",
+                template_repo,
+                branch
+            )
+        })?;
+    println!(
+        "🧔 Closest is {}: {} files as the template has them, {} changed or missing",
+        &base.commit[..base.commit.len().min(7)],
+        base.identical,
+        base.differing
+    );
+
+    fs::write(
+        path,
+        adopt::config(
+            &config.template.url,
+            Some(&base.commit),
+            cli.target.as_deref(),
+        ),
+    )
+    .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+
+    let copied = adopt::copied_commits(&adopt::blobs(&base.commit)?)?;
+    let mut state = SharedState::load()?;
+    let by = state::user_name();
+    let mut added = 0;
+    for (hash, subject) in copied {
+        if state.contribution(&hash, None).is_none() {
+            state.contributed.push(Contribution {
+                hash,
+                patch_id: None,
+                message: subject,
+                branch: branch.clone(),
+                by: by.clone(),
+                contributed_at: chrono::Utc::now(),
+            });
+            added += 1;
+        }
+    }
+    if added > 0 {
+        state.save()?;
+        println!(
+            "🧔 Recorded {} commit{} that copied the template in {}",
+            added,
+            if added == 1 { "" } else { "s" },
+            state::STATE_FILE
+        );
+    }
+    println!(
+        "🧔 Wrote {}, commit it{} so the whole team picks up from here",
+        path.display(),
+        if added > 0 {
+            format!(" along with {}", state::STATE_FILE)
+        } else {
+            String::new()
+        }
+    );
+    Ok(())
+}
+
+/// `chuck rebase`: replays the commits of a branch an earlier run pushed onto the
+/// template's head, with the same conflict handling as building it, and pushes the
+/// result over it unless someone else pushed there in the meantime
+fn run_rebase(cli: &Cli, arg: &str) -> Result<()> {
+    let _lock = lock::RepoLock::acquire(cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Hold your horses\": {}", e))?;
+    let mut config =
+        find_template_config("").map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+    if let Some(target) = &cli.target {
+        config.pr.target = Some(target.clone());
+    }
+    let template_repo = extract_repo_name_from_url(&config.template.url)
+        .map_err(|e| anyhow!("🧔 \"Hmm, having trouble here\": {}", e))?;
+
+    check_token(&template_repo)?;
+
+    // A PR knows its branch and where it's going, a bare branch is wherever Chuck pushes
+    let (access, branch, pr_url) = match rebase::Target::parse(arg) {
+        rebase::Target::PullRequest(number) => {
+            let pr = rebase::pull_request(&template_repo, number)
+                .map_err(|e| anyhow!("🧔 \"Which one was that again?\": {}", e))?;
+            config.pr.target = Some(pr.base);
+            let access = if pr.repo.eq_ignore_ascii_case(&template_repo) {
+                PushAccess::Direct
+            } else {
+                PushAccess::Fork { repo: pr.repo }
+            };
+            (access, pr.branch, Some(pr.url))
+        }
+        rebase::Target::Branch(branch) => {
+            let access = access::preflight(&template_repo, cli.verbose).unwrap_or_else(|e| {
+                println!("🧔 Couldn't check push access to {}: {}", template_repo, e);
+                PushAccess::Direct
+            });
+            (access, branch, None)
+        }
+    };
+
+    let (template_repo, template_remote) = fetch_template_for(&mut config, cli.verbose)?;
+    let push_target = if let Some(url) = access.fork_url(&config.template.url) {
+        url
+    } else if template_remote.temporary {
+        config.template.url.clone()
+    } else {
+        template_remote.name.clone()
+    };
+    let target_branch = config.target_branch().to_string();
+
+    let old_tip = rebase::fetch_branch(&push_target, &branch)
+        .map_err(|e| anyhow!("🧔 \"Which one was that again?\": {}", e))?;
+    let head_output = Command::new("git")
+        .args([
+            "rev-parse",
+            &format!("{}/{}^{{commit}}", template_remote.name, target_branch),
+        ])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+    if !head_output.status.success() {
+        return Err(anyhow!(
+            "🧔 \"Can't find the template's head\": {} has no branch {}",
+            template_repo,
+            target_branch
+        ));
+    }
+    let head = String::from_utf8(head_output.stdout)?.trim().to_string();
+
+    let base_output = Command::new("git")
+        .args(["merge-base", &old_tip, &head])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git merge-base"))?;
+    let old_base = String::from_utf8(base_output.stdout)?.trim().to_string();
+    if old_base.is_empty() {
+        return Err(anyhow!(
+            "🧔 \"That's not one of ours\": {} has no history in common with {}",
+            branch,
+            target_branch
+        ));
+    }
+    if old_base == head {
+        println!(
+            "🧔 {} is already on the latest {} ({})",
+            branch,
+            target_branch,
+            &head[..head.len().min(7)]
+        );
+        return Ok(());
+    }
+
+    // Oldest first, each picked as it is: any origin line or checks already went in
+    let mut commits: Vec<Commit> = list_local_commits(&[&format!("{}..{}", old_base, old_tip)])?
+        .into_iter()
+        .map(|(commit, _)| commit)
+        .collect();
+    commits.reverse();
+    let steps: Vec<PlanStep> = commits
+        .iter()
+        .enumerate()
+        .map(|(i, commit)| PlanStep {
+            commit: i,
+            action: PlanAction::Pick,
+            message: commit.message.clone(),
+            keep_template_files: false,
+            leave_out: Vec::new(),
+        })
+        .collect();
+    let plan: Vec<(&Commit, &PlanStep)> = commits.iter().zip(&steps).collect();
+    config.cherry_pick.record_origin = false;
+    config.cherry_pick.subtree = None;
+    config.checks.amend = false;
+
+    println!(
+        "🧔 Rebasing {} commit{} of {} onto {} ({})...",
+        commits.len(),
+        if commits.len() == 1 { "" } else { "s" },
+        branch,
+        target_branch,
+        &head[..head.len().min(7)]
+    );
+    if withhold_build(&plan, &head, &template_repo) {
+        return Ok(());
+    }
+    let original_ref = current_ref()?;
+    let (local_branch, _) = create_branch_with_commits(&plan, cli.verbose, &head, &config)
+        .map_err(|e| {
+            if e.to_string().contains("Cherry-pick failed") {
+                exit::fail(Code::Conflict, e)
+            } else {
+                e
+            }
+        })?;
+
+    println!("🧔 Pushing {} over {}...", local_branch, branch);
+    rebase::force_push(&push_target, &local_branch, &branch, &old_tip).map_err(|e| {
+        exit::fail(
+            Code::PushFailed,
+            anyhow!("🧔 \"Rebased, but it didn't stick\": {}", e),
+        )
+    })?;
+
+    // The branch lives on the template now, the local copy was only for building it
+    let _ = Command::new("git")
+        .args(["checkout", "-q", &original_ref])
+        .output_local();
+    let _ = Command::new("git")
+        .args(["branch", "-D", &local_branch])
+        .output_local();
+
+    println!(
+        "🧔 ✅ {} is on top of {} ({}) again",
+        branch,
+        target_branch,
+        &head[..head.len().min(7)]
+    );
+    if let Some(url) = pr_url {
+        println!("🧔 PR: {}", url);
+    }
+    Ok(())
+}
+
+/// A project built from the template, as `chuck backport` sees it
+struct Downstream {
+    repo: String,
+    url: String,
+    /// Its default branch, where the PR goes
+    branch: String,
+    head: String,
+    /// Template commits it doesn't have
+    missing: std::collections::HashSet<String>,
+    /// Where the template's files sit in it, from a .chuckrc naming the template
+    subtree: Option<String>,
+}
+
+/// `chuck backport`: template commits picked in the usual TUI, cherry-picked onto each
+/// downstream project's default branch and proposed there
+fn run_backport(cli: &Cli, ui: &UiConfig, to: &[String], since: Option<&str>) -> Result<Code> {
+    let _lock = lock::RepoLock::acquire(cli.verbose)
+        .map_err(|e| anyhow!("🧔 \"Hold your horses\": {}", e))?;
+    repo_state::ensure_idle().map_err(|e| anyhow!("🧔 \"One thing at a time\": {}", e))?;
+    let template_repo =
+        get_current_repo().map_err(|e| anyhow!("🧔 \"Can't figure out current repo\": {}", e))?;
+
+    let mut downstreams = Vec::new();
+    for arg in to {
+        let url = if arg.contains(':') {
+            arg.clone()
+        } else {
+            format!("https://github.com/{}.git", arg.trim_end_matches(".git"))
+        };
+        let repo = extract_repo_name_from_url(&url)
+            .map_err(|e| anyhow!("🧔 \"Where's that, now?\": {}", e))?;
+        let reach = |e: anyhow::Error| anyhow!("🧔 \"Can't reach {}\": {}", repo, e);
+        check_token(&repo)?;
+        let branch = backport::default_branch(&url).map_err(reach)?;
+        let head = rebase::fetch_branch(&url, &branch).map_err(reach)?;
+
+        let synced = match since {
+            Some(since) => backport::Synced::Commit(since.to_string()),
+            None => backport::synced("HEAD", &head).map_err(reach)?,
+        };
+        let missing = backport::candidates("HEAD", &synced).map_err(reach)?;
+        // The template's files go where the project's .chuckrc for it lives
+        let subtree = backport::chuckrc_files(&head)
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|(dir, content)| {
+                let config: ChuckConfig = toml::from_str(&content).ok()?;
+                let named = extract_repo_name_from_url(&config.template.url).ok()?;
+                (!dir.is_empty() && named.eq_ignore_ascii_case(&template_repo)).then_some(dir)
+            });
+
+        println!(
+            "🧔 {} ({}) is missing {} template commits{}",
+            repo,
+            branch,
+            missing.len(),
+            subtree
+                .as_deref()
+                .map(|dir| format!(", the template sits in {}", dir))
+                .unwrap_or_default()
+        );
+        downstreams.push(Downstream {
+            repo,
+            url,
+            branch,
+            head,
+            missing: missing.into_iter().collect(),
+            subtree,
+        });
+    }
+
+    let mut hashes: Vec<&str> = downstreams
+        .iter()
+        .flat_map(|d| d.missing.iter().map(String::as_str))
+        .collect();
+    hashes.sort_unstable();
+    hashes.dedup();
+    if hashes.is_empty() {
+        println!("🧔 \"They're all caught up. Nothing to backport.\"");
+        return Ok(Code::NoCandidates);
+    }
+    let mut rev_args = vec!["--no-walk=sorted"];
+    rev_args.extend(hashes);
+    let commits: Vec<Commit> = list_local_commits(&rev_args)?
+        .into_iter()
+        .map(|(commit, _)| commit)
+        .collect();
+
+    let mut app = App::new(commits);
+    app.show_graph = ui.graph;
+    if cli.select.is_empty() {
+        select_interactively(&mut app)?;
+    } else {
+        app.select_by_hash(&cli.select)
+            .map_err(|e| anyhow!("🧔 \"Which ones, exactly?\": {}", e))?;
+        app.start_plan();
+    }
+    let plan = app.planned();
+    if plan.is_empty() {
+        println!("🧔 \"No commits selected. That's fine, take your time.\"");
+        return Ok(Code::Aborted);
+    }
+
+    let original_ref = current_ref()?;
+    let mut failed = 0;
+    for downstream in &downstreams {
+        let plan: Vec<(&Commit, &PlanStep)> = plan
+            .iter()
+            .filter(|(commit, _)| downstream.missing.contains(&commit.hash))
+            .copied()
+            .collect();
+        if plan.is_empty() {
+            println!("\n🧔 {} already has those commits", downstream.repo);
+            continue;
+        }
+
+        println!(
+            "\n🧔 Backporting {} commits to {}...",
+            plan.len(),
+            downstream.repo
+        );
+        if withhold_build(&plan, &downstream.head, &downstream.repo) {
+            continue;
+        }
+        match backport_to(cli, downstream, &plan, &template_repo) {
+            Ok(url) => println!("🧔 ✅ Pull request opened: {}", url),
+            Err(e) => {
+                println!("🧔 ⚠️  Couldn't backport to {}: {}", downstream.repo, e);
+                failed += 1;
+            }
+        }
+        let _ = Command::new("git")
+            .args(["checkout", "-q", &original_ref])
+            .output_local();
+    }
+
+    Ok(if failed > 0 {
+        Code::PushFailed
+    } else {
+        Code::Success
+    })
+}
+
+/// Builds, pushes and proposes `plan` on one downstream project, returning the PR's URL
+fn backport_to(
+    cli: &Cli,
+    downstream: &Downstream,
+    plan: &[(&Commit, &PlanStep)],
+    template_repo: &str,
+) -> Result<String> {
+    let mut config = ChuckConfig::new(TemplateConfig {
+        url: downstream.url.clone(),
+        remote: default_remote_name(),
+        keep_remote: false,
+        base: None,
+    });
+    // The reverse of contributing from a workspace: git prefixes the paths instead
+    config.cherry_pick.subtree = downstream.subtree.clone();
+    let (branch_name, timestamp) =
+        create_branch_with_commits(plan, cli.verbose, &downstream.head, &config)?;
+
+    let access = access::preflight(&downstream.repo, cli.verbose).unwrap_or_else(|e| {
+        println!(
+            "🧔 Couldn't check push access to {}: {}",
+            downstream.repo, e
+        );
+        PushAccess::Direct
+    });
+    let push_target = access
+        .fork_url(&downstream.url)
+        .unwrap_or_else(|| downstream.url.clone());
+    let push_repo = match &access {
+        PushAccess::Fork { repo } => repo.clone(),
+        PushAccess::Direct => downstream.repo.clone(),
+    };
+    let exposure = exposes(template_repo, &push_repo);
+    let exposed = exposure != Some(false);
+    let guarded = exposed && config.leak_guard.enabled;
+    if guarded {
+        guard_leaks(
+            &config.leak_guard,
+            template_repo,
+            &downstream.head,
+            &branch_name,
+        )?;
+    }
+    let remote_branch = format!(
+        "chuck-backport-{}-{}",
+        if guarded {
+            leak::UNNAMED
+        } else {
+            template_repo
+        }
+        .replace('/', "-"),
+        timestamp
+    );
+    show_push(
+        &push_repo,
+        &push_target,
+        &branch_name,
+        &remote_branch,
+        &downstream.head,
+    );
+    let interactive = cli.select.is_empty() && io::stdin().is_terminal();
+    if exposed
+        && !confirm_exposure(
+            template_repo,
+            &push_repo,
+            exposure.is_some(),
+            interactive,
+            cli.push_public,
+        )?
+    {
+        return Err(anyhow!("Nothing was pushed, {} is still here", branch_name));
+    }
+    push_to_template_and_create_pr(
+        &branch_name,
+        &downstream.head,
+        &push_target,
+        &push_repo,
+        &downstream.branch,
+        &remote_branch,
+    )?;
+
+    let commits: Vec<&Commit> = plan.iter().map(|(commit, _)| *commit).collect();
+    let title = match commits.as_slice() {
+        [commit] => commit.message.clone(),
+        _ => format!("Backport {} commits from {}", commits.len(), template_repo),
+    };
+    let mut body = format!("Backported from template {} with chuck.\n\n", template_repo);
+    for commit in &commits {
+        body.push_str(&format!("- {} {}\n", commit.short_hash, commit.message));
+    }
+    let body = pr_metadata(template_repo, &commits).embed(&body);
+    let (title, body) = if guarded {
+        let words = config.leak_guard.words(template_repo);
+        (leak::redact(&title, &words), leak::redact(&body, &words))
+    } else {
+        (title, body)
+    };
+    let url = open_pull_request(
+        &downstream.repo,
+        &downstream.branch,
+        &access.pr_head(&remote_branch),
+        &title,
+        &body,
+        &[],
+        cli.prefs().draft == Some(true),
+    );
+
+    // Pushed, the local copy was only for building it
+    let _ = Command::new("git")
+        .args(["checkout", "-q", "--detach"])
+        .output_local();
+    let _ = Command::new("git")
+        .args(["branch", "-D", &branch_name])
+        .output_local();
+    url.map_err(|e| anyhow!("pushed {} but couldn't open the PR: {}", remote_branch, e))
+}
+
+/// Says how many commits the template has had since `chuck subscribe`, listing up to
+/// `shown` of them, and how to bring them in. `None` when this clone isn't subscribed
+/// to the template's target branch, otherwise how many there are.
+fn announce_template_updates(
+    config: &ChuckConfig,
+    template_repo: &str,
+    shown: usize,
+) -> Result<Option<usize>> {
+    let branch = config.target_branch();
+    let Some(subscription) = Subscription::load()?.filter(|s| s.follows(template_repo, branch))
+    else {
+        return Ok(None);
+    };
+    let head = get_template_base_commit(template_repo, branch)?;
+    let new = subscription.new_commits(&head)?;
+    if new.is_empty() {
+        return Ok(Some(0));
+    }
+
+    println!(
+        "🧔 Template has {} new commits since you last synced ({})",
+        new.len(),
+        subscription.synced_at.format("%Y-%m-%d")
+    );
+    for (hash, subject) in new.iter().take(shown) {
+        println!("  • {} - {}", hash, subject);
+    }
+    if new.len() > shown && shown > 0 {
+        println!("  … and {} more", new.len() - shown);
+    }
+    println!(
+        "🧔 Bring them in with `git pull --no-rebase {} {}`, then run `chuck subscribe`",
+        config.template.url, branch
+    );
+    Ok(Some(new.len()))
+}
+
+fn record_run(
+    current_repo: &str,
+    template_repo: &str,
+    branch: Option<&str>,
+    remote_branch: Option<&str>,
+    outcome: Outcome,
+    commits: &[&Commit],
+    verbose: bool,
+) {
+    let entry = JournalEntry {
+        timestamp: chrono::Utc::now(),
+        repo: current_repo.to_string(),
+        template: template_repo.to_string(),
+        branch: branch.map(|b| b.to_string()),
+        remote_branch: remote_branch.map(|b| b.to_string()),
+        outcome,
+        commits: commits
+            .iter()
+            .map(|c| JournalCommit {
+                hash: c.hash.clone(),
+                message: c.message.clone(),
+                author: c.author.clone(),
+                files: c.files.iter().map(|f| f.path.clone()).collect(),
+            })
+            .collect(),
+    };
+
+    // The journal is bookkeeping only, never fail a run because of it
+    if let Err(e) = journal::append(&entry) {
+        if verbose {
+            println!("🧔 VERBOSE: Couldn't write journal: {}", e);
+        }
+    }
+}
+
+/// Names of the template's recent `tags` and/or `branches`, in the order given
+fn list_template_refs(template_repo: &str, kinds: &[&str]) -> Result<Vec<String>> {
+    let mut refs = Vec::new();
+    for kind in kinds {
+        let json = forge::get_json(
+            &format!("repos/{}/{}?per_page=20", template_repo, kind),
+            &format!("Failed to list template {}", kind),
+        )?;
+        refs.extend(
+            json.as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|r| r.get("name")?.as_str())
+                .map(str::to_string),
+        );
+    }
+    Ok(refs)
+}
+
+fn pick_template_base(template_repo: &str, current: &str) -> Result<Option<String>> {
+    let refs = list_template_refs(template_repo, &["tags", "branches"])?;
+    if refs.is_empty() {
+        return Err(anyhow!(
+            "Template {} has no tags or branches",
+            template_repo
+        ));
+    }
+
+    pick_from_list(
+        &format!("🧔 Build on which {} ref?", template_repo),
+        &refs,
+        current,
+    )
+}
+
+fn pick_target_branch(template_repo: &str, current: &str) -> Result<Option<String>> {
+    let branches = list_template_refs(template_repo, &["branches"])?;
+    if branches.is_empty() {
+        return Err(anyhow!("Template {} has no branches", template_repo));
+    }
+
+    pick_from_list(
+        &format!("🧔 Open the PR against which {} branch?", template_repo),
+        &branches,
+        current,
+    )
+}
+
+/// Minimal full-screen list picker used before the main selection UI starts
+fn pick_from_list(title: &str, refs: &[String], current: &str) -> Result<Option<String>> {
+    let mut state = ListState::default();
+    state.select(Some(refs.iter().position(|r| r == current).unwrap_or(0)));
+
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let picked = loop {
+        terminal.draw(|frame| {
+            let items: Vec<ListItem> = refs.iter().map(|r| ListItem::new(r.as_str())).collect();
+            let list = List::new(items)
+                .block(
+                    Block::default()
+                        .title(format!("{} (Enter: pick │ Esc: cancel)", title))
+                        .borders(Borders::ALL)
+                        .border_style(Style::default().fg(Color::Gray)),
+                )
+                .highlight_style(
+                    Style::default()
+                        .bg(Color::DarkGray)
+                        .add_modifier(Modifier::BOLD),
+                )
+                .highlight_symbol("► ");
+            frame.render_stateful_widget(list, frame.area(), &mut state);
+            render_scrollbar(frame, frame.area(), refs.len(), state.offset());
+        })?;
+
+        if let Event::Key(key) = recording::next_event(event::read)? {
+            let selected = state.selected().unwrap_or(0);
+            match key.code {
+                KeyCode::Down | KeyCode::Char('j') => {
+                    state.select(Some((selected + 1) % refs.len()));
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    state.select(Some((selected + refs.len() - 1) % refs.len()));
+                }
+                KeyCode::Enter => break Some(refs[selected].clone()),
+                KeyCode::Esc | KeyCode::Char('q') => break None,
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break None,
+                _ => {}
+            }
+        }
+    };
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    Ok(picked)
+}
+
+/// Runs the selection TUI on the real terminal
+fn select_interactively(app: &mut App) -> Result<()> {
+    // Setup terminal for TUI
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+    let taken = screen::take();
+
+    // Run interactive selection
+    let result = run_selection(
+        &mut terminal,
+        app,
+        || recording::next_event(event::read),
+        |text| clipboard::copy(&mut io::stdout(), text),
+    );
+
+    // Restore terminal properly, failed or not, so errors print on a sane screen
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    // Ensure terminal is fully restored and flushed
+    drop(terminal);
+    io::stdout().flush()?;
+    drop(taken);
+    result?;
+    println!("\n🧔 Exiting interactive mode...");
+    Ok(())
+}
+
+/// Draws and handles keys until the user proceeds or quits. The real TUI passes
+/// `event::read`; tests pass synthetic events and a `TestBackend`.
+fn run_selection<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    mut next_event: impl FnMut() -> io::Result<Event>,
+    mut copy: impl FnMut(&str) -> io::Result<()>,
+) -> io::Result<()> {
+    loop {
+        app.show_errors();
+        terminal.draw(|frame| render_ui(frame, app))?;
+
+        if let Event::Key(key) = next_event()? {
+            app.notice = None;
+            if handle_key_event(app, key) {
+                return Ok(());
+            }
+            if let Some(text) = app.clipboard.take() {
+                copy(&text)?;
+            }
+        }
+    }
+}
+
+fn handle_key_event(app: &mut App, key: KeyEvent) -> bool {
+    if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+        app.should_quit = true;
+        return true;
+    }
+    if app.error.is_some() {
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => app.scroll_error(1),
+            KeyCode::Up | KeyCode::Char('k') => app.scroll_error(-1),
+            KeyCode::PageDown | KeyCode::Char(' ') => app.scroll_error(10),
+            KeyCode::PageUp => app.scroll_error(-10),
+            KeyCode::Char('y') => app.copy_error(),
+            KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => app.dismiss_error(),
+            _ => {}
+        }
+        return false;
+    }
+    if app.diff.is_some() {
+        handle_diff_key_event(app, key);
+        return false;
+    }
+    if app.screen == Screen::Plan {
+        return handle_plan_key_event(app, key);
+    }
+    if app.show_dirs {
+        match key.code {
+            KeyCode::Down | KeyCode::Char('j') => app.next_dir(),
+            KeyCode::Up | KeyCode::Char('k') => app.previous_dir(),
+            KeyCode::Char(' ') => app.toggle_dir(),
+            KeyCode::Char('c') => app.clear_dirs(),
+            KeyCode::Char('t') | KeyCode::Esc | KeyCode::Enter => app.show_dirs = false,
+            KeyCode::Char('q') => {
+                app.should_quit = true;
+                return true;
+            }
+            _ => {}
+        }
+        return false;
+    }
+    if let Some(pattern) = &mut app.pattern {
+        match key.code {
+            KeyCode::Enter => app.finish_pattern(),
+            KeyCode::Esc => app.pattern = None,
+            KeyCode::Backspace => {
+                pattern.pop();
+            }
+            KeyCode::Char(c) => pattern.push(c),
+            _ => {}
+        }
+        return false;
+    }
+    if let Some(title) = &mut app.titling {
+        match key.code {
+            KeyCode::Enter => app.finish_title(),
+            KeyCode::Esc => app.titling = None,
+            KeyCode::Backspace => {
+                title.pop();
+            }
+            KeyCode::Char(c) => title.push(c),
+            _ => {}
+        }
+        return false;
+    }
+    if let Some(query) = &mut app.palette {
+        match key.code {
+            KeyCode::Enter => return app.run_palette(),
+            KeyCode::Esc => app.palette = None,
+            KeyCode::Down => app.palette_next(),
+            KeyCode::Up => app.palette_previous(),
+            KeyCode::Backspace => {
+                query.pop();
+                app.palette_cursor = 0;
+            }
+            KeyCode::Char(c) => {
+                query.push(c);
+                app.palette_cursor = 0;
+            }
+            _ => {}
+        }
+        return false;
+    }
+
+    match key.code {
+        KeyCode::Char('q') | KeyCode::Esc => {
+            if app.show_help {
+                app.show_help = false;
+            } else {
+                app.should_quit = true;
+                return true;
+            }
+        }
+        KeyCode::Down | KeyCode::Char('j') => app.next(),
+        KeyCode::Up | KeyCode::Char('k') => app.previous(),
+        KeyCode::Char('g') => app.toggle_graph(),
+        KeyCode::Char('t') if !app.show_help => app.open_dirs(),
+        KeyCode::Char('v') if !app.show_help => app.open_diff(),
+        KeyCode::Char('h') | KeyCode::Char('?') => app.show_help = !app.show_help,
+        KeyCode::Char('y') if !app.show_help => app.copy_current(),
+        KeyCode::Char(':') if !app.show_help => app.open_palette(),
+        _ if app.read_only => {}
+        KeyCode::Char('Y') if !app.show_help => app.copy_selected(),
+        KeyCode::Char(' ') => app.toggle_current(),
+        KeyCode::Char('a') => app.select_all(),
+        KeyCode::Char('n') => app.select_none(),
+        KeyCode::Char('i') => app.invert_selection(),
+        KeyCode::Char('f') if !app.show_help => app.pattern = Some(String::new()),
+        KeyCode::Char('x') => app.toggle_ignored(),
+        KeyCode::Char('m') if !app.show_help => app.mark_current(Mark::Upstream),
+        KeyCode::Char('M') if !app.show_help => app.mark_current(Mark::Skip),
+        KeyCode::Enter if !app.show_help => return app.proceed(),
+        _ => {}
+    }
+    false
+}
+
+fn handle_plan_key_event(app: &mut App, key: KeyEvent) -> bool {
+    if let Some(message) = &mut app.editing {
+        match key.code {
+            KeyCode::Enter => app.finish_reword(),
+            KeyCode::Esc => app.editing = None,
+            KeyCode::Backspace => {
+                message.pop();
+            }
+            KeyCode::Char(c) => message.push(c),
+            _ => {}
+        }
+        return false;
+    }
+
+    match key.code {
+        KeyCode::Esc => app.screen = Screen::Select,
+        KeyCode::Char('q') => {
+            app.should_quit = true;
+            return true;
+        }
+        KeyCode::Down | KeyCode::Char('j') => app.plan_next(),
+        KeyCode::Up | KeyCode::Char('k') => app.plan_previous(),
+        KeyCode::Char('J') => app.move_step(1),
+        KeyCode::Char('K') => app.move_step(-1),
+        KeyCode::Char('p') => app.set_action(PlanAction::Pick),
+        KeyCode::Char('s') => app.set_action(PlanAction::Squash),
+        KeyCode::Char('r') => app.start_reword(),
+        KeyCode::Char('d') => app.set_action(PlanAction::Drop),
+        KeyCode::Char('t') => app.toggle_keep_template_files(),
+        KeyCode::Char('v') => app.open_diff(),
+        KeyCode::Enter => return true, // Build the branch from this plan
+        _ => {}
+    }
+    false
+}
+
+fn handle_diff_key_event(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('v') => app.diff = None,
+        KeyCode::Down | KeyCode::Char('j') => app.scroll_diff(1),
+        KeyCode::Up | KeyCode::Char('k') => app.scroll_diff(-1),
+        KeyCode::PageDown | KeyCode::Char(' ') => app.scroll_diff(10),
+        KeyCode::PageUp => app.scroll_diff(-10),
+        KeyCode::Tab => app.next_file(1),
+        KeyCode::BackTab => app.next_file(-1),
+        KeyCode::Char('s') => app.side_by_side = !app.side_by_side,
+        _ => {}
+    }
+}
+
+/// Below this the TUI asks for a bigger terminal instead of drawing
+const MIN_WIDTH: u16 = 40;
+const MIN_HEIGHT: u16 = 10;
+/// Narrower terminals drop the details pane and shorten the footer
+const NARROW_WIDTH: u16 = 90;
+/// Shorter terminals get a one-line header and footer
+const SHORT_HEIGHT: u16 = 15;
+
+fn render_ui(frame: &mut Frame, app: &mut App) {
+    let area = frame.area();
+    if area.width < MIN_WIDTH || area.height < MIN_HEIGHT {
+        render_too_small(frame, area);
+        return;
+    }
+    let narrow = area.width < NARROW_WIDTH;
+    let short = area.height < SHORT_HEIGHT;
+
+    // Create main layout
+    let chrome = if short { 1 } else { 3 };
+    let main_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(chrome),
+            Constraint::Min(0),
+            Constraint::Length(chrome),
+        ])
+        .split(area);
+
+    let header_area = main_layout[0];
+    let main_area = main_layout[1];
+    let footer_area = main_layout[2];
+
+    // Render header
+    render_header(frame, header_area, app, short);
+
+    // Render main content
+    if app.show_help {
+        render_help(frame, main_area);
+    } else if app.diff.is_some() {
+        render_diff(frame, main_area, app);
+    } else {
+        let dirs = app.show_dirs && app.screen == Screen::Select;
+        let constraints = match (dirs, narrow) {
+            (true, false) => vec![
+                Constraint::Percentage(25),
+                Constraint::Percentage(45),
+                Constraint::Percentage(30),
+            ],
+            (false, false) => vec![Constraint::Percentage(60), Constraint::Percentage(40)],
+            (true, true) => vec![Constraint::Percentage(40), Constraint::Percentage(60)],
+            (false, true) => vec![Constraint::Percentage(100)],
+        };
+        let content_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(constraints)
+            .split(main_area);
+
+        let mut panes = content_layout.iter().copied();
+        if dirs {
+            render_dirs(frame, panes.next().unwrap_or(main_area), app);
+        }
+        let list_area = panes.next().unwrap_or(main_area);
+        if app.screen == Screen::Plan {
+            render_plan(frame, list_area, app);
+        } else {
+            render_commit_list(frame, list_area, app);
+        }
+        // Narrow terminals have no room for details next to the list
+        if let Some(details_area) = panes.next() {
+            render_commit_details(frame, details_area, app);
+        }
+
+        if let Some(message) = &app.editing {
+            render_prompt(
+                frame,
+                main_area,
+                "New subject (Enter to save, Esc to cancel)",
+                message,
+            );
+        }
+        if let Some(pattern) = &app.pattern {
+            let title = format!(
+                "Select commits only touching ({} match, Enter to select, Esc to cancel)",
+                app.matching(pattern).len()
+            );
+            render_prompt(frame, main_area, &title, pattern);
+        }
+        if let Some(title) = &app.titling {
+            render_prompt(
+                frame,
+                main_area,
+                "Pull request title (Enter to save, empty for the usual one, Esc to cancel)",
+                title,
+            );
+        }
+        if let Some(query) = &app.palette {
+            render_palette(
+                frame,
+                main_area,
+                query,
+                &app.palette_matches(),
+                app.palette_cursor,
+            );
+        }
+    }
+
+    // Render footer
+    render_footer(frame, footer_area, app, narrow, short);
+
+    if let Some(error) = &app.error {
+        render_error(frame, main_area, error, app.error_scroll);
+    }
+}
+
+/// Errors from work done with the TUI up, over whatever else is shown
+fn render_error(frame: &mut Frame, area: Rect, error: &str, scroll: usize) {
+    let lines: Vec<Line> = error.lines().map(Line::raw).collect();
+    let total = lines.len();
+    let width = centered_rect(80, 100, area).width;
+    let height = (total as u16 + 2).min(area.height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let panel = Paragraph::new(lines)
+        .scroll((scroll as u16, 0))
+        .wrap(Wrap { trim: false })
+        .block(
+            Block::default()
+                .title("Error (↑/↓ to scroll, y to copy, Esc to close)")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Red)),
+        );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(panel, popup);
+    render_scrollbar(frame, popup, total, scroll);
+}
+
+fn render_too_small(frame: &mut Frame, area: Rect) {
+    let text = vec![
+        Line::styled(
+            "🧔 Terminal too small",
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        ),
+        Line::raw(""),
+        Line::raw(format!(
+            "Needs {}×{}, this is {}×{}.",
+            MIN_WIDTH, MIN_HEIGHT, area.width, area.height
+        )),
+        Line::raw("Make the window bigger, or press q to quit."),
+    ];
+    // Centered when it fits on as many lines as it has, running on down when it wraps
+    let top = area.height.saturating_sub(text.len() as u16) / 2;
+    let message = Rect {
+        y: area.y + top,
+        height: area.height - top,
+        ..area
+    };
+    frame.render_widget(
+        Paragraph::new(text)
+            .alignment(Alignment::Center)
+            .wrap(Wrap { trim: true }),
+        message,
+    );
+}
+
+fn render_header(frame: &mut Frame, area: Rect, app: &App, short: bool) {
+    let selected_count = app.selected_count();
+    let total_count = app.commits.len();
+
+    let title = if app.screen == Screen::Plan {
+        let kept = app
+            .plan
+            .iter()
+            .filter(|s| s.action != PlanAction::Drop)
+            .count();
+        format!(
+            "🧔 Chuck: plan for {} of {} selected commits",
+            kept,
+            app.plan.len()
+        )
+    } else if app.read_only {
+        format!(
+            "🧔 Chuck: reviewing {} commits since template (read-only)",
+            total_count
+        )
+    } else if selected_count > 0 {
+        format!(
+            "🧔 Chuck: {} of {} commits selected",
+            selected_count, total_count
+        )
+    } else {
+        format!("🧔 Chuck: {} commits found since template", total_count)
+    };
+
+    let header = Paragraph::new(title)
+        .style(
+            Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        )
+        .block(Block::default().borders(if short {
+            Borders::NONE
+        } else {
+            Borders::BOTTOM
+        }));
+
+    frame.render_widget(header, area);
+}
+
+/// The list row for `commit`. Owned so it can be cached across frames.
+fn commit_line(commit: &Commit, gutter: Option<&str>) -> Line<'static> {
+    let checkbox = if commit.selected { "✓" } else { " " };
+    let style = if commit.selected {
+        Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default()
+    };
+
+    let mut spans = Vec::new();
+    if let Some(gutter) = gutter {
+        spans.push(Span::styled(
+            gutter.to_string(),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    spans.extend([
+        Span::styled(format!("[{}] ", checkbox), style),
+        Span::styled(
+            commit.short_hash.clone(),
+            Style::default().fg(Color::Yellow),
+        ),
+        Span::raw(" - "),
+    ]);
+    if let Some(ci) = commit.ci {
+        let color = match ci {
+            CiStatus::Passing => Color::Green,
+            CiStatus::Failing => Color::Red,
+            CiStatus::Pending => Color::Yellow,
+        };
+        spans.push(Span::styled(
+            format!("{} ", ci.icon()),
+            Style::default().fg(color),
+        ));
+    }
+    if commit.ignored {
+        spans.push(Span::styled(
+            "✕ ignored ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if commit.mark == Some(Mark::Skip) {
+        spans.push(Span::styled(
+            "✕ skip ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if commit.contributed.is_some() {
+        spans.push(Span::styled(
+            "↑ proposed ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if commit.already_upstream {
+        spans.push(Span::styled(
+            "≡ upstream ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if commit.project_only {
+        spans.push(Span::styled(
+            "⌂ project ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if commit.provenance.is_some_and(|p| p.is_template_fix()) {
+        spans.push(Span::styled(
+            "◇ template ",
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+    if commit.provenance.is_some_and(|p| p.whitespace_only) {
+        spans.push(Span::styled(
+            "␣ whitespace ",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    if let Some(worst) = commit.risks.last() {
+        let labels: Vec<&str> = commit.risks.iter().map(|f| f.risk.label()).collect();
+        let color = if worst.risk.is_severe() {
+            Color::Red
+        } else {
+            Color::Yellow
+        };
+        spans.push(Span::styled(
+            format!("⚠ {} ", labels.join(",")),
+            Style::default().fg(color),
+        ));
+    }
+    if !commit.policy_issues.is_empty() {
+        spans.push(Span::styled(
+            "⛔ policy ",
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+        ));
+    }
+    if commit.is_merge() {
+        spans.push(Span::styled(
+            "⑂ merge ",
+            Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+        ));
+    }
+    let style = if commit.ignored {
+        Style::default().fg(Color::DarkGray)
+    } else {
+        style
+    };
+    spans.push(Span::styled(commit.message.clone(), style));
+    Line::from(spans)
+}
+
+fn render_commit_list(frame: &mut Frame, area: Rect, app: &mut App) {
+    // Only build rows for the visible window so thousands of commits stay cheap
+    let height = area.height.saturating_sub(2) as usize;
+    let rows = app.shown_commits();
+    let selected = app
+        .list_state
+        .selected()
+        .and_then(|i| rows.iter().position(|&row| row == i));
+    // No blank rows at the bottom when the filter or terminal leaves fewer to show
+    app.list_offset = app.list_offset.min(rows.len().saturating_sub(height));
+    if let Some(selected) = selected {
+        if selected < app.list_offset {
+            app.list_offset = selected;
+        } else if height > 0 && selected >= app.list_offset + height {
+            app.list_offset = selected + 1 - height;
+        }
+    }
+    let start = app.list_offset.min(rows.len());
+    let end = (start + height).min(rows.len());
+
+    let items: Vec<ListItem> = rows[start..end]
+        .iter()
+        .map(|&i| ListItem::new(app.cached_line(i)))
+        .collect();
+    let mut window_state =
+        ListState::default().with_selected(selected.and_then(|i| i.checked_sub(start)));
+
+    let title = if app.dir_filter.is_empty() {
+        "Commits".to_string()
+    } else {
+        format!(
+            "Commits touching {} ({} of {})",
+            app.dir_filter
+                .iter()
+                .map(|dir| format!("{}/", dir))
+                .collect::<Vec<_>>()
+                .join(", "),
+            rows.len(),
+            app.commits.len()
+        )
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("► ");
+
+    frame.render_stateful_widget(list, area, &mut window_state);
+    render_scrollbar(frame, area, rows.len(), start);
+}
+
+/// Scrollbar on the right border of the bordered list in `area`, which shows `total`
+/// rows from `offset` on. Nothing when they all fit or there's no room for one.
+fn render_scrollbar(frame: &mut Frame, area: Rect, total: usize, offset: usize) {
+    let track = area.inner(Margin {
+        vertical: 1,
+        horizontal: 0,
+    });
+    let visible = track.height as usize;
+    // The arrows take a row each and the thumb needs one
+    if total <= visible || track.height < 3 {
+        return;
+    }
+    let mut state = ScrollbarState::new(total - visible + 1)
+        .viewport_content_length(visible)
+        .position(offset.min(total - visible));
+    let scrollbar = Scrollbar::default()
+        .orientation(ScrollbarOrientation::VerticalRight)
+        .begin_symbol(Some("↑"))
+        .end_symbol(Some("↓"));
+    frame.render_stateful_widget(scrollbar, track, &mut state);
+}
+
+fn render_dirs(frame: &mut Frame, area: Rect, app: &mut App) {
+    let items: Vec<ListItem> = app
+        .dirs
+        .iter()
+        .map(|dir| {
+            let chosen = app.dir_filter.contains(&dir.path);
+            let line = format!(
+                "{}{} {}/ ({})",
+                "  ".repeat(dir.depth),
+                if chosen { "[x]" } else { "[ ]" },
+                dir.name(),
+                dir.commits
+            );
+            let style = if chosen {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Line::styled(line, style))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Directories")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("► ");
+
+    frame.render_stateful_widget(list, area, &mut app.dir_state);
+    render_scrollbar(frame, area, app.dirs.len(), app.dir_state.offset());
+}
+
+fn render_plan(frame: &mut Frame, area: Rect, app: &mut App) {
+    let items: Vec<ListItem> = app
+        .plan
+        .iter()
+        .map(|step| {
+            let commit = &app.commits[step.commit];
+            let message = if step.action == PlanAction::Reword {
+                &step.message
+            } else {
+                &commit.message
+            };
+            let message_style = if step.action == PlanAction::Drop {
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::CROSSED_OUT)
+            } else {
+                Style::default()
+            };
+
+            let mut spans = vec![
+                Span::styled(
+                    format!("{:<7}", step.action.label()),
+                    Style::default()
+                        .fg(step.action.color())
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(&commit.short_hash, Style::default().fg(Color::Yellow)),
+                Span::raw(" "),
+                Span::styled(message.as_str(), message_style),
+            ];
+            let deleted = commit.deleted_template_files.len();
+            if deleted > 0 && step.action != PlanAction::Drop {
+                spans.push(if step.keep_template_files {
+                    Span::styled(
+                        format!(" (keeps {} template files)", deleted),
+                        Style::default().fg(Color::Cyan),
+                    )
+                } else {
+                    Span::styled(
+                        format!(" ⚠ deletes {} template files", deleted),
+                        Style::default().fg(Color::Yellow),
+                    )
+                });
+            }
+
+            if !step.leave_out.is_empty() && step.action != PlanAction::Drop {
+                spans.push(Span::styled(
+                    format!(" (leaves out {} LFS files)", step.leave_out.len()),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+
+            if !commit.split_off.is_empty() && step.action != PlanAction::Drop {
+                spans.push(Span::styled(
+                    format!(" (splits off {} project files)", commit.split_off.len()),
+                    Style::default().fg(Color::Cyan),
+                ));
+            }
+
+            ListItem::new(Line::from(spans))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .title("Plan (applied top to bottom)")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .highlight_style(
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )
+        .highlight_symbol("► ");
+
+    // The plan itself matters more than its size on short terminals
+    let review_size = if area.height >= 10 { 5 } else { 0 };
+    let layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(review_size)])
+        .split(area);
+    frame.render_stateful_widget(list, layout[0], &mut app.plan_state);
+    render_scrollbar(frame, layout[0], app.plan.len(), app.plan_state.offset());
+    if review_size > 0 {
+        render_review_size(frame, layout[1], app);
+    }
+}
+
+/// Files, lines and areas the plan touches, and whether that's more than the
+/// template likes to review at once
+fn render_review_size(frame: &mut Frame, area: Rect, app: &App) {
+    let estimate = app.estimate();
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let mut summary = format!(
+        "{} file{} · {} area{}",
+        estimate.files,
+        plural(estimate.files),
+        estimate.areas,
+        plural(estimate.areas)
+    );
+    if let (Some(changed), Some(time)) = (estimate.changed, estimate.review_time()) {
+        summary = format!(
+            "{} · +{} −{} lines · {} to review",
+            summary, changed.added, changed.removed, time
+        );
+    }
+    let mut lines = vec![Line::from(summary)];
+    let warnings = app.soft_limits.warnings(&estimate);
+    if !warnings.is_empty() {
+        lines.push(Line::styled(
+            format!(
+                "⚠ {}, consider splitting the selection",
+                warnings.join(", ")
+            ),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    let paragraph = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
+        Block::default()
+            .title("Review size")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Gray)),
+    );
+    frame.render_widget(paragraph, area);
+}
+
+/// A one-line text input over `area`
+fn render_prompt(frame: &mut Frame, area: Rect, title: &str, text: &str) {
+    let input = Paragraph::new(format!("{}▏", text))
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: false });
+
+    // One line of input between the borders, whatever the terminal height
+    let width = centered_rect(80, 100, area).width;
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + area.height.saturating_sub(3) / 2,
+        width,
+        height: area.height.min(3),
+    };
+    frame.render_widget(Clear, popup);
+    frame.render_widget(input, popup);
+}
+
+/// The `:` palette: what's typed, then the commands matching it
+fn render_palette(
+    frame: &mut Frame,
+    area: Rect,
+    query: &str,
+    matches: &[PaletteCommand],
+    cursor: usize,
+) {
+    let mut lines = vec![Line::raw(format!("{}▏", query)), Line::raw("")];
+    if matches.is_empty() {
+        lines.push(Line::styled(
+            "No command matches",
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    for (i, command) in matches.iter().enumerate() {
+        if i == cursor {
+            lines.push(Line::styled(
+                format!("► {}", command.name()),
+                Style::default().add_modifier(Modifier::REVERSED),
+            ));
+        } else {
+            lines.push(Line::raw(format!("  {}", command.name())));
+        }
+    }
+
+    let width = centered_rect(60, 100, area).width;
+    let height = (lines.len() as u16 + 2).min(area.height);
+    // Keeps the cursor in view when the matches don't fit
+    let scroll = (cursor as u16 + 5).saturating_sub(height);
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let palette = Paragraph::new(lines).scroll((scroll, 0)).block(
+        Block::default()
+            .title("Command (↑/↓ to choose, Enter to run, Esc to cancel)")
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Cyan)),
+    );
+    frame.render_widget(Clear, popup);
+    frame.render_widget(palette, popup);
+}
+
+fn render_commit_details(frame: &mut Frame, area: Rect, app: &App) {
+    let content = if let Some(commit) = app.current_commit() {
+        let mut text = vec![
+            Line::from(vec![
+                Span::styled("Hash: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(&commit.hash, Style::default().fg(Color::Yellow)),
+            ]),
+            Line::from(vec![
+                Span::styled("Author: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(&commit.author),
+            ]),
+            Line::from(vec![
+                Span::styled("Date: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(&commit.date),
+            ]),
+        ];
+
+        if let Some(signature) = &commit.signature {
+            let color = match signature {
+                Signature::Verified(_) => Color::Green,
+                Signature::Invalid(..) | Signature::Bad(_) => Color::Red,
+                _ => Color::Gray,
+            };
+            text.push(Line::from(vec![
+                Span::styled("Signature: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(signature.describe(), Style::default().fg(color)),
+            ]));
+        }
+
+        if let Some(provenance) = commit.provenance {
+            text.push(Line::from(vec![
+                Span::styled(
+                    "Provenance: ",
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+                Span::raw(provenance.describe()),
+            ]));
+        }
+
+        if let Some(ci) = commit.ci {
+            let color = if ci == CiStatus::Failing {
+                Color::Red
+            } else {
+                Color::Gray
+            };
+            text.push(Line::from(vec![
+                Span::styled("CI: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::styled(ci.describe(), Style::default().fg(color)),
+            ]));
+        }
+
+        if commit.ignored {
+            text.push(Line::from(vec![Span::styled(
+                "Ignored: won't be suggested again (x to take it back)",
+                Style::default().fg(Color::DarkGray),
+            )]));
+        }
+
+        if let Some(mark) = commit.mark {
+            text.push(Line::from(vec![
+                Span::styled("Trailer: ", Style::default().add_modifier(Modifier::BOLD)),
+                Span::raw(format!("{}: {}", trailer::KEY, mark.value())),
+            ]));
+        }
+
+        if let Some(contribution) = &commit.contributed {
+            text.push(Line::from(vec![Span::styled(
+                format!(
+                    "Already proposed on {} by {}, {}",
+                    contribution.branch,
+                    contribution.by,
+                    contribution.contributed_at.format("%Y-%m-%d")
+                ),
+                Style::default().fg(Color::DarkGray),
+            )]));
+        }
+
+        if commit.already_upstream {
+            text.push(Line::from(vec![Span::styled(
+                "Upstream already has this (same patch-id on the template branch)",
+                Style::default().fg(Color::DarkGray),
+            )]));
+        }
+
+        if commit.project_only {
+            text.push(Line::from(vec![Span::styled(
+                "Only touches project-managed files (per .chuck-manifest.toml)",
+                Style::default().fg(Color::DarkGray),
+            )]));
+        }
+
+        if !commit.deleted_template_files.is_empty() {
+            text.push(Line::from(vec![Span::styled(
+                format!(
+                    "Deletes files the template still has: {} (t in the plan keeps them)",
+                    commit.deleted_template_files.join(", ")
+                ),
+                Style::default().fg(Color::Yellow),
+            )]));
+        }
+
+        if !commit.split_off.is_empty() {
+            text.push(Line::from(vec![Span::styled(
+                format!(
+                    "Goes upstream without the project's {}",
+                    commit.split_off.join(", ")
+                ),
+                Style::default().fg(Color::Cyan),
+            )]));
+        }
+
+        for finding in &commit.risks {
+            text.push(Line::from(vec![Span::styled(
+                format!("{}: {}", finding.risk.describe(), finding.paths.join(", ")),
+                Style::default().fg(if finding.risk.is_severe() {
+                    Color::Red
+                } else {
+                    Color::Yellow
+                }),
+            )]));
+        }
+
+        for issue in &commit.policy_issues {
+            text.push(Line::from(vec![Span::styled(
+                format!("Template policy: {}", issue),
+                Style::default().fg(Color::Red),
+            )]));
+        }
+
+        if commit.is_merge() {
+            text.push(Line::from(vec![Span::styled(
+                format!(
+                    "Merge commit ({} parents) - applied against its mainline parent",
+                    commit.parents.len()
+                ),
+                Style::default().fg(Color::Magenta),
+            )]));
+        }
+
+        text.extend([
+            Line::raw(""),
+            Line::from(vec![Span::styled(
+                "Message:",
+                Style::default().add_modifier(Modifier::BOLD),
+            )]),
+            Line::raw(&commit.message),
+            Line::raw(""),
+        ]);
+
+        if !commit.files.is_empty() {
+            text.push(Line::from(vec![Span::styled(
+                "Files:",
+                Style::default().add_modifier(Modifier::BOLD),
+            )]));
+
+            for file in &commit.files {
+                let mut line = vec![
+                    Span::raw(format!("  {} ", file.status)),
+                    Span::styled(file.display(), Style::default().fg(Color::Cyan)),
+                ];
+                if let Some(template_files) = &app.template_files {
+                    let upstream = file.upstream(template_files);
+                    let color = match upstream {
+                        Upstream::Exists => Color::Green,
+                        Upstream::New => Color::Blue,
+                        Upstream::Missing => Color::Red,
+                    };
+                    line.push(Span::styled(
+                        format!("  {}", upstream.badge()),
+                        Style::default().fg(color),
+                    ));
+                }
+                text.push(Line::from(line));
+            }
+        }
+
+        Text::from(text)
+    } else {
+        Text::from("No commit selected")
+    };
+
+    let details = Paragraph::new(content)
+        .block(
+            Block::default()
+                .title("Details")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .wrap(Wrap { trim: true });
+
+    frame.render_widget(details, area);
+}
+
+/// The current file as the template has it against after the pick. Changed words are
+/// highlighted and changed whitespace is drawn (`·` space, `→` tab, `␍` carriage
+/// return, `⏎` newline), so changes that are otherwise invisible show up.
+fn render_diff(frame: &mut Frame, area: Rect, app: &App) {
+    let Some(view) = &app.diff else {
+        return;
+    };
+    let commit = &app.commits[view.commit];
+    let title = format!(
+        "{} ({}/{}) - template vs after picking {}",
+        commit.files[view.file].display(),
+        view.file + 1,
+        commit.files.len(),
+        commit.short_hash
+    );
+
+    let mut lines = Vec::new();
+    match &view.preview {
+        Ok(preview) => {
+            if let Some(note) = preview.note {
+                lines.push(Line::styled(note, Style::default().fg(Color::Yellow)));
+            }
+            let width = area.width.saturating_sub(2) as usize;
+            if app.side_by_side {
+                lines.extend(preview.rows.iter().map(|row| side_by_side_line(row, width)));
+            } else {
+                lines.extend(interleaved_lines(&preview.rows));
+            }
+        }
+        Err(e) => lines.push(Line::styled(
+            format!("Couldn't compare with the template: {}", e),
+            Style::default().fg(Color::Red),
+        )),
+    }
+
+    let diff = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Gray)),
+        )
+        .scroll((view.scroll.min(u16::MAX as usize) as u16, 0));
+    frame.render_widget(diff, area);
+}
+
+/// Unchanged lines once, changed ones as the template's lines followed by the pick's
+fn interleaved_lines(rows: &[preview::Row]) -> Vec<Line<'static>> {
+    let mut lines = Vec::new();
+    let mut i = 0;
+    while i < rows.len() {
+        if rows[i].is_same() {
+            let side = rows[i].after.as_ref();
+            lines.push(Line::from(diff_side(side, ' ', Color::Reset, None)));
+            i += 1;
+            continue;
+        }
+        let end = (i..rows.len())
+            .find(|&k| rows[k].is_same())
+            .unwrap_or(rows.len());
+        for row in &rows[i..end] {
+            if row.before.is_some() {
+                lines.push(Line::from(diff_side(
+                    row.before.as_ref(),
+                    '-',
+                    Color::Red,
+                    None,
+                )));
+            }
+        }
+        for row in &rows[i..end] {
+            if row.after.is_some() {
+                lines.push(Line::from(diff_side(
+                    row.after.as_ref(),
+                    '+',
+                    Color::Green,
+                    None,
+                )));
+            }
+        }
+        i = end;
+    }
+    lines
+}
+
+/// The template's line on the left, the pick's on the right, each `width / 2` wide
+fn side_by_side_line(row: &preview::Row, width: usize) -> Line<'static> {
+    let half = width.saturating_sub(3) / 2;
+    let (before, after) = if row.is_same() {
+        (Color::Reset, Color::Reset)
+    } else {
+        (Color::Red, Color::Green)
+    };
+    let mut spans = diff_side(row.before.as_ref(), ' ', before, Some(half));
+    spans.push(Span::styled(" │ ", Style::default().fg(Color::DarkGray)));
+    spans.extend(diff_side(row.after.as_ref(), ' ', after, None));
+    Line::from(spans)
+}
+
+/// A line number, `sign` and the line's segments, padded or cut to `width` if given
+fn diff_side(
+    side: Option<&preview::Side>,
+    sign: char,
+    color: Color,
+    width: Option<usize>,
+) -> Vec<Span<'static>> {
+    let Some(side) = side else {
+        return vec![Span::raw(" ".repeat(width.unwrap_or(0)))];
+    };
+    let line = Style::default().fg(color);
+    let mut spans = vec![Span::styled(
+        format!("{:>4}{}", side.number, sign),
+        Style::default().fg(Color::DarkGray),
+    )];
+    let mut left = width.map(|w| w.saturating_sub(5));
+    for segment in &side.segments {
+        let mut text: String = if segment.changed {
+            segment
+                .text
+                .chars()
+                .map(|c| match c {
+                    ' ' => '·',
+                    '\t' => '→',
+                    '\r' => '␍',
+                    '\n' => '⏎',
+                    c => c,
+                })
+                .collect()
+        } else {
+            segment.text.replace('\t', "    ").replace('\r', "")
+        };
+        if let Some(left) = &mut left {
+            text = text.chars().take(*left).collect();
+            *left -= text.chars().count();
+        }
+        let style = if segment.changed {
+            line.add_modifier(Modifier::REVERSED)
+        } else {
+            line
+        };
+        spans.push(Span::styled(text, style));
+    }
+    if let Some(left) = left {
+        spans.push(Span::raw(" ".repeat(left)));
+    }
+    spans
+}
+
+fn render_footer(frame: &mut Frame, area: Rect, app: &App, narrow: bool, short: bool) {
+    let help_text = if let Some(notice) = &app.notice {
+        notice.as_str()
+    } else if narrow {
+        // Only what it takes to get around, the help screen has the rest
+        if app.show_help {
+            "h/?: close help"
+        } else if app.diff.is_some() {
+            "j/k: scroll │ Tab: file │ Esc: back"
+        } else if app.screen == Screen::Plan {
+            "j/k │ p/s/r/d │ Enter: build │ Esc: back"
+        } else if app.show_dirs {
+            "j/k │ Space: filter │ t/Esc: back"
+        } else if app.read_only {
+            "j/k │ v: diff │ h/?: help │ q: quit"
+        } else {
+            "j/k │ Space │ Enter: proceed │ h/?: help │ q: quit"
+        }
+    } else if app.show_help {
+        "Press 'h' or '?' to close help"
+    } else if app.diff.is_some() {
+        "j/k: scroll │ Tab/Shift+Tab: next/previous file │ s: side by side │ Esc: back"
+    } else if app.screen == Screen::Plan {
+        "j/k: navigate │ p: pick │ s: squash │ r: reword │ d: drop │ J/K: move │ Enter: build branch │ Esc: back │ q: quit"
+    } else if app.show_dirs {
+        "j/k: navigate │ Space: filter by directory │ c: clear filter │ t/Esc: back to commits │ q: quit"
+    } else if app.read_only {
+        "↑/↓/j/k: navigate │ v: diff │ g: graph │ t: directories │ h/?: help │ q: quit"
+    } else {
+        "↑/↓/j/k: navigate │ Space: toggle │ a: all │ n: none │ i: invert │ f: by path │ h/?: help │ Enter: proceed │ q: quit"
+    };
+
+    let footer = Paragraph::new(help_text)
+        .style(Style::default().fg(Color::Gray))
+        .block(Block::default().borders(if short { Borders::NONE } else { Borders::TOP }));
+
+    frame.render_widget(footer, area);
+}
+
+fn render_help(frame: &mut Frame, area: Rect) {
+    let help_text = Text::from(vec![
+        Line::from(vec![Span::styled(
+            "Chuck - Interactive Commit Selection",
+            Style::default()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Cyan),
+        )]),
+        Line::raw(""),
+        Line::from(vec![Span::styled(
+            "Navigation:",
+            Style::default().add_modifier(Modifier::BOLD),
+        )]),
+        Line::raw("  ↑/↓ or j/k    Move cursor up/down"),
+        Line::raw("  Space         Toggle commit selection"),
+        Line::raw("  Enter         Review the plan for the selected commits"),
+        Line::raw("  g             Show/hide the commit graph"),
+        Line::raw("  t             List only commits touching chosen directories"),
+        Line::raw("  v             Compare the commit's files with the template"),
+        Line::raw("  y             Copy the commit's hash to the clipboard"),
+        Line::raw("  :             Find any action by name, like sorting or the PR title"),
+        Line::raw(""),
+        Line::from(vec![Span::styled(
+            "Selection:",
+            Style::default().add_modifier(Modifier::BOLD),
+        )]),
+        Line::raw("  a             Select all commits"),
+        Line::raw("  n             Select none (clear all)"),
+        Line::raw("  i             Invert selection"),
+        Line::raw("  f             Select commits only touching paths matching a glob"),
+        Line::raw("  x             Never suggest this commit again"),
+        Line::raw("  m/M           Tag HEAD \"Chuck: upstream\"/\"Chuck: skip\" for next time"),
+        Line::raw("  Y             Copy the selected commits' hashes"),
+        Line::raw(""),
+        Line::from(vec![Span::styled(
+            "Plan:",
+            Style::default().add_modifier(Modifier::BOLD),
+        )]),
+        Line::raw("  p/s/r/d       Pick, squash into previous, reword, drop"),
+        Line::raw("  J/K           Move a commit down/up"),
+        Line::raw("  t             Keep template files a commit deletes"),
+        Line::raw("  Enter         Build the branch from the plan"),
+        Line::raw("  Esc           Back to selection"),
+        Line::raw(""),
+        Line::from(vec![Span::styled(
+            "Diff:",
+            Style::default().add_modifier(Modifier::BOLD),
+        )]),
+        Line::raw("  Tab/Shift+Tab Next/previous file"),
+        Line::raw("  s             Side by side or interleaved"),
+        Line::raw(""),
+        Line::from(vec![Span::styled(
+            "Other:",
+            Style::default().add_modifier(Modifier::BOLD),
+        )]),
+        Line::raw("  h or ?        Toggle this help"),
+        Line::raw("  q or Esc      Quit application"),
+        Line::raw("  Ctrl+C        Force quit"),
+        Line::raw(""),
+        Line::from(vec![Span::styled(
+            "About:",
+            Style::default().add_modifier(Modifier::BOLD),
+        )]),
+        Line::raw("Chuck helps you select commits to contribute back to"),
+        Line::raw("your template repository. Select the commits you want"),
+        Line::raw("to share, then press Enter to create a branch and PR."),
+    ]);
+
+    let help_popup = Paragraph::new(help_text)
+        .block(
+            Block::default()
+                .title("Help")
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan)),
+        )
+        .wrap(Wrap { trim: true });
+
+    // Center the help popup
+    let popup_area = centered_rect(80, 80, area);
+    frame.render_widget(Clear, popup_area);
+    frame.render_widget(help_popup, popup_area);
+}
+
+fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage((100 - percent_y) / 2),
+            Constraint::Percentage(percent_y),
+            Constraint::Percentage((100 - percent_y) / 2),
+        ])
+        .split(r);
+
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage((100 - percent_x) / 2),
+            Constraint::Percentage(percent_x),
+            Constraint::Percentage((100 - percent_x) / 2),
+        ])
+        .split(popup_layout[1])[1]
+}
+
+// Keep all the existing business logic functions unchanged
+fn read_chuck_config(path: &std::path::Path) -> Result<ChuckConfig> {
+    let config_content =
+        fs::read_to_string(path).map_err(|_| anyhow!("No {} file found", path.display()))?;
+
+    let config: ChuckConfig = toml::from_str(&config_content)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))?;
+
+    Ok(config)
+}
+
+fn read_global_config() -> Result<GlobalConfig> {
+    let _phase = profile::phase("config load");
+    let Some(path) = paths::config_file() else {
+        return Ok(GlobalConfig::default());
+    };
+    let Ok(config_content) = fs::read_to_string(&path) else {
+        return Ok(GlobalConfig::default());
+    };
+
+    toml::from_str(&config_content)
+        .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Hashes of the commits `chuck bot` contributes: those the rules match, out of the
+/// ones `a` would select in the TUI
+fn bot_selection(commits: &[Commit], rules: &BotRules, verified_only: bool) -> Result<Vec<String>> {
+    let eligible: Vec<&Commit> = commits
+        .iter()
+        .filter(|c| c.bulk_selectable() && (!verified_only || c.is_verified()))
+        .collect();
+    let hashes: Vec<&str> = eligible.iter().map(|c| c.hash.as_str()).collect();
+    let meta = bot::commit_meta(&hashes)?;
+
+    Ok(eligible
+        .into_iter()
+        .filter(|c| {
+            let files: Vec<&str> = c.files.iter().map(|f| f.path.as_str()).collect();
+            meta.get(&c.hash)
+                .is_some_and(|meta| rules.matches(&files, meta))
+        })
+        .map(|c| c.hash.clone())
+        .collect())
+}
+
+/// `--action`: lists what the project could contribute instead of contributing it
+fn run_action(cli: &Cli, commits: &[Commit], repo: &str, template: &str) -> Result<Code> {
+    let mut skipped = action::Skipped::default();
+    let mut candidates = Vec::new();
+    for commit in commits.iter().filter(|c| !c.ignored) {
+        if commit.already_upstream {
+            skipped.upstream += 1;
+            continue;
+        }
+        if commit.contributed.is_some() {
+            skipped.proposed += 1;
+            continue;
+        }
+        if commit.project_only {
+            skipped.project_only += 1;
+            continue;
+        }
+
+        candidates.push(action::Candidate {
+            short_hash: commit.short_hash.clone(),
+            message: commit.message.clone(),
+            author: commit.author.clone(),
+            files: commit.files.len(),
+            notes: commit_notes(commit),
+        });
+    }
+
+    let markdown = action::summary(repo, template, &candidates, &skipped);
+    action::write_job_summary(&markdown)
+        .map_err(|e| anyhow!("🧔 \"Couldn't write it down\": {}", e))?;
+    action::write_output(candidates.len())
+        .map_err(|e| anyhow!("🧔 \"Couldn't write it down\": {}", e))?;
+    println!("🧔 {} commits could go upstream", candidates.len());
+
+    if cli.tracking_issue {
+        match action::update_tracking_issue(template, repo, &markdown, !candidates.is_empty()) {
+            Ok(Some(url)) => println!("🧔 Updated the tracking issue: {}", url),
+            Ok(None) => {}
+            Err(e) => return Err(anyhow!("🧔 \"Couldn't leave a note\": {}", e)),
+        }
+    }
+
+    Ok(Code::Success)
+}
+
+/// What's worth knowing about a commit before contributing it
+fn commit_notes(commit: &Commit) -> Vec<String> {
+    let mut notes = Vec::new();
+    if commit.is_merge() {
+        notes.push("merge".to_string());
+    }
+    if commit.provenance.is_some_and(|p| p.is_template_fix()) {
+        notes.push("likely a template fix".to_string());
+    }
+    if commit.provenance.is_some_and(|p| p.whitespace_only) {
+        notes.push("only changes whitespace".to_string());
+    }
+    if !commit.policy_issues.is_empty() {
+        notes.push(format!("policy: {}", commit.policy_issues.join("; ")));
+    }
+    if !commit.risks.is_empty() {
+        let labels: Vec<&str> = commit.risks.iter().map(|f| f.risk.label()).collect();
+        notes.push(format!("changes {}", labels.join(", ")));
+    }
+    if !commit.split_off.is_empty() {
+        notes.push(format!(
+            "splits off {} project files",
+            commit.split_off.len()
+        ));
+    }
+    if !commit.deleted_template_files.is_empty() {
+        notes.push(format!(
+            "deletes {} template files",
+            commit.deleted_template_files.len()
+        ));
+    }
+    notes
+}
+
+/// `commit_notes`, plus why --action would have left the commit out
+fn candidate_notes(commit: &Commit) -> Vec<String> {
+    let mut notes = Vec::new();
+    if commit.already_upstream {
+        notes.push("already upstream".to_string());
+    }
+    if let Some(contributed) = &commit.contributed {
+        notes.push(format!(
+            "already proposed by {} on {} from {}",
+            contributed.by,
+            contributed.contributed_at.format("%Y-%m-%d"),
+            contributed.branch
+        ));
+    }
+    if commit.project_only {
+        notes.push("only project-managed files".to_string());
+    }
+    if commit.ignored {
+        notes.push("ignored".to_string());
+    }
+    notes.extend(commit_notes(commit));
+    notes
+}
+
+/// `chuck serve --stdio`: each request runs chuck again, with its output forwarded
+/// as "log" notifications, so nothing but JSON reaches stdout
+fn run_serve(cli: &Cli) -> Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    serve::serve(stdin.lock(), &mut stdout, |method, params, notifier| {
+        let param = |name: &str| params.get(name).and_then(Value::as_str);
+        let mut args: Vec<String> = Vec::new();
+        if cli.verbose {
+            args.push("--verbose".to_string());
+        }
+        for name in ["workspace", "base", "target", "author", "from", "range"] {
+            if let Some(value) = param(name) {
+                args.push(format!("--{}", name));
+                args.push(value.to_string());
+            }
+        }
+
+        match method {
+            "initialize" => Ok(serde_json::json!({
+                "name": "chuck",
+                "version": env!("CARGO_PKG_VERSION"),
+                "methods": ["discover", "execute", "shutdown"],
+            })),
+            "discover" => {
+                let path = journal::chuck_dir()
+                    .map_err(serve::RpcError::failed)?
+                    .join("candidates.json");
+                for flag in ["show_ignored", "since_template"] {
+                    if params.get(flag).and_then(Value::as_bool) == Some(true) {
+                        args.push(format!("--{}", flag.replace('_', "-")));
+                    }
+                }
+                args.push("--candidates".to_string());
+                args.push(path.display().to_string());
+                match run_child(&args, notifier)? {
+                    (Some(0), _) => {}
+                    (Some(5), _) => return Ok(serde_json::json!({ "commits": [] })),
+                    (_, error) => return Err(serve::RpcError::failed(error)),
+                }
+                let discovered = fs::read_to_string(&path).map_err(serve::RpcError::failed)?;
+                let _ = fs::remove_file(&path);
+                serde_json::from_str(&discovered).map_err(serve::RpcError::failed)
+            }
+            "execute" => {
+                let select: Vec<&str> = params
+                    .get("select")
+                    .and_then(Value::as_array)
+                    .map(|shas| shas.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                if select.is_empty() {
+                    return Err(serve::RpcError::new(
+                        serve::INVALID_PARAMS,
+                        "execute needs the SHAs to contribute in \"select\"",
+                    ));
+                }
+                for (name, on, off) in [
+                    ("squash", "--squash", "--no-squash"),
+                    ("draft", "--draft", "--no-draft"),
+                ] {
+                    if let Some(value) = params.get(name).and_then(Value::as_bool) {
+                        args.push(if value { on } else { off }.to_string());
+                    }
+                }
+                if params.get("push_public").and_then(Value::as_bool) == Some(true) {
+                    args.push("--push-public".to_string());
+                }
+                args.push("--select".to_string());
+                args.push(select.join(","));
+                let before = journal::read_all().map(|j| j.len()).unwrap_or_default();
+                let (code, error) = run_child(&args, notifier)?;
+                // The run's journal entry says which branch it pushed
+                let entry = journal::read_all()
+                    .ok()
+                    .filter(|journal| journal.len() > before)
+                    .and_then(|mut journal| journal.pop());
+                Ok(serde_json::json!({
+                    "exit_code": code,
+                    "error": (!error.is_empty()).then_some(error),
+                    "branch": entry.as_ref().and_then(|e| e.branch.clone()),
+                    "remote_branch": entry.and_then(|e| e.remote_branch),
+                }))
+            }
+            _ => Err(serve::RpcError::new(
+                serve::METHOD_NOT_FOUND,
+                format!("No method {}", method),
+            )),
+        }
+    })?;
+    Ok(())
+}
+
+/// Runs this chuck with `args` in the background, sending each line it prints as a
+/// "log" notification. Returns its exit code and the error it ended with.
+fn run_child(
+    args: &[String],
+    notifier: &mut serve::Notifier,
+) -> Result<(Option<i32>, String), serve::RpcError> {
+    // A run can take as long as it takes, the child applies the timeouts itself
+    let mut child = Command::new(std::env::current_exe().map_err(serve::RpcError::failed)?)
+        .args(args)
+        .stdin(std::process::Stdio::null())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .map_err(serve::RpcError::failed)?;
+
+    let stderr = child
+        .stderr
+        .take()
+        .map(|stderr| std::thread::spawn(move || io::read_to_string(stderr).unwrap_or_default()));
+    if let Some(stdout) = child.stdout.take() {
+        for line in io::BufRead::lines(io::BufReader::new(stdout)).map_while(Result::ok) {
+            notifier
+                .notify("log", serde_json::json!({ "line": line }))
+                .map_err(serve::RpcError::failed)?;
+        }
+    }
+    let status = child.wait().map_err(serve::RpcError::failed)?;
+    let stderr = stderr
+        .and_then(|thread| thread.join().ok())
+        .unwrap_or_default();
+    let error = stderr.trim().trim_start_matches("Error: ").to_string();
+    Ok((status.code(), error))
 }
 
-fn render_help(frame: &mut Frame, area: Rect) {
-    let help_text = Text::from(vec![
-        Line::from(vec![Span::styled(
-            "Chuck - Interactive Commit Selection",
-            Style::default()
-                .add_modifier(Modifier::BOLD)
-                .fg(Color::Cyan),
-        )]),
-        Line::raw(""),
-        Line::from(vec![Span::styled(
-            "Navigation:",
-            Style::default().add_modifier(Modifier::BOLD),
-        )]),
-        Line::raw("  ↑/↓ or j/k    Move cursor up/down"),
-        Line::raw("  Space         Toggle commit selection"),
-        Line::raw("  Enter         Proceed with selected commits"),
-        Line::raw(""),
-        Line::from(vec![Span::styled(
-            "Selection:",
-            Style::default().add_modifier(Modifier::BOLD),
-        )]),
-        Line::raw("  a             Select all commits"),
-        Line::raw("  n             Select none (clear all)"),
-        Line::raw("  i             Invert selection"),
-        Line::raw(""),
-        Line::from(vec![Span::styled(
-            "Other:",
-            Style::default().add_modifier(Modifier::BOLD),
-        )]),
-        Line::raw("  h or ?        Toggle this help"),
-        Line::raw("  q or Esc      Quit application"),
-        Line::raw("  Ctrl+C        Force quit"),
-        Line::raw(""),
-        Line::from(vec![Span::styled(
-            "About:",
-            Style::default().add_modifier(Modifier::BOLD),
-        )]),
-        Line::raw("Chuck helps you select commits to contribute back to"),
-        Line::raw("your template repository. Select the commits you want"),
-        Line::raw("to share, then press Enter to create a branch and PR."),
-    ]);
+/// `chuck paths`: where everything Chuck reads and writes on disk is, as configured
+fn print_paths() {
+    let show = |name: &str, path: Option<std::path::PathBuf>, missing: &str| match path {
+        Some(path) => println!(
+            "{:<8} {}",
+            name,
+            std::path::absolute(&path).unwrap_or(path).display()
+        ),
+        None => println!("{:<8} ({})", name, missing),
+    };
+    show("config", paths::config_file(), "no home directory");
+    let git_dir = journal::git_dir().ok();
+    let outside = "not in a git repository";
+    show("state", git_dir.as_deref().map(paths::state_dir), outside);
+    show("journal", journal::journal_path().ok(), outside);
+    show("cache", git_dir.as_deref().map(paths::cache_dir), outside);
+    show("temp", Some(paths::temp_dir()), "");
+}
+
+fn run_doctor() -> Code {
+    let template = find_template_config("")
+        .and_then(|config| {
+            let repo = extract_repo_name_from_url(&config.template.url)?;
+            Ok(doctor::Template {
+                url: config.template.url,
+                repo,
+            })
+        })
+        .map_err(|e| e.to_string());
+    println!();
+
+    if doctor::run(template) == 0 {
+        Code::Success
+    } else {
+        Code::Error
+    }
+}
+
+fn extract_repo_name_from_url(url: &str) -> Result<String> {
+    if url.starts_with("git@github.com:") {
+        let without_prefix = url.strip_prefix("git@github.com:").unwrap();
+        let without_suffix = without_prefix
+            .strip_suffix(".git")
+            .unwrap_or(without_prefix);
+        Ok(without_suffix.to_string())
+    } else if url.starts_with("https://github.com/") {
+        let without_prefix = url.strip_prefix("https://github.com/").unwrap();
+        let without_suffix = without_prefix
+            .strip_suffix(".git")
+            .unwrap_or(without_prefix);
+        Ok(without_suffix.to_string())
+    } else {
+        Err(anyhow!("Unsupported repository URL format: {}", url))
+    }
+}
+
+/// Remote names that conventionally point at the project a repo was derived from
+const TEMPLATE_REMOTE_NAMES: [&str; 3] = ["template", "upstream", "chuck-template"];
+
+/// The template for the workspace in `dir`, from its .chuckrc or, for the repository
+/// root, a conventionally named remote
+fn find_template_config(dir: &str) -> Result<ChuckConfig> {
+    let _phase = profile::phase("config load");
+    let path = std::path::Path::new(dir).join(workspace::CONFIG_FILE);
+    if path.exists() {
+        let mut config = read_chuck_config(&path)?;
+        println!(
+            "🧔 Found template in {}: {}",
+            path.display(),
+            config.template.url
+        );
+        config.path = Some(path);
+        return Ok(config);
+    }
+    if !dir.is_empty() {
+        return Err(anyhow!("No {} file found", path.display()));
+    }
+
+    let remotes = list_remotes().unwrap_or_default();
+    for candidate in TEMPLATE_REMOTE_NAMES {
+        if let Some((name, url)) = remotes.iter().find(|(name, _)| name == candidate) {
+            if extract_repo_name_from_url(url).is_ok() {
+                println!(
+                    "🧔 No .chuckrc, using the template from remote '{}': {}",
+                    name, url
+                );
+                return Ok(ChuckConfig::new(TemplateConfig {
+                    url: url.clone(),
+                    remote: name.clone(),
+                    keep_remote: true,
+                    base: None,
+                }));
+            }
+        }
+    }
+
+    Err(anyhow!(
+        "No template found. Chuck needs a .chuckrc file with template URL.\n  \
+        Add this to your template repository:\n  \
+        [template]\n  \
+        url = \"git@github.com:your-org/your-template.git\"\n  \
+        Or add a remote named 'template' or 'upstream' pointing at it."
+    ))
+}
+
+fn get_current_repo() -> Result<String> {
+    // Without gh, origin says which repository this is
+    if let forge::Backend::Api { .. } = forge::backend()? {
+        let (_, url) = list_remotes()?
+            .into_iter()
+            .find(|(name, _)| name == "origin")
+            .ok_or_else(|| anyhow!("No origin remote to tell which GitHub repository this is"))?;
+        return extract_repo_name_from_url(&url);
+    }
+    let output = Command::new("gh")
+        .args(["repo", "view", "--json", "owner,name"])
+        .output_network()
+        .map_err(|e| {
+            process::command_error(e, "GitHub CLI not found. Install with: brew install gh")
+        })?;
+
+    if !output.status.success() {
+        return Err(anyhow!("Failed to get current repo info. Make sure you're in a GitHub repository and authenticated with 'gh auth login'"));
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+
+    let owner = json
+        .get("owner")
+        .and_then(|o| o.get("login"))
+        .and_then(|l| l.as_str())
+        .ok_or_else(|| anyhow!("Could not get repository owner"))?;
+
+    let name = json
+        .get("name")
+        .and_then(|n| n.as_str())
+        .ok_or_else(|| anyhow!("Could not get repository name"))?;
+
+    Ok(format!("{}/{}", owner, name))
+}
+
+fn get_template_latest_commit_date(template_repo: &str, base_ref: &str) -> Result<String> {
+    let commit = get_template_commit(template_repo, base_ref)?;
+    commit
+        .pointer("/commit/author/date")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Failed to get template's latest commit date"))
+}
+
+/// `repos/<template>/commits/<ref>`, which the base SHA and the date both come from
+fn get_template_commit(template_repo: &str, base_ref: &str) -> Result<Value> {
+    let body = api::get(
+        &format!("repos/{}/commits/{}", template_repo, base_ref),
+        "Failed to get template commit info",
+    )?;
+    Ok(serde_json::from_str(&body)?)
+}
+
+fn get_template_base_commit(template_repo: &str, base_ref: &str) -> Result<String> {
+    let _phase = profile::phase("template lookup");
+    let commit = get_template_commit(template_repo, base_ref)?;
+    commit
+        .get("sha")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("Failed to get template's base commit SHA"))
+}
+
+/// Commits on `current_repo`'s default branch newer than the template, or than
+/// `last_push` when that's later
+fn get_commits_since_template(
+    current_repo: &str,
+    template_repo: &str,
+    base_ref: &str,
+    last_push: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Vec<Commit>> {
+    println!(
+        "🧔 Comparing {} with template {} ({})...",
+        current_repo, template_repo, base_ref
+    );
+
+    let template_date = get_template_latest_commit_date(template_repo, base_ref)?;
+    println!("🧔 Template last updated: {}", template_date);
+    let template_date = match last_push {
+        Some(pushed) if pushed > chrono::DateTime::parse_from_rfc3339(&template_date)? => {
+            println!(
+                "🧔 Showing commits since your last push, {} (--since-template shows them all)",
+                dates::display(&pushed.fixed_offset())
+            );
+            pushed.to_rfc3339()
+        }
+        _ => template_date,
+    };
+
+    // The clone has the same history without the round trips, unless it's cut short
+    match local_default_branch(current_repo) {
+        Ok(Some(branch)) => {
+            println!("🧔 Reading {} from the local clone", branch);
+            return local_commits_since(&branch, &template_date);
+        }
+        Ok(None) => {}
+        Err(e) => println!("🧔 Couldn't read the local history, asking GitHub: {}", e),
+    }
+
+    let body = api::get(
+        &format!("repos/{}/commits", current_repo),
+        "Failed to get commits from current repository",
+    )?;
+
+    let json: Value = serde_json::from_str(&body)?;
+    let template_timestamp = chrono::DateTime::parse_from_rfc3339(&template_date)?;
+    let newer: Vec<(
+        &str,
+        &Value,
+        Vec<String>,
+        chrono::DateTime<chrono::FixedOffset>,
+    )> = json
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|commit_data| {
+            let sha = commit_data.get("sha")?.as_str()?;
+            let commit_info = commit_data.get("commit")?;
+            let date_str = commit_info.pointer("/author/date")?.as_str()?;
+            let commit_timestamp = chrono::DateTime::parse_from_rfc3339(date_str).ok()?;
+            commit_info.get("message")?.as_str()?;
+            let parents: Vec<String> = commit_data
+                .get("parents")
+                .and_then(|p| p.as_array())
+                .map(|p| {
+                    p.iter()
+                        .filter_map(|p| p.get("sha")?.as_str())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+            (commit_timestamp > template_timestamp).then_some((
+                sha,
+                commit_info,
+                parents,
+                commit_timestamp,
+            ))
+        })
+        .collect();
+
+    let files = process::map_concurrently(
+        &newer,
+        FILE_LISTS,
+        |(sha, _, parents, _)| discovery::get_commit_files(sha, parents.len() > 1),
+        |_| {},
+    );
+    let mut commits = Vec::new();
+    for ((sha, commit_info, parents, commit_timestamp), files) in newer.into_iter().zip(files) {
+        let message = commit_info
+            .get("message")
+            .and_then(|m| m.as_str())
+            .unwrap_or_default();
+        // Extract author and format date
+        let author = commit_info
+            .get("author")
+            .and_then(|a| a.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or("Unknown")
+            .to_string();
+
+        commits.push(Commit {
+            hash: sha.to_string(),
+            short_hash: sha[..7].to_string(),
+            message: message.lines().next().unwrap_or(message).to_string(),
+            files: files?,
+            selected: false,
+            author,
+            date: dates::display(&commit_timestamp),
+            parents,
+            already_upstream: false,
+            policy_issues: Vec::new(),
+            risks: Vec::new(),
+            signature: None,
+            ci: None,
+            provenance: None,
+            deleted_template_files: Vec::new(),
+            split_off: Vec::new(),
+            patch_id: None,
+            ignored: false,
+            contributed: None,
+            mark: None,
+            project_only: false,
+        });
+    }
+
+    Ok(commits)
+}
+
+/// Commits on a local ref that are newer than the template, without checking it out
+fn get_commits_from_ref(from: &str, template_repo: &str, base_ref: &str) -> Result<Vec<Commit>> {
+    println!(
+        "🧔 Comparing {} with template {} ({})...",
+        from, template_repo, base_ref
+    );
+
+    let template_date = get_template_latest_commit_date(template_repo, base_ref)?;
+    println!("🧔 Template last updated: {}", template_date);
+    local_commits_since(from, &template_date)
+}
+
+/// Commits on the local ref `from` authored after `template_date`
+fn local_commits_since(from: &str, template_date: &str) -> Result<Vec<Commit>> {
+    let template_timestamp = chrono::DateTime::parse_from_rfc3339(template_date)?;
+
+    // --since prunes the walk by committer date, the author date check matches the API path
+    Ok(
+        list_local_commits(&[from, &format!("--since={}", template_date)])?
+            .into_iter()
+            .filter(|(_, timestamp)| *timestamp > template_timestamp)
+            .map(|(commit, _)| commit)
+            .collect(),
+    )
+}
+
+/// The remote-tracking ref of `current_repo`'s default branch, like `origin/main`.
+/// `None` when the clone is shallow or has no such ref, so only GitHub has the
+/// whole history.
+fn local_default_branch(current_repo: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--is-shallow-repository"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+    if String::from_utf8_lossy(&output.stdout).trim() == "true" {
+        println!("🧔 Shallow clone, asking GitHub for the history");
+        return Ok(None);
+    }
+
+    for (name, url) in list_remotes()? {
+        if !extract_repo_name_from_url(&url)
+            .is_ok_and(|repo| repo.eq_ignore_ascii_case(current_repo))
+        {
+            continue;
+        }
+        // Set by git clone, or by `git remote set-head <name> --auto`
+        let output = Command::new("git")
+            .args([
+                "symbolic-ref",
+                "--quiet",
+                "--short",
+                &format!("refs/remotes/{}/HEAD", name),
+            ])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git symbolic-ref"))?;
+        let branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if output.status.success() && !branch.is_empty() {
+            return Ok(Some(branch));
+        }
+    }
+    Ok(None)
+}
+
+/// Commits on `from` that write something the template never had, whether or not
+/// its history has anything in common with the template's
+fn get_commits_by_content(from: &str, template_base: &str, verbose: bool) -> Result<Vec<Commit>> {
+    println!(
+        "🧔 Comparing {} with the template's files by content...",
+        from
+    );
+
+    let template = fingerprint::Fingerprint::of_template(template_base)?;
+    let not_template = format!("^{}", template_base);
+    let origins = fingerprint::classify(&[from, &not_template], &template)?;
+
+    let mut commits = Vec::new();
+    let mut derived = 0;
+    for (commit, _) in list_local_commits(&["--no-merges", from, &not_template])? {
+        if origins.get(&commit.hash) == Some(&fingerprint::Origin::Template) {
+            if verbose {
+                println!(
+                    "🧔 VERBOSE: {} only brings in template content: {}",
+                    commit.short_hash, commit.message
+                );
+            }
+            derived += 1;
+        } else {
+            commits.push(commit);
+        }
+    }
+    if derived > 0 {
+        println!(
+            "🧔 Leaving out {} commits that only bring in the template's own files",
+            derived
+        );
+    }
+    Ok(commits)
+}
+
+fn get_commits_in_range(range: &str) -> Result<Vec<Commit>> {
+    if !range.contains("..") {
+        return Err(anyhow!("--range needs the form A..B, got '{}'", range));
+    }
+    println!("🧔 Looking at commits in {}...", range);
+
+    Ok(list_local_commits(&[range])?
+        .into_iter()
+        .map(|(commit, _)| commit)
+        .collect())
+}
+
+/// Commits whose files are listed at once, a git process each
+const FILE_LISTS: usize = 8;
+
+/// The commits `git log` finds for `rev_args` with the files each one changed,
+/// alongside their author dates
+fn list_local_commits(
+    rev_args: &[&str],
+) -> Result<Vec<(Commit, chrono::DateTime<chrono::FixedOffset>)>> {
+    let entries = discovery::read_log(rev_args)?;
+    let files = process::map_concurrently(
+        &entries,
+        FILE_LISTS,
+        |entry| discovery::get_commit_files(&entry.hash, entry.parents.len() > 1),
+        |_| {},
+    );
+    entries
+        .into_iter()
+        .zip(files)
+        .map(|(entry, files)| {
+            let commit = Commit {
+                short_hash: entry.hash[..7].to_string(),
+                files: files?,
+                message: entry.subject,
+                selected: false,
+                author: entry.author,
+                date: dates::display(&entry.date),
+                parents: entry.parents,
+                already_upstream: false,
+                policy_issues: Vec::new(),
+                risks: Vec::new(),
+                signature: None,
+                ci: None,
+                provenance: None,
+                deleted_template_files: Vec::new(),
+                split_off: Vec::new(),
+                patch_id: None,
+                ignored: false,
+                contributed: None,
+                mark: None,
+                project_only: false,
+                hash: entry.hash,
+            };
+            Ok((commit, entry.date))
+        })
+        .collect()
+}
+
+/// The git remote used to fetch the template for this run
+struct TemplateRemote {
+    name: String,
+    /// Added by this run, so it's removed again when dropped
+    temporary: bool,
+    verbose: bool,
+}
+
+impl Drop for TemplateRemote {
+    fn drop(&mut self) {
+        if !self.temporary {
+            return;
+        }
+        if self.verbose {
+            println!("🧔 VERBOSE: Removing temporary remote {}", self.name);
+        }
+        let _ = Command::new("git")
+            .args(["remote", "remove", &self.name])
+            .output_local();
+    }
+}
+
+/// All configured remotes as (name, url) pairs
+fn list_remotes() -> Result<Vec<(String, String)>> {
+    let output = Command::new("git")
+        .args(["config", "--get-regexp", r"^remote\..*\.url$"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git config"))?;
+
+    // Exit code 1 just means there are no remotes
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let (key, url) = line.split_once(' ')?;
+            let name = key.strip_prefix("remote.")?.strip_suffix(".url")?;
+            Some((name.to_string(), url.trim().to_string()))
+        })
+        .collect())
+}
+
+/// SSH and HTTPS URLs for the same GitHub repository are the same remote
+fn same_repository(a: &str, b: &str) -> bool {
+    if a == b {
+        return true;
+    }
+    match (extract_repo_name_from_url(a), extract_repo_name_from_url(b)) {
+        (Ok(a), Ok(b)) => a.eq_ignore_ascii_case(&b),
+        _ => false,
+    }
+}
+
+/// Adds or reuses the template's remote and fetches it. A template that moved on GitHub
+/// is followed to its new location.
+fn fetch_template_remote(
+    config: &mut ChuckConfig,
+    interactive: bool,
+    verbose: bool,
+) -> Result<TemplateRemote> {
+    let _phase = profile::phase("template lookup");
+    let remotes = list_remotes()?;
+    let template = &config.template;
+
+    let remote = if let Some((name, _)) = remotes
+        .iter()
+        .find(|(_, url)| same_repository(url, &template.url))
+    {
+        if verbose {
+            println!(
+                "🧔 VERBOSE: Reusing existing remote {} for the template",
+                name
+            );
+        }
+        TemplateRemote {
+            name: name.clone(),
+            temporary: false,
+            verbose,
+        }
+    } else {
+        // Never clobber a remote the user already has under our name
+        let mut name = template.remote.clone();
+        let mut suffix = 2;
+        while remotes.iter().any(|(existing, _)| existing == &name) {
+            name = format!("{}-{}", template.remote, suffix);
+            suffix += 1;
+        }
+
+        if verbose {
+            println!("🧔 VERBOSE: Adding template remote {}...", name);
+        }
+
+        let output = Command::new("git")
+            .args(["remote", "add", &name, &template.url])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git remote add"))?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to add template remote: {}", error));
+        }
+
+        TemplateRemote {
+            name,
+            temporary: !template.keep_remote,
+            verbose,
+        }
+    };
+
+    if verbose {
+        println!("🧔 VERBOSE: Fetching {}...", remote.name);
+    }
+
+    // A partial clone stays partial, the picks' files are fetched when they're needed
+    let mut fetch = Command::new("git");
+    fetch.arg("fetch");
+    if let Some(filter) = partial::setup().filter() {
+        fetch.arg(format!("--filter={}", filter));
+    }
+    let fetch_output = fetch
+        .arg(&remote.name)
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to fetch template remote"))?;
+
+    if !fetch_output.status.success() {
+        let error = String::from_utf8_lossy(&fetch_output.stderr);
+        if relocation::is_not_found(&error) && follow_template_move(config, interactive)? {
+            drop(remote);
+            return fetch_template_remote(config, interactive, verbose);
+        }
+        return Err(anyhow!(network::describe_failure(
+            "Failed to fetch template",
+            &error
+        )));
+    }
+
+    if verbose {
+        println!("🧔 VERBOSE: Template fetched successfully");
+    }
+
+    Ok(remote)
+}
+
+/// Points `config` at the template's new location when GitHub says it was renamed or
+/// transferred, and offers to update the `.chuckrc` too. Returns whether it moved.
+fn follow_template_move(config: &mut ChuckConfig, interactive: bool) -> Result<bool> {
+    let Ok(repo) = extract_repo_name_from_url(&config.template.url) else {
+        return Ok(false);
+    };
+    let moved = match relocation::current_name(&repo) {
+        Ok(Some(moved)) => moved,
+        Ok(None) => return Ok(false),
+        Err(e) => {
+            println!("🧔 Couldn't check whether {} moved: {}", repo, e);
+            return Ok(false);
+        }
+    };
+    let url = relocation::moved_url(&config.template.url, &moved);
+    println!("🧔 {} moved to {}, carrying on with {}", repo, moved, url);
+
+    match &config.path {
+        Some(path) if interactive => {
+            if mergetool::confirm(&format!("Update the url in {}?", path.display()))? {
+                match relocation::update_config(path, &config.template.url, &url) {
+                    Ok(()) => println!(
+                        "🧔 Updated {}, commit it when you get a chance",
+                        path.display()
+                    ),
+                    Err(e) => println!("🧔 Couldn't update {}: {}", path.display(), e),
+                }
+            }
+        }
+        Some(path) => println!(
+            "🧔 Update the url in {} to {} to skip the detour next time",
+            path.display(),
+            url
+        ),
+        None => {
+            println!(
+                "🧔 Point remote '{}' at it with: git remote set-url {} {}",
+                config.template.remote, config.template.remote, url
+            );
+            // The remote for the new location is only for this run
+            config.template.keep_remote = false;
+        }
+    }
+    config.template.url = url;
+    Ok(true)
+}
+
+/// Records each commit's `git patch-id` and flags those matching a commit already on
+/// the template branch
+fn mark_already_upstream(commits: &mut [Commit], template_base: &str, verbose: bool) -> Result<()> {
+    if commits.iter().all(|c| c.is_merge()) {
+        return Ok(());
+    }
+
+    let template_ids = discovery::patch_ids(&["log", "-p", "--no-merges", template_base])?;
+
+    let mut args = vec!["log", "-p", "--no-walk"];
+    args.extend(
+        commits
+            .iter()
+            .filter(|c| !c.is_merge())
+            .map(|c| c.hash.as_str()),
+    );
+    let candidate_ids = discovery::patch_ids(&args)?;
+
+    let upstream: std::collections::HashSet<&str> =
+        template_ids.iter().map(|(id, _)| id.as_str()).collect();
+
+    for (patch_id, sha) in candidate_ids {
+        let Some(commit) = commits.iter_mut().find(|c| c.hash == sha) else {
+            continue;
+        };
+        if upstream.contains(patch_id.as_str()) {
+            if verbose {
+                println!(
+                    "🧔 VERBOSE: {} is already upstream (patch-id {})",
+                    commit.short_hash,
+                    &patch_id[..12.min(patch_id.len())]
+                );
+            }
+            commit.already_upstream = true;
+        }
+        commit.patch_id = Some(patch_id);
+    }
 
-    let help_popup = Paragraph::new(help_text)
-        .block(
-            Block::default()
-                .title("Help")
-                .borders(Borders::ALL)
-                .border_style(Style::default().fg(Color::Cyan)),
-        )
-        .wrap(Wrap { trim: true });
+    Ok(())
+}
 
-    // Center the help popup
-    let popup_area = centered_rect(80, 80, area);
-    frame.render_widget(Clear, popup_area);
-    frame.render_widget(help_popup, popup_area);
+/// Under `--read-only`, says what building `plan` on `base` and proposing it to
+/// `repo` would have done instead, and returns true
+fn withhold_build(plan: &[(&Commit, &PlanStep)], base: &str, repo: &str) -> bool {
+    if !read_only::is_enabled() {
+        return false;
+    }
+    println!(
+        "\n🧔 Read-only, so nothing gets built. On {} this would have:",
+        &base[..base.len().min(7)]
+    );
+    for (commit, step) in plan {
+        let subject = match step.action {
+            PlanAction::Reword => step.message.as_str(),
+            _ => commit.message.lines().next().unwrap_or(""),
+        };
+        println!(
+            "  • {} {} {}",
+            step.action.label(),
+            commit.short_hash,
+            subject
+        );
+    }
+    println!(
+        "  • pushed the branch and opened a pull request against {}",
+        repo
+    );
+    true
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
+fn create_branch_with_commits(
+    plan: &[(&Commit, &PlanStep)],
+    verbose: bool,
+    template_base_sha: &str,
+    config: &ChuckConfig,
+) -> Result<(String, String)> {
+    let _phase = profile::phase("branch build");
+    let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
+    let mut timestamp_str = timestamp.to_string();
+    // Workspaces run back to back can start within the same second
+    if let Some(dir) = &config.cherry_pick.subtree {
+        timestamp_str = format!("{}-{}", timestamp_str, dir.replace('/', "-"));
+    }
+    let branch_name = format!("chuck/{}", timestamp_str);
 
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1]
-}
+    println!("🧔 Creating branch with {} selected commits...", plan.len());
 
-// Keep all the existing business logic functions unchanged
-fn read_chuck_config() -> Result<ChuckConfig> {
-    let config_content =
-        fs::read_to_string(".chuckrc").map_err(|_| anyhow!("No .chuckrc file found"))?;
+    if verbose {
+        println!("🧔 VERBOSE: About to create branch {}", branch_name);
+    }
 
-    let config: ChuckConfig =
-        toml::from_str(&config_content).map_err(|e| anyhow!("Failed to parse .chuckrc: {}", e))?;
+    if verbose {
+        println!(
+            "🧔 VERBOSE: Using template base commit: {}",
+            template_base_sha
+        );
+    }
 
-    Ok(config)
+    let original_ref = current_ref()?;
+
+    // Long builds leave checkpoints behind, so a failure late in the plan doesn't mean
+    // applying everything before it again
+    let checkpointing = plan.len() > checkpoint::EVERY;
+    let steps: Vec<selection::Step> = plan.iter().map(|(c, s)| s.export(c)).collect();
+    let mut done = 0;
+    let mut start = template_base_sha.to_string();
+    if checkpointing {
+        match checkpoint::Checkpoint::load() {
+            Ok(Some(saved)) => {
+                done = saved.covers(template_base_sha, &steps);
+                if done > 0 {
+                    println!(
+                        "🧔 Picking up from {}, which has {} of the {} commits",
+                        saved.branch,
+                        done,
+                        plan.len()
+                    );
+                    start = saved.branch;
+                } else if let Err(e) = checkpoint::Checkpoint::clear() {
+                    println!("🧔 Couldn't clear the last build's checkpoints: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => println!("🧔 Couldn't read the last build's checkpoint: {}", e),
+        }
+    }
+
+    let mut checkpointed = done;
+    if config.cherry_pick.engine == Engine::InMemory {
+        let from = done;
+        let (built, head) =
+            pick_in_memory(&plan[from..], &start, config, verbose, |built, head| {
+                let applied = from + built;
+                if checkpointing
+                    && applied - checkpointed >= checkpoint::EVERY
+                    && save_checkpoint(template_base_sha, &steps[..applied], head, verbose)
+                {
+                    checkpointed = applied;
+                }
+            })?;
+        done += built;
+        start = head;
+    }
+
+    let output = Command::new("git")
+        .args(["checkout", "-b", &branch_name, &start])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git checkout"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Failed to create branch from template base: {}",
+            error
+        ));
+    }
+
+    if verbose {
+        println!("🧔 VERBOSE: Branch created successfully from template base");
+    }
+
+    // A project imported as a copy has no history in common with the template, and
+    // cherry-picking across that merges in every difference between the two trees
+    let unrelated = match plan.first() {
+        Some((commit, _)) => unrelated_histories(&commit.hash, template_base_sha)?,
+        None => false,
+    };
+    if unrelated {
+        println!(
+            "🧔 The project shares no history with the template, applying each commit's diff instead"
+        );
+    }
+
+    // Commits squashed into the one on top of the branch so far, first one first
+    let mut squashed: Vec<&Commit> = Vec::new();
+    for (i, (commit, step)) in plan.iter().enumerate().skip(done) {
+        if step.action != PlanAction::Squash {
+            write_squash_message(&squashed, config)?;
+        }
+        println!(
+            "🧔 Cherry-picking: {} - {}",
+            commit.short_hash, commit.message
+        );
+        let head_before = head_sha()?;
+        if verbose {
+            println!("🧔 VERBOSE: About to cherry-pick commit {}", commit.hash);
+        }
+
+        let mut restore = step.leave_out.clone();
+        if step.keep_template_files {
+            restore.extend(commit.deleted_template_files.iter().cloned());
+        }
+        restore.extend(commit.split_off.iter().cloned());
+        let picked = if unrelated {
+            apply_commit_diff(
+                commit,
+                mainline(commit, config),
+                &restore,
+                &config.cherry_pick,
+                verbose,
+            )
+        } else if !restore.is_empty() {
+            cherry_pick_restoring(commit, config, &restore, verbose)
+        } else {
+            cherry_pick_commit(commit, config, verbose)
+        };
+        match picked {
+            Ok(()) => {
+                if verbose {
+                    println!(
+                        "🧔 VERBOSE: Cherry-pick completed for {}",
+                        commit.short_hash
+                    );
+                }
+            }
+            Err(e) => {
+                if e.to_string().contains("empty") {
+                    println!(
+                        "🧔 Skipping empty commit: {} - {}",
+                        commit.short_hash, commit.message
+                    );
+                    let skip_output = Command::new("git")
+                        .args(["cherry-pick", "--skip"])
+                        .output_local()
+                        .map_err(|e| process::command_error(e, "Failed to skip cherry-pick"))?;
+
+                    if !skip_output.status.success() {
+                        return Err(anyhow!("Failed to skip empty cherry-pick"));
+                    }
+                } else if !process::is_interrupted(&e)
+                    && resolve_generated(commit, config, verbose)?
+                {
+                    println!(
+                        "🧔 Settled the generated files in {}, carrying on",
+                        commit.short_hash
+                    );
+                } else if !process::is_interrupted(&e)
+                    && resolve_manifests(commit, config.cherry_pick.record_origin, verbose)?
+                {
+                    println!(
+                        "🧔 Merged the dependency changes in {}, carrying on",
+                        commit.short_hash
+                    );
+                } else if !process::is_interrupted(&e)
+                    && resolve_with_mergetool(commit, config.cherry_pick.record_origin)?
+                {
+                    println!("🧔 Resolved {}, carrying on", commit.short_hash);
+                } else {
+                    if process::is_interrupted(&e) {
+                        abandon_branch(&original_ref, &branch_name, verbose);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        // Empty picks leave HEAD alone and there's nothing to squash or reword
+        if head_sha()? != head_before {
+            let squash_into_previous =
+                step.action == PlanAction::Squash && head_before != template_base_sha;
+            if step.action != PlanAction::Pick {
+                apply_plan_step(commit, step, squash_into_previous, verbose)?;
+            }
+            if !squash_into_previous {
+                squashed.clear();
+            }
+            squashed.push(commit);
+        }
+
+        if config.checks.amend && !config.checks.is_empty() && head_sha()? != head_before {
+            match checks::apply(&config.checks, "HEAD^", true, verbose) {
+                Ok(0) => {}
+                Ok(count) => println!(
+                    "🧔 Amended {} with what the checks changed in {} files",
+                    commit.short_hash, count
+                ),
+                Err(e) => println!("🧔 Couldn't run the checks on {}: {}", commit.short_hash, e),
+            }
+        }
+
+        // Between squash groups, so the squashed message is final
+        let applied = i + 1;
+        if checkpointing
+            && applied - checkpointed >= checkpoint::EVERY
+            && plan
+                .get(applied)
+                .is_some_and(|(_, next)| next.action != PlanAction::Squash)
+        {
+            write_squash_message(&squashed, config)?;
+            squashed.clear();
+            if save_checkpoint(template_base_sha, &steps[..applied], "HEAD", verbose) {
+                checkpointed = applied;
+            }
+        }
+    }
+
+    write_squash_message(&squashed, config)?;
+
+    if checkpointing {
+        if let Err(e) = checkpoint::Checkpoint::clear() {
+            println!("🧔 Couldn't clear the checkpoints: {}", e);
+        }
+    }
+
+    println!("🧔 Created branch: {}", branch_name);
+    println!("🧔 Successfully processed {} commits", plan.len());
+
+    Ok((branch_name, timestamp_str))
 }
 
-fn extract_repo_name_from_url(url: &str) -> Result<String> {
-    if url.starts_with("git@github.com:") {
-        let without_prefix = url.strip_prefix("git@github.com:").unwrap();
-        let without_suffix = without_prefix
-            .strip_suffix(".git")
-            .unwrap_or(without_prefix);
-        Ok(without_suffix.to_string())
-    } else if url.starts_with("https://github.com/") {
-        let without_prefix = url.strip_prefix("https://github.com/").unwrap();
-        let without_suffix = without_prefix
-            .strip_suffix(".git")
-            .unwrap_or(without_prefix);
-        Ok(without_suffix.to_string())
-    } else {
-        Err(anyhow!("Unsupported repository URL format: {}", url))
+/// Checkpoints `head`, which has `steps` applied on the template base. False if that
+/// didn't work out, which only costs picking them again should the build fail.
+fn save_checkpoint(
+    template_base_sha: &str,
+    steps: &[selection::Step],
+    head: &str,
+    verbose: bool,
+) -> bool {
+    match checkpoint::Checkpoint::save(template_base_sha, steps, head) {
+        Ok(saved) => {
+            if verbose {
+                println!(
+                    "🧔 VERBOSE: Checkpointed {} steps as {}",
+                    steps.len(),
+                    saved.branch
+                );
+            }
+            true
+        }
+        Err(e) => {
+            println!("🧔 Couldn't save a checkpoint: {}", e);
+            false
+        }
     }
 }
 
-fn find_template_repo() -> Result<String> {
-    if let Ok(config) = read_chuck_config() {
-        println!("🧔 Found template in .chuckrc: {}", config.template.url);
-        return extract_repo_name_from_url(&config.template.url);
+/// Builds the leading steps of `plan` on `start` without a worktree, up to the first
+/// one only the cherry-pick loop can do: a conflict, a merge commit, files left out or
+/// a squash. `progress` hears how many are built after each one, and the commit they
+/// end at. Returns the same once it stops.
+fn pick_in_memory(
+    plan: &[(&Commit, &PlanStep)],
+    start: &str,
+    config: &ChuckConfig,
+    verbose: bool,
+    mut progress: impl FnMut(usize, &str),
+) -> Result<(usize, String)> {
+    let options = &config.cherry_pick;
+    if options.strategy_option.is_some()
+        || options.subtree.is_some()
+        || (config.checks.amend && !config.checks.is_empty())
+    {
+        if verbose {
+            println!("🧔 VERBOSE: These cherry-pick options need the worktree for every pick");
+        }
+        return Ok((0, start.to_string()));
+    }
+    let sign = Command::new("git")
+        .args(["config", "--type=bool", "--get", "commit.gpgsign"])
+        .output_local()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "true");
+
+    let mut head = start.to_string();
+    for (i, (commit, step)) in plan.iter().enumerate() {
+        let squashed = step.action == PlanAction::Squash
+            || plan
+                .get(i + 1)
+                .is_some_and(|(_, next)| next.action == PlanAction::Squash);
+        let leaves_out = !step.leave_out.is_empty()
+            || !commit.split_off.is_empty()
+            || (step.keep_template_files && !commit.deleted_template_files.is_empty());
+        let reason = if commit.is_merge() {
+            Some("it's a merge".to_string())
+        } else if squashed {
+            Some("it's squashed".to_string())
+        } else if leaves_out {
+            Some("it leaves files out".to_string())
+        } else {
+            None
+        };
+        let picked = match reason {
+            Some(reason) => in_memory::Outcome::NeedsWorktree(reason),
+            None => in_memory::pick(
+                &head,
+                &format!("{}^", commit.hash),
+                &commit.hash,
+                sign,
+                |message| {
+                    let message = if options.record_origin {
+                        format!(
+                            "{}\n\n(cherry picked from commit {})",
+                            message.trim_end(),
+                            commit.hash
+                        )
+                    } else {
+                        message.to_string()
+                    };
+                    match (step.action, message.split_once('\n')) {
+                        (PlanAction::Reword, Some((_, body))) => {
+                            format!("{}\n{}", step.message, body)
+                        }
+                        (PlanAction::Reword, None) => step.message.clone(),
+                        _ => message,
+                    }
+                },
+            )?,
+        };
+        match picked {
+            in_memory::Outcome::Picked(sha) => {
+                println!(
+                    "🧔 Cherry-picking: {} - {}",
+                    commit.short_hash, commit.message
+                );
+                head = sha;
+            }
+            in_memory::Outcome::Empty => println!(
+                "🧔 Skipping empty commit: {} - {}",
+                commit.short_hash, commit.message
+            ),
+            in_memory::Outcome::NeedsWorktree(reason) => {
+                println!(
+                    "🧔 {} needs the worktree ({}), cherry-picking from there",
+                    commit.short_hash, reason
+                );
+                return Ok((i, head));
+            }
+        }
+        progress(i + 1, &head);
     }
+    Ok((plan.len(), head))
+}
 
-    Err(anyhow!(
-        "No template found. Chuck needs a .chuckrc file with template URL.\n  \
-        Add this to your template repository:\n  \
-        [template]\n  \
-        url = \"git@github.com:your-org/your-template.git\""
-    ))
+/// Rewrites the message of `squashed`, the commits HEAD was squashed from, with the
+/// `[squash]` template, and credits their other authors with `Co-authored-by:`
+/// trailers. Their messages just strung together stay without a template.
+fn write_squash_message(squashed: &[&Commit], config: &ChuckConfig) -> Result<()> {
+    if squashed.len() < 2 {
+        return Ok(());
+    }
+    let co_authors = if config.squash.co_authors {
+        let hashes: Vec<&str> = squashed.iter().map(|c| c.hash.as_str()).collect();
+        squash::co_authors(&hashes)?
+    } else {
+        Vec::new()
+    };
+    if config.squash.message.is_none() && co_authors.is_empty() {
+        return Ok(());
+    }
+
+    let message = head_message()?;
+    let message = match &config.squash.message {
+        Some(template) => {
+            let summary = message.lines().next().unwrap_or_default();
+            let subjects: Vec<(&str, &str)> = squashed
+                .iter()
+                .filter(|c| squash::fold_of(&c.message).is_none())
+                .map(|c| (c.short_hash.as_str(), c.message.as_str()))
+                .collect();
+            let date = chrono::Local::now().format("%Y-%m-%d").to_string();
+            squash::render(template, summary, &subjects, &config.squash.repo, &date)
+        }
+        None => message,
+    };
+    let message = squash::with_co_authors(&message, &co_authors)?;
+    squash::amend(
+        &message,
+        config.squash.edit && config.squash.message.is_some(),
+    )
 }
 
-fn get_current_repo() -> Result<String> {
-    let output = Command::new("gh")
-        .args(&["repo", "view", "--json", "owner,name"])
-        .output()
-        .map_err(|_| anyhow!("GitHub CLI not found. Install with: brew install gh"))?;
+/// Squashes or rewords the commit just picked for `step`
+fn apply_plan_step(
+    commit: &Commit,
+    step: &PlanStep,
+    squash_into_previous: bool,
+    verbose: bool,
+) -> Result<()> {
+    let picked_message = head_message()?;
+
+    let message = match step.action {
+        PlanAction::Squash if squash_into_previous => {
+            if verbose {
+                println!(
+                    "🧔 VERBOSE: Squashing {} into the previous commit",
+                    commit.short_hash
+                );
+            }
+            let output = Command::new("git")
+                .args(["reset", "--soft", "HEAD~1"])
+                .output_local()
+                .map_err(|e| process::command_error(e, "Failed to execute git reset"))?;
+            if !output.status.success() {
+                let error = String::from_utf8_lossy(&output.stderr);
+                return Err(anyhow!("Failed to squash {}: {}", commit.short_hash, error));
+            }
+            // What a fixup! or squash! commit adds to the message, if anything
+            match squash::folded_message(&picked_message) {
+                Some(folded) if folded.is_empty() => head_message()?.trim_end().to_string(),
+                Some(folded) => format!("{}\n\n{}", head_message()?.trim_end(), folded),
+                None => format!(
+                    "{}\n\n{}",
+                    head_message()?.trim_end(),
+                    picked_message.trim_end()
+                ),
+            }
+        }
+        PlanAction::Reword => match picked_message.split_once('\n') {
+            Some((_, body)) => format!("{}\n{}", step.message, body),
+            None => step.message.clone(),
+        },
+        _ => return Ok(()),
+    };
+
+    let output = Command::new("git")
+        .args(["commit", "--amend", "--no-verify", "-m", &message])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git commit"))?;
 
     if !output.status.success() {
-        return Err(anyhow!("Failed to get current repo info. Make sure you're in a GitHub repository and authenticated with 'gh auth login'"));
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Failed to {} {}: {}",
+            step.action.label(),
+            commit.short_hash,
+            error
+        ));
     }
 
-    let json: Value = serde_json::from_slice(&output.stdout)?;
+    Ok(())
+}
 
-    let owner = json
-        .get("owner")
-        .and_then(|o| o.get("login"))
-        .and_then(|l| l.as_str())
-        .ok_or_else(|| anyhow!("Could not get repository owner"))?;
+fn head_sha() -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
 
-    let name = json
-        .get("name")
-        .and_then(|n| n.as_str())
-        .ok_or_else(|| anyhow!("Could not get repository name"))?;
+fn head_message() -> Result<String> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%B", "HEAD"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+    Ok(String::from_utf8(output.stdout)?)
+}
 
-    Ok(format!("{}/{}", owner, name))
+/// The checked-out branch name, or the commit SHA when HEAD is detached
+fn current_ref() -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+
+    let name = String::from_utf8(output.stdout)?.trim().to_string();
+    if output.status.success() && name != "HEAD" {
+        return Ok(name);
+    }
+
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("Failed to determine the current commit"));
+    }
+
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Puts the repo back the way it was after a cancelled or timed-out branch build
+fn abandon_branch(original_ref: &str, branch_name: &str, verbose: bool) {
+    println!(
+        "🧔 Cleaning up: returning to {} and deleting {}",
+        original_ref, branch_name
+    );
+
+    let steps: [&[&str]; 3] = [
+        &["cherry-pick", "--abort"],
+        &["checkout", original_ref],
+        &["branch", "-D", branch_name],
+    ];
+    for args in steps {
+        let result = Command::new("git").args(args).output_local();
+        if verbose {
+            if let Ok(output) = result {
+                if !output.status.success() {
+                    println!(
+                        "🧔 VERBOSE: git {} failed: {}",
+                        args.join(" "),
+                        String::from_utf8_lossy(&output.stderr).trim()
+                    );
+                }
+            }
+        }
+    }
 }
 
-fn get_template_latest_commit_date(template_repo: &str) -> Result<String> {
-    let output = Command::new("gh")
-        .args(&[
-            "api",
-            &format!("repos/{}/commits/main", template_repo),
-            "--jq",
-            ".commit.author.date",
-        ])
-        .output()
-        .map_err(|_| anyhow!("Failed to get template commit info"))?;
+fn cherry_pick_commit(commit: &Commit, config: &ChuckConfig, verbose: bool) -> Result<()> {
+    let merges = &config.merge_commits;
+    let options = &config.cherry_pick;
+
+    if commit.is_merge() && merges.mode == MergeMode::Flatten {
+        if verbose {
+            println!(
+                "🧔 VERBOSE: Flattening merge {} against parent {}",
+                commit.short_hash, merges.mainline
+            );
+        }
+        return apply_commit_diff(commit, merges.mainline, &[], options, verbose);
+    }
+
+    let mut args = vec!["cherry-pick".to_string()];
+    if options.record_origin {
+        args.push("-x".to_string());
+    }
+    if commit.is_merge() && verbose {
+        println!(
+            "🧔 VERBOSE: {} is a merge, using mainline parent {}",
+            commit.short_hash, merges.mainline
+        );
+    }
+    args.extend(cherry_pick_options(commit, config));
 
-    if !output.status.success() {
-        return Err(anyhow!("Failed to get template's latest commit date"));
+    if verbose {
+        println!("🧔 VERBOSE: git {}", args.join(" "));
     }
 
-    let date = String::from_utf8(output.stdout)?
-        .trim()
-        .trim_matches('"')
-        .to_string();
+    let output = Command::new("git")
+        .args(&args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git cherry-pick"))?;
 
-    Ok(date)
-}
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if verbose {
+            println!("🧔 VERBOSE: Cherry-pick error: {}", error);
+        }
 
-fn get_template_base_commit(template_repo: &str) -> Result<String> {
-    let output = Command::new("gh")
-        .args(&[
-            "api",
-            &format!("repos/{}/commits/main", template_repo),
-            "--jq",
-            ".sha",
-        ])
-        .output()
-        .map_err(|_| anyhow!("Failed to get template base commit"))?;
+        if options.three_way_fallback && !error.contains("empty") {
+            println!(
+                "🧔 Cherry-pick of {} failed, retrying with a three-way apply...",
+                commit.short_hash
+            );
+            let _ = Command::new("git")
+                .args(["cherry-pick", "--abort"])
+                .output_local();
+            return apply_commit_diff(commit, mainline(commit, config), &[], options, verbose);
+        }
 
-    if !output.status.success() {
-        return Err(anyhow!("Failed to get template's base commit SHA"));
+        return Err(anyhow!("Cherry-pick failed: {}", error));
     }
 
-    let sha = String::from_utf8(output.stdout)?
-        .trim()
-        .trim_matches('"')
-        .to_string();
+    Ok(())
+}
 
-    Ok(sha)
+/// The parent `commit`'s changes are taken against
+fn mainline(commit: &Commit, config: &ChuckConfig) -> u32 {
+    if commit.is_merge() {
+        config.merge_commits.mainline
+    } else {
+        1
+    }
 }
 
-fn get_commits_since_template(current_repo: &str, template_repo: &str) -> Result<Vec<Commit>> {
-    println!(
-        "🧔 Comparing {} with template {}...",
-        current_repo, template_repo
-    );
+/// Whether `commit` and `template_base` have no commit in common
+fn unrelated_histories(commit: &str, template_base: &str) -> Result<bool> {
+    let output = Command::new("git")
+        .args(["merge-base", commit, template_base])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git merge-base"))?;
+
+    // Exits with 1 and says nothing when there's no merge base
+    match output.status.code() {
+        Some(1) if output.stderr.is_empty() => Ok(true),
+        _ if output.status.success() => Ok(false),
+        _ => {
+            let error = String::from_utf8_lossy(&output.stderr);
+            Err(anyhow!("Failed to find a merge base: {}", error.trim()))
+        }
+    }
+}
 
-    let template_date = get_template_latest_commit_date(template_repo)?;
-    println!("🧔 Template last updated: {}", template_date);
+/// Strategy options and mainline parent for cherry-picking `commit`, ending with its hash
+fn cherry_pick_options(commit: &Commit, config: &ChuckConfig) -> Vec<String> {
+    let options = &config.cherry_pick;
+    let mut args = Vec::new();
+    if let Some(strategy_option) = &options.strategy_option {
+        args.push(format!("--strategy-option={}", strategy_option));
+    }
+    if let Some(threshold) = options.rename_threshold {
+        args.push(format!("--strategy-option=find-renames={}%", threshold));
+    }
+    // The workspace's files sit at the root of the template, changes outside it drop out
+    if let Some(dir) = &options.subtree {
+        args.push(format!("--strategy-option=subtree={}", dir));
+    }
+    if commit.is_merge() {
+        args.push("-m".to_string());
+        args.push(config.merge_commits.mainline.to_string());
+    }
+    args.push(commit.hash.clone());
+    args
+}
 
-    let output = Command::new("gh")
-        .args(&["api", &format!("repos/{}/commits", current_repo)])
-        .output()
-        .map_err(|_| anyhow!("Failed to get current repository commits"))?;
+/// Cherry-picks `commit` but leaves `paths` as they are on the branch so far: the
+/// template files it deletes when they're kept, and files left out of the pick. That
+/// also settles the modify/delete conflicts such deletions cause when the template
+/// changed the file since the project dropped it.
+fn cherry_pick_restoring(
+    commit: &Commit,
+    config: &ChuckConfig,
+    paths: &[String],
+    verbose: bool,
+) -> Result<()> {
+    let mut args = vec!["cherry-pick".to_string(), "--no-commit".to_string()];
+    args.extend(cherry_pick_options(commit, config));
 
-    if !output.status.success() {
-        return Err(anyhow!("Failed to get commits from current repository"));
+    if verbose {
+        println!("🧔 VERBOSE: git {}", args.join(" "));
     }
 
-    let json: Value = serde_json::from_slice(&output.stdout)?;
-    let mut commits = Vec::new();
+    let output = Command::new("git")
+        .args(&args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git cherry-pick"))?;
 
-    if let Some(commit_array) = json.as_array() {
-        let template_timestamp = chrono::DateTime::parse_from_rfc3339(&template_date)?;
-
-        for commit_data in commit_array {
-            if let (Some(sha), Some(commit_info)) = (
-                commit_data.get("sha").and_then(|s| s.as_str()),
-                commit_data.get("commit"),
-            ) {
-                if let Some(message) = commit_info.get("message").and_then(|m| m.as_str()) {
-                    if let Some(date_str) = commit_info
-                        .get("author")
-                        .and_then(|a| a.get("date"))
-                        .and_then(|d| d.as_str())
-                    {
-                        if let Ok(commit_timestamp) = chrono::DateTime::parse_from_rfc3339(date_str)
-                        {
-                            if commit_timestamp > template_timestamp {
-                                let short_hash = &sha[..7];
-                                let files = get_commit_files(sha)?;
-
-                                // Extract author and format date
-                                let author = commit_info
-                                    .get("author")
-                                    .and_then(|a| a.get("name"))
-                                    .and_then(|n| n.as_str())
-                                    .unwrap_or("Unknown")
-                                    .to_string();
-
-                                let date = commit_timestamp.format("%Y-%m-%d %H:%M").to_string();
-
-                                commits.push(Commit {
-                                    hash: sha.to_string(),
-                                    short_hash: short_hash.to_string(),
-                                    message: message.lines().next().unwrap_or(message).to_string(),
-                                    files,
-                                    selected: false,
-                                    author,
-                                    date,
-                                });
-                            }
-                        }
-                    }
-                }
-            }
+    // Conflicts are fine as long as restoring the files resolves all of them
+    if !output.status.success() && mergetool::unmerged_paths()?.is_empty() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Cherry-pick failed: {}", error));
+    }
+
+    if verbose {
+        println!("🧔 VERBOSE: Restoring {}", paths.join(", "));
+    }
+    // Files the branch has go back to how it has them, the rest are removed again.
+    // Outside a sparse checkout's cone, files only come back with the skip bits ignored.
+    let output = Command::new("git")
+        .args(["ls-tree", "-r", "-z", "--name-only", "HEAD", "--"])
+        .args(paths)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git ls-tree"))?;
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let in_head: std::collections::HashSet<&str> =
+        listing.split('\0').filter(|p| !p.is_empty()).collect();
+    let (restored, removed): (Vec<&String>, Vec<&String>) = paths
+        .iter()
+        .partition(|path| in_head.contains(path.as_str()));
+
+    let commands: [(&[&str], &Vec<&String>); 2] = [
+        (
+            &[
+                "restore",
+                "--source=HEAD",
+                "--staged",
+                "--worktree",
+                "--ignore-skip-worktree-bits",
+                "--",
+            ],
+            &restored,
+        ),
+        (&["rm", "-q", "-f", "--ignore-unmatch", "--"], &removed),
+    ];
+    for (args, paths) in commands {
+        if paths.is_empty() {
+            continue;
+        }
+        let output = Command::new("git")
+            .args(args)
+            .args(paths.iter().map(|path| path.as_str()))
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            let paths: Vec<&str> = paths.iter().map(|path| path.as_str()).collect();
+            return Err(anyhow!("Failed to restore {}: {}", paths.join(", "), error));
         }
     }
 
-    Ok(commits)
-}
+    let unmerged = mergetool::unmerged_paths()?;
+    if !unmerged.is_empty() {
+        return Err(anyhow!(
+            "Cherry-pick failed: conflicts in {}",
+            unmerged.join(", ")
+        ));
+    }
 
-fn get_commit_files(sha: &str) -> Result<Vec<String>> {
-    let output = Command::new("git")
-        .args(&["show", "--name-only", "--format=", sha])
-        .output()
-        .map_err(|_| anyhow!("Failed to execute git show"))?;
+    commit_resolved(commit, config.cherry_pick.record_origin)
+}
 
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!("Failed to get commit files: {}", error));
+/// When a pick stops on conflicts in files `[[generated]]` covers, settles them as
+/// configured. Commits the pick and returns whether it did when nothing else is left
+/// unresolved.
+fn resolve_generated(commit: &Commit, config: &ChuckConfig, verbose: bool) -> Result<bool> {
+    if config.generated.is_empty() {
+        return Ok(false);
+    }
+    let unmerged = mergetool::unmerged_paths()?;
+    let resolved = generated::resolve_conflicts(&config.generated, &unmerged, verbose)?;
+    if resolved.is_empty() {
+        return Ok(false);
+    }
+    println!("🧔 Settled generated {}", resolved.join(", "));
+    if resolved.len() < unmerged.len() {
+        return Ok(false);
     }
 
-    let files: Vec<String> = String::from_utf8(output.stdout)?
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.trim().to_string())
-        .collect();
+    commit_resolved(commit, config.cherry_pick.record_origin)?;
+    Ok(true)
+}
 
-    Ok(files)
+/// When a pick stops on conflicts in `Cargo.toml` or `package.json`, merges the
+/// packages each side added, removed or changed by name. Commits the pick and returns
+/// whether it did when that leaves nothing unresolved, otherwise the merged manifests
+/// stay staged and the other files are left to the merge tool.
+fn resolve_manifests(commit: &Commit, record_origin: bool, verbose: bool) -> Result<bool> {
+    let unmerged = mergetool::unmerged_paths()?;
+    let resolved = dependencies::resolve_conflicts(&unmerged, verbose)?;
+    if resolved.is_empty() {
+        return Ok(false);
+    }
+    println!("🧔 Merged packages by name in {}", resolved.join(", "));
+    if resolved.len() < unmerged.len() {
+        return Ok(false);
+    }
+
+    commit_resolved(commit, record_origin)?;
+    Ok(true)
 }
 
-fn create_branch_with_commits(
-    commits: &[&Commit],
-    verbose: bool,
-    template_repo: &str,
-) -> Result<(String, String)> {
-    let timestamp = chrono::Utc::now().format("%Y%m%d-%H%M%S");
-    let timestamp_str = timestamp.to_string();
-    let branch_name = format!("chuck/{}", timestamp_str);
+/// When a pick stops on conflicts and someone is at the terminal, offers them the
+/// merge tool until every file is resolved or they decline. Commits the resolved pick
+/// and returns whether it did.
+fn resolve_with_mergetool(commit: &Commit, record_origin: bool) -> Result<bool> {
+    if !mergetool::can_offer() {
+        return Ok(false);
+    }
+    let mut unresolved = mergetool::unmerged_paths()?;
+    if unresolved.is_empty() {
+        return Ok(false);
+    }
 
     println!(
-        "🧔 Creating branch with {} selected commits...",
-        commits.len()
+        "🧔 {} conflicts in {}",
+        commit.short_hash,
+        unresolved.join(", ")
     );
-
-    if verbose {
-        println!("🧔 VERBOSE: About to create branch {}", branch_name);
+    loop {
+        if !mergetool::confirm(&format!("Resolve them with {}?", mergetool::describe()))? {
+            return Ok(false);
+        }
+        unresolved = mergetool::launch()?;
+        if unresolved.is_empty() {
+            break;
+        }
+        println!("🧔 Still unresolved: {}", unresolved.join(", "));
     }
 
-    let template_base_sha = get_template_base_commit(template_repo)?;
+    commit_resolved(commit, record_origin)?;
+    Ok(true)
+}
 
-    if verbose {
+/// Commits the staged result of a pick that stopped before committing, or skips it if
+/// nothing is left to commit
+fn commit_resolved(commit: &Commit, record_origin: bool) -> Result<()> {
+    let staged = Command::new("git")
+        .args(["diff", "--cached", "--quiet"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git diff"))?;
+
+    if staged.status.success() {
         println!(
-            "🧔 VERBOSE: Using template base commit: {}",
-            template_base_sha
+            "🧔 Skipping empty commit: {} - {}",
+            commit.short_hash, commit.message
         );
+        let _ = Command::new("git")
+            .args(["cherry-pick", "--quit"])
+            .output_local();
+        return Ok(());
     }
 
-    let config = read_chuck_config()?;
-    let template_remote_name = "chuck-template";
+    commit_as(commit, record_origin)
+}
 
-    if verbose {
-        println!("🧔 VERBOSE: Adding template remote and fetching...");
+/// Applies the diff between `commit` and its `parent`th parent with `git apply --3way`,
+/// leaving out changes to `leave_out`, and commits the result with the original
+/// author and message
+fn apply_commit_diff(
+    commit: &Commit,
+    parent: u32,
+    leave_out: &[String],
+    options: &CherryPickConfig,
+    verbose: bool,
+) -> Result<()> {
+    let mut args = vec!["diff".to_string(), "--binary".to_string()];
+    if let Some(dir) = &options.subtree {
+        args.push(format!("--relative={}", dir));
     }
-
-    let _ = Command::new("git")
-        .args(&["remote", "add", template_remote_name, &config.template.url])
-        .output();
-
-    let fetch_output = Command::new("git")
-        .args(&["fetch", template_remote_name])
-        .output()
-        .map_err(|_| anyhow!("Failed to fetch template remote"))?;
-
-    if !fetch_output.status.success() {
-        let error = String::from_utf8_lossy(&fetch_output.stderr);
-        return Err(anyhow!("Failed to fetch template: {}", error));
+    args.push(format!("{}^{}", commit.hash, parent));
+    args.push(commit.hash.clone());
+    if !leave_out.is_empty() {
+        args.push("--".to_string());
+        args.push(".".to_string());
+        args.extend(leave_out.iter().map(|path| format!(":(exclude){}", path)));
+    }
+    let diff = Command::new("git")
+        .args(&args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git diff"))?;
+
+    if !diff.status.success() {
+        let error = String::from_utf8_lossy(&diff.stderr);
+        return Err(anyhow!("Failed to diff commit: {}", error));
     }
 
-    if verbose {
-        println!("🧔 VERBOSE: Template fetched successfully");
+    // Nothing to cherry-pick, so there's no `--skip` to run either
+    if diff.stdout.is_empty() {
+        println!(
+            "🧔 Skipping empty commit: {} - {}",
+            commit.short_hash, commit.message
+        );
+        return Ok(());
     }
 
     let output = Command::new("git")
-        .args(&["checkout", "-b", &branch_name, &template_base_sha])
-        .output()
-        .map_err(|_| anyhow!("Failed to execute git checkout"))?;
+        .args(["apply", "--3way", "--index"])
+        .output_local_with_input(&diff.stdout)
+        .map_err(|e| process::command_error(e, "Failed to execute git apply"))?;
 
     if !output.status.success() {
         let error = String::from_utf8_lossy(&output.stderr);
-        return Err(anyhow!(
-            "Failed to create branch from template base: {}",
-            error
-        ));
+        if verbose {
+            println!("🧔 VERBOSE: Three-way apply error: {}", error);
+        }
+        return Err(anyhow!("Cherry-pick failed: {}", error));
     }
 
-    if verbose {
-        println!("🧔 VERBOSE: Branch created successfully from template base");
+    commit_as(commit, options.record_origin)
+}
+
+/// Commits what's staged with `commit`'s author and message
+fn commit_as(commit: &Commit, record_origin: bool) -> Result<()> {
+    // Reuse the original author and message
+    let output = Command::new("git")
+        .args(["commit", "--no-verify", "-C", &commit.hash])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git commit"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to commit applied changes: {}", error));
     }
 
-    for commit in commits {
-        println!(
-            "🧔 Cherry-picking: {} - {}",
-            commit.short_hash, commit.message
+    if record_origin {
+        let message = Command::new("git")
+            .args(["log", "-1", "--format=%B", "HEAD"])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+        let message = format!(
+            "{}\n\n(cherry picked from commit {})",
+            String::from_utf8_lossy(&message.stdout).trim_end(),
+            commit.hash
         );
-        if verbose {
-            println!("🧔 VERBOSE: About to cherry-pick commit {}", commit.hash);
+        let output = Command::new("git")
+            .args(["commit", "--amend", "--no-verify", "-m", &message])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git commit"))?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("Failed to record commit origin: {}", error));
         }
+    }
 
-        match cherry_pick_commit(&commit.hash, verbose) {
-            Ok(()) => {
-                if verbose {
-                    println!(
-                        "🧔 VERBOSE: Cherry-pick completed for {}",
-                        commit.short_hash
-                    );
-                }
-            }
-            Err(e) => {
-                if e.to_string().contains("empty") {
-                    println!(
-                        "🧔 Skipping empty commit: {} - {}",
-                        commit.short_hash, commit.message
-                    );
-                    let skip_output = Command::new("git")
-                        .args(&["cherry-pick", "--skip"])
-                        .output()
-                        .map_err(|_| anyhow!("Failed to skip cherry-pick"))?;
+    Ok(())
+}
 
-                    if !skip_output.status.success() {
-                        return Err(anyhow!("Failed to skip empty cherry-pick"));
-                    }
-                } else {
-                    return Err(e);
-                }
+fn pull_request_url(template_repo: &str, target_branch: &str, head: &str) -> String {
+    format!(
+        "https://github.com/{}/compare/{}...{}?expand=1",
+        template_repo, target_branch, head
+    )
+}
+
+/// Stops the run before any picking when the token can't push to `repo` or open pull
+/// requests there. Not being able to check isn't a reason to stop.
+fn check_token(repo: &str) -> Result<()> {
+    if read_only::is_enabled() {
+        return Ok(());
+    }
+    match access::token_problem(repo) {
+        Ok(None) => Ok(()),
+        Ok(Some(problem)) => Err(exit::fail(
+            Code::PushFailed,
+            anyhow!(
+                "🧔 \"You'll want the right key for that door\": {}. Run `gh auth refresh -s repo`, or use a token with repo scope.",
+                problem
+            ),
+        )),
+        Err(e) => {
+            println!("🧔 Couldn't check what your token can do: {}", e);
+            Ok(())
+        }
+    }
+}
+
+/// Shows exactly what's about to be pushed: the refspec, where to, and what the
+/// branch will contain on top of `base`
+fn show_push(
+    push_repo: &str,
+    push_target: &str,
+    branch_name: &str,
+    remote_branch_name: &str,
+    base: &str,
+) {
+    println!("🧔 About to push to {}:", push_repo);
+    println!(
+        "  git push {} {}:refs/heads/{}",
+        push_target, branch_name, remote_branch_name
+    );
+    match push::diffstat(base, branch_name) {
+        Ok(stat) => {
+            for line in stat.lines() {
+                println!("  {}", line);
             }
         }
+        Err(e) => println!("🧔 Couldn't work out what the branch changes: {}", e),
     }
+}
 
-    println!("🧔 Created branch: {}", branch_name);
-    println!("🧔 Successfully processed {} commits", commits.len());
+/// What the template's CONTRIBUTING.md asks of contributions, condensed, with a link to
+/// the rest
+fn show_guidelines(guidelines: &guidelines::Guidelines, repo: &str, rev: &str) {
+    println!(
+        "\n🧔 What {} asks of contributions ({}):",
+        repo, guidelines.path
+    );
+    if guidelines.requirements.is_empty() {
+        if !guidelines.sections.is_empty() {
+            println!("  It covers {}", guidelines.sections.join(", "));
+        }
+    } else {
+        for requirement in &guidelines.requirements {
+            println!("  • {}", requirement);
+        }
+    }
+    println!("  Read it all: {}", guidelines.url(repo, rev));
+}
 
-    Ok((branch_name, timestamp_str))
+/// Whether pushing to `push_repo` shows `source_repo`'s work to people who can't see
+/// it now: the project is private and where it's going isn't. `None` when GitHub
+/// couldn't say, which callers treat as exposed.
+fn exposes(source_repo: &str, push_repo: &str) -> Option<bool> {
+    let exposes = access::is_private(push_repo)
+        .and_then(|private| Ok(!private && access::is_private(source_repo)?));
+    exposes
+        .map_err(|e| {
+            println!(
+                "🧔 Couldn't check who'll be able to see the push, so taking it as public: {}",
+                e
+            )
+        })
+        .ok()
 }
 
-fn cherry_pick_commit(commit_sha: &str, verbose: bool) -> Result<()> {
-    let output = Command::new("git")
-        .args(&["cherry-pick", commit_sha])
-        .output()
-        .map_err(|_| anyhow!("Failed to execute git cherry-pick"))?;
+/// Whether to go ahead with showing private `source_repo`'s work in public
+/// `push_repo`, or possibly so when `known` is false. It takes a typed "yes", or
+/// `--push-public` without a terminal to type it in.
+fn confirm_exposure(
+    source_repo: &str,
+    push_repo: &str,
+    known: bool,
+    interactive: bool,
+    push_public: bool,
+) -> Result<bool> {
+    if push_public {
+        return Ok(true);
+    }
+    let situation = if known {
+        format!("{} is private and {} is public", source_repo, push_repo)
+    } else {
+        format!(
+            "{} may be private and {} public, GitHub couldn't say",
+            source_repo, push_repo
+        )
+    };
+    if !interactive {
+        return Err(exit::fail(
+            Code::PushFailed,
+            anyhow!(
+                "🧔 \"Not so fast\": {}. Pass --push-public to push there anyway",
+                situation
+            ),
+        ));
+    }
+    println!(
+        "\n🧔 ⚠️  {}: anyone {} be able to see this.",
+        situation,
+        if known { "will" } else { "may" }
+    );
+    mergetool::confirm_typed("Push it anyway?", "yes")
+}
 
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        if verbose {
-            println!("🧔 VERBOSE: Cherry-pick error: {}", error);
+/// The stricter checks before a private project's work goes public: nothing that
+/// looks like a secret, and the project's name and `[leak_guard]` words out of the
+/// commit messages
+fn guard_leaks(
+    config: &leak::LeakGuardConfig,
+    source_repo: &str,
+    base: &str,
+    branch_name: &str,
+) -> Result<()> {
+    println!(
+        "\n🧔 This may show {}'s work in public, so it gets a closer look",
+        source_repo
+    );
+    let secrets = leak::scan(config, base, branch_name)
+        .map_err(|e| anyhow!("🧔 \"Can't vouch for it\": {}", e))?;
+    if !secrets.is_empty() {
+        println!("\n🧔 ⚠️  These look like secrets:");
+        for secret in &secrets {
+            println!("  • {}", secret);
         }
-        return Err(anyhow!("Cherry-pick failed: {}", error));
+        return Err(exit::fail(
+            Code::PushFailed,
+            anyhow!(
+                "🧔 \"Loose lips sink ships\": take them out of the commits, or list made-up ones under allow_paths in [leak_guard] ({} is left as it is)",
+                branch_name
+            ),
+        ));
+    }
+    let redacted = leak::redact_messages(base, &config.words(source_repo))
+        .map_err(|e| anyhow!("🧔 \"Couldn't scrub the messages\": {}", e))?;
+    if redacted > 0 {
+        println!(
+            "🧔 Took the project's name and [leak_guard] words out of {} commit messages",
+            redacted
+        );
     }
-
     Ok(())
 }
 
 fn push_to_template_and_create_pr(
     branch_name: &str,
-    template_url: &str,
-    current_repo: &str,
-    timestamp: &str,
+    base: &str,
+    push_target: &str,
+    push_repo: &str,
+    target_branch: &str,
+    remote_branch_name: &str,
 ) -> Result<()> {
-    let template_repo = extract_repo_name_from_url(template_url)?;
-    let remote_branch_name = format!(
-        "chuck-from-{}-{}",
-        current_repo.replace("/", "-"),
-        timestamp
-    );
+    let _phase = profile::phase("push");
 
-    println!("🧔 Executing git push command...");
-    let push_command = format!(
-        "git push {} {}:{}",
-        template_url, branch_name, remote_branch_name
-    );
-    println!("🧔 Command: {}", push_command);
+    push::push_branch(push_target, branch_name, remote_branch_name, base)?;
 
-    let output = Command::new("git")
-        .args(&[
-            "push",
-            template_url,
-            &format!("{}:{}", branch_name, remote_branch_name),
-        ])
-        .output()
-        .map_err(|_| anyhow!("Failed to execute git push command"))?;
+    println!("🧔 ✅ Branch pushed successfully to {}!", push_repo);
+    println!("🧔 Branch: {} -> {}", branch_name, remote_branch_name);
+    println!("🧔 Target: {}", target_branch);
 
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
+    Ok(())
+}
 
-        println!("🧔 Push failed!");
-        if !stdout.is_empty() {
-            println!("🧔 Git output: {}", stdout);
-        }
-        if !error.is_empty() {
-            println!("🧔 Git error: {}", error);
+/// Title and body of the pull request contributing `commits` from `current_repo`
+fn contribution_text(current_repo: &str, commits: &[&Commit]) -> (String, String) {
+    let title = match commits {
+        [commit] => commit.message.clone(),
+        _ => format!("Upstream {} commits from {}", commits.len(), current_repo),
+    };
+
+    let mut body = format!("Contributed from {} with chuck.\n\n", current_repo);
+    for commit in commits {
+        body.push_str(&format!("- {} {}\n", commit.short_hash, commit.message));
+    }
+    let split: Vec<&&Commit> = commits.iter().filter(|c| !c.split_off.is_empty()).collect();
+    if !split.is_empty() {
+        body.push_str("\nLeft out as specific to the project:\n");
+        for commit in split {
+            body.push_str(&format!(
+                "- {}: {}\n",
+                commit.short_hash,
+                commit.split_off.join(", ")
+            ));
         }
+    }
+    (title, pr_metadata(current_repo, commits).embed(&body))
+}
+
+/// What the pull request carrying `commits` from `source` says about itself to later runs
+fn pr_metadata(source: &str, commits: &[&Commit]) -> metadata::PrMetadata {
+    metadata::PrMetadata::new(
+        source,
+        &state::user_name(),
+        commits
+            .iter()
+            .map(|c| metadata::SourceCommit {
+                sha: c.hash.clone(),
+                patch_id: c.patch_id.clone(),
+                subject: c.message.clone(),
+            })
+            .collect(),
+    )
+}
 
-        return Err(anyhow!("Git push failed: {}", error));
+fn open_pull_request(
+    template_repo: &str,
+    target_branch: &str,
+    head: &str,
+    title: &str,
+    body: &str,
+    reviewers: &[&str],
+    draft: bool,
+) -> Result<String> {
+    if let forge::Backend::Api { .. } = forge::backend()? {
+        return create_pull_request(
+            template_repo,
+            target_branch,
+            head,
+            title,
+            body,
+            reviewers,
+            draft,
+        );
     }
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    if !stdout.is_empty() {
-        println!("🧔 Git output: {}", stdout);
+    let mut args = vec![
+        "pr".to_string(),
+        "create".to_string(),
+        "--repo".to_string(),
+        template_repo.to_string(),
+        "--base".to_string(),
+        target_branch.to_string(),
+        "--head".to_string(),
+        head.to_string(),
+        "--title".to_string(),
+        title.to_string(),
+        "--body".to_string(),
+        body.to_string(),
+    ];
+    for reviewer in reviewers {
+        args.push("--reviewer".to_string());
+        args.push(reviewer.to_string());
+    }
+    if draft {
+        args.push("--draft".to_string());
     }
 
-    println!("🧔 ✅ Branch pushed successfully to template repository!");
+    let output = Command::new("gh")
+        .args(&args)
+        .output_network()
+        .map_err(|e| {
+            process::command_error(e, "GitHub CLI not found. Install with: brew install gh")
+        })?;
 
-    let pr_url = format!(
-        "https://github.com/{}/pull/new/{}",
-        template_repo, remote_branch_name
-    );
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(network::describe_failure(
+            "Failed to create pull request",
+            &error
+        )));
+    }
 
-    println!("\n🧔 📝 Next step: Create your pull request");
-    println!("🧔 PR URL: {}", pr_url);
-    println!("🧔 Branch: {} -> {}", branch_name, remote_branch_name);
-    println!("🧔 \"Now go make that pull request, kiddo!\"");
+    // gh prints the new PR's URL as the last line
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .last()
+        .unwrap_or_default()
+        .trim()
+        .to_string())
+}
 
-    Ok(())
+/// What `gh pr create` does, through the REST API
+fn create_pull_request(
+    template_repo: &str,
+    target_branch: &str,
+    head: &str,
+    title: &str,
+    body: &str,
+    reviewers: &[&str],
+    draft: bool,
+) -> Result<String> {
+    let pr = forge::send(
+        "POST",
+        &format!("repos/{}/pulls", template_repo),
+        &serde_json::json!({
+            "title": title,
+            "head": head,
+            "base": target_branch,
+            "body": body,
+            "draft": draft,
+        }),
+        "Failed to create pull request",
+    )?;
+    let url = pr
+        .get("html_url")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    if let (Some(number), false) = (
+        pr.get("number").and_then(Value::as_u64),
+        reviewers.is_empty(),
+    ) {
+        // Teams come as "org/team", requested by their slug
+        let (teams, users): (Vec<&str>, Vec<&str>) = reviewers
+            .iter()
+            .partition(|reviewer| reviewer.contains('/'));
+        let teams: Vec<&str> = teams
+            .iter()
+            .filter_map(|team| team.split('/').nth(1))
+            .collect();
+        if let Err(e) = forge::send(
+            "POST",
+            &format!(
+                "repos/{}/pulls/{}/requested_reviewers",
+                template_repo, number
+            ),
+            &serde_json::json!({ "reviewers": users, "team_reviewers": teams }),
+            "Failed to request reviews",
+        ) {
+            println!("🧔 {}", e);
+        }
+    }
+    Ok(url)
 }
@@ -0,0 +1,133 @@
+use crate::process::{self, CommandExt};
+use crate::read_only;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+pub const MANIFEST_FILE: &str = ".chuck-manifest.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ownership {
+    /// Identical to the template's copy
+    Template,
+    /// Only exists in this project
+    Project,
+    /// Came from the template but has changed on one side since
+    Diverged,
+}
+
+/// `.chuck-manifest.toml`, committed in the project: who owns each tracked file
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Manifest {
+    pub template: String,
+    /// Template commit the files were compared against
+    pub base: String,
+    pub generated: DateTime<Utc>,
+    pub files: BTreeMap<String, Ownership>,
+}
+
+impl Manifest {
+    /// Classifies every file at HEAD by comparing blobs with the template at `base`
+    pub fn generate(template: &str, base: &str) -> Result<Self> {
+        let ours = tree_blobs("HEAD")?;
+        let theirs = tree_blobs(base)?;
+
+        let files = ours
+            .into_iter()
+            .filter(|(path, _)| path != MANIFEST_FILE)
+            .map(|(path, blob)| {
+                let ownership = match theirs.get(&path) {
+                    Some(template_blob) if *template_blob == blob => Ownership::Template,
+                    Some(_) => Ownership::Diverged,
+                    None => Ownership::Project,
+                };
+                (path, ownership)
+            })
+            .collect();
+
+        Ok(Self {
+            template: template.to_string(),
+            base: base.to_string(),
+            generated: Utc::now(),
+            files,
+        })
+    }
+
+    pub fn load() -> Result<Option<Self>> {
+        let path = manifest_path()?;
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => return Ok(None),
+        };
+        let manifest =
+            toml::from_str(&content).map_err(|e| anyhow!("{} is invalid: {}", MANIFEST_FILE, e))?;
+        Ok(Some(manifest))
+    }
+
+    pub fn save(&self) -> Result<PathBuf> {
+        let path = manifest_path()?;
+        let content = format!(
+            "# Generated by `chuck manifest`. Commit it, and run it again when chuck says it's behind.\n{}",
+            toml::to_string(self)?
+        );
+        if !read_only::withheld(&format!("written {}", path.display())) {
+            fs::write(&path, content)?;
+        }
+        Ok(path)
+    }
+
+    /// Same classification as `other`, ignoring when each was generated
+    pub fn same_files(&self, other: &Self) -> bool {
+        self.template == other.template && self.base == other.base && self.files == other.files
+    }
+
+    pub fn ownership(&self, path: &str) -> Option<Ownership> {
+        self.files.get(path).copied()
+    }
+
+    pub fn count(&self, ownership: Ownership) -> usize {
+        self.files.values().filter(|o| **o == ownership).count()
+    }
+}
+
+fn manifest_path() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--show-toplevel"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("Not inside a git work tree"));
+    }
+
+    let root = String::from_utf8(output.stdout)?.trim().to_string();
+    Ok(PathBuf::from(root).join(MANIFEST_FILE))
+}
+
+/// Path -> blob id for every file in `rev`
+fn tree_blobs(rev: &str) -> Result<BTreeMap<String, String>> {
+    let output = Command::new("git")
+        .args(["ls-tree", "-r", "-z", rev])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git ls-tree"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to list files in {}: {}", rev, error));
+    }
+
+    // Each entry is "<mode> <type> <blob>\t<path>"
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter_map(|entry| {
+            let (meta, path) = entry.split_once('\t')?;
+            let blob = meta.split_whitespace().nth(2)?;
+            Some((path.to_string(), blob.to_string()))
+        })
+        .collect())
+}
@@ -0,0 +1,164 @@
+use crate::notify;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{self, IsTerminal, Write};
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// `[merge]` in the global config
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MergeConfig {
+    /// Passed to `git mergetool --tool`, otherwise git's own `merge.tool` applies
+    pub tool: Option<String>,
+    /// Offer the merge tool when a pick conflicts. Only ever asked in a terminal.
+    #[serde(default = "default_offer")]
+    pub offer: bool,
+}
+
+impl Default for MergeConfig {
+    fn default() -> Self {
+        Self {
+            tool: None,
+            offer: default_offer(),
+        }
+    }
+}
+
+fn default_offer() -> bool {
+    true
+}
+
+static CONFIG: OnceLock<MergeConfig> = OnceLock::new();
+
+pub fn configure(config: MergeConfig) {
+    let _ = CONFIG.set(config);
+}
+
+fn config() -> &'static MergeConfig {
+    CONFIG.get_or_init(MergeConfig::default)
+}
+
+/// Whether there's someone at a terminal to offer the merge tool to
+pub fn can_offer() -> bool {
+    config().offer && io::stdin().is_terminal() && io::stdout().is_terminal()
+}
+
+/// Paths with unresolved conflicts in the index
+pub fn unmerged_paths() -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["diff", "--name-only", "--diff-filter=U"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git diff"))?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Asks a yes/no question on the terminal, no unless answered yes
+pub fn confirm(question: &str) -> Result<bool> {
+    notify::waiting(question);
+    print!("🧔 {} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Like `confirm`, but only `word` typed out in full counts as yes
+pub fn confirm_typed(question: &str, word: &str) -> Result<bool> {
+    notify::waiting(question);
+    print!("🧔 {} Type {} to go ahead: ", question, word);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(answer.trim() == word)
+}
+
+/// What `launch` runs, for the prompt
+pub fn describe() -> String {
+    match &config().tool {
+        Some(tool) => format!("git mergetool --tool={}", tool),
+        None => "git mergetool".to_string(),
+    }
+}
+
+/// Runs `git mergetool` on the conflicted files and returns the ones still unresolved
+/// afterwards: left unmerged, or staged with conflict markers in them
+pub fn launch() -> Result<Vec<String>> {
+    let mut command = Command::new("git");
+    command.arg("mergetool").arg("--no-prompt");
+    if let Some(tool) = &config().tool {
+        command.arg(format!("--tool={}", tool));
+    }
+    // A non-zero exit just means some files weren't resolved, which is checked below
+    command
+        .status_interactive()
+        .map_err(|e| anyhow!("Failed to run {}: {}", describe(), e))?;
+
+    let mut unresolved = unmerged_paths()?;
+    let check = Command::new("git")
+        .args(["diff", "--cached", "--check"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git diff"))?;
+    for path in leftover_markers(&String::from_utf8_lossy(&check.stdout)) {
+        if !unresolved.contains(&path) {
+            unresolved.push(path);
+        }
+    }
+    Ok(unresolved)
+}
+
+/// Files `git diff --check` found conflict markers in. Whitespace problems don't count.
+pub fn leftover_markers(check: &str) -> Vec<String> {
+    let mut paths: Vec<String> = Vec::new();
+    for line in check.lines() {
+        // "path:line: leftover conflict marker"
+        let Some((path, _)) = line
+            .strip_suffix(": leftover conflict marker")
+            .and_then(|location| location.rsplit_once(':'))
+        else {
+            continue;
+        };
+        if !paths.iter().any(|p| p == path) {
+            paths.push(path.to_string());
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn finds_files_with_leftover_markers() {
+        let check = "\
+src/lib.rs:12: leftover conflict marker
+src/lib.rs:20: leftover conflict marker
+README.md:3: trailing whitespace.
++hello
+src/a:b.rs:7: leftover conflict marker
+";
+        assert_eq!(leftover_markers(check), ["src/lib.rs", "src/a:b.rs"]);
+    }
+
+    #[test]
+    fn reports_files_still_unresolved_after_the_tool() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git diff --name-only --diff-filter=U", "src/db.rs\n")
+                .on(
+                    "git diff --cached --check",
+                    "src/lib.rs:3: leftover conflict marker\n",
+                ),
+        );
+        let unresolved = process::with_runner(fake.clone(), || launch().unwrap());
+
+        assert_eq!(unresolved, ["src/db.rs", "src/lib.rs"]);
+        assert_eq!(fake.calls()[0], "git mergetool --no-prompt");
+    }
+}
@@ -0,0 +1,93 @@
+use serde::{Deserialize, Serialize};
+
+const OPEN: &str = "<!-- chuck-metadata ";
+const CLOSE: &str = " -->";
+
+/// What Chuck hides in the body of the pull requests it opens, as JSON in an HTML
+/// comment, so later runs can tell which commits a pull request carries however its
+/// branch was rebased or squashed
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PrMetadata {
+    /// Version of chuck that opened it
+    pub version: String,
+    /// `owner/repo` the commits come from
+    pub source: String,
+    /// Name of whoever ran Chuck
+    #[serde(default)]
+    pub by: String,
+    pub commits: Vec<SourceCommit>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SourceCommit {
+    pub sha: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch_id: Option<String>,
+    #[serde(default)]
+    pub subject: String,
+}
+
+impl PrMetadata {
+    pub fn new(source: &str, by: &str, commits: Vec<SourceCommit>) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            source: source.to_string(),
+            by: by.to_string(),
+            commits,
+        }
+    }
+
+    /// `body` with this at the end, where GitHub doesn't show it
+    pub fn embed(&self, body: &str) -> String {
+        // A "-->" in a subject would end the comment early
+        let json = serde_json::to_string(self)
+            .unwrap_or_default()
+            .replace("-->", "--\\u003e");
+        format!("{}\n{}{}{}\n", body.trim_end(), OPEN, json, CLOSE)
+    }
+
+    /// What a pull request's `body` says about it, `None` when Chuck didn't open it
+    /// or someone broke the block while editing
+    pub fn extract(body: &str) -> Option<Self> {
+        let start = body.rfind(OPEN)? + OPEN.len();
+        let end = start + body[start..].find(CLOSE)?;
+        serde_json::from_str(&body[start..end]).ok()
+    }
+
+    /// Whether it carries `sha`, or the same patch under another SHA
+    pub fn carries(&self, sha: &str, patch_id: Option<&str>) -> bool {
+        self.commits
+            .iter()
+            .any(|c| c.sha == sha || (patch_id.is_some() && c.patch_id.as_deref() == patch_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_the_pr_body() {
+        let metadata = PrMetadata::new(
+            "acme/app",
+            "Jane Doe",
+            vec![SourceCommit {
+                sha: "aaa".to_string(),
+                patch_id: Some("p1".to_string()),
+                subject: "Parse <!-- comments -->".to_string(),
+            }],
+        );
+        let body = metadata.embed("Contributed from acme/app with chuck.\n\n- aaa Fix\n");
+        assert!(body.starts_with(
+            "Contributed from acme/app with chuck.\n\n- aaa Fix\n<!-- chuck-metadata {"
+        ));
+        assert_eq!(body.matches("-->").count(), 1);
+
+        let edited = format!("{}\nThanks for reviewing!\n", body);
+        let extracted = PrMetadata::extract(&edited).unwrap();
+        assert_eq!(extracted, metadata);
+        assert!(extracted.carries("bbb", Some("p1")));
+        assert!(!extracted.carries("bbb", None));
+        assert_eq!(PrMetadata::extract("Fixes the thing"), None);
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::PathBuf;
+
+/// `[network]` in the global config. Chuck talks to the network only through git, gh and
+/// curl, so these are handed to them through the environment variables they understand.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NetworkConfig {
+    pub https_proxy: Option<String>,
+    pub no_proxy: Option<String>,
+    /// PEM bundle with extra CAs, e.g. a corporate TLS-inspecting proxy's root
+    pub ca_bundle: Option<PathBuf>,
+}
+
+/// Exports proxy and CA settings for every git/gh process spawned afterwards.
+/// Variables already set in the environment take precedence over the config file.
+pub fn apply(config: &NetworkConfig, verbose: bool) -> Result<()> {
+    if let Some(proxy) = &config.https_proxy {
+        set_default_env(&["HTTPS_PROXY", "https_proxy"], proxy);
+    }
+    if let Some(no_proxy) = &config.no_proxy {
+        set_default_env(&["NO_PROXY", "no_proxy"], no_proxy);
+    }
+
+    if let Some(ca_bundle) = &config.ca_bundle {
+        if !ca_bundle.is_file() {
+            return Err(anyhow!(
+                "CA bundle {} from the global config doesn't exist",
+                ca_bundle.display()
+            ));
+        }
+        let path = ca_bundle.to_string_lossy();
+        // gh (Go) reads SSL_CERT_FILE, git reads GIT_SSL_CAINFO, curl CURL_CA_BUNDLE
+        set_default_env(&["SSL_CERT_FILE"], &path);
+        set_default_env(&["GIT_SSL_CAINFO"], &path);
+        set_default_env(&["CURL_CA_BUNDLE"], &path);
+    }
+
+    if verbose {
+        for var in [
+            "HTTPS_PROXY",
+            "NO_PROXY",
+            "SSL_CERT_FILE",
+            "GIT_SSL_CAINFO",
+            "CURL_CA_BUNDLE",
+        ] {
+            if let Ok(value) = env::var(var) {
+                println!("🧔 VERBOSE: {}={}", var, value);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn set_default_env(names: &[&str], value: &str) {
+    if names.iter().any(|name| env::var_os(name).is_some()) {
+        return;
+    }
+    for name in names {
+        env::set_var(name, value);
+    }
+}
+
+/// Turns git/gh stderr into an error message, adding a hint when it looks like a
+/// TLS or proxy problem rather than a git one
+pub fn describe_failure(context: &str, stderr: &str) -> String {
+    let stderr = stderr.trim();
+    match hint(stderr) {
+        Some(hint) => format!("{}: {}\n  🧔 {}", context, stderr, hint),
+        None => format!("{}: {}", context, stderr),
+    }
+}
+
+fn hint(stderr: &str) -> Option<&'static str> {
+    let lower = stderr.to_lowercase();
+
+    if ["x509", "certificate", "ssl", "tls"]
+        .iter()
+        .any(|needle| lower.contains(needle))
+    {
+        return Some(
+            "This looks like a TLS certificate problem. If you're behind a corporate proxy, \
+             set [network] ca_bundle in ~/.config/chuck/config.toml to its root CA.",
+        );
+    }
+
+    if [
+        "proxy",
+        "could not resolve host",
+        "connection timed out",
+        "connection refused",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+    {
+        return Some(
+            "This looks like a network or proxy problem. Check HTTPS_PROXY/NO_PROXY \
+             or set [network] https_proxy in ~/.config/chuck/config.toml.",
+        );
+    }
+
+    None
+}
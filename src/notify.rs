@@ -0,0 +1,199 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Write};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// `[notify]` in the global config: how to get the user's attention when they've
+/// switched windows
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct NotifyConfig {
+    /// Ring the terminal bell
+    #[serde(default)]
+    pub bell: bool,
+    /// Show a desktop notification, with notify-send on Linux and osascript on macOS
+    #[serde(default)]
+    pub desktop: bool,
+    /// Seconds a phase has to take before its end is worth a notification. Questions
+    /// always notify.
+    #[serde(default = "default_after")]
+    pub after: u64,
+}
+
+impl Default for NotifyConfig {
+    fn default() -> Self {
+        Self {
+            bell: false,
+            desktop: false,
+            after: default_after(),
+        }
+    }
+}
+
+fn default_after() -> u64 {
+    10
+}
+
+/// `[notify]` in .chuckrc: who else hears about the project's contributions
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct WebhookConfig {
+    /// Slack or Teams incoming webhook that gets a message after each successful run
+    pub webhook: Option<String>,
+}
+
+/// What the webhook says about `commits` (short hash, subject) going from `source`
+/// to `template`, with the pull request at `link`
+pub fn summary(source: &str, template: &str, commits: &[(&str, &str)], link: &str) -> String {
+    let mut text = format!(
+        "{} contributed {} commit{} to {}: {}",
+        source,
+        commits.len(),
+        if commits.len() == 1 { "" } else { "s" },
+        template,
+        link
+    );
+    for (short_hash, subject) in commits {
+        text.push_str(&format!("\n• {} {}", short_hash, subject));
+    }
+    text
+}
+
+/// Posts `text` to `webhook`. Slack and Teams both take `{"text": ...}`.
+pub fn post(webhook: &str, text: &str) -> Result<()> {
+    // The URL is the secret, so it goes in through stdin like the forge's token
+    let quote = |s: &str| format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""));
+    let body = serde_json::json!({ "text": text }).to_string();
+    let config = format!(
+        "url = {}\nheader = \"Content-Type: application/json\"\ndata-binary = {}\n",
+        quote(webhook),
+        quote(&body)
+    );
+    let output = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--config", "-"])
+        .output_network_with_input(config.as_bytes())
+        .map_err(|e| process::command_error(e, "Failed to execute curl"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to post to the webhook: {}", error.trim()));
+    }
+    Ok(())
+}
+
+static CONFIG: OnceLock<NotifyConfig> = OnceLock::new();
+
+pub fn configure(config: NotifyConfig) {
+    let _ = CONFIG.set(config);
+}
+
+fn config() -> &'static NotifyConfig {
+    CONFIG.get_or_init(NotifyConfig::default)
+}
+
+/// Says `message` when what it reports on took `elapsed`, long enough to have
+/// looked away
+pub fn finished(message: &str, elapsed: Duration) {
+    finished_with(config(), message, elapsed);
+}
+
+/// Says Chuck is waiting on an answer to `question`
+pub fn waiting(question: &str) {
+    alert(config(), question);
+}
+
+fn finished_with(config: &NotifyConfig, message: &str, elapsed: Duration) {
+    if elapsed >= Duration::from_secs(config.after) {
+        alert(config, message);
+    }
+}
+
+fn alert(config: &NotifyConfig, message: &str) {
+    if config.bell {
+        eprint!("\x07");
+        let _ = io::stderr().flush();
+    }
+    if config.desktop {
+        // Nice to have, so a missing notifier isn't worth a word
+        let _ = desktop_command(message).output_local();
+    }
+}
+
+fn desktop_command(message: &str) -> Command {
+    if cfg!(target_os = "macos") {
+        let mut command = Command::new("osascript");
+        command.args([
+            "-e",
+            &format!(
+                "display notification \"{}\" with title \"Chuck\"",
+                message.replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+        ]);
+        command
+    } else {
+        let mut command = Command::new("notify-send");
+        command.args(["Chuck", message]);
+        command
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    #[test]
+    fn notifies_only_after_slow_phases() {
+        let config = NotifyConfig {
+            desktop: true,
+            ..NotifyConfig::default()
+        };
+        let program = desktop_command("")
+            .get_program()
+            .to_string_lossy()
+            .to_string();
+        let fake = Rc::new(FakeRunner::new());
+
+        process::with_runner(fake.clone(), || {
+            finished_with(&config, "Found 3 commits", Duration::from_secs(2));
+            finished_with(&config, "Pushed the branch", Duration::from_secs(45));
+        });
+
+        let calls = fake.calls_to(&program);
+        assert_eq!(calls.len(), 1);
+        assert!(calls[0].contains("Pushed the branch"), "{}", calls[0]);
+    }
+
+    #[test]
+    fn posts_the_summary_to_the_webhook() {
+        let fake = Rc::new(FakeRunner::new());
+        let text = summary(
+            "acme/app",
+            "acme/template",
+            &[("abc1234", "Fix \"login\""), ("def5678", "Add retries")],
+            "https://github.com/acme/template/pull/42",
+        );
+        process::with_runner(fake.clone(), || {
+            post("https://hooks.slack.com/services/T0/B0/x", &text).unwrap()
+        });
+
+        let calls = fake.calls_to("curl");
+        assert_eq!(calls.len(), 1);
+        assert!(!calls[0].contains("hooks.slack.com"), "{}", calls[0]);
+        let input = String::from_utf8(fake.inputs()[0].clone()).unwrap();
+        assert!(
+            input.starts_with("url = \"https://hooks.slack.com/services/T0/B0/x\"\n"),
+            "{}",
+            input
+        );
+        assert!(
+            input.contains(
+                r#"{\"text\":\"acme/app contributed 2 commits to acme/template: https://github.com/acme/template/pull/42\\n• abc1234 Fix \\\"login\\\"\\n• def5678 Add retries\"}"#
+            ),
+            "{}",
+            input
+        );
+    }
+}
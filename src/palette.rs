@@ -0,0 +1,57 @@
+/// How well `query` matches `name`: its characters have to appear in order, ignoring
+/// case. Runs of adjacent characters and ones starting a word score higher, so "ga"
+/// ranks "Show the commit graph" above "Toggle squash". `None` when it doesn't match.
+pub fn score(query: &str, name: &str) -> Option<i32> {
+    let name: Vec<char> = name.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut from = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !c.is_whitespace())
+    {
+        let at = from + name[from..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == at) {
+            score += 4;
+        }
+        if at == 0 || !name[at - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous = Some(at);
+        from = at + 1;
+    }
+    Some(score)
+}
+
+/// Indices of the `names` matching `query`, best first. Equally good matches keep
+/// their order, so an empty query lists them all as given.
+pub fn rank(query: &str, names: &[&str]) -> Vec<usize> {
+    let mut matches: Vec<(usize, i32)> = names
+        .iter()
+        .enumerate()
+        .filter_map(|(i, name)| score(query, name).map(|score| (i, score)))
+        .collect();
+    matches.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    matches.into_iter().map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranks_word_starts_and_runs_first() {
+        let names = [
+            "Toggle squash",
+            "Select all",
+            "Show the commit graph",
+            "Quit",
+        ];
+        assert_eq!(rank("ga", &names), [2, 0]);
+        assert_eq!(rank("sel all", &names), [1]);
+        assert_eq!(rank("", &names), [0, 1, 2, 3]);
+        assert_eq!(score("qx", "Quit"), None);
+    }
+}
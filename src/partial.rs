@@ -0,0 +1,284 @@
+use crate::network;
+use crate::process::{self, CommandExt};
+use crate::workspace;
+use anyhow::{anyhow, Result};
+use std::collections::BTreeSet;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// How much of the repository is actually on disk
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Setup {
+    /// `(remote, filter)` of each remote git fetches missing objects from on demand,
+    /// e.g. `("origin", "blob:none")` after `git clone --filter=blob:none`
+    pub promisors: Vec<(String, String)>,
+    /// Only part of the work tree is checked out
+    pub sparse: bool,
+}
+
+impl Setup {
+    pub fn is_partial(&self) -> bool {
+        !self.promisors.is_empty()
+    }
+
+    /// Filter to fetch other remotes with, so they stay as partial as the clone
+    pub fn filter(&self) -> Option<&str> {
+        self.promisors.first().map(|(_, filter)| filter.as_str())
+    }
+
+    fn is_promisor(&self, remote: &str) -> bool {
+        self.promisors.iter().any(|(name, _)| name == remote)
+    }
+}
+
+static SETUP: OnceLock<Setup> = OnceLock::new();
+
+pub fn configure(setup: Setup) {
+    let _ = SETUP.set(setup);
+}
+
+pub fn setup() -> &'static Setup {
+    SETUP.get_or_init(Setup::default)
+}
+
+/// Looks for promisor remotes and a sparse checkout in the repo's config
+pub fn detect() -> Setup {
+    let config = |args: &[&str]| {
+        Command::new("git")
+            .arg("config")
+            .args(args)
+            .output_local()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+            .unwrap_or_default()
+    };
+    Setup {
+        promisors: parse_promisors(&config(&[
+            "--get-regexp",
+            r"^remote\..*\.(promisor|partialclonefilter)$",
+        ])),
+        sparse: config(&["--bool", "--get", "core.sparseCheckout"]).trim() == "true",
+    }
+}
+
+/// Promisor remotes from `git config --get-regexp` output
+pub fn parse_promisors(config: &str) -> Vec<(String, String)> {
+    let mut promisors: Vec<(String, String)> = Vec::new();
+    let mut filters: Vec<(String, String)> = Vec::new();
+    for line in config.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let Some(key) = key.strip_prefix("remote.") else {
+            continue;
+        };
+        if let Some(remote) = key.strip_suffix(".promisor") {
+            if value.trim() == "true" {
+                promisors.push((remote.to_string(), String::new()));
+            }
+        } else if let Some(remote) = key.strip_suffix(".partialclonefilter") {
+            filters.push((remote.to_string(), value.trim().to_string()));
+        }
+    }
+    for (remote, filter) in &mut promisors {
+        *filter = filters
+            .iter()
+            .find(|(name, _)| name == remote)
+            .map(|(_, filter)| filter.clone())
+            .unwrap_or_else(|| "blob:none".to_string());
+    }
+    promisors
+}
+
+/// Keeps `command` from downloading missing objects in a partial clone. For checks
+/// that are better skipped than paid for with a pile of blobs.
+pub fn local_only(command: &mut Command) -> &mut Command {
+    if setup().is_partial() {
+        command.env("GIT_NO_LAZY_FETCH", "1");
+    }
+    command
+}
+
+/// Why a `local_only` command failed, when it's for want of file contents
+pub fn missing_contents(stderr: &str) -> Option<&'static str> {
+    stderr
+        .contains("lazy fetching disabled")
+        .then_some("it needs file contents this partial clone doesn't have")
+}
+
+/// Fetches the file contents the picks need in one go, instead of git fetching them
+/// one cherry-pick at a time: the files each `(parent, commit)` changes, on both
+/// sides and as they are at `template_base`. Only what's missing is fetched, from the
+/// project's promisor remote and the template's. Returns how many objects that was.
+pub fn prefetch(
+    setup: &Setup,
+    picks: &[(String, String)],
+    template_remote: &str,
+    template_base: &str,
+    subtree: Option<&str>,
+) -> Result<usize> {
+    let mut paths = BTreeSet::new();
+    for (parent, commit) in picks {
+        let changed = git(&[
+            "diff-tree",
+            "-r",
+            "-z",
+            "--no-renames",
+            "--name-only",
+            parent,
+            commit,
+        ])?;
+        paths.extend(
+            changed
+                .split('\0')
+                .filter(|path| !path.is_empty())
+                .map(str::to_string),
+        );
+    }
+    if paths.is_empty() {
+        return Ok(0);
+    }
+
+    let mut fetched = 0;
+    // A project cloned straight from the template gets both sides from it
+    let project_remote = setup
+        .promisors
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .find(|name| *name != template_remote)
+        .or_else(|| {
+            setup
+                .is_promisor(template_remote)
+                .then_some(template_remote)
+        });
+    if let Some(remote) = project_remote {
+        let revs: Vec<&str> = picks
+            .iter()
+            .flat_map(|(parent, commit)| [parent.as_str(), commit.as_str()])
+            .collect();
+        fetched += fetch_missing(remote, &revs, paths.iter().map(String::as_str))?;
+    }
+    if setup.is_promisor(template_remote) {
+        let template_paths = paths
+            .iter()
+            .filter_map(|path| workspace::relative(subtree.unwrap_or(""), path));
+        fetched += fetch_missing(template_remote, &[template_base], template_paths)?;
+    }
+    Ok(fetched)
+}
+
+/// Fetches the objects under `paths` in `revs` that aren't here yet from `remote`
+fn fetch_missing<'a>(
+    remote: &str,
+    revs: &[&'a str],
+    paths: impl Iterator<Item = &'a str>,
+) -> Result<usize> {
+    let mut args = vec!["rev-list", "--objects", "--missing=print", "--no-walk"];
+    args.extend_from_slice(revs);
+    args.push("--");
+    args.extend(paths);
+    let objects = git(&args)?;
+    let missing: Vec<&str> = objects
+        .lines()
+        .filter_map(|line| line.strip_prefix('?'))
+        .collect();
+    if missing.is_empty() {
+        return Ok(0);
+    }
+
+    // What git runs itself to fetch a missing object, just for all of them at once
+    let output = Command::new("git")
+        .args([
+            "-c",
+            "fetch.negotiationAlgorithm=noop",
+            "fetch",
+            remote,
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+            "--filter=blob:none",
+            "--stdin",
+        ])
+        .output_network_with_input(format!("{}\n", missing.join("\n")).as_bytes())
+        .map_err(|e| process::command_error(e, "Failed to execute git fetch"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(network::describe_failure(
+            &format!("Failed to fetch file contents from {}", remote),
+            &error
+        )));
+    }
+    Ok(missing.len())
+}
+
+/// Runs git without lazy fetches, it's only asked about what's here
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .env("GIT_NO_LAZY_FETCH", "1")
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn finds_promisor_remotes() {
+        let config = "remote.origin.promisor true\n\
+                      remote.origin.partialclonefilter blob:limit=1m\n\
+                      remote.template.promisor true\n\
+                      remote.old.promisor false\n";
+        assert_eq!(
+            parse_promisors(config),
+            [
+                ("origin".to_string(), "blob:limit=1m".to_string()),
+                ("template".to_string(), "blob:none".to_string())
+            ]
+        );
+        assert!(parse_promisors("").is_empty());
+    }
+
+    #[test]
+    fn fetches_only_missing_objects_in_one_go() {
+        let setup = Setup {
+            promisors: vec![("origin".to_string(), "blob:none".to_string())],
+            sparse: false,
+        };
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git diff-tree", "src/a.rs\0src/b.rs\0")
+                .on("git rev-list", "abc\ndef src/a.rs\n?111\n?222\n"),
+        );
+
+        let fetched = process::with_runner(fake.clone(), || {
+            prefetch(
+                &setup,
+                &[("c1^1".to_string(), "c1".to_string())],
+                "template",
+                "base",
+                None,
+            )
+        });
+
+        assert_eq!(fetched.unwrap(), 2);
+        assert_eq!(
+            fake.calls_to("git rev-list"),
+            ["git rev-list --objects --missing=print --no-walk c1^1 c1 -- src/a.rs src/b.rs"]
+        );
+        // The template isn't a promisor remote, so it has everything already
+        assert_eq!(fake.calls_to("git -c").len(), 1);
+        assert_eq!(fake.inputs()[0], b"111\n222\n");
+    }
+}
@@ -0,0 +1,156 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// Overrides where the global config is read from, since the config can't say itself
+pub const CONFIG_DIR_VAR: &str = "CHUCK_CONFIG_DIR";
+
+/// `[paths]` in the global config: where Chuck keeps things on disk instead of the
+/// defaults, for machines where those aren't writable or get wiped
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PathsConfig {
+    /// Per-clone state (journal, preferences, ignore list, checkpoints) instead of
+    /// `.git/chuck`, a directory per clone
+    pub state: Option<PathBuf>,
+    /// Kept GitHub API responses instead of `api-cache` in the state directory, a
+    /// directory per clone
+    pub cache: Option<PathBuf>,
+    /// Scratch files, like merge previews and downloaded updates, instead of the
+    /// system's temporary directory
+    pub temp: Option<PathBuf>,
+}
+
+static PATHS: OnceLock<PathsConfig> = OnceLock::new();
+
+pub fn configure(config: PathsConfig) {
+    let _ = PATHS.set(config);
+}
+
+fn paths() -> &'static PathsConfig {
+    PATHS.get_or_init(PathsConfig::default)
+}
+
+/// Where the global `config.toml` lives: `$CHUCK_CONFIG_DIR`, or the platform's
+/// place for it. `None` without a home directory to put it in.
+pub fn config_file() -> Option<PathBuf> {
+    let dir = match env::var_os(CONFIG_DIR_VAR) {
+        Some(dir) => PathBuf::from(dir),
+        None => platform_config_dir()?.join("chuck"),
+    };
+    Some(dir.join("config.toml"))
+}
+
+/// `%APPDATA%`, the roaming app data known folder
+#[cfg(windows)]
+fn platform_config_dir() -> Option<PathBuf> {
+    env::var_os("APPDATA").map(PathBuf::from)
+}
+
+/// `$XDG_CONFIG_HOME` when it's set, then `~/.config` for configs written there
+/// before, then `~/Library/Application Support`
+#[cfg(target_os = "macos")]
+fn platform_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = env::var_os("XDG_CONFIG_HOME") {
+        return Some(PathBuf::from(dir));
+    }
+    let home = PathBuf::from(env::var_os("HOME")?);
+    let dot_config = home.join(".config");
+    if dot_config.join("chuck").is_dir() {
+        return Some(dot_config);
+    }
+    Some(home.join("Library").join("Application Support"))
+}
+
+/// `$XDG_CONFIG_HOME`, `~/.config` when it isn't set
+#[cfg(not(any(windows, target_os = "macos")))]
+fn platform_config_dir() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| Some(Path::new(&env::var_os("HOME")?).join(".config")))
+}
+
+/// The per-clone state directory of the clone whose git directory is `git_dir`
+pub fn state_dir(git_dir: &Path) -> PathBuf {
+    state_dir_in(paths(), git_dir)
+}
+
+fn state_dir_in(config: &PathsConfig, git_dir: &Path) -> PathBuf {
+    match &config.state {
+        Some(state) => state.join(clone_key(git_dir)),
+        None => git_dir.join("chuck"),
+    }
+}
+
+/// Where kept API responses of the clone whose git directory is `git_dir` go
+pub fn cache_dir(git_dir: &Path) -> PathBuf {
+    cache_dir_in(paths(), git_dir)
+}
+
+fn cache_dir_in(config: &PathsConfig, git_dir: &Path) -> PathBuf {
+    match &config.cache {
+        Some(cache) => cache.join(clone_key(git_dir)),
+        None => state_dir_in(config, git_dir).join("api-cache"),
+    }
+}
+
+/// Where scratch files go
+pub fn temp_dir() -> PathBuf {
+    paths().temp.clone().unwrap_or_else(env::temp_dir)
+}
+
+/// A directory name telling clones apart, from where their git directory is
+fn clone_key(git_dir: &Path) -> String {
+    let absolute = git_dir
+        .canonicalize()
+        .or_else(|_| env::current_dir().map(|dir| dir.join(git_dir)))
+        .unwrap_or_else(|_| git_dir.to_path_buf());
+    absolute
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .trim_matches('_')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_each_clone_apart_where_configured() {
+        let git_dir = Path::new("/work/app/.git");
+        let defaults = PathsConfig::default();
+        assert_eq!(
+            state_dir_in(&defaults, git_dir),
+            Path::new("/work/app/.git/chuck")
+        );
+        assert_eq!(
+            cache_dir_in(&defaults, git_dir),
+            Path::new("/work/app/.git/chuck/api-cache")
+        );
+
+        let moved = PathsConfig {
+            state: Some(PathBuf::from("/var/chuck")),
+            ..PathsConfig::default()
+        };
+        assert_eq!(
+            state_dir_in(&moved, git_dir),
+            Path::new("/var/chuck/work_app__git")
+        );
+        assert_eq!(
+            cache_dir_in(&moved, git_dir),
+            Path::new("/var/chuck/work_app__git/api-cache")
+        );
+
+        let cached = PathsConfig {
+            cache: Some(PathBuf::from("/cache/chuck")),
+            ..moved
+        };
+        assert_eq!(
+            cache_dir_in(&cached, git_dir),
+            Path::new("/cache/chuck/work_app__git")
+        );
+    }
+}
@@ -0,0 +1,277 @@
+use crate::adopt::Blobs;
+use crate::glob::gitignore_match;
+use crate::process::{self, CommandExt};
+use crate::size::SoftLimits;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::process::Command;
+
+const POLICY_FILE: &str = ".chuck-policy.toml";
+
+/// `.chuck-policy.toml`, published in the template to describe what it accepts
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    /// If set, only files matching one of these patterns may be contributed
+    pub allowed_paths: Vec<String>,
+    /// Files matching these patterns may never be contributed
+    pub forbidden_paths: Vec<String>,
+    /// CI checks the PR has to pass, listed so contributors know what's coming
+    pub required_checks: Vec<String>,
+    /// Files every project has to keep, like the license or the shared CI workflow
+    pub required_files: Vec<String>,
+    /// Most files a single PR may touch
+    pub max_files: Option<usize>,
+    /// Most commits a single PR may carry
+    pub max_commits: Option<usize>,
+    /// Only commits with a verified GPG or SSH signature may be contributed
+    pub require_signed: bool,
+    /// Refuse to build the branch on violations instead of only warning
+    pub enforce: bool,
+    /// Sizes past which contributors are nudged to split the PR
+    pub soft_limits: SoftLimits,
+    /// Name for pushed branches, e.g. `contrib/{repo}/{topic}`, filled in with
+    /// `{owner}` and `{repo}` of the contributing project, `{topic}` from the first
+    /// commit's subject and the `{timestamp}` of the run
+    pub branch_namespace: Option<String>,
+}
+
+impl Policy {
+    /// Reads the policy from the template at `rev`. `None` if the template has none.
+    pub fn load(rev: &str) -> Result<Option<Self>> {
+        let output = Command::new("git")
+            .args(["show", &format!("{}:{}", rev, POLICY_FILE)])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git show"))?;
+
+        if !output.status.success() {
+            return Ok(None);
+        }
+
+        let content = String::from_utf8(output.stdout)?;
+        let policy = toml::from_str(&content)
+            .map_err(|e| anyhow!("Template's {} is invalid: {}", POLICY_FILE, e))?;
+        Ok(Some(policy))
+    }
+
+    /// Why `path` can't be contributed, if it can't
+    pub fn path_violation(&self, path: &str) -> Option<String> {
+        if let Some(pattern) = self
+            .forbidden_paths
+            .iter()
+            .find(|pattern| gitignore_match(pattern, path))
+        {
+            return Some(format!("{} is forbidden ({})", path, pattern));
+        }
+        if !self.allowed_paths.is_empty()
+            && !self
+                .allowed_paths
+                .iter()
+                .any(|pattern| gitignore_match(pattern, path))
+        {
+            return Some(format!("{} is outside the allowed paths", path));
+        }
+        None
+    }
+
+    /// Everything wrong with contributing `commit_count` commits touching `paths` in one PR
+    pub fn violations<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a str>,
+        commit_count: usize,
+    ) -> Vec<String> {
+        let paths: BTreeSet<&str> = paths.into_iter().collect();
+        let mut violations: Vec<String> = paths
+            .iter()
+            .filter_map(|path| self.path_violation(path))
+            .collect();
+
+        if let Some(max) = self.max_files {
+            if paths.len() > max {
+                violations.push(format!(
+                    "PR touches {} files, the template accepts at most {}",
+                    paths.len(),
+                    max
+                ));
+            }
+        }
+        if let Some(max) = self.max_commits {
+            if commit_count > max {
+                violations.push(format!(
+                    "PR carries {} commits, the template accepts at most {}",
+                    commit_count, max
+                ));
+            }
+        }
+
+        violations
+    }
+
+    /// The required files the project at `project` doesn't have where the template at
+    /// `template` has them
+    pub fn missing_files(&self, template: &Blobs, project: &Blobs) -> Vec<MissingFile> {
+        self.required_files
+            .iter()
+            .filter(|path| !project.contains_key(path.as_str()))
+            .map(|path| MissingFile {
+                path: path.clone(),
+                // The template's version somewhere else is most likely a move
+                moved_to: template.get(path).and_then(|blob| {
+                    project
+                        .iter()
+                        .filter(|(_, b)| *b == blob)
+                        .map(|(p, _)| p.clone())
+                        .min()
+                }),
+            })
+            .collect()
+    }
+
+    /// The required files among `paths`
+    pub fn required<'a>(&self, paths: &'a [String]) -> Vec<&'a str> {
+        paths
+            .iter()
+            .filter(|path| self.required_files.contains(path))
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The branch to push a contribution from `current_repo` (`owner/name`) to:
+    /// `branch_namespace` filled in, or `chuck-from-<owner>-<name>-<timestamp>`
+    /// without one. A namespace that doesn't make a valid branch name is ignored.
+    pub fn remote_branch(&self, current_repo: &str, subject: &str, timestamp: &str) -> String {
+        let default = format!(
+            "chuck-from-{}-{}",
+            current_repo.replace('/', "-"),
+            timestamp
+        );
+        let Some(pattern) = &self.branch_namespace else {
+            return default;
+        };
+        let (owner, repo) = current_repo.split_once('/').unwrap_or(("", current_repo));
+        let name = pattern
+            .replace("{owner}", owner)
+            .replace("{repo}", repo)
+            .replace("{topic}", &topic(subject))
+            .replace("{timestamp}", timestamp);
+        if valid_branch(&name) {
+            name
+        } else {
+            println!(
+                "🧔 Ignoring the template's branch_namespace, {} isn't a valid branch name",
+                name
+            );
+            default
+        }
+    }
+}
+
+/// A file the template requires that the project removed or renamed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingFile {
+    pub path: String,
+    /// Where the project has the template's version of it instead
+    pub moved_to: Option<String>,
+}
+
+/// `subject` as a branch name component: lowercase words joined by dashes
+fn topic(subject: &str) -> String {
+    let mut topic = String::new();
+    for word in subject
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+    {
+        if topic.len() + word.len() >= 40 && !topic.is_empty() {
+            break;
+        }
+        if !topic.is_empty() {
+            topic.push('-');
+        }
+        topic.push_str(&word.to_ascii_lowercase());
+    }
+    if topic.is_empty() {
+        "changes".to_string()
+    } else {
+        topic
+    }
+}
+
+/// Whether git accepts `name` under refs/heads, per git-check-ref-format
+fn valid_branch(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with('-')
+        && !name.contains("..")
+        && !name.contains("@{")
+        && !name.contains("//")
+        && !name.ends_with(".lock")
+        && !name.ends_with('.')
+        && !name
+            .chars()
+            .any(|c| c.is_ascii_control() || " ~^:?*[\\{}".contains(c))
+        && name
+            .split('/')
+            .all(|part| !part.is_empty() && !part.starts_with('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_branches_after_the_namespace() {
+        let mut policy = Policy::default();
+        assert_eq!(
+            policy.remote_branch("jane/app", "Fix the login form", "20250120-143022"),
+            "chuck-from-jane-app-20250120-143022"
+        );
+
+        policy.branch_namespace = Some("contrib/{repo}/{topic}".to_string());
+        assert_eq!(
+            policy.remote_branch("jane/app", "Fix the login form!", "20250120-143022"),
+            "contrib/app/fix-the-login-form"
+        );
+
+        policy.branch_namespace = Some("contrib/{project}/{topic}".to_string());
+        assert_eq!(
+            policy.remote_branch("jane/app", "Fix", "20250120-143022"),
+            "chuck-from-jane-app-20250120-143022"
+        );
+    }
+
+    #[test]
+    fn finds_removed_and_renamed_required_files() {
+        let policy = Policy {
+            required_files: ["LICENSE", ".github/workflows/ci.yml", "SECURITY.md"]
+                .map(str::to_string)
+                .to_vec(),
+            ..Policy::default()
+        };
+        let blobs = |entries: &[(&str, &str)]| -> Blobs {
+            entries
+                .iter()
+                .map(|(path, blob)| (path.to_string(), blob.to_string()))
+                .collect()
+        };
+        let template = blobs(&[
+            ("LICENSE", "l1"),
+            (".github/workflows/ci.yml", "c1"),
+            ("SECURITY.md", "s1"),
+        ]);
+        let project = blobs(&[("LICENSE", "l2"), (".github/workflows/build.yml", "c1")]);
+
+        assert_eq!(
+            policy.missing_files(&template, &project),
+            [
+                MissingFile {
+                    path: ".github/workflows/ci.yml".to_string(),
+                    moved_to: Some(".github/workflows/build.yml".to_string()),
+                },
+                MissingFile {
+                    path: "SECURITY.md".to_string(),
+                    moved_to: None,
+                },
+            ]
+        );
+    }
+}
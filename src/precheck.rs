@@ -0,0 +1,175 @@
+use crate::paths;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Picks checked at once, a scratch index and a few git processes each
+const WORKERS: usize = 8;
+
+/// A commit as it would be picked: the diff from `parent` to `commit`, without the
+/// changes to `leave_out`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pick {
+    pub parent: String,
+    pub commit: String,
+    pub leave_out: Vec<String>,
+}
+
+/// How a pick would go on the template base by itself
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Applies,
+    /// The files the three-way merge leaves conflicted, or git's reason when it can't
+    /// merge at all, like a file the template doesn't have
+    Conflicts(String),
+    /// No changes, or ones the template already has
+    Empty,
+}
+
+impl Outcome {
+    pub fn badge(&self) -> &'static str {
+        match self {
+            Outcome::Applies => "✔ will apply",
+            Outcome::Conflicts(_) => "✖ will conflict",
+            Outcome::Empty => "∅ empty",
+        }
+    }
+}
+
+/// Checks every pick against `base` with `git apply --3way --check`, several at a time
+/// and without touching the worktree or the index. `subtree` is the workspace the
+/// picks come from, if any.
+pub fn check(picks: &[Pick], base: &str, subtree: Option<&str>) -> Vec<Result<Outcome>> {
+    process::map_concurrently(
+        picks,
+        WORKERS,
+        |pick| check_one(pick, base, subtree),
+        |_| {},
+    )
+}
+
+fn check_one(pick: &Pick, base: &str, subtree: Option<&str>) -> Result<Outcome> {
+    let mut args = vec!["diff".to_string(), "--binary".to_string()];
+    if let Some(dir) = subtree {
+        args.push(format!("--relative={}", dir));
+    }
+    args.push(pick.parent.clone());
+    args.push(pick.commit.clone());
+    if !pick.leave_out.is_empty() {
+        args.push("--".to_string());
+        args.push(".".to_string());
+        args.extend(
+            pick.leave_out
+                .iter()
+                .map(|path| format!(":(exclude){}", path)),
+        );
+    }
+    let diff = Command::new("git")
+        .args(&args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git diff"))?;
+    if !diff.status.success() {
+        let error = String::from_utf8_lossy(&diff.stderr);
+        return Err(anyhow!("Failed to diff {}: {}", pick.commit, error.trim()));
+    }
+    if diff.stdout.is_empty() {
+        return Ok(Outcome::Empty);
+    }
+
+    // Each pick gets an index of its own holding the template base, so they can be
+    // checked side by side without going near the real one
+    let index = paths::temp_dir().join(format!(
+        "chuck-check-{}-{}.index",
+        std::process::id(),
+        pick.commit
+    ));
+    let outcome = check_in(&index, &diff.stdout, base);
+    let _ = fs::remove_file(&index);
+    outcome
+}
+
+fn check_in(index: &Path, diff: &[u8], base: &str) -> Result<Outcome> {
+    let output = Command::new("git")
+        .env("GIT_INDEX_FILE", index)
+        .args(["read-tree", base])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git read-tree"))?;
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Failed to read the template base: {}",
+            error.trim()
+        ));
+    }
+
+    // Going backwards cleanly means the template already has it
+    let reverse = Command::new("git")
+        .env("GIT_INDEX_FILE", index)
+        .args(["apply", "--cached", "--check", "-R"])
+        .output_local_with_input(diff)
+        .map_err(|e| process::command_error(e, "Failed to execute git apply"))?;
+    if reverse.status.success() {
+        return Ok(Outcome::Empty);
+    }
+
+    let output = Command::new("git")
+        .env("GIT_INDEX_FILE", index)
+        .args(["apply", "--cached", "--3way", "--check"])
+        .output_local_with_input(diff)
+        .map_err(|e| process::command_error(e, "Failed to execute git apply"))?;
+    Ok(outcome(
+        output.status.success(),
+        &String::from_utf8_lossy(&output.stderr),
+    ))
+}
+
+/// What `git apply --3way --check` said. It succeeds with conflicts too, only naming
+/// the files it left conflicted.
+fn outcome(success: bool, stderr: &str) -> Outcome {
+    if !success {
+        let reason = stderr
+            .lines()
+            .filter_map(|line| line.strip_prefix("error: "))
+            .next()
+            .unwrap_or(stderr.trim());
+        return Outcome::Conflicts(reason.to_string());
+    }
+    let conflicted: Vec<&str> = stderr
+        .lines()
+        .filter_map(|line| {
+            line.strip_prefix("Applied patch to '")?
+                .strip_suffix("' with conflicts.")
+        })
+        .collect();
+    if conflicted.is_empty() {
+        Outcome::Applies
+    } else {
+        Outcome::Conflicts(format!("conflicts in {}", conflicted.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_what_git_apply_says() {
+        assert_eq!(
+            outcome(true, "Applied patch to 'src/a.rs' cleanly.\n"),
+            Outcome::Applies
+        );
+        assert_eq!(
+            outcome(
+                true,
+                "Applied patch to 'src/a.rs' with conflicts.\nApplied patch to 'src/b.rs' cleanly.\nApplied patch to 'README.md' with conflicts.\n"
+            ),
+            Outcome::Conflicts("conflicts in src/a.rs, README.md".to_string())
+        );
+        assert_eq!(
+            outcome(false, "error: src/gone.rs: does not exist in index\n"),
+            Outcome::Conflicts("src/gone.rs: does not exist in index".to_string())
+        );
+    }
+}
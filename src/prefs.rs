@@ -0,0 +1,127 @@
+use crate::journal;
+use crate::read_only;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+const PREFS_FILE: &str = "prefs.toml";
+
+/// Options last passed on the command line in this repository, kept in
+/// `.git/chuck/prefs.toml` as defaults for the next run. Only what was passed
+/// explicitly is remembered, so `.chuckrc` still decides the rest.
+#[derive(Debug, Default, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Prefs {
+    /// `--squash` / `--no-squash`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub squash: Option<bool>,
+    /// `--target`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    /// `--draft` / `--no-draft`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub draft: Option<bool>,
+    /// `--author`, `""` for everyone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+}
+
+impl Prefs {
+    pub fn path() -> Result<PathBuf> {
+        Ok(journal::chuck_dir()?.join(PREFS_FILE))
+    }
+
+    /// What earlier runs left, empty before the first
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        // Only for this run under --read-only, said while the TUI is up
+        if read_only::is_enabled() {
+            return Ok(());
+        }
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// These preferences with what `given` sets on top
+    pub fn with(&self, given: &Prefs) -> Prefs {
+        Prefs {
+            squash: given.squash.or(self.squash),
+            target: given.target.clone().or_else(|| self.target.clone()),
+            draft: given.draft.or(self.draft),
+            author: given.author.clone().or_else(|| self.author.clone()),
+        }
+    }
+
+    /// The flags that would set what these preferences set and `given` doesn't, to
+    /// say which ones came from last time
+    pub fn flags_besides(&self, given: &Prefs) -> Vec<String> {
+        let switch = |name: &str, on: bool| {
+            if on {
+                format!("--{}", name)
+            } else {
+                format!("--no-{}", name)
+            }
+        };
+        let mut flags = Vec::new();
+        if let (Some(squash), None) = (self.squash, given.squash) {
+            flags.push(switch("squash", squash));
+        }
+        if let (Some(target), None) = (&self.target, &given.target) {
+            flags.push(format!("--target {}", target));
+        }
+        if let (Some(draft), None) = (self.draft, given.draft) {
+            flags.push(switch("draft", draft));
+        }
+        if let (Some(author), None) = (&self.author, &given.author) {
+            flags.push(format!("--author {:?}", author));
+        }
+        flags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn given_options_win_and_the_rest_come_from_last_time() {
+        let saved: Prefs =
+            toml::from_str("squash = true\ntarget = \"release/1.x\"\nauthor = \"jane\"\n").unwrap();
+        let given = Prefs {
+            target: Some("main".to_string()),
+            draft: Some(false),
+            ..Prefs::default()
+        };
+
+        let prefs = saved.with(&given);
+        assert_eq!(
+            prefs,
+            Prefs {
+                squash: Some(true),
+                target: Some("main".to_string()),
+                draft: Some(false),
+                author: Some("jane".to_string()),
+            }
+        );
+        assert_eq!(
+            prefs.flags_besides(&given),
+            ["--squash", "--author \"jane\""]
+        );
+        assert_eq!(
+            toml::to_string(&prefs).unwrap(),
+            "squash = true\ntarget = \"main\"\ndraft = false\nauthor = \"jane\"\n"
+        );
+    }
+}
@@ -0,0 +1,456 @@
+use crate::lfs;
+use crate::paths;
+use crate::process::{self, CommandExt};
+use crate::workspace;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::process::Command;
+
+/// Past this many line pairs, the changed middle of a file is shown replaced as a
+/// whole instead of diffed line by line
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// A stretch of a line. `changed` when the other side of the row doesn't have it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    pub changed: bool,
+}
+
+/// A line on one side of a row. The newline is only kept when it's what changed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Side {
+    /// 1-based
+    pub number: usize,
+    pub segments: Vec<Segment>,
+}
+
+/// A line of the template's version next to the same line after the pick. Either side
+/// is `None` where the other has a line it doesn't.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub before: Option<Side>,
+    pub after: Option<Side>,
+}
+
+impl Row {
+    pub fn is_same(&self) -> bool {
+        match (&self.before, &self.after) {
+            (Some(before), Some(after)) => before
+                .segments
+                .iter()
+                .chain(&after.segments)
+                .all(|segment| !segment.changed),
+            _ => false,
+        }
+    }
+}
+
+/// A file as the template has it next to how it would be after cherry-picking a commit
+#[derive(Debug, Clone, PartialEq)]
+pub struct Preview {
+    pub rows: Vec<Row>,
+    /// Anything to know before reading the rows
+    pub note: Option<&'static str>,
+}
+
+/// Previews `path` as changed by `commit`. The template's side is `template_base`'s
+/// version, in the workspace at `subtree` if there is one. The other side is what the
+/// cherry-pick would make of it: a three-way merge with `parent` as the base.
+pub fn load(
+    template_base: &str,
+    parent: &str,
+    commit: &str,
+    path: &str,
+    old_path: Option<&str>,
+    subtree: &str,
+) -> Result<Preview> {
+    let source = old_path.unwrap_or(path);
+    let Some(template_path) = workspace::relative(subtree, source) else {
+        return Ok(Preview {
+            rows: Vec::new(),
+            note: Some("Outside the workspace, the pick leaves it out"),
+        });
+    };
+
+    let names = [
+        format!("{}:{}", template_base, template_path),
+        format!("{}:{}", parent, source),
+        format!("{}:{}", commit, path),
+    ];
+    let [template, base, theirs]: [Option<Vec<u8>>; 3] =
+        lfs::read_objects(names.iter().map(String::as_str))?
+            .try_into()
+            .map_err(|_| anyhow!("Unexpected git cat-file output"))?;
+
+    if [&template, &base, &theirs]
+        .iter()
+        .any(|content| content.as_ref().is_some_and(|c| c.contains(&0)))
+    {
+        return Ok(Preview {
+            rows: Vec::new(),
+            note: Some("Binary file, nothing to show side by side"),
+        });
+    }
+
+    let (after, note) = match (&template, &base, theirs) {
+        (_, _, None) => (Vec::new(), Some("The pick deletes it from the template")),
+        (None, None, Some(theirs)) => (theirs, Some("New in the template")),
+        (None, Some(_), Some(theirs)) => (
+            theirs,
+            Some("The template doesn't have it anymore, the pick stops with a conflict"),
+        ),
+        (Some(template), base, Some(theirs)) if base.as_ref() == Some(template) => (theirs, None),
+        (Some(template), base, Some(theirs)) => {
+            let (merged, conflicts) =
+                merge(template, base.as_deref().unwrap_or_default(), &theirs)?;
+            let note =
+                conflicts.then_some("Conflicts with the template's changes, markers show where");
+            (merged, note)
+        }
+    };
+
+    Ok(Preview {
+        rows: rows(
+            &String::from_utf8_lossy(template.as_deref().unwrap_or_default()),
+            &String::from_utf8_lossy(&after),
+        ),
+        note,
+    })
+}
+
+/// What `git cherry-pick` would make of the file: `theirs` applied to `ours` relative
+/// to `base`, with conflict markers where they disagree. Also says whether they did.
+pub(crate) fn merge(ours: &[u8], base: &[u8], theirs: &[u8]) -> Result<(Vec<u8>, bool)> {
+    let dir = paths::temp_dir().join(format!("chuck-preview-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let files = ["template", "base", "commit"].map(|name| dir.join(name));
+    for (file, content) in files.iter().zip([ours, base, theirs]) {
+        fs::write(file, content)?;
+    }
+
+    let output = Command::new("git")
+        .args([
+            "merge-file",
+            "-p",
+            "-L",
+            "template",
+            "-L",
+            "base",
+            "-L",
+            "commit",
+        ])
+        .args(&files)
+        .output_local();
+    let _ = fs::remove_dir_all(&dir);
+    let output =
+        output.map_err(|e| process::command_error(e, "Failed to execute git merge-file"))?;
+
+    // The exit code is the number of conflicts, negative on errors
+    match output.status.code() {
+        Some(conflicts @ 0..=127) => Ok((output.stdout, conflicts > 0)),
+        _ => {
+            let error = String::from_utf8_lossy(&output.stderr);
+            Err(anyhow!("git merge-file failed: {}", error.trim()))
+        }
+    }
+}
+
+/// `before` and `after` lined up side by side, changed lines paired up and diffed
+/// word by word
+pub fn rows(before: &str, after: &str) -> Vec<Row> {
+    let a: Vec<&str> = before.split_inclusive('\n').collect();
+    let b: Vec<&str> = after.split_inclusive('\n').collect();
+
+    let mut rows = Vec::new();
+    let (mut removed, mut added) = (Vec::new(), Vec::new());
+    for op in diff(&a, &b) {
+        match op {
+            Op::Same(i, j) => {
+                pair_up(&mut rows, &a, &b, &mut removed, &mut added);
+                rows.push(Row {
+                    before: Some(whole(i, a[i])),
+                    after: Some(whole(j, b[j])),
+                });
+            }
+            Op::Delete(i) => removed.push(i),
+            Op::Insert(j) => added.push(j),
+        }
+    }
+    pair_up(&mut rows, &a, &b, &mut removed, &mut added);
+    rows
+}
+
+/// Rows for a run of removed and added lines. Lines that look alike share a row,
+/// diffed word by word, the rest get one of their own.
+fn pair_up(
+    rows: &mut Vec<Row>,
+    a: &[&str],
+    b: &[&str],
+    removed: &mut Vec<usize>,
+    added: &mut Vec<usize>,
+) {
+    let ops = diff_by(removed.len(), added.len(), |i, j| {
+        alike(a[removed[i]], b[added[j]])
+    });
+    for op in ops {
+        rows.push(match op {
+            Op::Same(i, j) => {
+                let (i, j) = (removed[i], added[j]);
+                let (before, after) = words(a[i], b[j]);
+                Row {
+                    before: Some(Side {
+                        number: i + 1,
+                        segments: before,
+                    }),
+                    after: Some(Side {
+                        number: j + 1,
+                        segments: after,
+                    }),
+                }
+            }
+            Op::Delete(i) => Row {
+                before: Some(whole(removed[i], a[removed[i]])),
+                after: None,
+            },
+            Op::Insert(j) => Row {
+                before: None,
+                after: Some(whole(added[j], b[added[j]])),
+            },
+        });
+    }
+    removed.clear();
+    added.clear();
+}
+
+/// Whether two lines share at least half their words and punctuation, whitespace aside
+fn alike(before: &str, after: &str) -> bool {
+    let visible = |line| {
+        tokens(line)
+            .into_iter()
+            .filter(|token| !token.trim().is_empty())
+            .collect::<Vec<_>>()
+    };
+    let (a, mut b) = (visible(before), visible(after));
+    if a.is_empty() || b.is_empty() {
+        return a.is_empty() && b.is_empty();
+    }
+    let total = a.len() + b.len();
+    let mut shared = 0;
+    for token in a {
+        if let Some(k) = b.iter().position(|&other| other == token) {
+            b.swap_remove(k);
+            shared += 1;
+        }
+    }
+    shared * 4 >= total
+}
+
+/// A line as one segment. Lines only one side has count as unchanged: the whole line
+/// is what changed, there's nothing to pick out.
+fn whole(index: usize, line: &str) -> Side {
+    Side {
+        number: index + 1,
+        segments: vec![Segment {
+            text: line.strip_suffix('\n').unwrap_or(line).to_string(),
+            changed: false,
+        }],
+    }
+}
+
+/// Segments of `before` and `after`, marking the words and whitespace that differ
+fn words(before: &str, after: &str) -> (Vec<Segment>, Vec<Segment>) {
+    let a = tokens(before);
+    let b = tokens(after);
+    let (mut left, mut right) = (Vec::new(), Vec::new());
+    for op in diff(&a, &b) {
+        match op {
+            Op::Same(i, j) => {
+                push(&mut left, a[i], false);
+                push(&mut right, b[j], false);
+            }
+            Op::Delete(i) => push(&mut left, a[i], true),
+            Op::Insert(j) => push(&mut right, b[j], true),
+        }
+    }
+    (left, right)
+}
+
+/// Appends `text` to `segments`, extending the last one when it's marked the same
+fn push(segments: &mut Vec<Segment>, text: &str, changed: bool) {
+    // An unchanged line ending isn't worth showing
+    if text == "\n" && !changed {
+        return;
+    }
+    match segments.last_mut() {
+        Some(last) if last.changed == changed => last.text.push_str(text),
+        _ => segments.push(Segment {
+            text: text.to_string(),
+            changed,
+        }),
+    }
+}
+
+/// Words, runs of spaces and tabs, and single other characters. Carriage returns and
+/// newlines are tokens of their own, so a changed line ending shows up on its own.
+fn tokens(line: &str) -> Vec<&str> {
+    let kind = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            1
+        } else if c == ' ' || c == '\t' {
+            2
+        } else {
+            0
+        }
+    };
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let k = kind(c);
+        let continues = chars
+            .peek()
+            .is_some_and(|&(_, next)| k != 0 && kind(next) == k);
+        if !continues {
+            tokens.push(&line[start..i + c.len_utf8()]);
+            start = i + c.len_utf8();
+        }
+    }
+    tokens
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Same(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// The edits turning `a` into `b`, from their longest common subsequence
+fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Op> {
+    diff_by(a.len(), b.len(), |i, j| a[i] == b[j])
+}
+
+/// `diff` for `a_len` and `b_len` items that are the same when `same` says so
+fn diff_by(a_len: usize, b_len: usize, same: impl Fn(usize, usize) -> bool) -> Vec<Op> {
+    let prefix = (0..a_len.min(b_len)).take_while(|&i| same(i, i)).count();
+    let suffix = (0..(a_len - prefix).min(b_len - prefix))
+        .take_while(|&k| same(a_len - 1 - k, b_len - 1 - k))
+        .count();
+    let (n, m) = (a_len - prefix - suffix, b_len - prefix - suffix);
+
+    let mut ops: Vec<Op> = (0..prefix).map(|i| Op::Same(i, i)).collect();
+    if n * m > MAX_DIFF_CELLS {
+        ops.extend((0..n).map(|i| Op::Delete(prefix + i)));
+        ops.extend((0..m).map(|j| Op::Insert(prefix + j)));
+    } else {
+        // Length of the longest common subsequence of what's left from i and j on
+        let at = |i: usize, j: usize| i * (m + 1) + j;
+        let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+        for i in (0..n).rev() {
+            for j in (0..m).rev() {
+                lcs[at(i, j)] = if same(prefix + i, prefix + j) {
+                    lcs[at(i + 1, j + 1)] + 1
+                } else {
+                    lcs[at(i + 1, j)].max(lcs[at(i, j + 1)])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < n || j < m {
+            if i < n && j < m && same(prefix + i, prefix + j) {
+                ops.push(Op::Same(prefix + i, prefix + j));
+                i += 1;
+                j += 1;
+            } else if j == m || (i < n && lcs[at(i + 1, j)] >= lcs[at(i, j + 1)]) {
+                ops.push(Op::Delete(prefix + i));
+                i += 1;
+            } else {
+                ops.push(Op::Insert(prefix + j));
+                j += 1;
+            }
+        }
+    }
+    ops.extend((0..suffix).map(|k| Op::Same(a_len - suffix + k, b_len - suffix + k)));
+    ops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    fn texts(side: &Option<Side>) -> Vec<(&str, bool)> {
+        side.iter()
+            .flat_map(|side| &side.segments)
+            .map(|segment| (segment.text.as_str(), segment.changed))
+            .collect()
+    }
+
+    #[test]
+    fn lines_up_changes_word_by_word() {
+        let rows = rows(
+            "fn a() {\n    let x = 1;\n}\n",
+            "fn a() {\n\tlet x = 2;\n    y();\n}\r\n",
+        );
+
+        assert_eq!(rows.len(), 4);
+        assert!(rows[0].is_same());
+        assert_eq!(
+            texts(&rows[1].before),
+            [
+                ("    ", true),
+                ("let x = ", false),
+                ("1", true),
+                (";", false)
+            ]
+        );
+        assert_eq!(
+            texts(&rows[1].after),
+            [("\t", true), ("let x = ", false), ("2", true), (";", false)]
+        );
+        // Left over, so on its own
+        assert_eq!(rows[2].before, None);
+        assert_eq!(rows[2].after.as_ref().unwrap().number, 3);
+        // Only the line ending changed
+        assert_eq!(texts(&rows[3].before), [("}", false)]);
+        assert_eq!(texts(&rows[3].after), [("}", false), ("\r", true)]);
+    }
+
+    #[test]
+    fn shows_what_the_pick_makes_of_the_template_version() {
+        let template = "one\ntwo\n";
+        let parent = "one\n";
+        let commit = "one\nthree\n";
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on(
+                    "git cat-file --batch",
+                    &format!(
+                        "t blob {}\n{}\np blob {}\n{}\nc blob {}\n{}\n",
+                        template.len(),
+                        template,
+                        parent.len(),
+                        parent,
+                        commit.len(),
+                        commit
+                    ),
+                )
+                .on("git merge-file", "one\ntwo\nthree\n"),
+        );
+
+        let preview = process::with_runner(fake.clone(), || {
+            load("base", "c^1", "c", "apps/web/x.txt", None, "apps/web")
+        })
+        .unwrap();
+
+        assert_eq!(preview.note, None);
+        assert_eq!(preview.rows.len(), 3);
+        assert_eq!(texts(&preview.rows[2].after), [("three", false)]);
+        assert_eq!(
+            fake.inputs()[0],
+            b"base:x.txt\nc^1:apps/web/x.txt\nc:apps/web/x.txt\n"
+        );
+    }
+}
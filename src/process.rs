@@ -0,0 +1,577 @@
+use crate::read_only;
+use crossterm::{
+    event::{self, Event, KeyCode, KeyModifiers},
+    terminal::{self, disable_raw_mode, enable_raw_mode},
+};
+use serde::{Deserialize, Serialize};
+use std::cell::{Cell, RefCell};
+use std::io::{self, IsTerminal, Read, Write};
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub mod fake;
+
+pub const CANCELLED: &str = "Cancelled by user";
+
+/// Commands finishing faster than this never show progress or touch the terminal.
+/// Ones that can prompt, like ssh asking for a passphrase, never read the keyboard.
+const PROGRESS_AFTER: Duration = Duration::from_secs(2);
+
+/// `[timeouts]` in the global config, in seconds
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeoutConfig {
+    /// Local git operations (show, log, checkout, cherry-pick)
+    #[serde(default = "default_local_timeout")]
+    pub local: u64,
+    /// Anything that talks to the network (fetch, push, gh)
+    #[serde(default = "default_network_timeout")]
+    pub network: u64,
+}
+
+impl Default for TimeoutConfig {
+    fn default() -> Self {
+        Self {
+            local: default_local_timeout(),
+            network: default_network_timeout(),
+        }
+    }
+}
+
+fn default_local_timeout() -> u64 {
+    120
+}
+
+fn default_network_timeout() -> u64 {
+    600
+}
+
+static TIMEOUTS: OnceLock<TimeoutConfig> = OnceLock::new();
+
+pub fn configure(config: TimeoutConfig) {
+    let _ = TIMEOUTS.set(config);
+}
+
+fn timeouts() -> &'static TimeoutConfig {
+    TIMEOUTS.get_or_init(TimeoutConfig::default)
+}
+
+/// Executes the external commands chuck runs. Everything goes through here so tests
+/// can swap in a `fake::FakeRunner` and never touch git, gh or the network.
+pub trait CommandRunner {
+    fn run(
+        &self,
+        command: &mut Command,
+        input: Option<&[u8]>,
+        timeout: Duration,
+    ) -> io::Result<Output>;
+
+    /// Runs a command that takes over the terminal, like an editor or merge tool,
+    /// without a timeout
+    fn run_interactive(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        self.run(command, None, Duration::MAX)
+            .map(|output| output.status)
+    }
+}
+
+/// Actually runs the command, with timeouts and Esc cancellation
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn run(
+        &self,
+        command: &mut Command,
+        input: Option<&[u8]>,
+        timeout: Duration,
+    ) -> io::Result<Output> {
+        run(command, input, timeout)
+    }
+
+    fn run_interactive(&self, command: &mut Command) -> io::Result<ExitStatus> {
+        command.status()
+    }
+}
+
+thread_local! {
+    static RUNNER: RefCell<Option<Rc<dyn CommandRunner>>> = const { RefCell::new(None) };
+    /// A `map_concurrently` or `in_background` worker, which leaves the terminal to the
+    /// thread that started it
+    static BACKGROUND: Cell<bool> = const { Cell::new(false) };
+    /// The command running on this thread may ask for a passphrase or credentials on
+    /// the terminal, so the keyboard is the command's
+    static PROMPTING: Cell<bool> = const { Cell::new(false) };
+    static OBSERVER: RefCell<Option<Observer>> = const { RefCell::new(None) };
+}
+
+/// Sees every command run on its thread and what came of it, whichever runner ran it
+pub type Observer = Rc<dyn Fn(&Command, &io::Result<Output>)>;
+
+/// Runs `f` with every command on this thread going to `runner`
+pub fn with_runner<T>(runner: Rc<dyn CommandRunner>, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Rc<dyn CommandRunner>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            RUNNER.with(|r| *r.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(RUNNER.with(|r| r.replace(Some(runner))));
+    f()
+}
+
+/// Whether commands on this thread go to a runner `with_runner` put in place
+pub fn is_faked() -> bool {
+    RUNNER.with(|r| r.borrow().is_some())
+}
+
+/// Runs `f` with `observer` shown every command run on this thread
+pub fn with_observer<T>(observer: Observer, f: impl FnOnce() -> T) -> T {
+    struct Restore(Option<Observer>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            OBSERVER.with(|o| *o.borrow_mut() = previous);
+        }
+    }
+
+    let _restore = Restore(OBSERVER.with(|o| o.replace(Some(observer))));
+    f()
+}
+
+/// Whether an observer, like `--record`, is watching commands on this thread
+pub fn is_observed() -> bool {
+    OBSERVER.with(|o| o.borrow().is_some())
+}
+
+fn observe(command: &Command, result: &io::Result<Output>) {
+    if let Some(observer) = OBSERVER.with(|o| o.borrow().clone()) {
+        observer(command, result);
+    }
+}
+
+/// Runs `f` on every item on up to `workers` threads, calling `done` with how many
+/// have finished as each one does. Results come back in the items' order. A fake
+/// runner only answers and an observer only watches on its own thread, so faked and
+/// observed runs go one at a time.
+pub fn map_concurrently<T: Sync, R: Send>(
+    items: &[T],
+    workers: usize,
+    f: impl Fn(&T) -> R + Sync,
+    mut done: impl FnMut(usize),
+) -> Vec<R> {
+    if is_faked() || is_observed() || workers <= 1 || items.len() <= 1 {
+        return items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| {
+                let result = f(item);
+                done(i + 1);
+                result
+            })
+            .collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..workers.min(items.len()) {
+            let sender = sender.clone();
+            let (next, f) = (&next, &f);
+            scope.spawn(move || {
+                BACKGROUND.set(true);
+                loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(i) else {
+                        break;
+                    };
+                    if sender.send((i, f(item))).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+
+        let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+        for (finished, (i, result)) in receiver.iter().enumerate() {
+            results[i] = Some(result);
+            done(finished + 1);
+        }
+        results
+            .into_iter()
+            .map(|result| result.expect("every item was mapped"))
+            .collect()
+    })
+}
+
+/// A result `in_background` is working out
+pub struct Pending<T>(PendingState<T>);
+
+enum PendingState<T> {
+    Ready(T),
+    Running(thread::JoinHandle<T>),
+}
+
+impl<T> Pending<T> {
+    /// The result, once it's there
+    pub fn wait(self) -> T {
+        match self.0 {
+            PendingState::Ready(result) => result,
+            PendingState::Running(handle) => handle
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic)),
+        }
+    }
+}
+
+/// Starts `f` on another thread while this one gets on with something else, like a
+/// fetch. As with `map_concurrently`, faked and observed runs do it right away on
+/// this thread instead.
+pub fn in_background<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> Pending<T> {
+    if is_faked() || is_observed() {
+        return Pending(PendingState::Ready(f()));
+    }
+    Pending(PendingState::Running(thread::spawn(move || {
+        BACKGROUND.set(true);
+        f()
+    })))
+}
+
+fn dispatch(
+    command: &mut Command,
+    input: Option<&[u8]>,
+    timeout: Duration,
+    prompts: bool,
+) -> io::Result<Output> {
+    if let Some(output) = read_only::withhold(command, input) {
+        return Ok(output);
+    }
+    struct Restore(bool);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            PROMPTING.set(self.0);
+        }
+    }
+    let _restore = Restore(PROMPTING.replace(prompts));
+    let result = match RUNNER.with(|r| r.borrow().clone()) {
+        Some(runner) => runner.run(command, input, timeout),
+        None => SystemRunner.run(command, input, timeout),
+    };
+    observe(command, &result);
+    result
+}
+
+fn dispatch_interactive(command: &mut Command) -> io::Result<ExitStatus> {
+    if let Some(output) = read_only::withhold(command, None) {
+        return Ok(output.status);
+    }
+    let result = match RUNNER.with(|r| r.borrow().clone()) {
+        Some(runner) => runner.run_interactive(command),
+        None => SystemRunner.run_interactive(command),
+    };
+    if is_observed() {
+        // What it printed went to the terminal
+        let output = match &result {
+            Ok(status) => Ok(Output {
+                status: *status,
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            }),
+            Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+        };
+        observe(command, &output);
+    }
+    result
+}
+
+/// Drop-in replacements for `Command::output()` that give up after the configured
+/// timeout. Local commands can be cancelled with Esc while they run, network ones,
+/// which may prompt on the terminal, with Ctrl-C.
+pub trait CommandExt {
+    fn output_local(&mut self) -> io::Result<Output>;
+    fn output_network(&mut self) -> io::Result<Output>;
+    /// Like `output_local`, feeding `input` to the command's stdin
+    fn output_local_with_input(&mut self, input: &[u8]) -> io::Result<Output>;
+    /// Like `output_network`, feeding `input` to the command's stdin
+    fn output_network_with_input(&mut self, input: &[u8]) -> io::Result<Output>;
+    /// Hands the terminal to the command until it exits, for tools the user drives
+    fn status_interactive(&mut self) -> io::Result<ExitStatus>;
+}
+
+impl CommandExt for Command {
+    fn output_local(&mut self) -> io::Result<Output> {
+        dispatch(self, None, Duration::from_secs(timeouts().local), false)
+    }
+
+    fn output_network(&mut self) -> io::Result<Output> {
+        dispatch(self, None, Duration::from_secs(timeouts().network), true)
+    }
+
+    fn output_local_with_input(&mut self, input: &[u8]) -> io::Result<Output> {
+        dispatch(
+            self,
+            Some(input),
+            Duration::from_secs(timeouts().local),
+            false,
+        )
+    }
+
+    fn output_network_with_input(&mut self, input: &[u8]) -> io::Result<Output> {
+        dispatch(
+            self,
+            Some(input),
+            Duration::from_secs(timeouts().network),
+            true,
+        )
+    }
+
+    fn status_interactive(&mut self) -> io::Result<ExitStatus> {
+        dispatch_interactive(self)
+    }
+}
+
+/// Error for a command that couldn't be run to completion. Timeouts and cancellation
+/// keep their own message; anything else (usually a missing binary) gets `fallback`.
+pub fn command_error(error: io::Error, fallback: &str) -> anyhow::Error {
+    match error.kind() {
+        io::ErrorKind::TimedOut | io::ErrorKind::Interrupted => anyhow::anyhow!("{}", error),
+        _ => anyhow::anyhow!("{}", fallback),
+    }
+}
+
+/// Whether an error came from a command the user cancelled or that ran out of time
+pub fn is_interrupted(error: &anyhow::Error) -> bool {
+    let message = error.to_string();
+    message.contains(CANCELLED) || message.contains("timed out after")
+}
+
+fn describe(command: &Command) -> String {
+    let mut parts = vec![command.get_program().to_string_lossy().to_string()];
+    parts.extend(
+        command
+            .get_args()
+            .take(2)
+            .map(|a| a.to_string_lossy().to_string()),
+    );
+    parts.join(" ")
+}
+
+fn run(command: &mut Command, input: Option<&[u8]>, timeout: Duration) -> io::Result<Output> {
+    let label = describe(command);
+    let mut child = command
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Feed stdin from a thread so a large input can't deadlock against a full stdout pipe
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        let input = input.to_vec();
+        thread::spawn(move || stdin.write_all(&input));
+    }
+
+    let stdout = read_in_background(child.stdout.take());
+    let stderr = read_in_background(child.stderr.take());
+
+    // Only watch the keyboard if nobody else (the TUI, another worker) is driving the terminal
+    let interactive = !BACKGROUND.get()
+        && io::stdin().is_terminal()
+        && io::stderr().is_terminal()
+        && !terminal::is_raw_mode_enabled().unwrap_or(false);
+    // Keys typed into a passphrase prompt are the command's. Ctrl-C still cancels, it
+    // interrupts the command and chuck alike.
+    let watch_keys = interactive && !PROMPTING.get();
+
+    let start = Instant::now();
+    let mut progress = Progress::default();
+    // Most git calls finish in a few milliseconds, so start polling fast and back off
+    let mut poll = Duration::from_millis(1);
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+
+        let elapsed = start.elapsed();
+        if elapsed >= timeout {
+            progress.finish();
+            kill(&mut child);
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("'{}' timed out after {}s", label, timeout.as_secs()),
+            ));
+        }
+
+        if interactive && elapsed >= PROGRESS_AFTER {
+            progress.show(&label, elapsed, watch_keys);
+            if !watch_keys {
+                thread::sleep(Duration::from_millis(100));
+            } else if cancel_requested()? {
+                progress.finish();
+                kill(&mut child);
+                return Err(io::Error::new(io::ErrorKind::Interrupted, CANCELLED));
+            }
+        } else {
+            thread::sleep(poll);
+            poll = (poll * 2).min(Duration::from_millis(50));
+        }
+    };
+
+    progress.finish();
+
+    Ok(Output {
+        status,
+        stdout: stdout.join().unwrap_or_default(),
+        stderr: stderr.join().unwrap_or_default(),
+    })
+}
+
+fn read_in_background<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+fn kill(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn cancel_requested() -> io::Result<bool> {
+    if !event::poll(Duration::from_millis(100))? {
+        return Ok(false);
+    }
+    Ok(match event::read()? {
+        Event::Key(key) => {
+            key.code == KeyCode::Esc
+                || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+        }
+        _ => false,
+    })
+}
+
+/// Elapsed-time line for slow commands. Raw mode is needed to see Esc without Enter,
+/// so it's only turned on when `watch_keys`.
+#[derive(Default)]
+struct Progress {
+    active: bool,
+    raw: bool,
+}
+
+impl Progress {
+    fn show(&mut self, label: &str, elapsed: Duration, watch_keys: bool) {
+        if !self.active {
+            if watch_keys {
+                if enable_raw_mode().is_err() {
+                    return;
+                }
+                self.raw = true;
+            }
+            self.active = true;
+        }
+        eprint!(
+            "\r\x1b[2K🧔 ⏳ {} ({}s, {} to cancel)",
+            label,
+            elapsed.as_secs(),
+            if self.raw { "Esc" } else { "Ctrl-C" }
+        );
+        let _ = io::stderr().flush();
+    }
+
+    fn finish(&mut self) {
+        if self.active {
+            if self.raw {
+                let _ = disable_raw_mode();
+                self.raw = false;
+            }
+            eprint!("\r\x1b[2K");
+            let _ = io::stderr().flush();
+            self.active = false;
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn maps_concurrently_in_order() {
+        let started = Mutex::new(Vec::new());
+        let mut finished = Vec::new();
+        let items: Vec<u64> = (0..20).collect();
+
+        let results = map_concurrently(
+            &items,
+            4,
+            |item| {
+                started.lock().unwrap().push(thread::current().id());
+                // Later items finish first
+                thread::sleep(Duration::from_millis(20 - item));
+                item * 2
+            },
+            |done| finished.push(done),
+        );
+
+        assert_eq!(results, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+        assert_eq!(finished, (1..=20).collect::<Vec<_>>());
+        let mut threads = started.into_inner().unwrap();
+        threads.sort_by_key(|id| format!("{:?}", id));
+        threads.dedup();
+        assert!(threads.len() > 1 && threads.len() <= 4);
+    }
+
+    #[test]
+    fn works_in_the_background_unless_faked() {
+        let here = thread::current().id();
+        let pending = in_background(|| thread::current().id());
+        assert_ne!(pending.wait(), here);
+
+        let pending = with_runner(Rc::new(fake::FakeRunner::new()), || {
+            in_background(|| thread::current().id())
+        });
+        assert_eq!(pending.wait(), here);
+    }
+
+    #[test]
+    fn leaves_the_keyboard_to_network_commands() {
+        struct Prompting(RefCell<Vec<bool>>);
+        impl CommandRunner for Prompting {
+            fn run(&self, _: &mut Command, _: Option<&[u8]>, _: Duration) -> io::Result<Output> {
+                self.0.borrow_mut().push(PROMPTING.get());
+                Ok(Output {
+                    status: ExitStatus::default(),
+                    stdout: Vec::new(),
+                    stderr: Vec::new(),
+                })
+            }
+        }
+
+        let runner = Rc::new(Prompting(RefCell::new(Vec::new())));
+        with_runner(runner.clone(), || {
+            let _ = Command::new("git").arg("fetch").output_network();
+            let _ = Command::new("git").arg("status").output_local();
+        });
+        assert_eq!(*runner.0.borrow(), [true, false]);
+        assert!(!PROMPTING.get());
+    }
+}
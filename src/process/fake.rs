@@ -0,0 +1,112 @@
+use super::CommandRunner;
+use std::cell::RefCell;
+use std::io;
+use std::process::{Command, ExitStatus, Output};
+use std::time::Duration;
+
+/// A `CommandRunner` that answers from canned outputs and records every command
+/// line it was asked to run. Commands nobody registered succeed with no output.
+#[derive(Default)]
+pub struct FakeRunner {
+    /// Command line prefix -> response; the longest matching prefix wins
+    responses: Vec<(String, Output)>,
+    calls: RefCell<Vec<String>>,
+    inputs: RefCell<Vec<Vec<u8>>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Answers commands starting with `prefix` (e.g. `"git rev-parse HEAD"`) with `stdout`
+    pub fn on(self, prefix: &str, stdout: &str) -> Self {
+        self.respond(prefix, exit_status(0), stdout, "")
+    }
+
+    /// Fails commands starting with `prefix` with exit code 1 and `stderr`
+    pub fn fail(self, prefix: &str, stderr: &str) -> Self {
+        self.respond(prefix, exit_status(1), "", stderr)
+    }
+
+    fn respond(mut self, prefix: &str, status: ExitStatus, stdout: &str, stderr: &str) -> Self {
+        self.responses.push((
+            prefix.to_string(),
+            Output {
+                status,
+                stdout: stdout.as_bytes().to_vec(),
+                stderr: stderr.as_bytes().to_vec(),
+            },
+        ));
+        self
+    }
+
+    /// Every command run so far, as `program arg arg...`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.borrow().clone()
+    }
+
+    /// Commands run so far that start with `prefix`
+    pub fn calls_to(&self, prefix: &str) -> Vec<String> {
+        self.calls
+            .borrow()
+            .iter()
+            .filter(|call| call.starts_with(prefix))
+            .cloned()
+            .collect()
+    }
+
+    /// Whatever was written to stdin, for commands run with input
+    pub fn inputs(&self) -> Vec<Vec<u8>> {
+        self.inputs.borrow().clone()
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn run(
+        &self,
+        command: &mut Command,
+        input: Option<&[u8]>,
+        _timeout: Duration,
+    ) -> io::Result<Output> {
+        let line = std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|part| part.to_string_lossy())
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        self.calls.borrow_mut().push(line.clone());
+        if let Some(input) = input {
+            self.inputs.borrow_mut().push(input.to_vec());
+        }
+
+        let response = self
+            .responses
+            .iter()
+            .filter(|(prefix, _)| line.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len());
+
+        Ok(match response {
+            Some((_, output)) => output.clone(),
+            None => Output {
+                status: exit_status(0),
+                stdout: Vec::new(),
+                stderr: Vec::new(),
+            },
+        })
+    }
+}
+
+/// An exit status with `code`, as if a process had exited with it
+#[cfg(unix)]
+pub fn exit_status(code: i32) -> ExitStatus {
+    use std::os::unix::process::ExitStatusExt;
+    // Raw wait status: the exit code lives in the second byte
+    ExitStatus::from_raw(code << 8)
+}
+
+#[cfg(windows)]
+pub fn exit_status(code: i32) -> ExitStatus {
+    use std::os::windows::process::ExitStatusExt;
+    ExitStatus::from_raw(code as u32)
+}
@@ -0,0 +1,84 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Phase name -> (total time, times entered), in the order phases first started
+static PHASES: Mutex<Vec<(&'static str, Duration, u32)>> = Mutex::new(Vec::new());
+
+static STARTED: Mutex<Option<Instant>> = Mutex::new(None);
+
+/// Turns on `--profile` timing for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+    if let Ok(mut started) = STARTED.lock() {
+        *started = Some(Instant::now());
+    }
+}
+
+/// Times everything until the returned guard is dropped. Phases entered more than
+/// once (like the per-commit file-list fetch) add up.
+pub fn phase(name: &'static str) -> Phase {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Phase { name, start: None };
+    }
+    if let Ok(mut phases) = PHASES.lock() {
+        if !phases.iter().any(|(existing, _, _)| *existing == name) {
+            phases.push((name, Duration::ZERO, 0));
+        }
+    }
+    Phase {
+        name,
+        start: Some(Instant::now()),
+    }
+}
+
+pub struct Phase {
+    name: &'static str,
+    start: Option<Instant>,
+}
+
+impl Drop for Phase {
+    fn drop(&mut self) {
+        let Some(start) = self.start else {
+            return;
+        };
+        if let Ok(mut phases) = PHASES.lock() {
+            if let Some(entry) = phases.iter_mut().find(|(name, _, _)| *name == self.name) {
+                entry.1 += start.elapsed();
+                entry.2 += 1;
+            }
+        }
+    }
+}
+
+/// Prints the breakdown if profiling is on
+pub fn report() {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    let total = STARTED
+        .lock()
+        .ok()
+        .and_then(|started| started.map(|s| s.elapsed()))
+        .unwrap_or_default();
+    let Ok(phases) = PHASES.lock() else {
+        return;
+    };
+
+    println!("\n🧔 Profile ({:.0?} total):", total);
+    for (name, elapsed, count) in phases.iter() {
+        let share = if total.is_zero() {
+            0.0
+        } else {
+            elapsed.as_secs_f64() / total.as_secs_f64() * 100.0
+        };
+        let calls = if *count > 1 {
+            format!(" ({} calls)", count)
+        } else {
+            String::new()
+        };
+        println!("  {:<18} {:>10.1?} {:>5.1}%{}", name, elapsed, share, calls);
+    }
+}
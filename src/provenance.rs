@@ -0,0 +1,343 @@
+use crate::partial;
+use crate::process::{self, CommandExt};
+use crate::profile;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::process::Command;
+
+/// Letters and digits a line needs before having the same line as the template says
+/// anything. Braces, `end`, blank lines and short imports are in almost every file.
+const DISTINCTIVE: usize = 10;
+
+/// Where the lines a commit modifies or deletes came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Provenance {
+    /// Changed lines that blame back to the template
+    pub from_template: usize,
+    /// Pre-existing lines the commit changed, additions don't count
+    pub changed: usize,
+    /// Whitespace is all it changes, only known when whitespace is ignored
+    pub whitespace_only: bool,
+}
+
+impl Provenance {
+    /// Most of what the commit touches is template code, so it's likely a template fix
+    pub fn is_template_fix(&self) -> bool {
+        self.changed > 0 && self.from_template * 2 > self.changed
+    }
+
+    pub fn describe(&self) -> String {
+        if self.whitespace_only {
+            return "only changes whitespace".to_string();
+        }
+        if self.changed == 0 {
+            return "only adds new lines".to_string();
+        }
+        format!(
+            "{} of {} changed lines came from the template",
+            self.from_template, self.changed
+        )
+    }
+}
+
+/// Blames the lines `commit` changes in its parent and counts those that came from
+/// the template. A line counts when the commit that introduced it is in the template
+/// base's history, or, for repos created from a template with fresh history, when
+/// the template base has the same line in that file and it's long enough to tell
+/// (`DISTINCTIVE`). Ignoring whitespace, lines that
+/// only changed in whitespace aren't changed, and a line is the template's when it
+/// matches one there but for whitespace.
+pub struct Tracer<'a> {
+    template_base: &'a str,
+    ignore_whitespace: bool,
+    ancestors: HashMap<String, bool>,
+    template_files: HashMap<String, HashSet<String>>,
+}
+
+impl<'a> Tracer<'a> {
+    pub fn new(template_base: &'a str, ignore_whitespace: bool) -> Self {
+        Self {
+            template_base,
+            ignore_whitespace,
+            ancestors: HashMap::new(),
+            template_files: HashMap::new(),
+        }
+    }
+
+    pub fn trace(&mut self, commit: &str) -> Result<Provenance> {
+        let _phase = profile::phase("provenance");
+        let parent = format!("{}^", commit);
+        let mut diff_args = vec!["diff", "-U0", "--no-color", "--no-renames"];
+        if self.ignore_whitespace {
+            diff_args.push("-w");
+        }
+        diff_args.extend([parent.as_str(), commit]);
+        let diff = git(&diff_args)?;
+
+        let mut provenance = Provenance {
+            // git prints nothing at all for changes that disappear without whitespace
+            whitespace_only: self.ignore_whitespace && diff.trim().is_empty(),
+            ..Provenance::default()
+        };
+        for (path, ranges) in changed_ranges(&diff) {
+            let mut args = vec!["blame".to_string(), "--porcelain".to_string()];
+            if self.ignore_whitespace {
+                args.push("-w".to_string());
+            }
+            for (start, count) in &ranges {
+                args.push(format!("-L{},+{}", start, count));
+            }
+            args.extend([parent.clone(), "--".to_string(), path.clone()]);
+            let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+            for (origin, line) in parse_blame(&git(&args)?) {
+                provenance.changed += 1;
+                if self.in_template_history(&origin) || self.template_has_line(&path, &line) {
+                    provenance.from_template += 1;
+                }
+            }
+        }
+        Ok(provenance)
+    }
+
+    fn in_template_history(&mut self, origin: &str) -> bool {
+        let base = self.template_base;
+        *self.ancestors.entry(origin.to_string()).or_insert_with(|| {
+            Command::new("git")
+                .args(["merge-base", "--is-ancestor", origin, base])
+                .output_local()
+                .is_ok_and(|output| output.status.success())
+        })
+    }
+
+    fn template_has_line(&mut self, path: &str, line: &str) -> bool {
+        if line.chars().filter(|c| c.is_alphanumeric()).count() < DISTINCTIVE {
+            return false;
+        }
+        let base = self.template_base;
+        let key = |line: &str| {
+            if self.ignore_whitespace {
+                line.split_whitespace().collect()
+            } else {
+                line.to_string()
+            }
+        };
+        self.template_files
+            .entry(path.to_string())
+            .or_insert_with(|| {
+                git(&["show", &format!("{}:{}", base, path)])
+                    .map(|content| content.lines().map(key).collect())
+                    .unwrap_or_default()
+            })
+            .contains(&key(line))
+    }
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = partial::local_only(Command::new("git").args(args))
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `(path, [(first line, line count)])` of the pre-image lines each file's hunks
+/// replace, from `git diff -U0`
+pub fn changed_ranges(diff: &str) -> Vec<(String, Vec<(usize, usize)>)> {
+    let mut files: Vec<(String, Vec<(usize, usize)>)> = Vec::new();
+    // A removed line can look like a `--- ` header too, so only look for those up front
+    let mut in_header = false;
+    for line in diff.lines() {
+        if line.starts_with("diff --git ") {
+            in_header = true;
+        } else if let (true, Some(path)) = (in_header, line.strip_prefix("--- ")) {
+            if let Some(path) = path.strip_prefix("a/") {
+                files.push((path.to_string(), Vec::new()));
+            }
+        } else if line.starts_with("+++ ") {
+            in_header = false;
+        } else if let Some(hunk) = line.strip_prefix("@@ -") {
+            let Some(old) = hunk.split_whitespace().next() else {
+                continue;
+            };
+            let (start, count) = match old.split_once(',') {
+                Some((start, count)) => (start.parse().ok(), count.parse().ok()),
+                None => (old.parse().ok(), Some(1)),
+            };
+            if let (Some((_, ranges)), Some(start), Some(count)) = (files.last_mut(), start, count)
+            {
+                if count > 0 {
+                    ranges.push((start, count));
+                }
+            }
+        }
+    }
+    files.retain(|(_, ranges)| !ranges.is_empty());
+    files
+}
+
+/// `(commit that introduced it, content)` for each line of `git blame --porcelain`
+pub fn parse_blame(porcelain: &str) -> Vec<(String, String)> {
+    let mut lines = Vec::new();
+    let mut origin = "";
+    for line in porcelain.lines() {
+        if let Some(content) = line.strip_prefix('\t') {
+            lines.push((origin.to_string(), content.to_string()));
+        } else {
+            let first = line.split(' ').next().unwrap_or("");
+            if first.len() == 40 && first.bytes().all(|b| b.is_ascii_hexdigit()) {
+                origin = first;
+            }
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    const DIFF: &str = "\
+diff --git a/src/lib.rs b/src/lib.rs
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3,2 +3,2 @@ fn main() {
+-old
+-old
++new
++new
+@@ -10 +10,0 @@
+--- a/not/a/header
+@@ -20,0 +21,3 @@
++added
+diff --git a/new.rs b/new.rs
+--- /dev/null
++++ b/new.rs
+@@ -0,0 +1 @@
++fn new() {}
+";
+
+    fn sha(c: char) -> String {
+        c.to_string().repeat(40)
+    }
+
+    #[test]
+    fn finds_replaced_ranges_per_file() {
+        assert_eq!(
+            changed_ranges(DIFF),
+            [("src/lib.rs".to_string(), vec![(3, 2), (10, 1)])]
+        );
+    }
+
+    #[test]
+    fn reads_blame_origins() {
+        let porcelain = format!(
+            "{a} 3 3 2\nauthor Jane\nfilename src/lib.rs\n\told\n{a} 4 4\n\told too\n{b} 10 10 1\nauthor Joe\n\t-- a/not/a/header\n",
+            a = sha('a'),
+            b = sha('b')
+        );
+        assert_eq!(
+            parse_blame(&porcelain),
+            [
+                (sha('a'), "old".to_string()),
+                (sha('a'), "old too".to_string()),
+                (sha('b'), "-- a/not/a/header".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn counts_lines_from_template_history_or_content() {
+        let porcelain = format!(
+            "{a} 3 3 2\n\ttemplate line\n{a} 4 4\n\tanother\n{b} 10 10 1\n\tcopied from template\n",
+            a = sha('a'),
+            b = sha('b')
+        );
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git diff -U0", DIFF)
+                .on("git blame", &porcelain)
+                // a is in the template's history, b isn't
+                .fail(&format!("git merge-base --is-ancestor {}", sha('b')), "")
+                .on("git show base:src/lib.rs", "copied from template\n"),
+        );
+
+        let provenance = process::with_runner(fake.clone(), || {
+            Tracer::new("base", false).trace("abc").unwrap()
+        });
+
+        assert_eq!(
+            provenance,
+            Provenance {
+                from_template: 3,
+                changed: 3,
+                whitespace_only: false
+            }
+        );
+        assert!(provenance.is_template_fix());
+        assert_eq!(
+            fake.calls_to("git blame"),
+            ["git blame --porcelain -L3,+2 -L10,+1 abc^ -- src/lib.rs"]
+        );
+    }
+
+    #[test]
+    fn needs_more_than_common_lines_to_match_the_template() {
+        let porcelain = format!(
+            "{b} 3 3 2\n\t}}\n{b} 4 4\n\t\n{b} 10 10 1\n\tuse std::fs;\n",
+            b = sha('b')
+        );
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git diff -U0", DIFF)
+                .on("git blame", &porcelain)
+                .fail("git merge-base --is-ancestor", "")
+                .on("git show base:src/lib.rs", "use std::fs;\n\nfn a() {\n}\n"),
+        );
+
+        let provenance = process::with_runner(fake.clone(), || {
+            Tracer::new("base", false).trace("abc").unwrap()
+        });
+
+        assert_eq!((provenance.from_template, provenance.changed), (0, 3));
+        assert!(!provenance.is_template_fix());
+        assert!(fake.calls_to("git show").is_empty());
+    }
+
+    #[test]
+    fn ignores_whitespace_when_asked() {
+        let fake = Rc::new(FakeRunner::new().on("git diff -U0", ""));
+        let provenance =
+            process::with_runner(fake.clone(), || Tracer::new("base", true).trace("abc"));
+        assert!(provenance.unwrap().whitespace_only);
+        assert_eq!(
+            fake.calls_to("git diff"),
+            ["git diff -U0 --no-color --no-renames -w abc^ abc"]
+        );
+
+        // Reindented, the template's line is still the template's
+        let porcelain = format!("{} 3 3 1\n\t    copied  from\ttemplate\n", sha('b'));
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git diff -U0", DIFF)
+                .on("git blame", &porcelain)
+                .fail("git merge-base --is-ancestor", "")
+                .on("git show base:src/lib.rs", "copied from template\n"),
+        );
+        let provenance = process::with_runner(fake.clone(), || {
+            Tracer::new("base", true).trace("abc").unwrap()
+        });
+        assert_eq!((provenance.from_template, provenance.changed), (1, 1));
+        assert!(!provenance.whitespace_only);
+        assert_eq!(
+            fake.calls_to("git blame"),
+            ["git blame --porcelain -w -L3,+2 -L10,+1 abc^ -- src/lib.rs"]
+        );
+    }
+}
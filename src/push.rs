@@ -0,0 +1,317 @@
+use crate::network;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::Command;
+use std::sync::OnceLock;
+
+/// `[push]` in the global config
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PushConfig {
+    /// Commits per push. Each push only sends what the previous ones didn't, so a
+    /// dropped connection costs one chunk instead of the whole branch.
+    #[serde(default = "default_chunk")]
+    pub chunk: usize,
+    /// Extra attempts per chunk after a network failure
+    #[serde(default = "default_retries")]
+    pub retries: u32,
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self {
+            chunk: default_chunk(),
+            retries: default_retries(),
+        }
+    }
+}
+
+fn default_chunk() -> usize {
+    50
+}
+
+fn default_retries() -> u32 {
+    2
+}
+
+static CONFIG: OnceLock<PushConfig> = OnceLock::new();
+
+pub fn configure(config: PushConfig) {
+    let _ = CONFIG.set(config);
+}
+
+fn config() -> &'static PushConfig {
+    CONFIG.get_or_init(PushConfig::default)
+}
+
+/// Pushes `branch` to `remote_branch` on `target` a chunk of commits at a time, oldest
+/// first. A chunk that fails on a flaky connection is retried from wherever the
+/// remote branch got to, so only the ref update or the missing objects go again.
+pub fn push_branch(target: &str, branch: &str, remote_branch: &str, base: &str) -> Result<()> {
+    push_in_chunks(config(), target, branch, remote_branch, base)
+}
+
+fn push_in_chunks(
+    config: &PushConfig,
+    target: &str,
+    branch: &str,
+    remote_branch: &str,
+    base: &str,
+) -> Result<()> {
+    let commits = commits_to_push(base, branch)?;
+    let ends = chunk_ends(commits.len(), config.chunk);
+    let remote_ref = format!("refs/heads/{}", remote_branch);
+
+    for (i, &end) in ends.iter().enumerate() {
+        // The last chunk pushes the branch itself, which is what a plain push would do
+        let source = if i + 1 == ends.len() {
+            branch.to_string()
+        } else {
+            commits[end - 1].clone()
+        };
+        let expected = end.checked_sub(1).map(|last| commits[last].as_str());
+
+        let mut failures = 0;
+        let stderr = loop {
+            let result = Command::new("git")
+                .args([
+                    "push",
+                    "--progress",
+                    target,
+                    &format!("{}:{}", source, remote_ref),
+                ])
+                .output_network();
+
+            let error = match result {
+                Ok(output) if output.status.success() => {
+                    break String::from_utf8_lossy(&output.stderr).into_owned();
+                }
+                Ok(output) => String::from_utf8_lossy(&output.stderr).into_owned(),
+                // Slow, not broken: worth another go
+                Err(e) if e.kind() == io::ErrorKind::TimedOut => e.to_string(),
+                Err(e) => {
+                    return Err(process::command_error(
+                        e,
+                        "Failed to execute git push command",
+                    ))
+                }
+            };
+
+            // The connection can drop after the remote updated the ref but before git
+            // heard back, in which case the chunk is already there
+            if expected.is_some()
+                && remote_tip(target, &remote_ref).ok().flatten().as_deref() == expected
+            {
+                break String::new();
+            }
+
+            failures += 1;
+            if !is_transient(&error) || failures > config.retries {
+                println!("🧔 Push failed!");
+                if !error.trim().is_empty() {
+                    println!("🧔 Git error: {}", error.trim());
+                }
+                return Err(anyhow!(network::describe_failure(
+                    "Git push failed",
+                    &error
+                )));
+            }
+            println!(
+                "🧔 Push interrupted, retrying ({}/{})...",
+                failures, config.retries
+            );
+        };
+
+        let mut line = if ends.len() > 1 {
+            format!("🧔 Pushed {} of {} commits", end, commits.len())
+        } else {
+            format!("🧔 Pushed {} commits", commits.len())
+        };
+        if let Some(written) = written(&stderr) {
+            line.push_str(&format!(" ({})", written));
+        }
+        println!("{}", line);
+    }
+
+    Ok(())
+}
+
+/// `git diff --stat` of what `branch` changes on top of `base`, which is what the
+/// remote branch will contain
+pub fn diffstat(base: &str, branch: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["diff", "--stat", base, branch])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git diff"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to compare with the base: {}", error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// SHAs on `branch` that aren't in `base`, oldest first
+fn commits_to_push(base: &str, branch: &str) -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args(["rev-list", "--reverse", &format!("{}..{}", base, branch)])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-list"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to list commits to push: {}", error.trim()));
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Where `remote_ref` points on `target`, if it exists yet
+fn remote_tip(target: &str, remote_ref: &str) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["ls-remote", target, remote_ref])
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to execute git ls-remote"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "Failed to look up {}: {}",
+            remote_ref,
+            error.trim()
+        ));
+    }
+    Ok(String::from_utf8(output.stdout)?
+        .split_whitespace()
+        .next()
+        .map(str::to_string))
+}
+
+/// How many commits each push ends at, e.g. 120 commits in chunks of 50 is
+/// `[50, 100, 120]`. Always at least one push, even with nothing new to send.
+pub fn chunk_ends(commits: usize, chunk: usize) -> Vec<usize> {
+    let chunk = chunk.max(1);
+    let mut ends: Vec<usize> = (chunk..commits).step_by(chunk).collect();
+    ends.push(commits);
+    ends
+}
+
+/// Failures a retry can fix, as opposed to rejections and missing permissions
+pub fn is_transient(stderr: &str) -> bool {
+    let lower = stderr.to_lowercase();
+    [
+        "timed out",
+        "remote end hung up",
+        "early eof",
+        "rpc failed",
+        "connection reset",
+        "connection refused",
+        "connection closed",
+        "broken pipe",
+        "could not resolve host",
+        "unexpected disconnect",
+        "http 502",
+        "http 503",
+        "http 504",
+    ]
+    .iter()
+    .any(|needle| lower.contains(needle))
+}
+
+/// "12 objects, 1.23 MiB" from the last `Writing objects:` progress line of `git push
+/// --progress`, if it had to send any
+pub fn written(stderr: &str) -> Option<String> {
+    let line = stderr
+        .split(['\r', '\n'])
+        .rfind(|line| line.contains("Writing objects:"))?;
+    let counts = line.split_once('(')?.1.split_once(')')?;
+    let objects = counts.0.split_once('/')?.1;
+    let size = counts
+        .1
+        .trim_start_matches(',')
+        .split([',', '|'])
+        .next()
+        .map(str::trim)
+        .filter(|size| !size.is_empty() && *size != "done.");
+    Some(match size {
+        Some(size) => format!("{} objects, {}", objects, size),
+        None => format!("{} objects", objects),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn splits_long_branches_into_chunks() {
+        assert_eq!(chunk_ends(120, 50), [50, 100, 120]);
+        assert_eq!(chunk_ends(100, 50), [50, 100]);
+        assert_eq!(chunk_ends(3, 50), [3]);
+        assert_eq!(chunk_ends(0, 50), [0]);
+        assert_eq!(chunk_ends(2, 0), [1, 2]);
+    }
+
+    #[test]
+    fn reads_objects_and_bytes_written() {
+        let stderr = "Enumerating objects: 5, done.\n\
+            Writing objects:  40% (2/5)\rWriting objects: 100% (5/5), 1.21 MiB | 3.10 MiB/s, done.\n\
+            Total 5 (delta 1), reused 0 (delta 0)\n";
+        assert_eq!(written(stderr).as_deref(), Some("5 objects, 1.21 MiB"));
+        assert_eq!(written("Everything up-to-date\n"), None);
+    }
+
+    #[test]
+    fn tells_dropped_connections_from_rejections() {
+        assert!(is_transient(
+            "error: RPC failed; curl 56 OpenSSL SSL_read: Connection reset by peer"
+        ));
+        assert!(is_transient("fatal: the remote end hung up unexpectedly"));
+        assert!(!is_transient(
+            "ERROR: Permission to acme/template.git denied to jane."
+        ));
+    }
+
+    #[test]
+    fn resumes_a_chunk_the_remote_already_has() {
+        let commits = (1..=3)
+            .map(|i| i.to_string().repeat(40))
+            .collect::<Vec<_>>();
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git rev-list", &commits.join("\n"))
+                .fail("git push", "fatal: the remote end hung up unexpectedly")
+                // The ref update landed for the first chunk, not for the last
+                .on(
+                    "git ls-remote template refs/heads/chuck-from-x",
+                    &format!("{}\trefs/heads/chuck-from-x\n", commits[1]),
+                ),
+        );
+        let config = PushConfig {
+            chunk: 2,
+            retries: 1,
+        };
+
+        let result = process::with_runner(fake.clone(), || {
+            push_in_chunks(&config, "template", "chuck/x", "chuck-from-x", "base")
+        });
+
+        assert!(result.is_err());
+        assert_eq!(
+            fake.calls_to("git push"),
+            [
+                format!(
+                    "git push --progress template {}:refs/heads/chuck-from-x",
+                    commits[1]
+                ),
+                "git push --progress template chuck/x:refs/heads/chuck-from-x".to_string(),
+                "git push --progress template chuck/x:refs/heads/chuck-from-x".to_string(),
+            ]
+        );
+    }
+}
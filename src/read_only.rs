@@ -0,0 +1,323 @@
+use std::ffi::OsStr;
+use std::process::{Command, ExitStatus, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Turns on `--read-only` for the rest of the process
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Under `--read-only`, says Chuck would have done `what`, like "written .chuckrc",
+/// and returns true, so the caller leaves it undone
+pub fn withheld(what: &str) -> bool {
+    if !is_enabled() {
+        return false;
+    }
+    println!("🧔 Read-only, would have {}", what);
+    true
+}
+
+/// What a command withheld under `--read-only` answers with instead of running: it
+/// worked and said nothing
+pub fn withhold(command: &Command, input: Option<&[u8]>) -> Option<Output> {
+    if !is_enabled() || !mutates(command, input) {
+        return None;
+    }
+    println!("🧔 Read-only, would have run: {}", describe(command));
+    Some(Output {
+        status: ExitStatus::default(),
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+    })
+}
+
+/// Whether running `command` could change a repository, GitHub or anything else.
+/// Only commands known to just look are let through; anything unknown mutates.
+pub fn mutates(command: &Command, input: Option<&[u8]>) -> bool {
+    let args: Vec<String> = command
+        .get_args()
+        .map(|arg| arg.to_string_lossy().into_owned())
+        .collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let input = input.map(String::from_utf8_lossy);
+    let program = command.get_program();
+    if program == OsStr::new("git") {
+        let scratch_index = command
+            .get_envs()
+            .any(|(name, value)| name == "GIT_INDEX_FILE" && value.is_some());
+        git_mutates(&args, scratch_index)
+    } else if program == OsStr::new("gh") {
+        gh_mutates(&args)
+    } else if program == OsStr::new("curl") {
+        curl_mutates(&args, input.as_deref().unwrap_or(""))
+    } else if program == OsStr::new("kill") {
+        // Whether a process is still alive
+        args.first() != Some(&"-0")
+    } else {
+        ![
+            "hostname",
+            "tasklist",
+            "certutil",
+            "shasum",
+            "sha256sum",
+            "osascript",
+            "notify-send",
+        ]
+        .iter()
+        .any(|known| program == OsStr::new(known))
+    }
+}
+
+/// `scratch_index` when the command works on an index of its own instead of the
+/// repository's
+fn git_mutates(args: &[&str], scratch_index: bool) -> bool {
+    // Past global options like -C <dir> and -c <key=value>
+    let mut args = args.iter().copied();
+    let subcommand = loop {
+        match args.next() {
+            Some("-C" | "-c") => {
+                args.next();
+            }
+            Some(arg) if arg.starts_with('-') => {}
+            Some(subcommand) => break subcommand,
+            None => return false,
+        }
+    };
+    let rest: Vec<&str> = args.collect();
+    let has = |flags: &[&str]| rest.iter().any(|arg| flags.contains(arg));
+    let positional = rest.iter().filter(|arg| !arg.starts_with('-')).count();
+    let first = rest.first().copied();
+
+    let reads = match subcommand {
+        "rev-parse" | "log" | "show" | "diff" | "diff-tree" | "diff-index" | "ls-tree"
+        | "ls-files" | "ls-remote" | "cat-file" | "for-each-ref" | "show-ref" | "rev-list"
+        | "merge-base" | "patch-id" | "check-mailmap" | "check-attr" | "check-ignore" | "var"
+        | "status" | "describe" | "name-rev" | "blame" | "grep" | "cherry" | "shortlog"
+        | "range-diff" | "interpret-trailers" | "verify-commit" | "verify-tag"
+        | "count-objects" | "version" | "help" => true,
+        // Writes the merged trees as objects, but nothing points at them
+        "merge-tree" => true,
+        "read-tree" => scratch_index,
+        "config" => {
+            has(&[
+                "--get",
+                "--get-all",
+                "--get-regexp",
+                "--get-urlmatch",
+                "--list",
+                "-l",
+            ]) || matches!(first, Some("get" | "list"))
+        }
+        "remote" => matches!(first, None | Some("-v" | "--verbose" | "get-url" | "show")),
+        "branch" | "tag" => {
+            has(&[
+                "--list",
+                "-l",
+                "--show-current",
+                "--contains",
+                "--no-contains",
+                "--merged",
+                "--no-merged",
+                "--points-at",
+            ]) || positional == 0
+        }
+        "symbolic-ref" => positional <= 1 && !has(&["-d", "--delete"]),
+        "stash" => matches!(first, Some("list" | "show")),
+        "worktree" => first == Some("list"),
+        "notes" => matches!(first, Some("list" | "show")),
+        "submodule" => matches!(first, Some("status" | "summary")),
+        "lfs" => {
+            matches!(first, Some("ls-files" | "env" | "version" | "status"))
+                || (first == Some("track") && rest.len() == 1)
+        }
+        "hash-object" => !has(&["-w"]),
+        "format-patch" => has(&["--stdout"]),
+        "apply" => {
+            has(&["--check"]) || (has(&["--stat", "--numstat", "--summary"]) && !has(&["--apply"]))
+        }
+        _ => false,
+    };
+    !reads
+}
+
+fn gh_mutates(args: &[&str]) -> bool {
+    match args {
+        ["api", path, rest @ ..] => {
+            if *path == "graphql" {
+                return rest.iter().any(|arg| graphql_mutation(arg));
+            }
+            let method = rest
+                .windows(2)
+                .find(|pair| matches!(pair[0], "--method" | "-X"))
+                .map(|pair| pair[1].to_ascii_uppercase());
+            let fields = rest
+                .iter()
+                .any(|arg| matches!(*arg, "-f" | "-F" | "--field" | "--raw-field" | "--input"));
+            match method {
+                Some(method) => !matches!(method.as_str(), "GET" | "HEAD"),
+                // gh sends fields with POST unless told otherwise
+                None => fields,
+            }
+        }
+        ["auth", "status" | "token", ..]
+        | ["repo", "view", ..]
+        | ["pr", "list" | "view" | "status" | "checks" | "diff", ..]
+        | ["release", "list" | "view", ..]
+        | ["attestation", "verify", ..]
+        | ["run", "list" | "view", ..]
+        | ["--version", ..] => false,
+        _ => true,
+    }
+}
+
+fn curl_mutates(args: &[&str], input: &str) -> bool {
+    let method = args
+        .windows(2)
+        .find(|pair| matches!(pair[0], "--request" | "-X"))
+        .map(|pair| pair[1].to_ascii_uppercase());
+    let url = args.last().copied().unwrap_or("");
+    match method.as_deref() {
+        None | Some("GET" | "HEAD") => args.iter().any(|arg| {
+            arg.starts_with("--data")
+                || matches!(*arg, "-d" | "-F" | "--form" | "-T" | "--upload-file")
+        }),
+        // GraphQL queries are POSTed too
+        Some("POST") if url.ends_with("/graphql") => graphql_mutation(input),
+        Some(_) => true,
+    }
+}
+
+/// Whether a GraphQL request, as `query=...` or in a JSON body, is a mutation
+fn graphql_mutation(text: &str) -> bool {
+    text.split("query").skip(1).any(|rest| {
+        rest.trim_start_matches(['\\', '"', ':', '=', ' '])
+            .starts_with("mutation")
+    })
+}
+
+/// The command as it would be typed, long arguments cut short
+fn describe(command: &Command) -> String {
+    const LONGEST: usize = 60;
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|arg| {
+            let arg = arg
+                .to_string_lossy()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let arg = match arg.char_indices().nth(LONGEST) {
+                Some((end, _)) => format!("{}…", &arg[..end]),
+                None => arg,
+            };
+            if arg.is_empty() || arg.contains(' ') {
+                format!("\"{}\"", arg)
+            } else {
+                arg
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(line: &[&str]) -> Command {
+        let mut command = Command::new(line[0]);
+        command.args(&line[1..]);
+        command
+    }
+
+    #[test]
+    fn lets_through_only_commands_that_look() {
+        let reads: &[&[&str]] = &[
+            &["git", "rev-parse", "HEAD"],
+            &["git", "-C", "/tmp/x", "log", "--format=%H"],
+            &["git", "-c", "core.quotepath=off", "diff", "a..b"],
+            &["git", "config", "--get", "remote.origin.url"],
+            &["git", "remote", "-v"],
+            &["git", "branch", "--list", "chuck/*"],
+            &["git", "branch", "-r"],
+            &["git", "symbolic-ref", "--short", "HEAD"],
+            &["git", "merge-tree", "--write-tree", "a", "b"],
+            &["git", "apply", "--cached", "--3way", "--check"],
+            &["gh", "api", "repos/acme/template/commits/main", "--include"],
+            &[
+                "gh",
+                "api",
+                "graphql",
+                "-f",
+                "query=query { viewer { login } }",
+            ],
+            &["gh", "pr", "list", "--repo", "acme/template"],
+            &[
+                "curl",
+                "--request",
+                "GET",
+                "--config",
+                "-",
+                "https://x/repos/a/b",
+            ],
+            &["kill", "-0", "123"],
+        ];
+        for line in reads {
+            assert!(!mutates(&command(line), None), "{:?}", line);
+        }
+
+        let writes: &[&[&str]] = &[
+            &["git", "remote", "add", "chuck-template", "https://x"],
+            &["git", "fetch", "chuck-template"],
+            &["git", "checkout", "-b", "chuck/fix"],
+            &["git", "-C", "/tmp/x", "push", "origin", "chuck/fix"],
+            &["git", "config", "user.email", "jane@acme.com"],
+            &["git", "branch", "-D", "chuck/fix"],
+            &["git", "symbolic-ref", "HEAD", "refs/heads/main"],
+            &["git", "apply", "--index", "fix.patch"],
+            &["gh", "pr", "create", "--fill"],
+            &["gh", "api", "repos/acme/template/pulls", "--input", "-"],
+            &[
+                "gh",
+                "api",
+                "repos/acme/template/issues/4",
+                "--method",
+                "PATCH",
+            ],
+            &[
+                "gh",
+                "api",
+                "graphql",
+                "-f",
+                "query=mutation($id: ID!) { x }",
+            ],
+            &["curl", "--request", "POST", "https://x/repos/a/b/pulls"],
+            &["sh", "-c", "cargo fmt"],
+        ];
+        for line in writes {
+            assert!(mutates(&command(line), None), "{:?}", line);
+        }
+
+        let mut scratch = command(&["git", "read-tree", "HEAD"]);
+        assert!(mutates(&scratch, None));
+        scratch.env("GIT_INDEX_FILE", "/tmp/chuck-check.index");
+        assert!(!mutates(&scratch, None));
+
+        let graphql = command(&[
+            "curl",
+            "--request",
+            "POST",
+            "https://api.github.com/graphql",
+        ]);
+        let query = br#"data = "{\"query\":\"query { viewer { login } }\"}""#;
+        assert!(!mutates(&graphql, Some(query)));
+        let mutation = br#"data = "{\"query\":\"mutation { x }\"}""#;
+        assert!(mutates(&graphql, Some(mutation)));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::forge;
+use crate::network;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::process::Command;
+
+/// What `chuck rebase` was asked to rebase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Target {
+    /// A pull request on the template, by number
+    PullRequest(u64),
+    /// A branch Chuck pushed, by name
+    Branch(String),
+}
+
+impl Target {
+    /// `123`, `#123` or a pull request URL name a PR, anything else a branch
+    pub fn parse(arg: &str) -> Self {
+        let number = arg
+            .rsplit_once("/pull/")
+            .map_or(arg.trim_start_matches('#'), |(_, rest)| {
+                rest.split(['/', '#', '?']).next().unwrap_or(rest)
+            });
+        match number.parse() {
+            Ok(number) => Target::PullRequest(number),
+            Err(_) => Target::Branch(arg.to_string()),
+        }
+    }
+}
+
+/// The branch behind an open pull request and where it's going
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequest {
+    /// Repository the branch lives in, `owner/name`: the template or a fork of it
+    pub repo: String,
+    pub branch: String,
+    /// Template branch the PR merges into
+    pub base: String,
+    pub url: String,
+}
+
+/// Looks up pull request `number` on `template_repo`, refusing closed ones
+pub fn pull_request(template_repo: &str, number: u64) -> Result<PullRequest> {
+    let json = forge::get_json(
+        &format!("repos/{}/pulls/{}", template_repo, number),
+        &format!("Failed to look up PR #{}", number),
+    )?;
+    let text = |pointer: &str| {
+        json.pointer(pointer)
+            .and_then(|value| value.as_str())
+            .unwrap_or_default()
+            .to_string()
+    };
+
+    if text("/state") != "open" {
+        return Err(anyhow!(
+            "PR #{} on {} is {}, there's nothing to keep mergeable",
+            number,
+            template_repo,
+            if json.get("merged_at").is_some_and(|m| !m.is_null()) {
+                "merged"
+            } else {
+                "closed"
+            }
+        ));
+    }
+    let repo = text("/head/repo/full_name");
+    if repo.is_empty() {
+        return Err(anyhow!(
+            "PR #{}'s branch is gone along with the repository it was in",
+            number
+        ));
+    }
+    Ok(PullRequest {
+        repo,
+        branch: text("/head/ref"),
+        base: text("/base/ref"),
+        url: text("/html_url"),
+    })
+}
+
+/// Fetches `branch` from `target`, returning the commit it points at
+pub fn fetch_branch(target: &str, branch: &str) -> Result<String> {
+    let output = Command::new("git")
+        .args(["fetch", target, &format!("refs/heads/{}", branch)])
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to execute git fetch"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if error.contains("couldn't find remote ref") {
+            return Err(anyhow!("There's no branch {} on {}", branch, target));
+        }
+        return Err(anyhow!(network::describe_failure(
+            &format!("Failed to fetch {}", branch),
+            &error
+        )));
+    }
+
+    let output = Command::new("git")
+        .args(["rev-parse", "FETCH_HEAD"])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+    Ok(String::from_utf8(output.stdout)?.trim().to_string())
+}
+
+/// Replaces `remote_branch` on `target` with `branch`, as long as it still points at
+/// `expected`. Someone pushing to it in the meantime makes this fail rather than
+/// losing their commits.
+pub fn force_push(target: &str, branch: &str, remote_branch: &str, expected: &str) -> Result<()> {
+    let remote_ref = format!("refs/heads/{}", remote_branch);
+    let output = Command::new("git")
+        .args([
+            "push",
+            &format!("--force-with-lease={}:{}", remote_ref, expected),
+            target,
+            &format!("{}:{}", branch, remote_ref),
+        ])
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to execute git push command"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        if error.contains("stale info") {
+            return Err(anyhow!(
+                "{} changed on {} since it was fetched, run chuck rebase again to pick that up",
+                remote_branch,
+                target
+            ));
+        }
+        return Err(anyhow!(network::describe_failure(
+            "Git push failed",
+            &error
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn tells_pull_requests_from_branches() {
+        assert_eq!(Target::parse("42"), Target::PullRequest(42));
+        assert_eq!(Target::parse("#42"), Target::PullRequest(42));
+        assert_eq!(
+            Target::parse("https://github.com/acme/template/pull/42/files"),
+            Target::PullRequest(42)
+        );
+        assert_eq!(
+            Target::parse("chuck-from-jane-app-20250120-143022"),
+            Target::Branch("chuck-from-jane-app-20250120-143022".to_string())
+        );
+    }
+
+    #[test]
+    fn finds_the_branch_behind_a_pull_request() {
+        let fake = Rc::new(FakeRunner::new().on(
+            "gh api repos/acme/template/pulls/42",
+            "HTTP/2.0 200 OK\n\n{\"state\":\"open\",\"html_url\":\"https://github.com/acme/template/pull/42\",\
+             \"head\":{\"ref\":\"chuck-from-jane-app-1\",\"repo\":{\"full_name\":\"jane/template\"}},\
+             \"base\":{\"ref\":\"main\"}}",
+        ));
+        let pr = process::with_runner(fake, || pull_request("acme/template", 42)).unwrap();
+        assert_eq!(
+            pr,
+            PullRequest {
+                repo: "jane/template".to_string(),
+                branch: "chuck-from-jane-app-1".to_string(),
+                base: "main".to_string(),
+                url: "https://github.com/acme/template/pull/42".to_string(),
+            }
+        );
+
+        let fake = Rc::new(FakeRunner::new().on(
+            "gh api repos/acme/template/pulls/42",
+            "HTTP/2.0 200 OK\n\n{\"state\":\"closed\",\"merged_at\":\"2025-01-20T14:30:22Z\"}",
+        ));
+        let error = process::with_runner(fake, || pull_request("acme/template", 42));
+        assert!(error.unwrap_err().to_string().contains("is merged"));
+    }
+}
@@ -0,0 +1,111 @@
+use crate::forge;
+use crate::read_only;
+use anyhow::{anyhow, Result};
+use std::fs;
+use std::path::Path;
+
+/// Whether a failed fetch says the repository isn't there, as opposed to unreachable
+pub fn is_not_found(stderr: &str) -> bool {
+    let lower = stderr.to_lowercase();
+    ["not found", "does not appear to be a git repository"]
+        .iter()
+        .any(|needle| lower.contains(needle))
+}
+
+/// Where GitHub says `repo` lives now, `None` when that's still `repo`. The API
+/// follows the redirect GitHub keeps after a rename or transfer.
+pub fn current_name(repo: &str) -> Result<Option<String>> {
+    let json = forge::get_json(
+        &format!("repos/{}", repo),
+        &format!("Failed to look up {}", repo),
+    )?;
+    let name = json
+        .get("full_name")
+        .and_then(|name| name.as_str())
+        .unwrap_or_default()
+        .to_string();
+    Ok((!name.is_empty() && !name.eq_ignore_ascii_case(repo)).then_some(name))
+}
+
+/// `url` pointed at `repo` instead, keeping its style (SSH or HTTPS) and `.git` suffix
+pub fn moved_url(url: &str, repo: &str) -> String {
+    let prefix = if url.starts_with("git@github.com:") {
+        "git@github.com:"
+    } else {
+        "https://github.com/"
+    };
+    let suffix = if url.ends_with(".git") { ".git" } else { "" };
+    format!("{}{}{}", prefix, repo, suffix)
+}
+
+/// `content` with the `url` value swapped for `new_url`, leaving everything else as
+/// it was. `None` when the url isn't there as a quoted string.
+pub fn rewrite(content: &str, url: &str, new_url: &str) -> Option<String> {
+    ['"', '\''].iter().find_map(|quote| {
+        let old = format!("{}{}{}", quote, url, quote);
+        content
+            .contains(&old)
+            .then(|| content.replacen(&old, &format!("{}{}{}", quote, new_url, quote), 1))
+    })
+}
+
+/// Points the template url in the `.chuckrc` at `path` to `new_url`
+pub fn update_config(path: &Path, url: &str, new_url: &str) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let updated = rewrite(&content, url, new_url)
+        .ok_or_else(|| anyhow!("{} doesn't have url = \"{}\"", path.display(), url))?;
+    if read_only::withheld(&format!("pointed {} at {}", path.display(), new_url)) {
+        return Ok(());
+    }
+    fs::write(path, updated).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    #[test]
+    fn follows_a_renamed_repository() {
+        let fake = Rc::new(FakeRunner::new().on(
+            "gh api repos/acme/old-template",
+            r#"{"full_name":"acme/template"}"#,
+        ));
+        let name = process::with_runner(fake.clone(), || current_name("acme/old-template"));
+        assert_eq!(name.unwrap().as_deref(), Some("acme/template"));
+
+        let fake = Rc::new(FakeRunner::new().on("gh api", r#"{"full_name":"Acme/Template"}"#));
+        let name = process::with_runner(fake.clone(), || current_name("acme/template"));
+        assert_eq!(name.unwrap(), None);
+
+        assert_eq!(
+            moved_url("git@github.com:acme/old.git", "org/new"),
+            "git@github.com:org/new.git"
+        );
+        assert_eq!(
+            moved_url("https://github.com/acme/old", "org/new"),
+            "https://github.com/org/new"
+        );
+    }
+
+    #[test]
+    fn rewrites_only_the_url() {
+        let content = "# Our template\n[template]\nurl = \"git@github.com:acme/old.git\" # moved?\nremote = \"up\"\n";
+        assert_eq!(
+            rewrite(
+                content,
+                "git@github.com:acme/old.git",
+                "git@github.com:acme/new.git"
+            )
+            .unwrap(),
+            "# Our template\n[template]\nurl = \"git@github.com:acme/new.git\" # moved?\nremote = \"up\"\n"
+        );
+        assert_eq!(
+            rewrite("url = 'a'\n", "a", "b").as_deref(),
+            Some("url = 'b'\n")
+        );
+        assert_eq!(rewrite("url = \"a\"\n", "c", "b"), None);
+    }
+}
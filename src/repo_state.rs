@@ -0,0 +1,99 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::path::PathBuf;
+use std::process::Command;
+
+/// An operation git leaves half-finished in the repository
+struct InProgress {
+    /// Marker under the git dir whose presence means the operation is running
+    marker: &'static str,
+    what: &'static str,
+    fix: &'static str,
+}
+
+const IN_PROGRESS: [InProgress; 7] = [
+    InProgress {
+        marker: "rebase-merge",
+        what: "a rebase",
+        fix: "git rebase --continue (or git rebase --abort)",
+    },
+    InProgress {
+        marker: "rebase-apply/applying",
+        what: "a git am",
+        fix: "git am --continue (or git am --abort)",
+    },
+    InProgress {
+        marker: "rebase-apply",
+        what: "a rebase",
+        fix: "git rebase --continue (or git rebase --abort)",
+    },
+    InProgress {
+        marker: "MERGE_HEAD",
+        what: "a merge",
+        fix: "commit the merge (or git merge --abort)",
+    },
+    InProgress {
+        marker: "CHERRY_PICK_HEAD",
+        what: "a cherry-pick",
+        fix: "git cherry-pick --continue (or git cherry-pick --abort)",
+    },
+    InProgress {
+        marker: "REVERT_HEAD",
+        what: "a revert",
+        fix: "git revert --continue (or git revert --abort)",
+    },
+    InProgress {
+        marker: "BISECT_LOG",
+        what: "a bisect",
+        fix: "git bisect reset",
+    },
+];
+
+/// Refuses to start while git is in the middle of something, since every
+/// checkout and cherry-pick chuck runs would fail in confusing ways
+pub fn ensure_idle() -> Result<()> {
+    for state in &IN_PROGRESS {
+        if !git_path(state.marker)?.exists() {
+            continue;
+        }
+
+        let mut message = format!(
+            "This repository is in the middle of {}. Finish it first: {}",
+            state.what, state.fix
+        );
+        if on_chuck_branch() {
+            message.push_str(
+                "\n  It looks left over from an earlier chuck run; abort it and \
+                 `git checkout -` back to your branch.",
+            );
+        } else {
+            message.push_str(
+                "\n  To keep unfinished work aside, abort, then `git stash` and run chuck again.",
+            );
+        }
+        return Err(anyhow!(message));
+    }
+    Ok(())
+}
+
+/// Resolves a path inside the git dir, honoring worktrees
+fn git_path(name: &str) -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", name])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("Not inside a git repository"));
+    }
+
+    Ok(PathBuf::from(String::from_utf8(output.stdout)?.trim()))
+}
+
+fn on_chuck_branch() -> bool {
+    Command::new("git")
+        .args(["symbolic-ref", "--short", "-q", "HEAD"])
+        .output_local()
+        .map(|o| String::from_utf8_lossy(&o.stdout).starts_with("chuck/"))
+        .unwrap_or(false)
+}
@@ -0,0 +1,247 @@
+use crate::forge;
+use crate::journal::{self, JournalEntry, Outcome};
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde_json::Value;
+use std::fmt::Write;
+use std::io::{self, IsTerminal, Write as _};
+use std::process::Command;
+
+/// Pull request lookups in flight at once, across however many templates
+const LOOKUPS: usize = 8;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ReportFormat {
+    Md,
+    Html,
+}
+
+struct PullRequest {
+    number: u64,
+    url: String,
+    state: String,
+}
+
+struct ReportItem<'a> {
+    entry: &'a JournalEntry,
+    pr: Option<PullRequest>,
+}
+
+pub fn run(since: Option<&str>, format: ReportFormat, verbose: bool) -> Result<()> {
+    let since = since
+        .map(|s| {
+            NaiveDate::parse_from_str(s, "%Y-%m-%d")
+                .map_err(|_| anyhow!("Invalid --since date '{}', expected YYYY-MM-DD", s))
+        })
+        .transpose()?;
+
+    let entries = journal::read_all()?;
+    let pushed: Vec<&JournalEntry> = entries
+        .iter()
+        .filter(|e| e.outcome == Outcome::Pushed)
+        .filter(|e| since.is_none_or(|date| e.timestamp.date_naive() >= date))
+        .collect();
+
+    // The report itself goes to stdout, so progress goes to the terminal on stderr
+    let show_progress = pushed.len() > 1 && io::stderr().is_terminal();
+    let prs = process::map_concurrently(
+        &pushed,
+        LOOKUPS,
+        |entry| find_pull_request(entry, verbose),
+        |done| {
+            if show_progress {
+                eprint!(
+                    "\r\x1b[2K🧔 Looked up {} of {} pull requests",
+                    done,
+                    pushed.len()
+                );
+                let _ = io::stderr().flush();
+            }
+        },
+    );
+    if show_progress {
+        eprint!("\r\x1b[2K");
+    }
+    let items: Vec<ReportItem> = pushed
+        .into_iter()
+        .zip(prs)
+        .map(|(entry, pr)| ReportItem { entry, pr })
+        .collect();
+
+    let title = match since {
+        Some(date) => format!("Upstream contributions since {}", date),
+        None => "Upstream contributions".to_string(),
+    };
+
+    let report = match format {
+        ReportFormat::Md => render_markdown(&title, &items),
+        ReportFormat::Html => render_html(&title, &items),
+    };
+    print!("{}", report);
+
+    Ok(())
+}
+
+fn find_pull_request(entry: &JournalEntry, verbose: bool) -> Option<PullRequest> {
+    let remote_branch = entry.remote_branch.as_deref()?;
+    // Pull request status comes from gh only
+    forge::require_gh("Looking up pull requests").ok()?;
+
+    let output = Command::new("gh")
+        .args([
+            "pr",
+            "list",
+            "--repo",
+            &entry.template,
+            "--head",
+            remote_branch,
+            "--state",
+            "all",
+            "--json",
+            "number,url,state",
+        ])
+        .output_network()
+        .ok()?;
+
+    if !output.status.success() {
+        if verbose {
+            eprintln!(
+                "🧔 VERBOSE: Couldn't look up PR for {}: {}",
+                remote_branch,
+                String::from_utf8_lossy(&output.stderr)
+            );
+        }
+        return None;
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout).ok()?;
+    let pr = json.as_array()?.first()?;
+
+    Some(PullRequest {
+        number: pr.get("number")?.as_u64()?,
+        url: pr.get("url")?.as_str()?.to_string(),
+        state: pr.get("state")?.as_str()?.to_lowercase(),
+    })
+}
+
+fn status_label(pr: &Option<PullRequest>) -> &str {
+    match pr {
+        Some(pr) => &pr.state,
+        None => "no PR",
+    }
+}
+
+fn render_markdown(title: &str, items: &[ReportItem]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "# {}\n", title);
+
+    if items.is_empty() {
+        let _ = writeln!(out, "Nothing was contributed upstream in this period.");
+        return out;
+    }
+
+    let commit_count: usize = items.iter().map(|i| i.entry.commits.len()).sum();
+    let merged = items
+        .iter()
+        .filter(|i| status_label(&i.pr) == "merged")
+        .count();
+    let _ = writeln!(
+        out,
+        "{} contributions, {} commits, {} merged.\n",
+        items.len(),
+        commit_count,
+        merged
+    );
+
+    for item in items {
+        let entry = item.entry;
+        let pr = match &item.pr {
+            Some(pr) => format!("[#{}]({})", pr.number, pr.url),
+            None => entry.remote_branch.clone().unwrap_or_default(),
+        };
+        let _ = writeln!(
+            out,
+            "## {} -> {} ({})\n",
+            entry.repo,
+            entry.template,
+            entry.timestamp.format("%Y-%m-%d")
+        );
+        let _ = writeln!(out, "- PR: {}", pr);
+        let _ = writeln!(out, "- Status: {}\n", status_label(&item.pr));
+        for commit in &entry.commits {
+            let _ = writeln!(
+                out,
+                "- `{}` {} ({})",
+                &commit.hash[..7.min(commit.hash.len())],
+                commit.message,
+                commit.author
+            );
+        }
+        let _ = writeln!(out);
+    }
+
+    out
+}
+
+fn render_html(title: &str, items: &[ReportItem]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>",
+        escape_html(title)
+    );
+
+    if items.is_empty() {
+        let _ = writeln!(
+            out,
+            "<p>Nothing was contributed upstream in this period.</p>"
+        );
+    } else {
+        let _ = writeln!(
+            out,
+            "<table>\n<tr><th>Date</th><th>Repository</th><th>Template</th><th>PR</th><th>Status</th><th>Commits</th></tr>"
+        );
+        for item in items {
+            let entry = item.entry;
+            let pr = match &item.pr {
+                Some(pr) => format!("<a href=\"{}\">#{}</a>", escape_html(&pr.url), pr.number),
+                None => escape_html(entry.remote_branch.as_deref().unwrap_or("")),
+            };
+            let commits: Vec<String> = entry
+                .commits
+                .iter()
+                .map(|c| {
+                    format!(
+                        "<code>{}</code> {}",
+                        &c.hash[..7.min(c.hash.len())],
+                        escape_html(&c.message)
+                    )
+                })
+                .collect();
+            let _ = writeln!(
+                out,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                entry.timestamp.format("%Y-%m-%d"),
+                escape_html(&entry.repo),
+                escape_html(&entry.template),
+                pr,
+                status_label(&item.pr),
+                commits.join("<br>")
+            );
+        }
+        let _ = writeln!(out, "</table>");
+    }
+
+    let _ = writeln!(out, "</body>\n</html>");
+    out
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
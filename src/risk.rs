@@ -0,0 +1,190 @@
+use crate::glob::gitignore_match;
+use serde::{Deserialize, Serialize};
+
+/// Infrastructure a commit changes, worth a second look before it goes upstream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Risk {
+    Lockfile,
+    Dependencies,
+    Ci,
+    Sensitive,
+}
+
+impl Risk {
+    pub fn label(self) -> &'static str {
+        match self {
+            Risk::Lockfile => "lockfile",
+            Risk::Dependencies => "deps",
+            Risk::Ci => "ci",
+            Risk::Sensitive => "sensitive",
+        }
+    }
+
+    /// What the badge stands for, in the details pane
+    pub fn describe(self) -> &'static str {
+        match self {
+            Risk::Lockfile => "Changes a lockfile",
+            Risk::Dependencies => "Changes dependencies",
+            Risk::Ci => "Changes CI configuration",
+            Risk::Sensitive => "Touches security-sensitive files",
+        }
+    }
+
+    /// Whether it can break or expose every project built from the template
+    pub fn is_severe(self) -> bool {
+        matches!(self, Risk::Ci | Risk::Sensitive)
+    }
+}
+
+/// `[risk]` in .chuckrc: which paths flag a commit, as `.gitignore` patterns. A list
+/// that's set replaces the built-in one, `[]` turns the flag off.
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct RiskConfig {
+    pub ci: Option<Vec<String>>,
+    pub lockfiles: Option<Vec<String>>,
+    pub dependencies: Option<Vec<String>>,
+    pub sensitive: Option<Vec<String>>,
+}
+
+const CI: &[&str] = &[
+    ".github/workflows/",
+    ".github/actions/",
+    ".gitlab-ci.yml",
+    ".circleci/",
+    ".buildkite/",
+    ".travis.yml",
+    "azure-pipelines.yml",
+    "Jenkinsfile",
+];
+
+const LOCKFILES: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Gemfile.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "composer.lock",
+    "go.sum",
+];
+
+const DEPENDENCIES: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "Gemfile",
+    "pyproject.toml",
+    "Pipfile",
+    "requirements*.txt",
+    "composer.json",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+];
+
+const SENSITIVE: &[&str] = &[
+    "CODEOWNERS",
+    ".env",
+    ".env.*",
+    "*.pem",
+    "*.key",
+    ".npmrc",
+    ".pypirc",
+    "Dockerfile",
+    "**/auth/",
+    "**/security/",
+    "**/secrets/",
+];
+
+/// A risk and the files of the commit that raised it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Finding {
+    pub risk: Risk,
+    pub paths: Vec<String>,
+}
+
+impl RiskConfig {
+    fn patterns(&self, risk: Risk) -> Vec<&str> {
+        let (configured, defaults) = match risk {
+            Risk::Ci => (&self.ci, CI),
+            Risk::Lockfile => (&self.lockfiles, LOCKFILES),
+            Risk::Dependencies => (&self.dependencies, DEPENDENCIES),
+            Risk::Sensitive => (&self.sensitive, SENSITIVE),
+        };
+        match configured {
+            Some(patterns) => patterns.iter().map(String::as_str).collect(),
+            None => defaults.to_vec(),
+        }
+    }
+
+    /// What a commit touching `paths` risks, least severe first
+    pub fn assess<'a>(&self, paths: impl IntoIterator<Item = &'a str> + Clone) -> Vec<Finding> {
+        [
+            Risk::Lockfile,
+            Risk::Dependencies,
+            Risk::Ci,
+            Risk::Sensitive,
+        ]
+        .into_iter()
+        .filter_map(|risk| {
+            let patterns = self.patterns(risk);
+            let paths: Vec<String> = paths
+                .clone()
+                .into_iter()
+                .filter(|path| patterns.iter().any(|p| gitignore_match(p, path)))
+                .map(str::to_string)
+                .collect();
+            (!paths.is_empty()).then_some(Finding { risk, paths })
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_infrastructure_files() {
+        let config = RiskConfig::default();
+        let findings = config.assess([
+            "src/lib.rs",
+            ".github/workflows/ci.yml",
+            "Cargo.toml",
+            "Cargo.lock",
+            "app/auth/session.rs",
+        ]);
+        let risks: Vec<Risk> = findings.iter().map(|f| f.risk).collect();
+        assert_eq!(
+            risks,
+            [
+                Risk::Lockfile,
+                Risk::Dependencies,
+                Risk::Ci,
+                Risk::Sensitive
+            ]
+        );
+        assert_eq!(findings[3].paths, ["app/auth/session.rs"]);
+        assert!(config.assess(["src/lib.rs", "README.md"]).is_empty());
+    }
+
+    #[test]
+    fn configured_lists_replace_the_defaults() {
+        let config = RiskConfig {
+            lockfiles: Some(Vec::new()),
+            sensitive: Some(vec!["config/keys/".to_string()]),
+            ..Default::default()
+        };
+        let findings = config.assess(["Cargo.lock", ".env", "config/keys/prod.json"]);
+        assert_eq!(
+            findings,
+            [Finding {
+                risk: Risk::Sensitive,
+                paths: vec!["config/keys/prod.json".to_string()],
+            }]
+        );
+    }
+}
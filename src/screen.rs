@@ -0,0 +1,57 @@
+use std::cell::{Cell, RefCell};
+
+// While the TUI has the terminal, printing would garble its screen. Messages wait
+// until it's gone and errors go to its error panel.
+thread_local! {
+    static TAKEN: Cell<bool> = const { Cell::new(false) };
+    static HELD: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+    static ERRORS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The TUI has the terminal until this is dropped, once it's torn down. Dropping it
+/// prints what was held back, errors the panel never got to show included.
+pub struct Taken(());
+
+impl Drop for Taken {
+    fn drop(&mut self) {
+        TAKEN.set(false);
+        for line in HELD.take() {
+            println!("{}", line);
+        }
+        for error in ERRORS.take() {
+            println!("🧔 {}", error);
+        }
+    }
+}
+
+pub fn take() -> Taken {
+    TAKEN.set(true);
+    Taken(())
+}
+
+pub fn is_taken() -> bool {
+    TAKEN.get()
+}
+
+/// Prints `line`, or holds it back while the TUI is up
+pub fn say(line: &str) {
+    if is_taken() {
+        HELD.with_borrow_mut(|held| held.push(line.to_string()));
+    } else {
+        println!("{}", line);
+    }
+}
+
+/// Shows `error` in the TUI's error panel while it's up, otherwise prints it
+pub fn error(error: &str) {
+    if is_taken() {
+        ERRORS.with_borrow_mut(|errors| errors.push(error.to_string()));
+    } else {
+        println!("🧔 {}", error);
+    }
+}
+
+/// Errors reported since the last call, for the error panel
+pub fn take_errors() -> Vec<String> {
+    ERRORS.take()
+}
@@ -0,0 +1,139 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const HEADER: &str = "# Written by chuck export-selection. Reorder, drop or reword commits, then\n\
+# build the branch with chuck --selection <this file>.\n\n";
+
+/// What to do with a commit, as on the plan screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+    #[default]
+    Pick,
+    Squash,
+    Reword,
+    Drop,
+}
+
+/// One commit of the plan
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Step {
+    /// Full SHA or an unambiguous prefix
+    pub hash: String,
+    /// The commit's subject, for whoever reviews the file. Not read back.
+    #[serde(default)]
+    pub subject: String,
+    #[serde(default)]
+    pub action: Action,
+    /// New subject line with `action = "reword"`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Keep the template files the commit deletes
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub keep_template_files: bool,
+    /// Files left out of the pick
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub leave_out: Vec<String>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A planned contribution kept in a file, so it can go through code review before
+/// anyone pushes it
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Selection {
+    /// `owner/name` of the template it was planned against
+    pub template: String,
+    /// Template commit the branch is built on
+    pub base: String,
+    /// Template branch the PR targets
+    pub target: String,
+    /// In the order they're picked
+    #[serde(rename = "commits")]
+    pub steps: Vec<Step>,
+}
+
+impl Selection {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let selection: Self = toml::from_str(&content)
+            .map_err(|e| anyhow!("{} is invalid: {}", path.display(), e))?;
+        if selection.steps.is_empty() {
+            return Err(anyhow!("{} doesn't list any commits", path.display()));
+        }
+        Ok(selection)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string(self)
+            .map_err(|e| anyhow!("Failed to serialize the selection: {}", e))?;
+        fs::write(path, format!("{}{}", HEADER, content))
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Every commit listed, dropped ones too, to select before the plan is applied
+    pub fn hashes(&self) -> Vec<String> {
+        self.steps.iter().map(|step| step.hash.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_a_hand_edited_selection() {
+        let selection: Selection = toml::from_str(
+            r#"
+            template = "acme/template"
+            base = "1234567"
+            target = "main"
+
+            [[commits]]
+            hash = "aaa1111"
+            subject = "Fix bug in auth middleware"
+
+            [[commits]]
+            hash = "bbb2222"
+            action = "reword"
+            message = "Pool database connections"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(selection.steps[0].action, Action::Pick);
+        assert_eq!(selection.steps[1].action, Action::Reword);
+        assert_eq!(
+            selection.steps[1].message.as_deref(),
+            Some("Pool database connections")
+        );
+        assert_eq!(selection.hashes(), ["aaa1111", "bbb2222"]);
+    }
+
+    #[test]
+    fn writes_only_what_differs_from_a_plain_pick() {
+        let selection = Selection {
+            template: "acme/template".to_string(),
+            base: "1234567".to_string(),
+            target: "main".to_string(),
+            steps: vec![Step {
+                hash: "aaa1111".to_string(),
+                subject: "Fix bug".to_string(),
+                action: Action::Pick,
+                message: None,
+                keep_template_files: false,
+                leave_out: Vec::new(),
+            }],
+        };
+        assert_eq!(
+            toml::to_string(&selection).unwrap(),
+            "template = \"acme/template\"\nbase = \"1234567\"\ntarget = \"main\"\n\n\
+             [[commits]]\nhash = \"aaa1111\"\nsubject = \"Fix bug\"\naction = \"pick\"\n"
+        );
+    }
+}
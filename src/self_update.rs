@@ -0,0 +1,305 @@
+use crate::forge;
+use crate::paths;
+use crate::process::{self, CommandExt};
+use crate::read_only;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const RELEASE_REPO: &str = "Midwest-Dads/chuck-cli";
+const CHECKSUM_ASSET: &str = "SHA256SUMS";
+
+struct Release {
+    tag: String,
+    assets: Vec<String>,
+}
+
+pub fn run(check_only: bool, verbose: bool) -> Result<()> {
+    let current_version = env!("CARGO_PKG_VERSION");
+    println!("🧔 Current version: {}", current_version);
+    forge::require_gh("chuck self-update")?;
+
+    let release = get_latest_release()?;
+    let latest_version = release.tag.trim_start_matches('v');
+    println!("🧔 Latest release: {}", latest_version);
+
+    if !is_newer_version(latest_version, current_version) {
+        println!("🧔 \"You're already up to date. Nothing to do here.\"");
+        return Ok(());
+    }
+
+    if check_only {
+        println!("🧔 Run 'chuck self-update' to install {}", latest_version);
+        return Ok(());
+    }
+
+    let asset_name = platform_asset_name();
+    if !release.assets.iter().any(|a| a == &asset_name) {
+        return Err(anyhow!(
+            "No release binary for this platform ({}) in {}",
+            asset_name,
+            release.tag
+        ));
+    }
+    if !release.assets.iter().any(|a| a == CHECKSUM_ASSET) {
+        return Err(anyhow!(
+            "Release {} has no {} file, refusing to install an unverified binary",
+            release.tag,
+            CHECKSUM_ASSET
+        ));
+    }
+
+    // Nothing downloaded means nothing to verify or install
+    if read_only::withheld(&format!(
+        "downloaded {} from {}, verified it and installed it",
+        asset_name, release.tag
+    )) {
+        return Ok(());
+    }
+
+    let download_dir = paths::temp_dir().join(format!("chuck-update-{}", std::process::id()));
+    fs::create_dir_all(&download_dir)?;
+
+    let result = download_and_install(&release.tag, &asset_name, &download_dir, verbose);
+    let _ = fs::remove_dir_all(&download_dir);
+    result?;
+
+    println!(
+        "🧔 ✅ Updated chuck {} -> {}",
+        current_version, latest_version
+    );
+    Ok(())
+}
+
+fn download_and_install(
+    tag: &str,
+    asset_name: &str,
+    download_dir: &Path,
+    verbose: bool,
+) -> Result<()> {
+    println!("🧔 Downloading {}...", asset_name);
+    if verbose {
+        println!("🧔 VERBOSE: Download directory: {}", download_dir.display());
+    }
+
+    let output = Command::new("gh")
+        .args([
+            "release",
+            "download",
+            tag,
+            "--repo",
+            RELEASE_REPO,
+            "--pattern",
+            asset_name,
+            "--pattern",
+            CHECKSUM_ASSET,
+            "--dir",
+        ])
+        .arg(download_dir)
+        .output_network()
+        .map_err(|e| {
+            process::command_error(e, "GitHub CLI not found. Install with: brew install gh")
+        })?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to download release: {}", error));
+    }
+
+    let binary_path = download_dir.join(asset_name);
+    let checksums = fs::read_to_string(download_dir.join(CHECKSUM_ASSET))?;
+    let expected = expected_checksum(&checksums, asset_name)
+        .ok_or_else(|| anyhow!("{} has no entry for {}", CHECKSUM_ASSET, asset_name))?;
+    let actual = sha256_file(&binary_path)?;
+
+    if verbose {
+        println!("🧔 VERBOSE: Expected checksum: {}", expected);
+        println!("🧔 VERBOSE: Actual checksum:   {}", actual);
+    }
+
+    if !expected.eq_ignore_ascii_case(&actual) {
+        return Err(anyhow!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            asset_name,
+            expected,
+            actual
+        ));
+    }
+    println!("🧔 Checksum verified");
+
+    // The checksum comes from the same release, so it only catches a broken download
+    verify_attestation(&binary_path, asset_name)?;
+    println!("🧔 Signature verified, built by {}", RELEASE_REPO);
+
+    replace_current_exe(&binary_path)
+}
+
+/// Checks the binary's signed build attestation: GitHub only issues those to the
+/// release workflow in `RELEASE_REPO`, so uploading a release asset isn't enough to
+/// pass this
+fn verify_attestation(binary_path: &Path, asset_name: &str) -> Result<()> {
+    let output = Command::new("gh")
+        .args(["attestation", "verify"])
+        .arg(binary_path)
+        .args(["--repo", RELEASE_REPO])
+        .output_network()
+        .map_err(|e| process::command_error(e, "Failed to execute gh attestation verify"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!(
+            "{} has no valid signature from {}, refusing to install it: {}",
+            asset_name,
+            RELEASE_REPO,
+            error.trim()
+        ));
+    }
+    Ok(())
+}
+
+fn get_latest_release() -> Result<Release> {
+    let output = Command::new("gh")
+        .args([
+            "release",
+            "view",
+            "--repo",
+            RELEASE_REPO,
+            "--json",
+            "tagName,assets",
+        ])
+        .output_network()
+        .map_err(|e| {
+            process::command_error(e, "GitHub CLI not found. Install with: brew install gh")
+        })?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to get latest release: {}", error));
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout)?;
+
+    let tag = json
+        .get("tagName")
+        .and_then(|t| t.as_str())
+        .ok_or_else(|| anyhow!("Could not get release tag"))?
+        .to_string();
+
+    let assets = json
+        .get("assets")
+        .and_then(|a| a.as_array())
+        .map(|assets| {
+            assets
+                .iter()
+                .filter_map(|a| a.get("name").and_then(|n| n.as_str()))
+                .map(|n| n.to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+
+    Ok(Release { tag, assets })
+}
+
+fn platform_asset_name() -> String {
+    let os = match env::consts::OS {
+        "macos" => "darwin",
+        other => other,
+    };
+    let suffix = if cfg!(windows) { ".exe" } else { "" };
+    format!("chuck-{}-{}{}", os, env::consts::ARCH, suffix)
+}
+
+fn is_newer_version(latest: &str, current: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.split(['.', '-'])
+            .map_while(|part| part.parse().ok())
+            .collect()
+    };
+    parse(latest) > parse(current)
+}
+
+fn expected_checksum(checksums: &str, asset_name: &str) -> Option<String> {
+    checksums.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        let sum = parts.next()?;
+        let name = parts.next()?.trim_start_matches('*');
+        if name == asset_name {
+            Some(sum.to_string())
+        } else {
+            None
+        }
+    })
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    // sha256sum on Linux, shasum on macOS, certutil on Windows
+    let output = if cfg!(windows) {
+        Command::new("certutil")
+            .arg("-hashfile")
+            .arg(path)
+            .arg("SHA256")
+            .output_local()
+    } else if cfg!(target_os = "macos") {
+        Command::new("shasum")
+            .args(["-a", "256"])
+            .arg(path)
+            .output_local()
+    } else {
+        Command::new("sha256sum").arg(path).output_local()
+    }
+    .map_err(|e| process::command_error(e, "Failed to run checksum tool"))?;
+
+    if !output.status.success() {
+        return Err(anyhow!("Failed to compute checksum of {}", path.display()));
+    }
+
+    let stdout = String::from_utf8(output.stdout)?;
+    let sum = if cfg!(windows) {
+        // certutil prints a header line, the hash, then a status line
+        stdout.lines().nth(1).unwrap_or("").replace(' ', "")
+    } else {
+        stdout.split_whitespace().next().unwrap_or("").to_string()
+    };
+
+    if sum.is_empty() {
+        return Err(anyhow!("Checksum tool returned no output"));
+    }
+
+    Ok(sum)
+}
+
+fn replace_current_exe(new_binary: &Path) -> Result<()> {
+    let current_exe = env::current_exe()?;
+    let staged: PathBuf = current_exe.with_extension("new");
+
+    fs::copy(new_binary, &staged).map_err(|e| {
+        anyhow!(
+            "Can't write next to {} ({}). Try running with sudo.",
+            current_exe.display(),
+            e
+        )
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+
+    // Windows won't let us overwrite a running executable, but it will let us rename it
+    if cfg!(windows) {
+        let old = current_exe.with_extension("old");
+        let _ = fs::remove_file(&old);
+        fs::rename(&current_exe, &old)?;
+    }
+
+    fs::rename(&staged, &current_exe).map_err(|e| {
+        let _ = fs::remove_file(&staged);
+        anyhow!("Failed to replace {}: {}", current_exe.display(), e)
+    })?;
+
+    Ok(())
+}
@@ -0,0 +1,211 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+// JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// What a method that ran but failed answers with
+pub const FAILED: i64 = -32000;
+
+/// A commit `discover` offers, as an editor shows it
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Candidate {
+    pub hash: String,
+    pub short_hash: String,
+    pub subject: String,
+    pub author: String,
+    pub date: String,
+    pub files: Vec<String>,
+    pub merge: bool,
+    /// Whether `execute` can take it, as the TUI would let it be selected
+    pub selectable: bool,
+    /// What the TUI badges it with, like "already upstream" or "changes CI config"
+    pub notes: Vec<String>,
+}
+
+/// What `discover` answers with
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Discovered {
+    /// `owner/name` of the project
+    pub repo: String,
+    /// `owner/name` of the template
+    pub template: String,
+    /// Template commit the branch would be built on
+    pub base: String,
+    /// Template branch the pull request would target
+    pub target: String,
+    pub commits: Vec<Candidate>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn failed(error: impl std::fmt::Display) -> Self {
+        Self::new(FAILED, error.to_string())
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    /// Absent for notifications, which get no answer
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+/// Where a method sends notifications while it runs, like the lines a build prints
+pub struct Notifier<'a> {
+    output: &'a mut dyn Write,
+}
+
+impl Notifier<'_> {
+    pub fn notify(&mut self, method: &str, params: Value) -> io::Result<()> {
+        send(
+            self.output,
+            &json!({ "jsonrpc": "2.0", "method": method, "params": params }),
+        )
+    }
+}
+
+/// Answers JSON-RPC 2.0 requests from `input`, one per line, with `handle`, until
+/// `input` ends or a "shutdown" request comes in. Answers and notifications go to
+/// `output`, one per line too.
+pub fn serve(
+    input: impl BufRead,
+    output: &mut dyn Write,
+    mut handle: impl FnMut(&str, Value, &mut Notifier) -> Result<Value, RpcError>,
+) -> io::Result<()> {
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request: Request = match serde_json::from_str::<Value>(&line) {
+            Err(e) => {
+                answer(
+                    output,
+                    Value::Null,
+                    Err(RpcError::new(PARSE_ERROR, e.to_string())),
+                )?;
+                continue;
+            }
+            Ok(value) => match serde_json::from_value(value) {
+                Ok(request) => request,
+                Err(e) => {
+                    let error = RpcError::new(INVALID_REQUEST, e.to_string());
+                    answer(output, Value::Null, Err(error))?;
+                    continue;
+                }
+            },
+        };
+
+        let shutdown = request.method == "shutdown";
+        let result = if shutdown {
+            Ok(Value::Null)
+        } else {
+            handle(
+                &request.method,
+                request.params,
+                &mut Notifier {
+                    output: &mut *output,
+                },
+            )
+        };
+        if let Some(id) = request.id {
+            answer(output, id, result)?;
+        }
+        if shutdown {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn answer(output: &mut dyn Write, id: Value, result: Result<Value, RpcError>) -> io::Result<()> {
+    send(
+        output,
+        &match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(error) => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "error": { "code": error.code, "message": error.message },
+            }),
+        },
+    )
+}
+
+fn send(output: &mut dyn Write, message: &Value) -> io::Result<()> {
+    writeln!(output, "{}", message)?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn answers_requests_until_shutdown() {
+        let input = [
+            r#"{"jsonrpc":"2.0","id":1,"method":"echo","params":{"say":"hi"}}"#,
+            r#"{"jsonrpc":"2.0","method":"echo","params":{}}"#,
+            "not json",
+            r#"{"jsonrpc":"2.0","id":"b","method":"nope"}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"shutdown"}"#,
+            r#"{"jsonrpc":"2.0","id":4,"method":"echo"}"#,
+        ]
+        .join("\n");
+        let mut output = Vec::new();
+        serve(
+            input.as_bytes(),
+            &mut output,
+            |method, params, notifier| match method {
+                "echo" => {
+                    notifier
+                        .notify("log", json!({ "line": "echoing" }))
+                        .unwrap();
+                    Ok(params)
+                }
+                _ => Err(RpcError::new(
+                    METHOD_NOT_FOUND,
+                    format!("No method {}", method),
+                )),
+            },
+        )
+        .unwrap();
+
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                json!({"jsonrpc":"2.0","method":"log","params":{"line":"echoing"}}),
+                json!({"jsonrpc":"2.0","id":1,"result":{"say":"hi"}}),
+                json!({"jsonrpc":"2.0","method":"log","params":{"line":"echoing"}}),
+                lines[3].clone(),
+                json!({"jsonrpc":"2.0","id":"b","error":{"code":METHOD_NOT_FOUND,"message":"No method nope"}}),
+                json!({"jsonrpc":"2.0","id":3,"result":null}),
+            ]
+        );
+        assert_eq!(lines[3]["error"]["code"], PARSE_ERROR);
+    }
+}
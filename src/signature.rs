@@ -0,0 +1,91 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use std::process::Command;
+
+/// What git makes of a commit's GPG or SSH signature (`%G?`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Signature {
+    /// Good signature from a trusted key
+    Verified(String),
+    /// Good signature, but the key isn't trusted locally
+    Untrusted(String),
+    /// Signature that's expired, made by an expired key, or made by a revoked key
+    Invalid(String, &'static str),
+    Bad(String),
+    /// Signed, but the key isn't available to check it
+    Unverifiable,
+    Unsigned,
+}
+
+impl Signature {
+    fn parse(status: &str, signer: &str) -> Self {
+        let signer = if signer.is_empty() {
+            "unknown signer".to_string()
+        } else {
+            signer.to_string()
+        };
+        match status {
+            "G" => Signature::Verified(signer),
+            "U" => Signature::Untrusted(signer),
+            "X" => Signature::Invalid(signer, "signature expired"),
+            "Y" => Signature::Invalid(signer, "key expired"),
+            "R" => Signature::Invalid(signer, "key revoked"),
+            "B" => Signature::Bad(signer),
+            "E" => Signature::Unverifiable,
+            _ => Signature::Unsigned,
+        }
+    }
+
+    pub fn is_verified(&self) -> bool {
+        matches!(self, Signature::Verified(_))
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Signature::Verified(signer) => format!("✔ verified, signed by {}", signer),
+            Signature::Untrusted(signer) => {
+                format!("good signature by {}, but the key isn't trusted", signer)
+            }
+            Signature::Invalid(signer, reason) => format!("✘ {} ({})", reason, signer),
+            Signature::Bad(signer) => format!("✘ BAD signature claiming {}", signer),
+            Signature::Unverifiable => "signed, but the key isn't available to check".to_string(),
+            Signature::Unsigned => "not signed".to_string(),
+        }
+    }
+}
+
+/// Checks the signatures of `hashes` in one `git log` call, reading them from stdin
+/// so any number of them fits
+pub fn verify(hashes: &[&str]) -> Result<HashMap<String, Signature>> {
+    if hashes.is_empty() {
+        return Ok(HashMap::new());
+    }
+
+    let input = format!("{}\n", hashes.join("\n"));
+    let output = Command::new("git")
+        .args([
+            "log",
+            "--no-walk=unsorted",
+            "--stdin",
+            "--format=%H%x1f%G?%x1f%GS%x1e",
+        ])
+        .output_local_with_input(input.as_bytes())
+        .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to check signatures: {}", error.trim()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\x1e')
+        .filter_map(|record| {
+            let mut fields = record.trim_start_matches('\n').split('\x1f');
+            let hash = fields.next().filter(|h| !h.is_empty())?;
+            let status = fields.next()?;
+            let signer = fields.next().unwrap_or_default();
+            Some((hash.to_string(), Signature::parse(status, signer)))
+        })
+        .collect())
+}
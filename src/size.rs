@@ -0,0 +1,194 @@
+use crate::partial;
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::process::Command;
+
+/// Roughly what a reviewer gets through, 300 lines an hour
+const LINES_PER_MINUTE: usize = 5;
+/// Finding your way around each file takes a bit on top of reading it
+const MINUTES_PER_FILE: usize = 2;
+
+/// `[soft_limits]` in `.chuck-policy.toml`: how big a PR the template would rather
+/// not review in one go. Going over only warns, unlike `max_files`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default)]
+pub struct SoftLimits {
+    pub max_files: Option<usize>,
+    /// Lines added plus lines removed
+    pub max_lines: Option<usize>,
+    /// Distinct directories, two levels deep
+    pub max_areas: Option<usize>,
+}
+
+impl SoftLimits {
+    /// How `estimate` goes over the limits
+    pub fn warnings(&self, estimate: &Estimate) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if let Some(max) = self.max_files.filter(|&max| estimate.files > max) {
+            warnings.push(format!(
+                "{} files (template prefers {})",
+                estimate.files, max
+            ));
+        }
+        if let Some((lines, max)) = estimate
+            .lines()
+            .zip(self.max_lines)
+            .filter(|(lines, max)| lines > max)
+        {
+            warnings.push(format!("{} lines (template prefers {})", lines, max));
+        }
+        if let Some(max) = self.max_areas.filter(|&max| estimate.areas > max) {
+            warnings.push(format!(
+                "{} areas (template prefers {})",
+                estimate.areas, max
+            ));
+        }
+        warnings
+    }
+}
+
+/// Lines a commit adds and removes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineCount {
+    pub added: usize,
+    pub removed: usize,
+}
+
+/// Counts the lines `commit` changes against its `mainline` parent, only under
+/// `subtree` when that isn't `""`. Binary files don't count. A partial clone
+/// isn't worth a download for this, so there it may fail for want of contents.
+pub fn line_count(commit: &str, mainline: u32, subtree: &str) -> Result<LineCount> {
+    let parent = format!("{}^{}", commit, mainline);
+    let relative = format!("--relative={}", subtree);
+    let mut args = vec!["diff", "--numstat", "--no-renames"];
+    if !subtree.is_empty() {
+        args.push(&relative);
+    }
+    args.extend([parent.as_str(), commit]);
+
+    let output = partial::local_only(Command::new("git").args(&args))
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git diff"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to count lines: {}", error.trim()));
+    }
+
+    let mut count = LineCount::default();
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let mut fields = line.split('\t');
+        // Binary files show up as "-"
+        let mut next = || fields.next().and_then(|n| n.parse::<usize>().ok());
+        count.added += next().unwrap_or(0);
+        count.removed += next().unwrap_or(0);
+    }
+    Ok(count)
+}
+
+/// How much reviewing a PR is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Estimate {
+    pub files: usize,
+    /// `None` when some commit's lines couldn't be counted
+    pub changed: Option<LineCount>,
+    pub areas: usize,
+}
+
+impl Estimate {
+    pub fn new<'a>(paths: impl IntoIterator<Item = &'a str>, changed: Option<LineCount>) -> Self {
+        let paths: BTreeSet<&str> = paths.into_iter().collect();
+        let areas: BTreeSet<&str> = paths.iter().map(|path| area(path)).collect();
+        Self {
+            files: paths.len(),
+            changed,
+            areas: areas.len(),
+        }
+    }
+
+    pub fn lines(&self) -> Option<usize> {
+        self.changed.map(|c| c.added + c.removed)
+    }
+
+    /// Rough time to review, e.g. "≈ 25 min"
+    pub fn review_time(&self) -> Option<String> {
+        let minutes = self.lines()?.div_ceil(LINES_PER_MINUTE) + self.files * MINUTES_PER_FILE;
+        Some(match minutes {
+            0..=5 => "< 5 min".to_string(),
+            6..=59 => format!("≈ {} min", minutes.div_ceil(5) * 5),
+            _ => format!("≈ {:.1} h", minutes as f64 / 60.0),
+        })
+    }
+}
+
+/// The part of the tree `path` belongs to: its directory, at most two levels deep,
+/// `""` for files at the top
+fn area(path: &str) -> &str {
+    let dir = path.rfind('/').map_or("", |i| &path[..i]);
+    match dir.match_indices('/').nth(1) {
+        Some((i, _)) => &dir[..i],
+        None => dir,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn counts_lines_without_binary_files() {
+        let fake = Rc::new(FakeRunner::new().on(
+            "git diff --numstat",
+            "10\t2\tsrc/lib.rs\n-\t-\tlogo.png\n0\t7\tREADME.md\n",
+        ));
+        let count = process::with_runner(fake.clone(), || line_count("abc", 2, "apps/web"));
+
+        assert_eq!(
+            count.unwrap(),
+            LineCount {
+                added: 10,
+                removed: 9
+            }
+        );
+        assert_eq!(
+            fake.calls(),
+            ["git diff --numstat --no-renames --relative=apps/web abc^2 abc"]
+        );
+    }
+
+    #[test]
+    fn estimates_and_warns_over_soft_limits() {
+        let paths = [
+            "README.md",
+            "src/main.rs",
+            "src/ui/list.rs",
+            "src/ui/widgets/button.rs",
+            "src/ui/list.rs",
+        ];
+        let changed = LineCount {
+            added: 180,
+            removed: 40,
+        };
+        let estimate = Estimate::new(paths, Some(changed));
+        assert_eq!((estimate.files, estimate.areas), (4, 3));
+        assert_eq!(estimate.review_time().as_deref(), Some("≈ 55 min"));
+        assert_eq!(Estimate::new(paths, None).review_time(), None);
+
+        let limits = SoftLimits {
+            max_files: Some(4),
+            max_lines: Some(200),
+            max_areas: Some(2),
+        };
+        assert_eq!(
+            limits.warnings(&estimate),
+            [
+                "220 lines (template prefers 200)",
+                "3 areas (template prefers 2)"
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::glob::gitignore_match;
+use crate::policy::Policy;
+use serde::{Deserialize, Serialize};
+
+/// `[split]` in .chuckrc: which files of a commit are the project's own, as
+/// `.gitignore` patterns, so commits mixing them with shared code can go upstream
+/// without them
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct SplitConfig {
+    /// Split every mixed commit without asking, like `--auto-split`
+    #[serde(default)]
+    pub auto: bool,
+    /// If set, only files matching one of these go upstream
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Files matching these stay in the project
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+impl SplitConfig {
+    /// Whether `path` stays in the project, by these rules or the template's
+    /// allowed and forbidden paths
+    pub fn is_downstream(&self, path: &str, policy: Option<&Policy>) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| gitignore_match(pattern, path))
+            || (!self.include.is_empty()
+                && !self
+                    .include
+                    .iter()
+                    .any(|pattern| gitignore_match(pattern, path)))
+            || policy.is_some_and(|policy| policy.path_violation(path).is_some())
+    }
+
+    /// The downstream part of a commit touching `paths`, when it also has an
+    /// upstream part. Commits entirely on one side aren't split.
+    pub fn downstream_part<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a str>,
+        policy: Option<&Policy>,
+    ) -> Vec<String> {
+        let (downstream, upstream): (Vec<&str>, Vec<&str>) = paths
+            .into_iter()
+            .partition(|path| self.is_downstream(path, policy));
+        if upstream.is_empty() {
+            return Vec::new();
+        }
+        downstream.into_iter().map(str::to_string).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_only_mixed_commits() {
+        let config = SplitConfig {
+            auto: true,
+            include: Vec::new(),
+            exclude: vec!["config/local/".to_string(), "*.env".to_string()],
+        };
+        let policy = Policy {
+            forbidden_paths: vec!["deploy/".to_string()],
+            ..Policy::default()
+        };
+
+        assert_eq!(
+            config.downstream_part(
+                [
+                    "src/lib.rs",
+                    "config/local/db.toml",
+                    "deploy/app.yml",
+                    "prod.env"
+                ],
+                Some(&policy)
+            ),
+            ["config/local/db.toml", "deploy/app.yml", "prod.env"]
+        );
+        assert!(config
+            .downstream_part(["deploy/app.yml", "prod.env"], Some(&policy))
+            .is_empty());
+        assert!(config
+            .downstream_part(["src/lib.rs", "deploy/app.yml"], None)
+            .is_empty());
+
+        let config = SplitConfig {
+            include: vec!["src/".to_string()],
+            ..SplitConfig::default()
+        };
+        assert_eq!(
+            config.downstream_part(["src/lib.rs", "docs/ours.md"], None),
+            ["docs/ours.md"]
+        );
+    }
+}
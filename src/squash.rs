@@ -0,0 +1,314 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, IsTerminal};
+use std::path::Path;
+use std::process::Command;
+
+/// `[squash]` in .chuckrc: how the message of commits squashed together is written
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SquashConfig {
+    /// Template for the combined message, with `{summary}`, `{commit_list}`, `{repo}`
+    /// and `{date}` filled in. Without one, the messages are strung together.
+    pub message: Option<String>,
+    /// Open the combined message in git's editor before committing, in a terminal
+    #[serde(default = "default_edit")]
+    pub edit: bool,
+    /// Credit every author of the squashed commits with a `Co-authored-by:` trailer
+    #[serde(default = "default_co_authors")]
+    pub co_authors: bool,
+    /// Fold `fixup!` and `squash!` commits into the commits they name when planning,
+    /// like `git rebase --autosquash`
+    #[serde(default = "default_autosquash")]
+    pub autosquash: bool,
+    /// The project the commits come from, set at startup
+    #[serde(skip)]
+    pub repo: String,
+}
+
+impl Default for SquashConfig {
+    fn default() -> Self {
+        Self {
+            message: None,
+            edit: default_edit(),
+            co_authors: default_co_authors(),
+            autosquash: default_autosquash(),
+            repo: String::new(),
+        }
+    }
+}
+
+fn default_edit() -> bool {
+    true
+}
+
+fn default_co_authors() -> bool {
+    true
+}
+
+fn default_autosquash() -> bool {
+    true
+}
+
+/// How a `fixup!` or `squash!` commit folds into the one it names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fold {
+    /// Its changes only, the message goes
+    Fixup,
+    /// Its changes and whatever its message says below the subject
+    Squash,
+}
+
+/// How `subject` folds and the subject or hash it names, if it's a `fixup!` or
+/// `squash!` commit. `fixup! fixup! Add x` names `Add x`, like git has it.
+pub fn fold_of(subject: &str) -> Option<(Fold, &str)> {
+    let mut fold = None;
+    let mut rest = subject;
+    loop {
+        // The outermost one says how it folds
+        if let Some(named) = rest.strip_prefix("fixup! ") {
+            fold.get_or_insert(Fold::Fixup);
+            rest = named;
+        } else if let Some(named) = rest.strip_prefix("squash! ") {
+            fold.get_or_insert(Fold::Squash);
+            rest = named;
+        } else {
+            break;
+        }
+    }
+    Some((fold?, rest.trim()))
+}
+
+/// The order `(hash, subject)` commits go in once every `fixup!` and `squash!` commit
+/// is moved behind the earlier one it names, after that one's earlier fixups, with how
+/// it folds. One naming a commit that isn't there stays where it is, as a plain pick.
+pub fn autosquash(commits: &[(&str, &str)]) -> Vec<(usize, Option<Fold>)> {
+    let mut behind: Vec<Vec<(usize, Fold)>> = vec![Vec::new(); commits.len()];
+    let mut moved = vec![false; commits.len()];
+    for (i, (_, subject)) in commits.iter().enumerate() {
+        let Some((fold, named)) = fold_of(subject) else {
+            continue;
+        };
+        let targets = || {
+            commits[..i]
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, subject))| fold_of(subject).is_none())
+        };
+        // Like git: the subject itself, then a hash, then the start of a subject
+        let target = targets()
+            .find(|(_, (_, subject))| *subject == named)
+            .or_else(|| {
+                targets().find(|(_, (hash, _))| named.len() >= 4 && hash.starts_with(named))
+            })
+            .or_else(|| targets().find(|(_, (_, subject))| subject.starts_with(named)));
+        if let Some((target, _)) = target {
+            behind[target].push((i, fold));
+            moved[i] = true;
+        }
+    }
+    (0..commits.len())
+        .filter(|&i| !moved[i])
+        .flat_map(|i| {
+            std::iter::once((i, None))
+                .chain(behind[i].iter().map(|&(fixup, fold)| (fixup, Some(fold))))
+        })
+        .collect()
+}
+
+/// What's left of `message`, a commit folded in by autosquash, for the combined
+/// message: nothing for a fixup, everything below the subject for a squash.
+/// `None` when it isn't a `fixup!` or `squash!` commit.
+pub fn folded_message(message: &str) -> Option<String> {
+    let (subject, body) = message.split_once('\n').unwrap_or((message, ""));
+    match fold_of(subject)? {
+        (Fold::Fixup, _) => Some(String::new()),
+        (Fold::Squash, _) => Some(body.trim().to_string()),
+    }
+}
+
+/// `Name <email>` of everyone who wrote or co-wrote `hashes`, as far as their
+/// authors and `Co-authored-by:` trailers say, besides the author of the first one,
+/// whose name the squashed commit keeps. First seen first, each email once.
+pub fn co_authors(hashes: &[&str]) -> Result<Vec<String>> {
+    let mut args = vec![
+        "show",
+        "-s",
+        "--format=%an <%ae>%x1f%(trailers:key=Co-authored-by,valueonly,separator=%x1f)%x1e",
+    ];
+    args.extend_from_slice(hashes);
+    let log = git(&args)?;
+
+    let mut seen = Vec::new();
+    let mut co_authors = Vec::new();
+    for ident in log
+        .split(['\x1e', '\x1f'])
+        .map(str::trim)
+        .filter(|ident| !ident.is_empty())
+    {
+        let email = ident
+            .rsplit_once('<')
+            .map_or(ident, |(_, email)| email.trim_end_matches('>'))
+            .to_lowercase();
+        if seen.contains(&email) {
+            continue;
+        }
+        // The first one is the first commit's author, whom the squashed commit keeps
+        if !seen.is_empty() {
+            co_authors.push(ident.to_string());
+        }
+        seen.push(email);
+    }
+    Ok(co_authors)
+}
+
+/// `message` with a `Co-authored-by:` trailer for each of `co_authors` it doesn't
+/// have yet
+pub fn with_co_authors(message: &str, co_authors: &[String]) -> Result<String> {
+    if co_authors.is_empty() {
+        return Ok(message.to_string());
+    }
+    let mut command = Command::new("git");
+    command.args(["interpret-trailers", "--if-exists", "addIfDifferent"]);
+    for co_author in co_authors {
+        command.args(["--trailer", &format!("Co-authored-by: {}", co_author)]);
+    }
+    let output = command
+        .output_local_with_input(message.as_bytes())
+        .map_err(|e| process::command_error(e, "Failed to execute git interpret-trailers"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to add co-authors: {}", error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Fills in `template` for commits squashed into one. `subjects` are the squashed
+/// commits' `(short hash, subject)`, first one first; `summary` is the first's
+/// subject as it ends up on the branch.
+pub fn render(
+    template: &str,
+    summary: &str,
+    subjects: &[(&str, &str)],
+    repo: &str,
+    date: &str,
+) -> String {
+    let commit_list = subjects
+        .iter()
+        .map(|(hash, subject)| format!("- {} ({})", subject, hash))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let message = template
+        .replace("{summary}", summary)
+        .replace("{commit_list}", &commit_list)
+        .replace("{repo}", repo)
+        .replace("{date}", date);
+    format!("{}\n", message.trim_end())
+}
+
+/// Amends HEAD with `message`, first handing it to git's editor when `edit` is set
+/// and someone is at a terminal to do it
+pub fn amend(message: &str, edit: bool) -> Result<()> {
+    if edit && io::stdin().is_terminal() && io::stdout().is_terminal() {
+        let git_dir = git(&["rev-parse", "--absolute-git-dir"])?;
+        let path = Path::new(git_dir.trim()).join("CHUCK_SQUASH_MSG");
+        fs::write(&path, message)
+            .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+        let status = Command::new("git")
+            .args(["commit", "--amend", "--no-verify", "--edit", "-F"])
+            .arg(&path)
+            .status_interactive()
+            .map_err(|e| anyhow!("Failed to open the editor: {}", e));
+        let _ = fs::remove_file(&path);
+        match status {
+            Ok(status) if status.success() => return Ok(()),
+            // Emptied in the editor or the editor failed, the generated one will do
+            Ok(_) => println!("🧔 Keeping the generated squash message"),
+            Err(e) => println!("🧔 {}, keeping the generated squash message", e),
+        }
+    }
+    git(&["commit", "--amend", "--no-verify", "-m", message])?;
+    Ok(())
+}
+
+fn git(args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn moves_fixups_behind_what_they_fix() {
+        let commits = [
+            ("aaaa1111", "Add login"),
+            ("bbbb2222", "Add logout"),
+            ("cccc3333", "fixup! Add login"),
+            ("dddd4444", "squash! bbbb2222"),
+            ("eeee5555", "fixup! fixup! Add log"),
+            ("ffff6666", "fixup! Add signup"),
+        ];
+        assert_eq!(
+            autosquash(&commits),
+            [
+                (0, None),
+                (2, Some(Fold::Fixup)),
+                (4, Some(Fold::Fixup)),
+                (1, None),
+                (3, Some(Fold::Squash)),
+                (5, None),
+            ]
+        );
+
+        assert_eq!(
+            folded_message("fixup! Add login\n\nTypo"),
+            Some(String::new())
+        );
+        assert_eq!(
+            folded_message("squash! Add logout\n\nAlso clear the session.\n"),
+            Some("Also clear the session.".to_string())
+        );
+        assert_eq!(folded_message("Add logout"), None);
+    }
+
+    #[test]
+    fn fills_in_the_template() {
+        let message = render(
+            "{summary}\n\nSquashed from {repo} on {date}:\n{commit_list}\n\n",
+            "Add retries",
+            &[("aaa1111", "Add retries"), ("bbb2222", "Fix retry delay")],
+            "acme/app",
+            "2025-01-20",
+        );
+        assert_eq!(
+            message,
+            "Add retries\n\nSquashed from acme/app on 2025-01-20:\n- Add retries (aaa1111)\n- Fix retry delay (bbb2222)\n"
+        );
+    }
+
+    #[test]
+    fn credits_everyone_but_the_first_author() {
+        let log = "Jane <jane@acme.com>\x1f\x1e\n\
+                   Bob <bob@acme.com>\x1fJANE <Jane@acme.com>\x1fAl <al@acme.com>\x1e\n\
+                   Al <al@acme.com>\x1f\x1e\n";
+        let fake = FakeRunner::new().on("git show -s", log);
+        let co_authors = process::with_runner(Rc::new(fake), || {
+            co_authors(&["aaa", "bbb", "ccc"]).unwrap()
+        });
+        assert_eq!(co_authors, ["Bob <bob@acme.com>", "Al <al@acme.com>"]);
+    }
+}
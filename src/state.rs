@@ -0,0 +1,337 @@
+use crate::duplicates::PullRequest;
+use crate::ignored::{IgnoreList, IgnoredCommit};
+use crate::journal::{self, JournalEntry, Outcome};
+use crate::process::{self, CommandExt};
+use crate::read_only;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Relative to the work tree root, meant to be committed
+pub const STATE_FILE: &str = ".chuck/state.toml";
+
+/// In the clone's chuck directory, what runs couldn't write to `STATE_FILE` yet
+const PENDING_FILE: &str = "pending-state.toml";
+
+/// A commit that was pushed to the template for a pull request
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Contribution {
+    pub hash: String,
+    pub patch_id: Option<String>,
+    pub message: String,
+    /// Branch on the template the pull request was opened from
+    pub branch: String,
+    /// Name of whoever ran Chuck
+    pub by: String,
+    pub contributed_at: DateTime<Utc>,
+}
+
+/// Decisions the whole team shares through `.chuck/state.toml`: commits nobody wants
+/// suggested and commits someone already proposed upstream
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct SharedState {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignored: Vec<IgnoredCommit>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contributed: Vec<Contribution>,
+}
+
+impl SharedState {
+    pub fn path() -> Result<PathBuf> {
+        let output = Command::new("git")
+            .args(["rev-parse", "--show-toplevel"])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git rev-parse"))?;
+
+        if !output.status.success() {
+            return Err(anyhow!("Not inside a git work tree"));
+        }
+
+        let root = String::from_utf8(output.stdout)?.trim().to_string();
+        Ok(PathBuf::from(root).join(STATE_FILE))
+    }
+
+    /// The committed state, empty if nobody has recorded anything yet
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => Self::parse(&content)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(content: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(content)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if read_only::withheld(&format!("written {}", path.display())) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// The earlier contribution of this commit, or of the same patch under another SHA
+    pub fn contribution(&self, hash: &str, patch_id: Option<&str>) -> Option<&Contribution> {
+        self.contributed
+            .iter()
+            .find(|c| c.hash == hash || (patch_id.is_some() && c.patch_id.as_deref() == patch_id))
+    }
+
+    /// Adds the commits the local journal shows were pushed and that aren't recorded
+    /// yet, returning how many. `patch_ids` fills in patch-ids where they're known.
+    pub fn record_pushed(
+        &mut self,
+        journal: &[JournalEntry],
+        patch_ids: &HashMap<String, String>,
+        by: &str,
+    ) -> usize {
+        let before = self.contributed.len();
+        for entry in journal.iter().filter(|e| e.outcome == Outcome::Pushed) {
+            let Some(branch) = &entry.remote_branch else {
+                continue;
+            };
+            for commit in &entry.commits {
+                let patch_id = patch_ids.get(&commit.hash).map(String::as_str);
+                if self.contribution(&commit.hash, patch_id).is_none() {
+                    self.contributed.push(Contribution {
+                        hash: commit.hash.clone(),
+                        patch_id: patch_id.map(str::to_string),
+                        message: commit.message.clone(),
+                        branch: branch.clone(),
+                        by: by.to_string(),
+                        contributed_at: entry.timestamp,
+                    });
+                }
+            }
+        }
+        self.contributed.len() - before
+    }
+
+    /// Adds the commits from `source` that pull requests' metadata lists and that
+    /// aren't recorded yet, returning how many
+    pub fn record_proposed(&mut self, prs: &[PullRequest], source: &str) -> usize {
+        let before = self.contributed.len();
+        for pr in prs {
+            let Some(metadata) = pr.metadata() else {
+                continue;
+            };
+            if !metadata.source.eq_ignore_ascii_case(source) {
+                continue;
+            }
+            for commit in metadata.commits {
+                if self
+                    .contribution(&commit.sha, commit.patch_id.as_deref())
+                    .is_none()
+                {
+                    self.contributed.push(Contribution {
+                        hash: commit.sha,
+                        patch_id: commit.patch_id,
+                        message: commit.subject,
+                        branch: pr.branch.clone(),
+                        by: metadata.by.clone(),
+                        contributed_at: pr.created_at,
+                    });
+                }
+            }
+        }
+        self.contributed.len() - before
+    }
+}
+
+/// What runs changed in the shared state and haven't written yet. `.chuck/state.toml`
+/// is tracked, and changing it in the middle of a run would stop the chuck branch from
+/// being checked out, so a run only writes it where it started. Until then the changes
+/// wait in `.git/chuck`.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct PendingChanges {
+    #[serde(default)]
+    pub ignored: IgnoreList,
+    /// Taken off the ignore list
+    #[serde(default)]
+    pub unignored: IgnoreList,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub contributed: Vec<Contribution>,
+}
+
+impl PendingChanges {
+    fn path() -> Result<PathBuf> {
+        Ok(journal::chuck_dir()?.join(PENDING_FILE))
+    }
+
+    /// What earlier runs left, nothing if they wrote it all
+    pub fn load() -> Result<Self> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ignored.commits.is_empty()
+            && self.unignored.commits.is_empty()
+            && self.contributed.is_empty()
+    }
+
+    pub fn ignore(&mut self, hash: &str, patch_id: Option<&str>, message: &str) {
+        self.unignored.remove(hash, patch_id);
+        self.ignored.add(hash, patch_id, message);
+    }
+
+    pub fn unignore(&mut self, hash: &str, patch_id: Option<&str>, message: &str) {
+        self.ignored.remove(hash, patch_id);
+        self.unignored.add(hash, patch_id, message);
+    }
+
+    /// `state` as it is once these are written
+    pub fn apply_to(&self, state: &mut SharedState) {
+        let mut list = IgnoreList {
+            commits: std::mem::take(&mut state.ignored),
+        };
+        for commit in &self.unignored.commits {
+            list.remove(&commit.hash, commit.patch_id.as_deref());
+        }
+        for commit in &self.ignored.commits {
+            if !list.contains(&commit.hash, commit.patch_id.as_deref()) {
+                list.commits.push(commit.clone());
+            }
+        }
+        state.ignored = list.commits;
+        for contribution in &self.contributed {
+            if state
+                .contribution(&contribution.hash, contribution.patch_id.as_deref())
+                .is_none()
+            {
+                state.contributed.push(contribution.clone());
+            }
+        }
+    }
+
+    /// Writes the changes into `.chuck/state.toml`, or with `on_users_branch` false
+    /// keeps them for the next run that ends there
+    pub fn write(&self, on_users_branch: bool) -> Result<()> {
+        let path = Self::path()?;
+        if !on_users_branch {
+            fs::write(&path, toml::to_string(self)?)?;
+            return Ok(());
+        }
+        let mut state = SharedState::load()?;
+        self.apply_to(&mut state);
+        state.save()?;
+        match fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The name git would author commits with, or "someone" when it has none
+pub fn user_name() -> String {
+    // "Name <email> timestamp zone", honouring GIT_AUTHOR_NAME as well as user.name
+    Command::new("git")
+        .args(["var", "GIT_AUTHOR_IDENT"])
+        .output_local()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| {
+            let ident = String::from_utf8_lossy(&output.stdout).into_owned();
+            let name = ident.split('<').next()?.trim().to_string();
+            (!name.is_empty()).then_some(name)
+        })
+        .unwrap_or_else(|| "someone".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_contributions_by_hash_or_patch_id() {
+        let state = SharedState::parse(
+            r#"
+            [[ignored]]
+            hash = "aaa"
+            message = "Local config"
+            ignored_at = "2025-01-10T12:00:00Z"
+
+            [[contributed]]
+            hash = "bbb"
+            patch_id = "p1"
+            message = "Fix auth"
+            branch = "chuck-from-acme-app-20250120-143022"
+            by = "Jane Doe"
+            contributed_at = "2025-01-20T14:30:22Z"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(state.ignored[0].hash, "aaa");
+        assert_eq!(state.contribution("bbb", None).unwrap().by, "Jane Doe");
+        assert!(state.contribution("rebased", Some("p1")).is_some());
+        assert!(state.contribution("ccc", Some("p2")).is_none());
+    }
+
+    #[test]
+    fn records_pushed_runs_from_the_journal_once() {
+        let entry = |outcome, hash: &str| JournalEntry {
+            timestamp: Utc::now(),
+            repo: "acme/app".to_string(),
+            template: "acme/template".to_string(),
+            branch: Some("chuck/20250120-143022".to_string()),
+            remote_branch: Some("chuck-from-acme-app-20250120-143022".to_string()),
+            outcome,
+            commits: vec![crate::journal::JournalCommit {
+                hash: hash.to_string(),
+                message: "Fix auth".to_string(),
+                author: "Jane Doe".to_string(),
+                files: Vec::new(),
+            }],
+        };
+        let journal = [
+            entry(Outcome::Pushed, "aaa"),
+            entry(Outcome::Conflict, "bbb"),
+        ];
+        let patch_ids = HashMap::from([("aaa".to_string(), "p1".to_string())]);
+
+        let mut state = SharedState::default();
+        assert_eq!(state.record_pushed(&journal, &patch_ids, "Jane Doe"), 1);
+        assert_eq!(state.contributed[0].patch_id.as_deref(), Some("p1"));
+        assert_eq!(state.record_pushed(&journal, &patch_ids, "Jane Doe"), 0);
+    }
+
+    #[test]
+    fn applies_what_runs_left_to_the_shared_state() {
+        let mut state = SharedState::parse(
+            r#"
+            [[ignored]]
+            hash = "aaa"
+            message = "Local config"
+            ignored_at = "2025-01-10T12:00:00Z"
+            "#,
+        )
+        .unwrap();
+
+        let mut pending = PendingChanges::default();
+        pending.ignore("bbb", Some("p2"), "Bump version");
+        pending.unignore("aaa", None, "Local config");
+        pending.ignore("ccc", None, "Debug logging");
+        pending.unignore("ccc", None, "Debug logging");
+        let pending: PendingChanges = toml::from_str(&toml::to_string(&pending).unwrap()).unwrap();
+        pending.apply_to(&mut state);
+
+        let ignored: Vec<&str> = state.ignored.iter().map(|c| c.hash.as_str()).collect();
+        assert_eq!(ignored, ["bbb"]);
+    }
+}
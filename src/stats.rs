@@ -0,0 +1,173 @@
+use crate::journal::{self, JournalEntry, Outcome};
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum StatsFormat {
+    Text,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Serialize)]
+struct RepoStats {
+    repo: String,
+    template: String,
+    runs: usize,
+    prs: usize,
+    commits_upstreamed: usize,
+    commits_by_month: BTreeMap<String, usize>,
+    avg_pr_commits: f64,
+    avg_pr_files: f64,
+    conflict_rate: f64,
+    top_contributors: Vec<(String, usize)>,
+}
+
+pub fn run(format: StatsFormat) -> Result<()> {
+    let entries = journal::read_all()?;
+
+    let mut by_repo: BTreeMap<&str, Vec<&JournalEntry>> = BTreeMap::new();
+    for entry in &entries {
+        by_repo.entry(&entry.repo).or_default().push(entry);
+    }
+
+    let stats: Vec<RepoStats> = by_repo
+        .into_iter()
+        .map(|(repo, entries)| compute_repo_stats(repo, &entries))
+        .collect();
+
+    match format {
+        StatsFormat::Text if stats.is_empty() => {
+            println!("🧔 \"Nothing in the journal yet. Upstream something first!\"");
+        }
+        StatsFormat::Text => print_text(&stats),
+        StatsFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+        StatsFormat::Csv => print_csv(&stats),
+    }
+
+    Ok(())
+}
+
+fn compute_repo_stats(repo: &str, entries: &[&JournalEntry]) -> RepoStats {
+    let pushed: Vec<&&JournalEntry> = entries
+        .iter()
+        .filter(|e| e.outcome == Outcome::Pushed)
+        .collect();
+    let conflicts = entries
+        .iter()
+        .filter(|e| e.outcome == Outcome::Conflict)
+        .count();
+
+    let mut commits_by_month = BTreeMap::new();
+    let mut contributors: BTreeMap<String, usize> = BTreeMap::new();
+    let mut total_commits = 0;
+    let mut total_files = 0;
+
+    for entry in &pushed {
+        let month = entry.timestamp.format("%Y-%m").to_string();
+        *commits_by_month.entry(month).or_insert(0) += entry.commits.len();
+        total_commits += entry.commits.len();
+
+        let mut files: Vec<&String> = entry.commits.iter().flat_map(|c| &c.files).collect();
+        files.sort();
+        files.dedup();
+        total_files += files.len();
+
+        for commit in &entry.commits {
+            *contributors.entry(commit.author.clone()).or_insert(0) += 1;
+        }
+    }
+
+    let mut top_contributors: Vec<(String, usize)> = contributors.into_iter().collect();
+    top_contributors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_contributors.truncate(5);
+
+    let average = |total: usize| {
+        if pushed.is_empty() {
+            0.0
+        } else {
+            total as f64 / pushed.len() as f64
+        }
+    };
+
+    RepoStats {
+        repo: repo.to_string(),
+        template: entries
+            .last()
+            .map(|e| e.template.clone())
+            .unwrap_or_default(),
+        runs: entries.len(),
+        prs: pushed.len(),
+        commits_upstreamed: total_commits,
+        commits_by_month,
+        avg_pr_commits: average(total_commits),
+        avg_pr_files: average(total_files),
+        conflict_rate: conflicts as f64 / entries.len() as f64,
+        top_contributors,
+    }
+}
+
+fn print_text(stats: &[RepoStats]) {
+    for repo in stats {
+        println!("🧔 {} -> {}", repo.repo, repo.template);
+        println!("   Runs:               {}", repo.runs);
+        println!("   PRs pushed:         {}", repo.prs);
+        println!("   Commits upstreamed: {}", repo.commits_upstreamed);
+        println!(
+            "   Average PR size:    {:.1} commits, {:.1} files",
+            repo.avg_pr_commits, repo.avg_pr_files
+        );
+        println!("   Conflict rate:      {:.0}%", repo.conflict_rate * 100.0);
+
+        if !repo.commits_by_month.is_empty() {
+            println!("   Commits by month:");
+            for (month, count) in &repo.commits_by_month {
+                println!("     {}  {}", month, count);
+            }
+        }
+
+        if !repo.top_contributors.is_empty() {
+            println!("   Top contributors:");
+            for (author, count) in &repo.top_contributors {
+                println!("     {} ({})", author, count);
+            }
+        }
+        println!();
+    }
+}
+
+const CSV_HEADER: &str =
+    "repo,template,runs,prs,commits_upstreamed,avg_pr_commits,avg_pr_files,conflict_rate,top_contributor";
+
+fn print_csv(stats: &[RepoStats]) {
+    println!("{}", CSV_HEADER);
+    for repo in stats {
+        println!(
+            "{},{},{},{},{},{:.2},{:.2},{:.4},{}",
+            csv_field(&repo.repo),
+            csv_field(&repo.template),
+            repo.runs,
+            repo.prs,
+            repo.commits_upstreamed,
+            repo.avg_pr_commits,
+            repo.avg_pr_files,
+            repo.conflict_rate,
+            csv_field(
+                repo.top_contributors
+                    .first()
+                    .map(|(author, _)| author.as_str())
+                    .unwrap_or("")
+            ),
+        );
+    }
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,125 @@
+use crate::journal;
+use crate::process::{self, CommandExt};
+use crate::read_only;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const SUBSCRIPTION_FILE: &str = "subscription.toml";
+
+/// The template head this clone last synced with, recorded by `chuck subscribe` in
+/// `.git/chuck/subscription.toml`
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Subscription {
+    /// `owner/repo` of the template
+    pub template: String,
+    pub branch: String,
+    pub head: String,
+    pub synced_at: DateTime<Utc>,
+}
+
+impl Subscription {
+    pub fn new(template: &str, branch: &str, head: &str) -> Self {
+        Self {
+            template: template.to_string(),
+            branch: branch.to_string(),
+            head: head.to_string(),
+            synced_at: Utc::now(),
+        }
+    }
+
+    pub fn path() -> Result<PathBuf> {
+        Ok(journal::chuck_dir()?.join(SUBSCRIPTION_FILE))
+    }
+
+    /// `None` until `chuck subscribe` has run
+    pub fn load() -> Result<Option<Self>> {
+        let path = Self::path()?;
+        match fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map(Some)
+                .map_err(|e| anyhow!("Failed to parse {}: {}", path.display(), e)),
+            Err(_) => Ok(None),
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path()?;
+        if read_only::withheld(&format!("written {}", path.display())) {
+            return Ok(());
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, toml::to_string(self)?)?;
+        Ok(())
+    }
+
+    /// Whether this is about `template`'s `branch`. A moved template still counts,
+    /// GitHub names aren't case-sensitive.
+    pub fn follows(&self, template: &str, branch: &str) -> bool {
+        self.template.eq_ignore_ascii_case(template) && self.branch == branch
+    }
+
+    /// `(short hash, subject)` of the template's commits since the recorded head up to
+    /// `head`, newest first. The template has to have been fetched.
+    pub fn new_commits(&self, head: &str) -> Result<Vec<(String, String)>> {
+        let output = Command::new("git")
+            .args([
+                "log",
+                "--format=%h%x09%s",
+                &format!("{}..{}", self.head, head),
+            ])
+            .output_local()
+            .map_err(|e| process::command_error(e, "Failed to execute git log"))?;
+
+        if !output.status.success() {
+            let error = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!(
+                "Failed to compare with {}: {}",
+                &self.head[..self.head.len().min(7)],
+                error.trim()
+            ));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| line.split_once('\t'))
+            .map(|(hash, subject)| (hash.to_string(), subject.to_string()))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    #[test]
+    fn lists_template_commits_since_the_last_sync() {
+        let subscription = Subscription::new("acme/template", "main", "1111111abc");
+        let fake = Rc::new(FakeRunner::new().on(
+            "git log",
+            "3333333\tFix the login form\n2222222\tAdd retries\n",
+        ));
+
+        let commits = process::with_runner(fake.clone(), || subscription.new_commits("3333333def"));
+
+        assert_eq!(
+            commits.unwrap(),
+            [
+                ("3333333".to_string(), "Fix the login form".to_string()),
+                ("2222222".to_string(), "Add retries".to_string())
+            ]
+        );
+        assert_eq!(
+            fake.calls(),
+            ["git log --format=%h%x09%s 1111111abc..3333333def"]
+        );
+        assert!(subscription.follows("Acme/Template", "main"));
+        assert!(!subscription.follows("acme/template", "release/1.x"));
+    }
+}
@@ -0,0 +1,142 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use clap::ValueEnum;
+use std::process::Command;
+
+/// Trailer key commits are tagged with at commit time
+pub const KEY: &str = "Chuck";
+
+/// What a `Chuck:` trailer asks for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Mark {
+    /// Pre-select it for the template
+    Upstream,
+    /// Never offer it
+    Skip,
+}
+
+impl Mark {
+    pub fn value(self) -> &'static str {
+        match self {
+            Mark::Upstream => "upstream",
+            Mark::Skip => "skip",
+        }
+    }
+
+    /// The mark from a commit's trailers. The last `Chuck:` trailer wins, values
+    /// Chuck doesn't know are ignored.
+    pub fn from_trailers(trailers: &[(String, String)]) -> Option<Self> {
+        trailers
+            .iter()
+            .rev()
+            .filter(|(key, _)| key.eq_ignore_ascii_case(KEY))
+            .find_map(|(_, value)| match value.to_lowercase().as_str() {
+                "upstream" => Some(Mark::Upstream),
+                "skip" => Some(Mark::Skip),
+                _ => None,
+            })
+    }
+}
+
+/// The commit HEAD points at
+pub fn head() -> Result<String> {
+    let hash = git(&["rev-parse", "HEAD"], None)?;
+    Ok(String::from_utf8_lossy(&hash).trim().to_string())
+}
+
+/// Adds `Chuck: <mark>` to HEAD's message, replacing an earlier `Chuck:` trailer,
+/// and amends HEAD with it, leaving staged changes out. Returns the amended HEAD.
+pub fn mark_head(mark: Mark) -> Result<String> {
+    let message = git(&["log", "-1", "--format=%B", "HEAD"], None)?;
+    let trailer = format!("{}: {}", KEY, mark.value());
+    let message = git(
+        &[
+            "interpret-trailers",
+            "--if-exists",
+            "replace",
+            "--trailer",
+            &trailer,
+        ],
+        Some(&message),
+    )?;
+    git(
+        &[
+            "commit",
+            "--amend",
+            "--only",
+            "--no-verify",
+            "--quiet",
+            "-F",
+            "-",
+        ],
+        Some(&message),
+    )?;
+    head()
+}
+
+fn git(args: &[&str], input: Option<&[u8]>) -> Result<Vec<u8>> {
+    let mut command = Command::new("git");
+    command.args(args);
+    let output = match input {
+        Some(input) => command.output_local_with_input(input),
+        None => command.output_local(),
+    }
+    .map_err(|e| process::command_error(e, "Failed to execute git"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("git {} failed: {}", args[0], error.trim()));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::fake::FakeRunner;
+    use std::rc::Rc;
+
+    fn trailers(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn reads_the_last_chuck_trailer() {
+        assert_eq!(
+            Mark::from_trailers(&trailers(&[("Chuck", "skip"), ("chuck", "Upstream")])),
+            Some(Mark::Upstream)
+        );
+        assert_eq!(
+            Mark::from_trailers(&trailers(&[("Chuck", "skip"), ("Chuck", "maybe")])),
+            Some(Mark::Skip)
+        );
+        assert_eq!(
+            Mark::from_trailers(&trailers(&[("Signed-off-by", "Jane")])),
+            None
+        );
+    }
+
+    #[test]
+    fn amends_head_with_the_trailer() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on("git log -1", "Fix auth\n")
+                .on("git interpret-trailers", "Fix auth\n\nChuck: upstream\n"),
+        );
+
+        process::with_runner(fake.clone(), || mark_head(Mark::Upstream)).unwrap();
+
+        assert_eq!(
+            fake.calls_to("git commit"),
+            ["git commit --amend --only --no-verify --quiet -F -"]
+        );
+        assert_eq!(
+            fake.calls_to("git interpret-trailers"),
+            ["git interpret-trailers --if-exists replace --trailer Chuck: upstream"]
+        );
+        assert_eq!(fake.inputs()[1], b"Fix auth\n\nChuck: upstream\n");
+    }
+}
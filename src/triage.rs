@@ -0,0 +1,166 @@
+use crate::forge;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+/// Where pull requests Chuck opens are filed upstream, from `[pr]` in .chuckrc
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Triage {
+    /// Title of an open milestone in the template repository
+    pub milestone: Option<String>,
+    /// Title of a project (board) of the template repository's owner
+    pub project: Option<String>,
+}
+
+impl Triage {
+    pub fn is_empty(&self) -> bool {
+        self.milestone.is_none() && self.project.is_none()
+    }
+
+    /// Files the pull request at `url` in `repo`, saying what went wrong for each
+    /// part that didn't work out. The pull request is open either way.
+    pub fn apply(&self, repo: &str, url: &str) -> Vec<String> {
+        let Some(number) = pr_number(url) else {
+            return vec![format!(
+                "Couldn't tell the pull request number from {}",
+                url
+            )];
+        };
+        let mut errors = Vec::new();
+        if let Some(milestone) = &self.milestone {
+            if let Err(e) = set_milestone(repo, number, milestone) {
+                errors.push(format!("Couldn't set milestone \"{}\": {}", milestone, e));
+            }
+        }
+        if let Some(project) = &self.project {
+            if let Err(e) = add_to_project(repo, number, project) {
+                errors.push(format!("Couldn't add it to project \"{}\": {}", project, e));
+            }
+        }
+        errors
+    }
+}
+
+/// The number at the end of `https://github.com/owner/repo/pull/<number>`
+pub fn pr_number(url: &str) -> Option<u64> {
+    url.trim_end_matches('/').rsplit('/').next()?.parse().ok()
+}
+
+/// Puts pull request `number` in the open milestone titled `title`
+pub fn set_milestone(repo: &str, number: u64, title: &str) -> Result<()> {
+    let milestones = forge::get_json(
+        &format!("repos/{}/milestones?state=open&per_page=100", repo),
+        "Failed to list milestones",
+    )?;
+    let milestone = milestones
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|m| m["title"].as_str() == Some(title))
+        .and_then(|m| m["number"].as_u64())
+        .ok_or_else(|| anyhow!("{} has no open milestone with that title", repo))?;
+
+    // Pull requests are issues as far as milestones go
+    forge::send(
+        "PATCH",
+        &format!("repos/{}/issues/{}", repo, number),
+        &serde_json::json!({ "milestone": milestone }),
+        "Failed to set the milestone",
+    )?;
+    Ok(())
+}
+
+/// Adds pull request `number` to the project titled `title`, looked up among the
+/// projects of the repository's owner
+pub fn add_to_project(repo: &str, number: u64, title: &str) -> Result<()> {
+    let (owner, name) = repo
+        .split_once('/')
+        .ok_or_else(|| anyhow!("Expected owner/name, got {}", repo))?;
+
+    let json = forge::graphql(
+        &format!(
+            "query($owner: String!, $name: String!, $title: String!) {{ \
+             repository(owner: $owner, name: $name) {{ \
+             pullRequest(number: {}) {{ id }} \
+             owner {{ ... on ProjectV2Owner {{ projectsV2(first: 20, query: $title) {{ nodes {{ id title }} }} }} }} }} }}",
+            number
+        ),
+        &[("owner", owner), ("name", name), ("title", title)],
+        "Failed to look up the project",
+    )?;
+    let repository = &json["data"]["repository"];
+    let pr = repository["pullRequest"]["id"]
+        .as_str()
+        .ok_or_else(|| anyhow!("{} has no pull request #{}", repo, number))?;
+    // The search matches words anywhere, so take only the exact title
+    let project = repository["owner"]["projectsV2"]["nodes"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|p| p["title"].as_str() == Some(title))
+        .and_then(|p| p["id"].as_str())
+        .ok_or_else(|| anyhow!("{} has no project with that title", owner))?;
+
+    forge::graphql(
+        "mutation($project: ID!, $content: ID!) { \
+         addProjectV2ItemById(input: { projectId: $project, contentId: $content }) { item { id } } }",
+        &[("project", project), ("content", pr)],
+        "Failed to add to the project",
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::{self, fake::FakeRunner};
+    use std::rc::Rc;
+
+    #[test]
+    fn files_the_pull_request_in_milestone_and_project() {
+        let fake = Rc::new(
+            FakeRunner::new()
+                .on(
+                    "gh api repos/acme/template/milestones",
+                    r#"[{"number": 3, "title": "v2.0"}, {"number": 4, "title": "v2.1"}]"#,
+                )
+                .on(
+                    "gh api graphql -f query=query",
+                    r#"{"data":{"repository":{"pullRequest":{"id":"PR_1"},"owner":{"projectsV2":{"nodes":[
+                        {"id":"P_old","title":"Triage (old)"},{"id":"P_1","title":"Triage"}]}}}}}"#,
+                )
+                .on("gh api repos/acme/template/issues/42", "{}")
+                .on("gh api graphql -f query=mutation", "{}"),
+        );
+        let triage = Triage {
+            milestone: Some("v2.1".to_string()),
+            project: Some("Triage".to_string()),
+        };
+        let errors = process::with_runner(fake.clone(), || {
+            triage.apply("acme/template", "https://github.com/acme/template/pull/42")
+        });
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let patch = &fake.calls_to("gh api repos/acme/template/issues/42")[0];
+        assert!(patch.contains("--method PATCH"), "{}", patch);
+        assert!(fake.inputs().contains(&br#"{"milestone":4}"#.to_vec()));
+        let mutation = &fake.calls_to("gh api graphql -f query=mutation")[0];
+        assert!(mutation.contains("project=P_1"), "{}", mutation);
+        assert!(mutation.contains("content=PR_1"), "{}", mutation);
+    }
+
+    #[test]
+    fn reports_a_missing_milestone() {
+        let fake = FakeRunner::new().on("gh api repos/acme/template/milestones", "[]");
+        let triage = Triage {
+            milestone: Some("v9".to_string()),
+            project: None,
+        };
+        let errors = process::with_runner(Rc::new(fake), || {
+            triage.apply("acme/template", "https://github.com/acme/template/pull/42")
+        });
+        assert_eq!(
+            errors,
+            ["Couldn't set milestone \"v9\": acme/template has no open milestone with that title"]
+        );
+    }
+}
@@ -0,0 +1,568 @@
+//! Golden-file tests for the selection TUI. Screens are drawn into a `TestBackend`
+//! and compared with `tests/snapshots/<name>.txt`; run with `UPDATE_SNAPSHOTS=1`
+//! to accept changes.
+
+use super::*;
+use chuck::process::fake::FakeRunner;
+use ratatui::backend::TestBackend;
+use ratatui::buffer::Buffer;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const WIDTH: u16 = 100;
+const HEIGHT: u16 = 16;
+
+fn commit(hash: &str, message: &str, files: &[&str]) -> Commit {
+    Commit {
+        hash: format!("{:0<40}", hash),
+        short_hash: hash.to_string(),
+        message: message.to_string(),
+        files: files
+            .iter()
+            .map(|path| FileChange {
+                status: 'M',
+                path: path.to_string(),
+                old_path: None,
+            })
+            .collect(),
+        selected: false,
+        author: "Jane Doe".to_string(),
+        date: "2025-01-20 14:30".to_string(),
+        parents: vec!["0".repeat(40)],
+        already_upstream: false,
+        policy_issues: Vec::new(),
+        risks: Vec::new(),
+        project_only: false,
+        signature: None,
+        ci: None,
+        provenance: None,
+        deleted_template_files: Vec::new(),
+        split_off: Vec::new(),
+        patch_id: None,
+        ignored: false,
+        contributed: None,
+        mark: None,
+    }
+}
+
+fn fixture() -> App {
+    let mut merge = commit("ccc3333", "Merge branch 'feature'", &["src/lib.rs"]);
+    merge.parents.push("1".repeat(40));
+    let mut upstream = commit("ddd4444", "Fix typo in README", &["README.md"]);
+    upstream.already_upstream = true;
+    let mut signed = commit("aaa1111", "Fix bug in auth middleware", &["lib/auth.rs"]);
+    signed.signature = Some(Signature::Verified("Jane Doe".to_string()));
+    signed.ci = Some(CiStatus::Passing);
+    let mut pooling = commit(
+        "bbb2222",
+        "Improve database connection pooling",
+        &["lib/db.rs", "lib/pool.rs"],
+    );
+    pooling.ci = Some(CiStatus::Failing);
+
+    App::new(vec![signed, pooling, merge, upstream])
+}
+
+fn key(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn keys(text: &str) -> Vec<Event> {
+    text.chars().map(|c| key(KeyCode::Char(c))).collect()
+}
+
+/// Feeds `events` through the real event loop and returns the last frame drawn.
+/// Running out of events ends the loop like a closed terminal would.
+fn run(app: &mut App, events: Vec<Event>) -> Buffer {
+    run_copying(app, events).0
+}
+
+/// `run`, along with what was put on the clipboard
+fn run_copying(app: &mut App, events: Vec<Event>) -> (Buffer, Vec<String>) {
+    run_sized(app, events, WIDTH, HEIGHT)
+}
+
+/// `run_copying` in a terminal of another size
+fn run_sized(app: &mut App, events: Vec<Event>, width: u16, height: u16) -> (Buffer, Vec<String>) {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    let mut events = events.into_iter();
+    let mut copied = Vec::new();
+    let result = run_selection(
+        &mut terminal,
+        app,
+        || {
+            events
+                .next()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "no more events"))
+        },
+        |text| {
+            copied.push(text.to_string());
+            Ok(())
+        },
+    );
+    if let Err(e) = result {
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof, "{}", e);
+    }
+    (terminal.backend().buffer().clone(), copied)
+}
+
+fn to_text(buffer: &Buffer) -> String {
+    let area = buffer.area;
+    (0..area.height)
+        .map(|y| {
+            let line: String = (0..area.width).map(|x| buffer[(x, y)].symbol()).collect();
+            line.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        + "\n"
+}
+
+fn assert_snapshot(name: &str, buffer: &Buffer) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(format!("{}.txt", name));
+    let actual = to_text(buffer);
+
+    if std::env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = fs::read_to_string(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing snapshot {}, run with UPDATE_SNAPSHOTS=1 to create it",
+            path.display()
+        )
+    });
+    assert_eq!(actual, expected, "snapshot {} changed", name);
+}
+
+#[test]
+fn commit_list() {
+    let mut app = fixture();
+    assert_snapshot("commit_list", &run(&mut app, vec![]));
+}
+
+#[test]
+fn risk_badges() {
+    let mut deps = commit("eee5555", "Bump serde", &["Cargo.toml", "Cargo.lock"]);
+    let mut ci = commit(
+        "fff6666",
+        "Cache builds in CI",
+        &[".github/workflows/ci.yml"],
+    );
+    let config = risk::RiskConfig::default();
+    for commit in [&mut deps, &mut ci] {
+        commit.risks = config.assess(commit.files.iter().map(|f| f.path.as_str()));
+    }
+    let mut app = App::new(vec![deps, ci]);
+    assert_snapshot("risk_badges", &run(&mut app, vec![]));
+}
+
+#[test]
+fn files_say_where_they_stand_upstream() {
+    let mut commit = commit("aaa1111", "Move auth", &["lib/auth.rs", "lib/session.rs"]);
+    commit.files.push(FileChange {
+        status: 'A',
+        path: "lib/token.rs".to_string(),
+        old_path: None,
+    });
+    let mut app = App::new(vec![commit]);
+    app.template_files = Some(["lib/auth.rs".to_string()].into_iter().collect());
+    let (buffer, _) = run_sized(&mut app, vec![], WIDTH, 21);
+    assert_snapshot("upstream_files", &buffer);
+}
+
+#[test]
+fn select_all_skips_merges_and_upstreamed() {
+    let mut app = fixture();
+    let buffer = run(&mut app, keys("aj"));
+
+    let selected: Vec<&str> = app
+        .commits
+        .iter()
+        .filter(|c| c.selected)
+        .map(|c| c.short_hash.as_str())
+        .collect();
+    assert_eq!(selected, ["aaa1111", "bbb2222"]);
+    assert_snapshot("select_all", &buffer);
+}
+
+#[test]
+fn ignored_commits_are_dimmed_and_never_selected() {
+    let mut app = fixture();
+    let buffer = run(&mut app, keys("xa "));
+
+    let ignored: Vec<&str> = app
+        .commits
+        .iter()
+        .filter(|c| c.ignored)
+        .map(|c| c.short_hash.as_str())
+        .collect();
+    assert_eq!(ignored, ["aaa1111"]);
+    assert!(!app.commits[0].selected);
+    assert!(app.commits[1].selected);
+    assert_snapshot("ignored", &buffer);
+}
+
+#[test]
+fn select_by_file_pattern() {
+    let mut app = fixture();
+    let mut events = vec![key(KeyCode::Char('f'))];
+    events.extend(keys("lib/"));
+    let buffer = run(&mut app, events.clone());
+    assert_snapshot("file_pattern", &buffer);
+
+    events.extend(keys("**"));
+    events.push(key(KeyCode::Enter));
+    let mut app = fixture();
+    run(&mut app, events);
+    let selected: Vec<&str> = app
+        .commits
+        .iter()
+        .filter(|c| c.selected)
+        .map(|c| c.short_hash.as_str())
+        .collect();
+    assert_eq!(selected, ["aaa1111", "bbb2222"]);
+    assert!(app.pattern.is_none());
+}
+
+#[test]
+fn review_is_read_only() {
+    let mut app = fixture();
+    app.read_only = true;
+    let mut events = keys(" aixf");
+    events.push(key(KeyCode::Enter));
+    events.extend(keys("j"));
+    let buffer = run(&mut app, events);
+
+    assert_eq!(app.selected_count(), 0);
+    assert!(app.commits.iter().all(|c| !c.ignored));
+    assert!(app.pattern.is_none());
+    assert_eq!(app.screen, Screen::Select);
+    assert_snapshot("review", &buffer);
+}
+
+#[test]
+fn filter_by_directory() {
+    let mut app = fixture();
+    let buffer = run(&mut app, keys("t "));
+    assert_snapshot("dir_tree", &buffer);
+
+    // Closed again, the list keeps the filter and `a` only selects what it shows
+    let mut app = fixture();
+    let mut events = keys("t ");
+    events.push(key(KeyCode::Esc));
+    events.extend(keys("ja"));
+    run(&mut app, events);
+    assert!(!app.show_dirs);
+    assert_eq!(app.shown_commits(), [0, 1]);
+    assert_eq!(app.list_state.selected(), Some(1));
+    let selected: Vec<&str> = app
+        .commits
+        .iter()
+        .filter(|c| c.selected)
+        .map(|c| c.short_hash.as_str())
+        .collect();
+    assert_eq!(selected, ["aaa1111", "bbb2222"]);
+}
+
+#[test]
+fn command_palette() {
+    let mut app = fixture();
+    let buffer = run(&mut app, keys(":sort"));
+    assert_snapshot("palette", &buffer);
+
+    // Sorted by subject, the plan still applies them oldest first
+    let mut app = fixture();
+    let mut events = keys(":subj");
+    events.push(key(KeyCode::Enter));
+    events.extend(keys(" j "));
+    events.extend(keys(":title"));
+    events.push(key(KeyCode::Enter));
+    events.extend(keys("Pool connections"));
+    events.push(key(KeyCode::Enter));
+    events.extend(keys(":plan"));
+    events.push(key(KeyCode::Enter));
+    run(&mut app, events);
+
+    let listed: Vec<&str> = app.commits.iter().map(|c| c.short_hash.as_str()).collect();
+    assert_eq!(listed, ["aaa1111", "ddd4444", "bbb2222", "ccc3333"]);
+    let planned: Vec<&str> = app
+        .plan
+        .iter()
+        .map(|s| app.commits[s.commit].short_hash.as_str())
+        .collect();
+    assert_eq!(planned, ["ddd4444", "aaa1111"]);
+    assert_eq!(app.pr_title.as_deref(), Some("Pool connections"));
+}
+
+#[test]
+fn scrollbar_follows_the_filtered_list() {
+    let commits = (0..40)
+        .map(|i| {
+            let dir = if i % 2 == 0 { "lib" } else { "docs" };
+            commit(
+                &format!("{:07x}", i),
+                &format!("Change {}", i),
+                &[&format!("{}/{}.rs", dir, i)],
+            )
+        })
+        .collect();
+    let mut app = App::new(commits);
+    // 20 commits touch docs/, and k wraps around to the last of them
+    let mut events = keys("t ");
+    events.push(key(KeyCode::Esc));
+    events.extend(keys("k"));
+    let buffer = run(&mut app, events);
+    assert_snapshot("long_list", &buffer);
+}
+
+#[test]
+fn narrow_terminal_drops_the_details() {
+    let mut app = fixture();
+    let (buffer, _) = run_sized(&mut app, keys("j"), 70, HEIGHT);
+    assert_snapshot("narrow", &buffer);
+}
+
+#[test]
+fn short_terminal_condenses_header_and_footer() {
+    let mut app = fixture();
+    let (buffer, _) = run_sized(&mut app, keys("j"), WIDTH, 12);
+    assert_snapshot("short", &buffer);
+}
+
+#[test]
+fn tiny_terminal_asks_for_more_room() {
+    let mut app = fixture();
+    let (buffer, _) = run_sized(&mut app, keys("j"), 36, 12);
+    assert_snapshot("too_small", &buffer);
+
+    // Whatever is open, at sizes around the breakpoints
+    for (width, height) in [(0, 0), (1, 1), (40, 10), (41, 11), (89, 14), (90, 15)] {
+        for events in [keys("j"), keys("t "), keys(":so"), keys(" j \n"), keys("?")] {
+            let events = events
+                .into_iter()
+                .map(|e| match e {
+                    Event::Key(k) if k.code == KeyCode::Char('\n') => key(KeyCode::Enter),
+                    e => e,
+                })
+                .collect();
+            run_sized(&mut fixture(), events, width, height);
+        }
+    }
+}
+
+#[test]
+fn help_screen() {
+    let mut app = fixture();
+    assert_snapshot("help", &run(&mut app, keys("?")));
+}
+
+#[test]
+fn plan_screen() {
+    let mut app = fixture();
+    app.diff_source = Some(DiffSource {
+        template_base: "base".to_string(),
+        subtree: String::new(),
+        mainline: 1,
+    });
+    app.soft_limits.max_lines = Some(100);
+    let fake = Rc::new(FakeRunner::new().on("git diff --numstat", "60\t12\tlib/db.rs\n"));
+    let mut events = keys(" j ");
+    events.push(key(KeyCode::Enter));
+    events.extend(keys("js"));
+    let buffer = process::with_runner(fake.clone(), || run(&mut app, events));
+
+    assert_eq!(fake.calls_to("git diff").len(), 2);
+
+    assert_eq!(app.screen, Screen::Plan);
+    let actions: Vec<PlanAction> = app.plan.iter().map(|s| s.action).collect();
+    assert_eq!(actions, [PlanAction::Pick, PlanAction::Squash]);
+    assert_snapshot("plan", &buffer);
+}
+
+#[test]
+fn reword_popup() {
+    let mut app = fixture();
+    let mut events = keys(" ");
+    events.push(key(KeyCode::Enter));
+    events.push(key(KeyCode::Char('r')));
+    events.extend((0..10).map(|_| key(KeyCode::Backspace)));
+    events.extend(keys("handler"));
+    let buffer = run(&mut app, events);
+
+    assert_eq!(app.editing.as_deref(), Some("Fix bug in auth handler"));
+    assert_snapshot("reword", &buffer);
+}
+
+#[test]
+fn enter_on_plan_proceeds() {
+    let mut app = fixture();
+    let mut events = keys(" ");
+    events.push(key(KeyCode::Enter));
+    events.push(key(KeyCode::Enter));
+    events.extend(keys("j")); // Never read, the loop has already returned
+    run(&mut app, events);
+
+    let planned: Vec<&str> = app
+        .planned()
+        .iter()
+        .map(|(c, _)| c.short_hash.as_str())
+        .collect();
+    assert_eq!(planned, ["aaa1111"]);
+}
+
+#[test]
+fn quitting_plans_nothing() {
+    let mut app = fixture();
+    let mut events = keys(" ");
+    events.push(key(KeyCode::Enter));
+    events.push(key(KeyCode::Char('q')));
+    run(&mut app, events);
+
+    assert!(app.planned().is_empty());
+}
+
+#[test]
+fn graph_gutter() {
+    // A merge of a feature branch, drawn like `git log --graph`
+    let mut merge = commit("aaa1111", "Merge branch 'feature'", &[]);
+    let mut feature = commit("bbb2222", "Add feature", &["src/feature.rs"]);
+    let mut main = commit("ccc3333", "Fix main", &["src/main.rs"]);
+    let base = commit("ddd4444", "Start", &["README.md"]);
+    merge.parents = vec![main.hash.clone(), feature.hash.clone()];
+    feature.parents = vec![base.hash.clone()];
+    main.parents = vec![base.hash.clone()];
+
+    let mut app = App::new(vec![merge, feature, main, base]);
+    assert_snapshot("graph", &run(&mut app, keys("g")));
+}
+
+/// `fixture()` with its first commit changing `lib/auth.rs` from `template` to `after`,
+/// a file the template hasn't changed since
+fn diff_fixture(template: &str, after: &str) -> (App, Rc<FakeRunner>) {
+    let mut app = fixture();
+    app.diff_source = Some(DiffSource {
+        template_base: "base".to_string(),
+        subtree: String::new(),
+        mainline: 1,
+    });
+    let fake = Rc::new(FakeRunner::new().on(
+        "git cat-file --batch",
+        &format!(
+            "t blob {}\n{}\nt blob {}\n{}\nc blob {}\n{}\n",
+            template.len(),
+            template,
+            template.len(),
+            template,
+            after.len(),
+            after
+        ),
+    ));
+    (app, fake)
+}
+
+const AUTH_BEFORE: &str = "fn check(token: &str) -> bool {\n    token.len() > 0\n}\n";
+const AUTH_AFTER: &str = "fn check(token: &str) -> bool {\n\t!token.is_empty()\n}\r\n";
+
+#[test]
+fn diff_view() {
+    let (mut app, fake) = diff_fixture(AUTH_BEFORE, AUTH_AFTER);
+    let buffer = process::with_runner(fake.clone(), || run(&mut app, keys("v")));
+
+    assert_eq!(
+        fake.inputs()[0],
+        format!(
+            "base:lib/auth.rs\n{0}^1:lib/auth.rs\n{0}:lib/auth.rs\n",
+            app.commits[0].hash
+        )
+        .as_bytes()
+    );
+    assert_snapshot("diff", &buffer);
+}
+
+#[test]
+fn side_by_side_diff_view() {
+    let (mut app, fake) = diff_fixture(AUTH_BEFORE, AUTH_AFTER);
+    let mut events = keys("vs");
+    let buffer = process::with_runner(fake.clone(), || run(&mut app, events.clone()));
+    assert!(app.side_by_side);
+    assert_snapshot("diff_side_by_side", &buffer);
+
+    // Esc goes back to the list, the side-by-side choice sticks
+    events.push(key(KeyCode::Esc));
+    let (mut app, fake) = diff_fixture(AUTH_BEFORE, AUTH_AFTER);
+    process::with_runner(fake, || run(&mut app, events));
+    assert!(app.diff.is_none() && app.side_by_side && !app.should_quit);
+}
+
+#[test]
+fn copies_hashes() {
+    let mut app = fixture();
+    let (buffer, copied) = run_copying(&mut app, keys("jy"));
+    assert_eq!(copied, [format!("{:0<40}", "bbb2222")]);
+    assert!(to_text(&buffer).contains("Copied bbb2222 to the clipboard"));
+
+    let mut app = fixture();
+    let (_, copied) = run_copying(&mut app, keys("Y"));
+    assert!(copied.is_empty());
+
+    let mut app = fixture();
+    let (buffer, copied) = run_copying(&mut app, keys(" j Y"));
+    assert_eq!(copied, [format!("{:0<40}\n{:0<40}", "aaa1111", "bbb2222")]);
+    assert!(to_text(&buffer).contains("Copied 2 selected hashes to the clipboard"));
+}
+
+#[test]
+fn tags_head_from_the_list() {
+    let fake = Rc::new(
+        FakeRunner::new()
+            .on("git rev-parse HEAD", &format!("{:0<40}\n", "aaa1111"))
+            .on("git log -1", "Fix bug in auth middleware\n")
+            .on(
+                "git interpret-trailers",
+                "Fix bug in auth middleware\n\nChuck: upstream\n",
+            ),
+    );
+    let mut app = fixture();
+    let buffer = process::with_runner(fake.clone(), || run(&mut app, keys("m")));
+    assert_eq!(app.commits[0].mark, Some(Mark::Upstream));
+    assert!(app.commits[0].selected);
+    assert_eq!(fake.calls_to("git commit").len(), 1);
+    assert!(to_text(&buffer).contains("Amended aaa1111 with \"Chuck: upstream\""));
+
+    // Older commits would need a rebase
+    let mut app = fixture();
+    let buffer = process::with_runner(fake.clone(), || run(&mut app, keys("jM")));
+    assert_eq!(app.commits[1].mark, None);
+    assert_eq!(fake.calls_to("git commit").len(), 1);
+    assert!(to_text(&buffer).contains("Only HEAD can be tagged here"));
+}
+
+#[test]
+fn shows_errors_in_a_panel() {
+    let mut app = fixture();
+    let _taken = screen::take();
+    screen::error("Couldn't count the lines bbb2222 changes: fatal: bad object bbb2222");
+    let buffer = run(&mut app, vec![]);
+    assert_snapshot("error", &buffer);
+
+    // Keys go to the panel until it's closed
+    let mut app = fixture();
+    screen::error("Couldn't count the lines bbb2222 changes: fatal: bad object bbb2222");
+    let (buffer, copied) = run_copying(&mut app, keys("jy"));
+    assert_eq!(
+        copied,
+        ["Couldn't count the lines bbb2222 changes: fatal: bad object bbb2222"]
+    );
+    assert_eq!(app.list_state.selected(), Some(0));
+    assert!(to_text(&buffer).contains("Copied the error to the clipboard"));
+
+    let buffer = run(&mut app, vec![key(KeyCode::Esc), key(KeyCode::Char('j'))]);
+    assert!(!to_text(&buffer).contains("Error"));
+    assert_eq!(app.list_state.selected(), Some(1));
+}
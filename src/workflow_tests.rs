@@ -0,0 +1,578 @@
+//! Discovery, branch-building and push logic against a `FakeRunner`, so no git
+//! repository or network is needed
+
+use super::*;
+use chuck::process::fake::FakeRunner;
+use std::rc::Rc;
+
+const BASE: &str = "1111111111111111111111111111111111111111";
+
+fn commit(hash: &str, message: &str) -> Commit {
+    Commit {
+        hash: hash.to_string(),
+        short_hash: hash[..7].to_string(),
+        message: message.to_string(),
+        files: Vec::new(),
+        selected: true,
+        author: "Jane Doe".to_string(),
+        date: "2025-01-20 14:30".to_string(),
+        parents: vec!["0".repeat(40)],
+        already_upstream: false,
+        policy_issues: Vec::new(),
+        risks: Vec::new(),
+        project_only: false,
+        signature: None,
+        ci: None,
+        provenance: None,
+        deleted_template_files: Vec::new(),
+        split_off: Vec::new(),
+        patch_id: None,
+        ignored: false,
+        contributed: None,
+        mark: None,
+    }
+}
+
+fn config(extra: &str) -> ChuckConfig {
+    toml::from_str(&format!(
+        "[template]\nurl = \"git@github.com:acme/template.git\"\n{}",
+        extra
+    ))
+    .unwrap()
+}
+
+fn picks(commits: &[Commit]) -> Vec<PlanStep> {
+    commits
+        .iter()
+        .enumerate()
+        .map(|(i, c)| PlanStep {
+            commit: i,
+            action: PlanAction::Pick,
+            message: c.message.clone(),
+            keep_template_files: false,
+            leave_out: Vec::new(),
+        })
+        .collect()
+}
+
+fn build(
+    fake: FakeRunner,
+    commits: &[Commit],
+    config: &ChuckConfig,
+) -> (Result<String>, Rc<FakeRunner>) {
+    let fake = Rc::new(fake.on("git rev-parse --abbrev-ref HEAD", "main\n"));
+    let steps = picks(commits);
+    let plan: Vec<(&Commit, &PlanStep)> = commits.iter().zip(steps.iter()).collect();
+    let result = process::with_runner(fake.clone(), || {
+        create_branch_with_commits(&plan, false, BASE, config).map(|(branch, _)| branch)
+    });
+    (result, fake)
+}
+
+#[test]
+fn builds_branch_from_template_base_in_plan_order() {
+    let commits = [
+        commit("aaaaaaaaaa", "First"),
+        commit("bbbbbbbbbb", "Second"),
+    ];
+    let (branch, fake) = build(FakeRunner::new(), &commits, &config(""));
+
+    let branch = branch.unwrap();
+    assert!(branch.starts_with("chuck/"));
+    assert_eq!(
+        fake.calls_to("git checkout"),
+        [format!("git checkout -b {} {}", branch, BASE)]
+    );
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        ["git cherry-pick aaaaaaaaaa", "git cherry-pick bbbbbbbbbb"]
+    );
+}
+
+#[test]
+fn passes_cherry_pick_options() {
+    let commits = [commit("aaaaaaaaaa", "First")];
+    let config = config(
+        "[cherry_pick]\nrecord_origin = true\nstrategy_option = \"theirs\"\nrename_threshold = 40\n",
+    );
+    let (result, fake) = build(FakeRunner::new(), &commits, &config);
+
+    result.unwrap();
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        ["git cherry-pick -x --strategy-option=theirs --strategy-option=find-renames=40% aaaaaaaaaa"]
+    );
+}
+
+#[test]
+fn reports_conflicts_as_cherry_pick_failures() {
+    let commits = [
+        commit("aaaaaaaaaa", "First"),
+        commit("bbbbbbbbbb", "Second"),
+    ];
+    let fake = FakeRunner::new().fail(
+        "git cherry-pick aaaaaaaaaa",
+        "error: could not apply aaaaaaa... First\nCONFLICT (content): Merge conflict in src/lib.rs",
+    );
+    let (result, fake) = build(fake, &commits, &config(""));
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("Cherry-pick failed"), "{}", error);
+    assert!(error.contains("CONFLICT"), "{}", error);
+    // Stops at the first conflict
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        ["git cherry-pick aaaaaaaaaa"]
+    );
+}
+
+#[test]
+fn skips_empty_cherry_picks() {
+    let commits = [
+        commit("aaaaaaaaaa", "First"),
+        commit("bbbbbbbbbb", "Second"),
+    ];
+    let fake = FakeRunner::new().fail(
+        "git cherry-pick aaaaaaaaaa",
+        "The previous cherry-pick is now empty, possibly due to conflict resolution.",
+    );
+    let (result, fake) = build(fake, &commits, &config(""));
+
+    result.unwrap();
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        [
+            "git cherry-pick aaaaaaaaaa",
+            "git cherry-pick --skip",
+            "git cherry-pick bbbbbbbbbb"
+        ]
+    );
+}
+
+#[test]
+fn falls_back_to_three_way_apply() {
+    let commits = [commit("aaaaaaaaaa", "First")];
+    let fake = FakeRunner::new()
+        .fail("git cherry-pick aaaaaaaaaa", "CONFLICT (content)")
+        .on("git diff --binary", "diff --git a/x b/x\n");
+    let (result, fake) = build(
+        fake,
+        &commits,
+        &config("[cherry_pick]\nthree_way_fallback = true\n"),
+    );
+
+    result.unwrap();
+    assert_eq!(fake.calls_to("git cherry-pick --abort").len(), 1);
+    assert_eq!(fake.calls_to("git apply"), ["git apply --3way --index"]);
+    assert_eq!(fake.inputs(), [b"diff --git a/x b/x\n".to_vec()]);
+    assert_eq!(
+        fake.calls_to("git commit"),
+        ["git commit --no-verify -C aaaaaaaaaa"]
+    );
+}
+
+#[test]
+fn applies_diffs_when_the_histories_are_unrelated() {
+    let mut deleting = commit("aaaaaaaaaa", "Drop docs");
+    deleting.deleted_template_files = vec!["docs/setup.md".to_string()];
+    let mut steps = picks(std::slice::from_ref(&deleting));
+    steps[0].keep_template_files = true;
+    let plan = [(&deleting, &steps[0])];
+
+    let fake = Rc::new(
+        FakeRunner::new()
+            .on("git rev-parse --abbrev-ref HEAD", "main\n")
+            .fail("git merge-base", "")
+            .on("git diff --binary", "diff --git a/x b/x\n"),
+    );
+    let result = process::with_runner(fake.clone(), || {
+        create_branch_with_commits(&plan, false, BASE, &config(""))
+    });
+
+    result.unwrap();
+    assert!(fake.calls_to("git cherry-pick").is_empty());
+    assert_eq!(
+        fake.calls_to("git diff --binary"),
+        ["git diff --binary aaaaaaaaaa^1 aaaaaaaaaa -- . :(exclude)docs/setup.md"]
+    );
+    assert_eq!(fake.calls_to("git apply"), ["git apply --3way --index"]);
+    assert_eq!(
+        fake.calls_to("git commit"),
+        ["git commit --no-verify -C aaaaaaaaaa"]
+    );
+}
+
+#[test]
+fn keeps_template_files_a_commit_deletes() {
+    let mut deleting = commit("aaaaaaaaaa", "Drop docs");
+    deleting.deleted_template_files = vec!["docs/setup.md".to_string()];
+    let mut steps = picks(std::slice::from_ref(&deleting));
+    steps[0].keep_template_files = true;
+    let plan = [(&deleting, &steps[0])];
+
+    let fake = Rc::new(
+        FakeRunner::new()
+            .on("git rev-parse --abbrev-ref HEAD", "main\n")
+            .on("git ls-tree", "docs/setup.md\0")
+            // Something besides the deletion is staged
+            .fail("git diff --cached --quiet", ""),
+    );
+    let result = process::with_runner(fake.clone(), || {
+        create_branch_with_commits(&plan, false, BASE, &config(""))
+    });
+
+    result.unwrap();
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        ["git cherry-pick --no-commit aaaaaaaaaa"]
+    );
+    assert_eq!(
+        fake.calls_to("git restore"),
+        ["git restore --source=HEAD --staged --worktree --ignore-skip-worktree-bits -- docs/setup.md"]
+    );
+    assert_eq!(
+        fake.calls_to("git commit"),
+        ["git commit --no-verify -C aaaaaaaaaa"]
+    );
+}
+
+#[test]
+fn pushes_to_a_named_branch_on_the_template() {
+    let fake = Rc::new(FakeRunner::new().on("git rev-list", "aaaaaaaaaa\nbbbbbbbbbb\n"));
+    process::with_runner(fake.clone(), || {
+        push_to_template_and_create_pr(
+            "chuck/20250120-143022",
+            BASE,
+            "git@github.com:acme/template.git",
+            "acme/template",
+            "main",
+            "chuck-from-jane-app-20250120-143022",
+        )
+    })
+    .unwrap();
+
+    assert_eq!(
+        fake.calls_to("git push"),
+        ["git push --progress git@github.com:acme/template.git chuck/20250120-143022:refs/heads/chuck-from-jane-app-20250120-143022"]
+    );
+}
+
+#[test]
+fn explains_push_failures() {
+    let fake = Rc::new(FakeRunner::new().fail(
+        "git push",
+        "ERROR: Permission to acme/template.git denied to jane.",
+    ));
+    let result = process::with_runner(fake.clone(), || {
+        push_to_template_and_create_pr(
+            "chuck/x",
+            BASE,
+            "template",
+            "acme/template",
+            "main",
+            "chuck-from-jane-app-x",
+        )
+    });
+
+    let error = result.unwrap_err().to_string();
+    assert!(error.contains("Git push failed"), "{}", error);
+    assert!(error.contains("Permission"), "{}", error);
+    // Retrying can't fix a rejection
+    assert_eq!(fake.calls_to("git push").len(), 1);
+}
+
+#[test]
+fn discovers_commits_newer_than_the_template() {
+    let commits = serde_json::json!([
+        {
+            "sha": "cccccccccccccccccccccccccccccccccccccccc",
+            "parents": [{}],
+            "commit": {
+                "message": "Add feature\n\nDetails",
+                "author": { "name": "Jane", "date": "2025-02-01T10:00:00Z" }
+            }
+        },
+        {
+            "sha": "dddddddddddddddddddddddddddddddddddddddd",
+            "parents": [{}],
+            "commit": {
+                "message": "From the template",
+                "author": { "name": "Joe", "date": "2024-12-01T10:00:00Z" }
+            }
+        }
+    ]);
+    let fake = Rc::new(
+        FakeRunner::new()
+            .fail("git rev-parse --git-dir", "")
+            .on(
+                "gh api repos/acme/template/commits/main",
+                r#"{"commit":{"author":{"date":"2025-01-01T00:00:00Z"}}}"#,
+            )
+            .on("gh api repos/jane/app/commits", &commits.to_string())
+            .on("git show --name-status", "M\tsrc/feature.rs\n"),
+    );
+
+    let found = process::with_runner(fake.clone(), || {
+        get_commits_since_template("jane/app", "acme/template", "main", None)
+    })
+    .unwrap();
+
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].short_hash, "ccccccc");
+    assert_eq!(found[0].message, "Add feature");
+    assert_eq!(found[0].author, "Jane");
+    assert_eq!(found[0].files[0].path, "src/feature.rs");
+}
+
+#[test]
+fn discovers_commits_in_the_local_clone() {
+    let log = "cccccccccccccccccccccccccccccccccccccccc\x1fbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\x1fJane\x1f2025-02-01T10:00:00Z\x1fAdd feature\x1e\n\
+               dddddddddddddddddddddddddddddddddddddddd\x1faaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\x1fJoe\x1f2024-12-01T10:00:00Z\x1fFrom the template\x1e\n";
+    let local = || {
+        FakeRunner::new()
+            .fail("git rev-parse --git-dir", "")
+            .on(
+                "gh api repos/acme/template/commits/main",
+                r#"{"commit":{"author":{"date":"2025-01-01T00:00:00Z"}}}"#,
+            )
+            .on(
+                "git config --get-regexp",
+                "remote.origin.url git@github.com:jane/app.git\n",
+            )
+            .on(
+                "git symbolic-ref --quiet --short refs/remotes/origin/HEAD",
+                "origin/main\n",
+            )
+            .on("git log --format=", log)
+            .on("git show --name-status", "M\tsrc/feature.rs\n")
+    };
+
+    let fake = Rc::new(local());
+    let found = process::with_runner(fake.clone(), || {
+        get_commits_since_template("jane/app", "acme/template", "main", None)
+    })
+    .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].message, "Add feature");
+    assert!(fake.calls_to("gh api repos/jane/app/commits").is_empty());
+    assert!(fake.calls_to("git log")[0].contains("origin/main"));
+
+    // A shallow clone doesn't have it all
+    let fake = Rc::new(
+        local()
+            .on("git rev-parse --is-shallow-repository", "true\n")
+            .on("gh api repos/jane/app/commits", "[]"),
+    );
+    let found = process::with_runner(fake.clone(), || {
+        get_commits_since_template("jane/app", "acme/template", "main", None)
+    })
+    .unwrap();
+    assert!(found.is_empty());
+    assert_eq!(fake.calls_to("gh api repos/jane/app/commits").len(), 1);
+    assert!(fake.calls_to("git log").is_empty());
+}
+
+#[test]
+fn starts_the_window_at_the_last_push() {
+    let log = "cccccccccccccccccccccccccccccccccccccccc\x1fbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\x1fJane\x1f2025-03-01T10:00:00Z\x1fAdd feature\x1e\n\
+               bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\x1faaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\x1fJane\x1f2025-02-01T10:00:00Z\x1fPushed last time\x1e\n";
+    let fake = || {
+        Rc::new(
+            FakeRunner::new()
+                .fail("git rev-parse --git-dir", "")
+                .on(
+                    "gh api repos/acme/template/commits/main",
+                    r#"{"commit":{"author":{"date":"2025-01-01T00:00:00Z"}}}"#,
+                )
+                .on(
+                    "git config --get-regexp",
+                    "remote.origin.url git@github.com:jane/app.git\n",
+                )
+                .on(
+                    "git symbolic-ref --quiet --short refs/remotes/origin/HEAD",
+                    "origin/main\n",
+                )
+                .on("git log --format=", log)
+                .on("git show --name-status", "M\tsrc/feature.rs\n"),
+        )
+    };
+    let pushed = "2025-02-15T00:00:00Z".parse().unwrap();
+
+    let found = process::with_runner(fake(), || {
+        get_commits_since_template("jane/app", "acme/template", "main", Some(pushed))
+    })
+    .unwrap();
+    let messages: Vec<&str> = found.iter().map(|c| c.message.as_str()).collect();
+    assert_eq!(messages, ["Add feature"]);
+
+    // A push from before the template's last update doesn't narrow anything
+    let pushed = "2024-12-01T00:00:00Z".parse().unwrap();
+    let fake = fake();
+    let found = process::with_runner(fake.clone(), || {
+        get_commits_since_template("jane/app", "acme/template", "main", Some(pushed))
+    })
+    .unwrap();
+    assert_eq!(found.len(), 2);
+    assert!(fake.calls_to("git log")[0].contains("--since=2025-01-01T00:00:00Z"));
+}
+
+#[test]
+fn picks_up_a_long_build_from_its_last_checkpoint() {
+    let dir = std::env::temp_dir().join(format!("chuck-checkpoint-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let git_dir = format!("{}\n", dir.display());
+    let commits: Vec<Commit> = (0..12)
+        .map(|i| commit(&format!("{:02}aaaaaaaa", i), &format!("Change {}", i)))
+        .collect();
+
+    let fake = FakeRunner::new()
+        .on("git rev-parse --git-dir", &git_dir)
+        .fail("git cherry-pick 11aaaaaaaa", "CONFLICT (content)");
+    let (result, fake) = build(fake, &commits, &config(""));
+    assert!(result.is_err());
+    assert_eq!(
+        fake.calls_to("git branch -f"),
+        ["git branch -f chuck/wip-10 HEAD"]
+    );
+
+    let fake = FakeRunner::new()
+        .on("git rev-parse --git-dir", &git_dir)
+        .on("git for-each-ref", "chuck/wip-10\n");
+    let (branch, fake) = build(fake, &commits, &config(""));
+    let branch = branch.unwrap();
+    assert_eq!(
+        fake.calls_to("git checkout"),
+        [format!("git checkout -b {} chuck/wip-10", branch)]
+    );
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        ["git cherry-pick 10aaaaaaaa", "git cherry-pick 11aaaaaaaa"]
+    );
+    assert_eq!(
+        fake.calls_to("git branch -D"),
+        ["git branch -D chuck/wip-10"]
+    );
+    assert!(!dir.join("chuck").join("checkpoint.toml").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pr_body_lists_what_was_split_off() {
+    let mut mixed = commit("aaa1111", "Add app");
+    mixed.split_off = vec!["config/local/db.toml".to_string()];
+    let plain = commit("bbb2222", "Fix typo");
+
+    let (title, body) = contribution_text("jane/app", &[&mixed, &plain]);
+    assert_eq!(title, "Upstream 2 commits from jane/app");
+    assert!(body.starts_with(
+        "Contributed from jane/app with chuck.\n\n\
+         - aaa1111 Add app\n\
+         - bbb2222 Fix typo\n\n\
+         Left out as specific to the project:\n\
+         - aaa1111: config/local/db.toml\n"
+    ));
+}
+
+#[test]
+fn builds_in_memory_until_a_pick_needs_the_worktree() {
+    let mut merge = commit("bbbbbbbbbb", "Merge feature");
+    merge.parents.push("f".repeat(40));
+    let commits = [commit("aaaaaaaaaa", "First"), merge];
+    let fake = FakeRunner::new()
+        .on("git write-tree", "7777777\n")
+        .on(
+            "git log -1 --date=raw",
+            "Jane Doe\0jane@acme.com\x001737383400 +0100\0First\n\nWhy.\n",
+        )
+        .on("git commit-tree", "cccccccccc\n");
+    let (result, fake) = build(
+        fake,
+        &commits,
+        &config("[cherry_pick]\nengine = \"in_memory\"\nrecord_origin = true\n"),
+    );
+
+    let branch = result.unwrap();
+    assert_eq!(
+        fake.calls_to("git read-tree"),
+        [format!(
+            "git read-tree -i -m --aggressive aaaaaaaaaa^ {} aaaaaaaaaa",
+            BASE
+        )]
+    );
+    assert_eq!(
+        fake.calls_to("git commit-tree"),
+        [format!("git commit-tree 7777777 -p {} -F -", BASE)]
+    );
+    assert_eq!(
+        fake.inputs(),
+        [b"First\n\nWhy.\n\n(cherry picked from commit aaaaaaaaaa)\n".to_vec()]
+    );
+    // The merge is left to cherry-pick, on top of what was built in memory
+    assert_eq!(
+        fake.calls_to("git checkout"),
+        [format!("git checkout -b {} cccccccccc", branch)]
+    );
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        ["git cherry-pick -x -m 1 bbbbbbbbbb"]
+    );
+}
+
+#[test]
+fn leaves_conflicts_to_the_worktree() {
+    let commits = [commit("aaaaaaaaaa", "First")];
+    let fake = FakeRunner::new()
+        .on(
+            "git ls-files -u",
+            "100644 1111111 1\tsrc/lib.rs\x00100644 2222222 2\tsrc/lib.rs\x00100644 3333333 3\tsrc/lib.rs\0",
+        )
+        .fail("git merge-file", "");
+    let (result, fake) = build(
+        fake,
+        &commits,
+        &config("[cherry_pick]\nengine = \"in_memory\"\n"),
+    );
+
+    let branch = result.unwrap();
+    assert!(fake.calls_to("git commit-tree").is_empty());
+    assert_eq!(
+        fake.calls_to("git checkout"),
+        [format!("git checkout -b {} {}", branch, BASE)]
+    );
+    assert_eq!(
+        fake.calls_to("git cherry-pick"),
+        ["git cherry-pick aaaaaaaaaa"]
+    );
+}
+
+#[test]
+fn checkpoints_what_was_built_in_memory() {
+    let dir = std::env::temp_dir().join(format!("chuck-in-memory-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let mut commits: Vec<Commit> = (0..11)
+        .map(|i| commit(&format!("{:02}aaaaaaaa", i), &format!("Change {}", i)))
+        .collect();
+    let mut merge = commit("11aaaaaaaa", "Merge feature");
+    merge.parents.push("f".repeat(40));
+    commits.push(merge);
+
+    let fake = FakeRunner::new()
+        .on("git rev-parse --git-dir", &format!("{}\n", dir.display()))
+        .on("git write-tree", "7777777\n")
+        .on("git commit-tree", "cccccccccc\n")
+        .fail("git cherry-pick", "CONFLICT (content)");
+    let (result, fake) = build(
+        fake,
+        &commits,
+        &config("[cherry_pick]\nengine = \"in_memory\"\n"),
+    );
+
+    assert!(result.is_err());
+    assert_eq!(fake.calls_to("git commit-tree").len(), 11);
+    // The failed build starts over from the commits built in memory
+    assert_eq!(
+        fake.calls_to("git branch -f"),
+        ["git branch -f chuck/wip-10 cccccccccc"]
+    );
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,96 @@
+use crate::process::{self, CommandExt};
+use anyhow::{anyhow, Result};
+use std::process::Command;
+
+/// Per-workspace template config, at the root of each workspace
+pub const CONFIG_FILE: &str = ".chuckrc";
+
+/// Directories with their own `.chuckrc`, each an app derived from its own template.
+/// `""` is the repository root. Tracked and untracked-but-not-ignored files count.
+pub fn find() -> Result<Vec<String>> {
+    let output = Command::new("git")
+        .args([
+            "ls-files",
+            "--cached",
+            "--others",
+            "--exclude-standard",
+            "-z",
+            "--",
+            &format!(":(glob){}", CONFIG_FILE),
+            &format!(":(glob)**/{}", CONFIG_FILE),
+        ])
+        .output_local()
+        .map_err(|e| process::command_error(e, "Failed to execute git ls-files"))?;
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("Failed to look for workspaces: {}", error.trim()));
+    }
+    Ok(parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+pub fn parse(ls_files: &str) -> Vec<String> {
+    let mut dirs: Vec<String> = ls_files
+        .split('\0')
+        .filter_map(|path| {
+            if path == CONFIG_FILE {
+                return Some(String::new());
+            }
+            path.strip_suffix(CONFIG_FILE)?
+                .strip_suffix('/')
+                .map(str::to_string)
+        })
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs
+}
+
+/// `dir` the way users type and read it: no trailing slash, `.` for the root
+pub fn display(dir: &str) -> &str {
+    if dir.is_empty() {
+        "."
+    } else {
+        dir
+    }
+}
+
+/// Reverses `display`, tolerating `./apps/web/` and the like
+pub fn normalize(dir: &str) -> String {
+    let dir = dir.trim_start_matches("./").trim_end_matches('/');
+    if dir == "." {
+        String::new()
+    } else {
+        dir.to_string()
+    }
+}
+
+/// `path` relative to the workspace, `None` when it's outside it
+pub fn relative<'a>(dir: &str, path: &'a str) -> Option<&'a str> {
+    if dir.is_empty() {
+        return Some(path);
+    }
+    path.strip_prefix(dir)?.strip_prefix('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_workspace_roots() {
+        assert_eq!(
+            parse("apps/web/.chuckrc\0.chuckrc\0apps/api/.chuckrc\0docs/not.chuckrc\0"),
+            ["", "apps/api", "apps/web"]
+        );
+    }
+
+    #[test]
+    fn scopes_paths_to_a_workspace() {
+        assert_eq!(relative("apps/web", "apps/web/src/x.rs"), Some("src/x.rs"));
+        assert_eq!(relative("apps/web", "apps/website/x.rs"), None);
+        assert_eq!(relative("", "src/x.rs"), Some("src/x.rs"));
+        assert_eq!(normalize("./apps/web/"), "apps/web");
+        assert_eq!(display(&normalize(".")), ".");
+    }
+}